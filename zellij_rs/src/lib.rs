@@ -1,10 +1,14 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::str;
+use std::time::Duration;
 pub mod options;
 use options::ZellijOptions;
+use zesh_proc::{Exec, ProcError, ProcessRunner, SystemRunner};
 
 /// Result type for zellij operations
 pub type ZellijResult<T> = Result<T, ZellijError>;
@@ -25,11 +29,44 @@ pub enum ZellijError {
     Utf8(#[from] std::str::Utf8Error),
 }
 
+/// stderr fragments zellij emits while a session is still starting up
+const TRANSIENT_ERRORS: &[&str] = &[
+    "Connection refused",
+    "Resource temporarily unavailable",
+    "not ready",
+];
+
+impl ZellijError {
+    /// Whether the error looks like a session that is still starting up,
+    /// rather than one that is missing or broken, so retrying may help
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ZellijError::CommandExecution(stderr) => TRANSIENT_ERRORS
+                .iter()
+                .any(|fragment| stderr.contains(fragment)),
+            _ => false,
+        }
+    }
+}
+
+impl From<ProcError> for ZellijError {
+    fn from(err: ProcError) -> Self {
+        match err {
+            ProcError::Io(e) => ZellijError::Io(e),
+            ProcError::Timeout { .. } => ZellijError::CommandExecution(err.to_string()),
+        }
+    }
+}
+
 /// Represents a Zellij session
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Session {
     pub name: String,
     pub is_current: bool,
+    /// The session is no longer running, but can be resurrected
+    pub is_exited: bool,
+    /// How long ago the session was created, as of listing it
+    pub created: Option<Duration>,
 }
 
 /// Represents a Zellij pane
@@ -39,6 +76,8 @@ pub struct Pane {
     pub name: Option<String>,
     pub is_focused: bool,
     pub is_plugin: bool,
+    /// Working directory the pane runs in, if zellij reported one
+    pub cwd: Option<PathBuf>,
 }
 
 /// Represents a Zellij tab
@@ -55,20 +94,87 @@ pub trait ZellijOperations {
     /// List all active sessions
     fn list_sessions(&self) -> ZellijResult<Vec<Session>>;
 
+    /// List sessions whose name contains `pattern`, ignoring case. zellij
+    /// has no server-side filter, so this filters `list_sessions`.
+    fn list_sessions_matching(&self, pattern: &str) -> ZellijResult<Vec<Session>> {
+        let pattern = pattern.to_lowercase();
+        let mut sessions = self.list_sessions()?;
+        sessions.retain(|s| s.name.to_lowercase().contains(&pattern));
+        Ok(sessions)
+    }
+
+    /// List only the exited sessions, which zellij can resurrect. zellij
+    /// lists them along with the running ones, so this filters
+    /// `list_sessions`.
+    fn list_resurrectable(&self) -> ZellijResult<Vec<Session>> {
+        let mut sessions = self.list_sessions()?;
+        sessions.retain(|s| s.is_exited);
+        Ok(sessions)
+    }
+
+    /// Get the name of the session we are running inside of, if any
+    fn current_session(&self) -> ZellijResult<Option<String>> {
+        Ok(find_current(self.list_sessions()?))
+    }
+
+    /// Whether we're running inside any zellij session, in which case
+    /// another session has to be switched to rather than attached to
+    fn is_attached(&self) -> bool;
+
     /// Attach to an existing session
     fn attach_session(&self, session_name: &str) -> ZellijResult<()>;
 
+    /// Attach to a session like `zellij attach`. With `create`, a missing
+    /// session is created with `options` instead of failing, so attaching or
+    /// creating takes a single call.
+    fn attach_session_with(
+        &self,
+        session_name: &str,
+        create: bool,
+        options: &ZellijOptions,
+    ) -> ZellijResult<()>;
+
     /// Create a new session
     fn new_session(&self, session_name: &str, options: &ZellijOptions) -> ZellijResult<()>;
 
+    /// Create a new session in the background without attaching to it
+    fn new_session_detached(&self, session_name: &str, options: &ZellijOptions)
+    -> ZellijResult<()>;
+
     /// Close a session
     fn kill_session(&self, session_name: &str) -> ZellijResult<()>;
 
+    /// Rename the session we are running inside of
+    fn rename_session(&self, new_name: &str) -> ZellijResult<()>;
+
+    /// Detach the client we are running inside of from its session, which
+    /// keeps running in the background
+    fn detach(&self) -> ZellijResult<()>;
+
+    /// Move the client we are running inside of to another existing session.
+    /// Unlike [`attach_session`](Self::attach_session) this doesn't nest a
+    /// new client inside the current one.
+    fn switch_session(&self, session_name: &str) -> ZellijResult<()>;
+
     /// List all tabs in the current session
     fn list_tabs(&self) -> ZellijResult<Vec<Tab>>;
 
-    /// Create a new tab with optional name
-    fn new_tab(&self, name: Option<&str>) -> ZellijResult<()>;
+    /// The working directory of every pane in the current session, by pane
+    /// id. Plugins and panes zellij doesn't know the directory of are left
+    /// out.
+    fn pane_cwds(&self) -> ZellijResult<Vec<(u32, PathBuf)>>;
+
+    /// Create a new tab with optional name, starting in `cwd` if given
+    fn new_tab(&self, name: Option<&str>, cwd: Option<&Path>) -> ZellijResult<()>;
+
+    /// Open a new tab in another running session, like one just created in
+    /// the background
+    fn new_tab_in(
+        &self,
+        session_name: &str,
+        name: Option<&str>,
+        cwd: Option<&Path>,
+    ) -> ZellijResult<()>;
 
     /// Rename the current tab
     fn rename_tab(&self, name: &str) -> ZellijResult<()>;
@@ -76,18 +182,77 @@ pub trait ZellijOperations {
     /// Close the current tab
     fn close_tab(&self) -> ZellijResult<()>;
 
+    /// Focus a tab of a running session, by its 1-based number like
+    /// `zellij action go-to-tab` or otherwise by name. Fails without
+    /// changing anything if the session has no such tab.
+    fn go_to_tab(&self, session_name: &str, tab: &str) -> ZellijResult<()>;
+
+    /// Move the focused pane to the end of the tab at `tab_position`
+    /// (0-based, like [`Tab::position`]). Focus follows the pane.
+    fn move_pane_to_tab(&self, tab_position: u32) -> ZellijResult<()>;
+
+    /// Show or hide the floating panes of the current tab
+    fn toggle_floating_panes(&self) -> ZellijResult<()>;
+
+    /// Float the focused pane if it's embedded, or embed it if it's floating
+    fn toggle_pane_embed_or_floating(&self) -> ZellijResult<()>;
+
     /// Run a command in a new pane
     fn run_command(&self, command: &str, args: &[&str]) -> ZellijResult<()>;
+
+    /// Run a command in a new pane of another running session, like one
+    /// just created in the background
+    fn run_command_in(&self, session_name: &str, command: &str, args: &[&str]) -> ZellijResult<()>;
+
+    /// The installed zellij version, as printed by `zellij --version`
+    fn version(&self) -> ZellijResult<String>;
 }
 
 /// Default implementation that calls the real zellij command
 #[derive(Copy, Clone)]
-pub struct ZellijClient;
+pub struct ZellijClient<R: ProcessRunner = SystemRunner> {
+    // Attaching runs on the terminal, everything else through the runner
+    exec: Exec,
+    runner: R,
+}
 
 impl ZellijClient {
     /// Create a new ZellijClient
     pub fn new() -> Self {
-        ZellijClient
+        ZellijClient {
+            exec: Exec::default(),
+            runner: SystemRunner::default(),
+        }
+    }
+
+    /// Kill non-interactive zellij commands that run longer than `timeout`.
+    /// Attaching to or creating a session is never timed out.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.runner.exec.timeout = timeout;
+        self
+    }
+
+    /// Print every zellij command to stderr before running it
+    pub fn with_print_cmd(mut self, print_cmd: bool) -> Self {
+        self.exec.print_cmd = print_cmd;
+        self.runner.exec.print_cmd = print_cmd;
+        self
+    }
+}
+
+impl<R: ProcessRunner> ZellijClient<R> {
+    /// Run zellij commands that don't need the terminal through `runner`
+    /// instead
+    pub fn with_runner<R2: ProcessRunner>(self, runner: R2) -> ZellijClient<R2> {
+        ZellijClient {
+            exec: self.exec,
+            runner,
+        }
+    }
+
+    /// The runner zellij commands go through
+    pub fn runner(&self) -> &R {
+        &self.runner
     }
 }
 
@@ -97,29 +262,42 @@ impl Default for ZellijClient {
     }
 }
 
-impl ZellijOperations for ZellijClient {
+impl<R: ProcessRunner> ZellijOperations for ZellijClient<R> {
     fn list_sessions(&self) -> ZellijResult<Vec<Session>> {
-        let output = Command::new("zellij")
-            .arg("list-sessions")
-            .arg("--no-formatting")
-            .output()?;
+        let output = self.runner.run_command(
+            Command::new("zellij")
+                .arg("list-sessions")
+                .arg("--no-formatting"),
+        )?;
 
         // if there are no sessions, success will be false.
-        let stdout = if !output.status.success() {
-            ""
-        } else {
-            str::from_utf8(&output.stdout)?
-        };
+        if !output.success() {
+            return Ok(Vec::new());
+        }
+
+        parse_session_output(&output.stdout)
+    }
+
+    fn current_session(&self) -> ZellijResult<Option<String>> {
+        // zellij sets this for everything running inside a session, which
+        // saves listing the sessions
+        current_session_from(env::var("ZELLIJ_SESSION_NAME").ok(), || {
+            self.list_sessions()
+        })
+    }
 
-        parse_session_list(stdout)
+    fn is_attached(&self) -> bool {
+        // zellij sets this to its server's pid inside every session
+        env::var_os("ZELLIJ").is_some()
     }
 
     fn attach_session(&self, session_name: &str) -> ZellijResult<()> {
-        let mut child = Command::new("zellij")
-            .arg("attach")
-            .arg(session_name)
-            .stderr(Stdio::piped())
-            .spawn()?;
+        let mut child = self.exec.spawn(
+            Command::new("zellij")
+                .arg("attach")
+                .arg(session_name)
+                .stderr(Stdio::piped()),
+        )?;
 
         let mut stderr = String::new();
 
@@ -136,38 +314,40 @@ impl ZellijOperations for ZellijClient {
         Ok(())
     }
 
-    fn new_session(&self, session_name: &str, options: &ZellijOptions) -> ZellijResult<()> {
+    fn attach_session_with(
+        &self,
+        session_name: &str,
+        create: bool,
+        options: &ZellijOptions,
+    ) -> ZellijResult<()> {
         let mut cmd = Command::new("zellij");
-        cmd.arg("--session")
-            .arg(session_name)
-            .stderr(Stdio::piped());
-
-        // Apply options
-        if let Some(layout) = &options.new_session_with_layout {
-            cmd.arg("--new-session-with-layout").arg(layout);
+        apply_options(&mut cmd, options);
+        cmd.arg("attach");
+        if create {
+            cmd.arg("--create");
         }
+        cmd.arg(session_name).stderr(Stdio::piped());
 
-        if let Some(config) = &options.config {
-            cmd.arg("--config").arg(config);
-        }
+        let mut child = self.exec.spawn(&mut cmd)?;
 
-        if let Some(config_dir) = &options.config_dir {
-            cmd.arg("--config-dir").arg(config_dir);
+        let mut stderr = String::new();
+        if let Some(mut err) = child.stderr.take() {
+            err.read_to_string(&mut stderr)?;
         }
 
-        if let Some(data_dir) = &options.data_dir {
-            cmd.arg("--data-dir").arg(data_dir);
-        }
+        let status = child.wait()?;
 
-        if let Some(max_panes) = &options.max_panes {
-            cmd.arg("--max-panes").arg(max_panes.to_string());
+        if !status.success() {
+            return Err(ZellijError::CommandExecution(stderr));
         }
 
-        if options.debug {
-            cmd.arg("--debug");
-        }
+        Ok(())
+    }
 
-        let mut child = cmd.spawn()?;
+    fn new_session(&self, session_name: &str, options: &ZellijOptions) -> ZellijResult<()> {
+        let mut child = self
+            .exec
+            .spawn(&mut new_session_command(session_name, options))?;
 
         let mut stderr = String::new();
         if let Some(mut err) = child.stderr.take() {
@@ -183,13 +363,18 @@ impl ZellijOperations for ZellijClient {
         Ok(())
     }
 
-    fn kill_session(&self, session_name: &str) -> ZellijResult<()> {
-        let output = Command::new("zellij")
-            .arg("kill-session")
-            .arg(session_name)
-            .output()?;
+    fn new_session_detached(
+        &self,
+        session_name: &str,
+        options: &ZellijOptions,
+    ) -> ZellijResult<()> {
+        let mut cmd = Command::new("zellij");
+        apply_options(&mut cmd, options);
+        cmd.args(["attach", "--create-background", session_name]);
+
+        let output = self.runner.run_command(&cmd)?;
 
-        if !output.status.success() {
+        if !output.success() {
             let error = String::from_utf8_lossy(&output.stderr);
             return Err(ZellijError::CommandExecution(error.to_string()));
         }
@@ -197,32 +382,27 @@ impl ZellijOperations for ZellijClient {
         Ok(())
     }
 
-    fn list_tabs(&self) -> ZellijResult<Vec<Tab>> {
-        // This requires zellij 0.35.0+ for JSON output format
-        let output = Command::new("zellij")
-            .args(["action", "query", "--tabs"])
-            .output()?;
+    fn kill_session(&self, session_name: &str) -> ZellijResult<()> {
+        let output = self
+            .runner
+            .run_command(Command::new("zellij").arg("kill-session").arg(session_name))?;
 
-        if !output.status.success() {
+        if !output.success() {
             let error = String::from_utf8_lossy(&output.stderr);
             return Err(ZellijError::CommandExecution(error.to_string()));
         }
 
-        let stdout = str::from_utf8(&output.stdout)?;
-        parse_tabs_json(stdout)
+        Ok(())
     }
 
-    fn new_tab(&self, name: Option<&str>) -> ZellijResult<()> {
-        let mut cmd = Command::new("zellij");
-        cmd.args(["action", "new-tab"]);
-
-        if let Some(tab_name) = name {
-            cmd.args(["--name", tab_name]);
-        }
-
-        let output = cmd.output()?;
+    fn rename_session(&self, new_name: &str) -> ZellijResult<()> {
+        let output = self.runner.run_command(Command::new("zellij").args([
+            "action",
+            "rename-session",
+            new_name,
+        ]))?;
 
-        if !output.status.success() {
+        if !output.success() {
             let error = String::from_utf8_lossy(&output.stderr);
             return Err(ZellijError::CommandExecution(error.to_string()));
         }
@@ -230,12 +410,49 @@ impl ZellijOperations for ZellijClient {
         Ok(())
     }
 
+    fn detach(&self) -> ZellijResult<()> {
+        self.action(&["detach"])?;
+        Ok(())
+    }
+
+    fn switch_session(&self, session_name: &str) -> ZellijResult<()> {
+        self.action(&["switch-session", session_name])?;
+        Ok(())
+    }
+
+    fn list_tabs(&self) -> ZellijResult<Vec<Tab>> {
+        // zellij has no structured tab query, but the layout dump of the
+        // current session lists every tab and pane
+        parse_layout_tabs(&self.action(&["dump-layout"])?)
+    }
+
+    fn pane_cwds(&self) -> ZellijResult<Vec<(u32, PathBuf)>> {
+        parse_pane_cwds(&self.action(&["dump-layout"])?)
+    }
+
+    fn new_tab(&self, name: Option<&str>, cwd: Option<&Path>) -> ZellijResult<()> {
+        let mut cmd = Command::new("zellij");
+        cmd.arg("action");
+        self.run_new_tab(cmd, name, cwd)
+    }
+
+    fn new_tab_in(
+        &self,
+        session_name: &str,
+        name: Option<&str>,
+        cwd: Option<&Path>,
+    ) -> ZellijResult<()> {
+        let mut cmd = Command::new("zellij");
+        cmd.args(["--session", session_name, "action"]);
+        self.run_new_tab(cmd, name, cwd)
+    }
+
     fn rename_tab(&self, name: &str) -> ZellijResult<()> {
-        let output = Command::new("zellij")
-            .args(["action", "rename-tab", name])
-            .output()?;
+        let output =
+            self.runner
+                .run_command(Command::new("zellij").args(["action", "rename-tab", name]))?;
 
-        if !output.status.success() {
+        if !output.success() {
             let error = String::from_utf8_lossy(&output.stderr);
             return Err(ZellijError::CommandExecution(error.to_string()));
         }
@@ -244,11 +461,11 @@ impl ZellijOperations for ZellijClient {
     }
 
     fn close_tab(&self) -> ZellijResult<()> {
-        let output = Command::new("zellij")
-            .args(["action", "close-tab"])
-            .output()?;
+        let output = self
+            .runner
+            .run_command(Command::new("zellij").args(["action", "close-tab"]))?;
 
-        if !output.status.success() {
+        if !output.success() {
             let error = String::from_utf8_lossy(&output.stderr);
             return Err(ZellijError::CommandExecution(error.to_string()));
         }
@@ -256,6 +473,59 @@ impl ZellijOperations for ZellijClient {
         Ok(())
     }
 
+    fn go_to_tab(&self, session_name: &str, tab: &str) -> ZellijResult<()> {
+        let names = self.session_action(session_name, &["query-tab-names"])?;
+        let exists = match tab.parse::<usize>() {
+            Ok(number) => (1..=names.lines().count()).contains(&number),
+            Err(_) => names.lines().any(|name| name.trim() == tab),
+        };
+        if !exists {
+            return Err(ZellijError::CommandExecution(format!(
+                "No tab '{}' in session '{}'",
+                tab, session_name
+            )));
+        }
+
+        if tab.parse::<usize>().is_ok() {
+            self.session_action(session_name, &["go-to-tab", tab])?;
+        } else {
+            self.session_action(session_name, &["go-to-tab-name", tab])?;
+        }
+        Ok(())
+    }
+
+    fn move_pane_to_tab(&self, tab_position: u32) -> ZellijResult<()> {
+        let tab_count = self.action(&["query-tab-names"])?.lines().count() as u32;
+        if tab_position >= tab_count {
+            return Err(ZellijError::CommandExecution(format!(
+                "No tab at position {}",
+                tab_position
+            )));
+        }
+
+        // zellij can only break a pane out into a new tab or a neighbouring
+        // one, so break it out to a new last tab and walk it left from there.
+        // If the pane was alone, its old tab closes and later tabs shift left,
+        // just like with zellij's own break-pane.
+        self.action(&["break-pane"])?;
+        let tab_count = self.action(&["query-tab-names"])?.lines().count() as u32;
+        for _ in tab_position + 1..tab_count {
+            self.action(&["break-pane-left"])?;
+        }
+
+        Ok(())
+    }
+
+    fn toggle_floating_panes(&self) -> ZellijResult<()> {
+        self.action(&["toggle-floating-panes"])?;
+        Ok(())
+    }
+
+    fn toggle_pane_embed_or_floating(&self) -> ZellijResult<()> {
+        self.action(&["toggle-pane-embed-or-floating"])?;
+        Ok(())
+    }
+
     fn run_command(&self, command: &str, args: &[&str]) -> ZellijResult<()> {
         let mut cmd = Command::new("zellij");
         cmd.arg("run");
@@ -266,15 +536,166 @@ impl ZellijOperations for ZellijClient {
             cmd.arg(arg);
         }
 
-        let output = cmd.output()?;
+        let output = self.runner.run_command(&cmd)?;
+
+        if !output.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(ZellijError::CommandExecution(error.to_string()));
+        }
+
+        Ok(())
+    }
+
+    fn run_command_in(&self, session_name: &str, command: &str, args: &[&str]) -> ZellijResult<()> {
+        let output = self.runner.run_command(
+            Command::new("zellij")
+                .args(["--session", session_name, "run", "--", command])
+                .args(args),
+        )?;
+
+        if !output.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(ZellijError::CommandExecution(error.to_string()));
+        }
+
+        Ok(())
+    }
+
+    fn version(&self) -> ZellijResult<String> {
+        let output = self
+            .runner
+            .run_command(Command::new("zellij").arg("--version"))?;
+
+        if !output.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(ZellijError::CommandExecution(error.to_string()));
+        }
+
+        Ok(str::from_utf8(&output.stdout)?.trim().to_string())
+    }
+}
+
+impl<R: ProcessRunner> ZellijClient<R> {
+    /// Run `zellij action <args>` and return its stdout
+    fn action(&self, args: &[&str]) -> ZellijResult<String> {
+        let output = self
+            .runner
+            .run_command(Command::new("zellij").arg("action").args(args))?;
+
+        if !output.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(ZellijError::CommandExecution(error.to_string()));
+        }
+
+        Ok(str::from_utf8(&output.stdout)?.to_string())
+    }
+
+    /// Finish a `zellij [--session <name>] action` command with `new-tab`
+    /// and its arguments, and run it
+    fn run_new_tab(
+        &self,
+        mut cmd: Command,
+        name: Option<&str>,
+        cwd: Option<&Path>,
+    ) -> ZellijResult<()> {
+        cmd.arg("new-tab");
+
+        if let Some(tab_name) = name {
+            cmd.args(["--name", tab_name]);
+        }
+
+        if let Some(cwd) = cwd {
+            cmd.arg("--cwd").arg(cwd);
+        }
+
+        let output = self.runner.run_command(&cmd)?;
 
-        if !output.status.success() {
+        if !output.success() {
             let error = String::from_utf8_lossy(&output.stderr);
             return Err(ZellijError::CommandExecution(error.to_string()));
         }
 
         Ok(())
     }
+
+    /// Run `zellij --session <name> action <args>` against another session
+    /// and return its stdout
+    fn session_action(&self, session_name: &str, args: &[&str]) -> ZellijResult<String> {
+        let output = self.runner.run_command(
+            Command::new("zellij")
+                .args(["--session", session_name, "action"])
+                .args(args),
+        )?;
+
+        if !output.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(ZellijError::CommandExecution(error.to_string()));
+        }
+
+        Ok(str::from_utf8(&output.stdout)?.to_string())
+    }
+}
+
+/// Command that creates a session and attaches to it
+fn new_session_command(session_name: &str, options: &ZellijOptions) -> Command {
+    let mut cmd = Command::new("zellij");
+    cmd.arg("--session")
+        .arg(session_name)
+        .stderr(Stdio::piped());
+    apply_options(&mut cmd, options);
+    cmd
+}
+
+/// Pass session creation options through to zellij
+fn apply_options(cmd: &mut Command, options: &ZellijOptions) {
+    if let Some(layout) = &options.new_session_with_layout {
+        cmd.arg("--new-session-with-layout").arg(layout);
+    }
+
+    if let Some(config) = &options.config {
+        cmd.arg("--config").arg(config);
+    }
+
+    if let Some(config_dir) = &options.config_dir {
+        cmd.arg("--config-dir").arg(config_dir);
+    }
+
+    if let Some(data_dir) = &options.data_dir {
+        cmd.arg("--data-dir").arg(data_dir);
+    }
+
+    if let Some(max_panes) = &options.max_panes {
+        cmd.arg("--max-panes").arg(max_panes.to_string());
+    }
+
+    if options.debug {
+        cmd.arg("--debug");
+    }
+}
+
+/// Use the session name from the environment if set, otherwise fall back to
+/// scanning the session list
+fn current_session_from<F>(
+    env_name: Option<String>,
+    list_sessions: F,
+) -> ZellijResult<Option<String>>
+where
+    F: FnOnce() -> ZellijResult<Vec<Session>>,
+{
+    match env_name {
+        Some(name) if !name.is_empty() => Ok(Some(name)),
+        _ => Ok(find_current(list_sessions()?)),
+    }
+}
+
+fn find_current(sessions: Vec<Session>) -> Option<String> {
+    sessions.into_iter().find(|s| s.is_current).map(|s| s.name)
+}
+
+/// Parse raw zellij list-sessions output. Invalid UTF-8 is replaced rather
+/// than failing, so one oddly named session doesn't hide all the others.
+fn parse_session_output(stdout: &[u8]) -> ZellijResult<Vec<Session>> {
+    parse_session_list(&String::from_utf8_lossy(stdout))
 }
 
 /// Parse zellij list-sessions output
@@ -288,35 +709,360 @@ fn parse_session_list(output: &str) -> ZellijResult<Vec<Session>> {
         }
 
         let is_current = line.contains("(current)");
+        let is_exited = line.contains("(EXITED");
         let name: String = line.splitn(2, ' ').collect::<Vec<&str>>()[0].to_string();
 
-        sessions.push(Session { name, is_current });
+        sessions.push(Session {
+            name,
+            is_current,
+            is_exited,
+            created: parse_created(line),
+        });
     }
 
     Ok(sessions)
 }
 
-/// Parse zellij query --tabs JSON output
-fn parse_tabs_json(_json: &str) -> ZellijResult<Vec<Tab>> {
-    unimplemented!("Not implemented yet");
+/// Pull the age out of a `list-sessions` line like
+/// `alpha [Created 2h 3m ago] (current)`
+fn parse_created(line: &str) -> Option<Duration> {
+    let start = line.find("[Created ")? + "[Created ".len();
+    let rest = &line[start..];
+    let end = rest.find(" ago]")?;
+    parse_age(&rest[..end])
 }
 
+/// Parse the humantime-style ages zellij prints, made of space separated
+/// `<n><unit>` parts such as `1year 2months 3days 4h 5m 6s`. Months and
+/// years are averaged the way humantime does.
+fn parse_age(age: &str) -> Option<Duration> {
+    let mut secs = 0;
+    let mut parts = 0;
+
+    for part in age.split_whitespace() {
+        let unit_start = part.find(|c: char| !c.is_ascii_digit())?;
+        let (count, unit) = part.split_at(unit_start);
+        let count: u64 = count.parse().ok()?;
+        let unit_secs = match unit {
+            "s" | "sec" | "secs" | "second" | "seconds" => 1,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60,
+            "h" | "hr" | "hrs" | "hour" | "hours" => 3_600,
+            "d" | "day" | "days" => 86_400,
+            "w" | "week" | "weeks" => 604_800,
+            "M" | "month" | "months" => 2_630_016,
+            "y" | "year" | "years" => 31_557_600,
+            // Too small to matter for a session's age
+            "ms" | "us" | "ns" => 0,
+            _ => return None,
+        };
+        secs += count * unit_secs;
+        parts += 1;
+    }
+
+    (parts > 0).then(|| Duration::from_secs(secs))
+}
+
+/// A node from the KDL `zellij action dump-layout` prints, keeping only
+/// what's needed to find tabs and panes
+#[derive(Debug, Default)]
+struct LayoutNode {
+    name: String,
+    /// The rest of the node's line, e.g. `name="editor" focus=true`
+    props: String,
+    children: Vec<LayoutNode>,
+}
+
+impl LayoutNode {
+    fn new(line: &str) -> Self {
+        let line = line.trim();
+        let (name, props) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        Self {
+            name: name.to_string(),
+            props: props.trim().to_string(),
+            children: Vec::new(),
+        }
+    }
+
+    /// A quoted property like `name="editor"`
+    fn prop(&self, key: &str) -> Option<String> {
+        let needle = format!("{}=\"", key);
+        self.props.match_indices(&needle).find_map(|(start, _)| {
+            let at_word_start = self.props[..start]
+                .chars()
+                .next_back()
+                .is_none_or(char::is_whitespace);
+            let value = &self.props[start + needle.len()..];
+            at_word_start.then(|| value.split('"').next().unwrap_or("").to_string())
+        })
+    }
+
+    fn has_focus(&self) -> bool {
+        self.props.split_whitespace().any(|p| p == "focus=true")
+    }
+}
+
+/// Byte offset of the first `{` that isn't inside a quoted string
+fn find_block_start(line: &str) -> Option<usize> {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '{' if !quoted => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parse the node tree of a layout dump. This only understands the
+/// one-node-per-line shape zellij writes, not arbitrary KDL.
+fn parse_layout_nodes(kdl: &str) -> ZellijResult<Vec<LayoutNode>> {
+    let mut stack = vec![LayoutNode::default()];
+
+    for line in kdl.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+
+        if line == "}" {
+            if stack.len() < 2 {
+                return Err(ZellijError::OutputParsing(
+                    "unmatched '}' in layout".to_string(),
+                ));
+            }
+            let node = stack.pop().unwrap_or_default();
+            stack.last_mut().unwrap().children.push(node);
+            continue;
+        }
+
+        let Some(open) = find_block_start(line) else {
+            stack
+                .last_mut()
+                .unwrap()
+                .children
+                .push(LayoutNode::new(line));
+            continue;
+        };
+
+        let mut node = LayoutNode::new(&line[..open]);
+        let rest = line[open + 1..].trim();
+        match rest.strip_suffix('}') {
+            // A whole block on one line, like `pane { plugin location="x"; }`
+            Some(inline) => {
+                node.children = inline
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|child| !child.is_empty())
+                    .map(LayoutNode::new)
+                    .collect();
+                stack.last_mut().unwrap().children.push(node);
+            }
+            None => stack.push(node),
+        }
+    }
+
+    if stack.len() != 1 {
+        return Err(ZellijError::OutputParsing(
+            "unclosed block in layout".to_string(),
+        ));
+    }
+    Ok(stack.pop().unwrap_or_default().children)
+}
+
+/// Read the tabs of a session from `zellij action dump-layout` output.
+/// The dump has no pane ids, so panes are numbered by their order in the
+/// tab, and split containers are flattened into the panes they hold.
+/// Relative working directories are resolved against the layout's `cwd`.
+fn parse_layout_tabs(kdl: &str) -> ZellijResult<Vec<Tab>> {
+    let nodes = parse_layout_nodes(kdl)?;
+    let layout = nodes
+        .iter()
+        .find(|node| node.name == "layout")
+        .ok_or_else(|| ZellijError::OutputParsing("no layout in dump".to_string()))?;
+    let layout_cwd = layout
+        .children
+        .iter()
+        .find(|node| node.name == "cwd")
+        .map(|node| PathBuf::from(node.props.trim_matches('"')));
+
+    // Templates and swap layouts sit next to the tabs but aren't tabs
+    let tabs = layout
+        .children
+        .iter()
+        .filter(|node| node.name == "tab")
+        .enumerate()
+        .map(|(position, tab)| {
+            let mut panes = Vec::new();
+            let cwd = node_cwd(tab, layout_cwd.as_deref());
+            collect_layout_panes(&tab.children, cwd.as_deref(), &mut panes);
+            Tab {
+                position: position as u32,
+                name: tab.prop("name"),
+                is_active: tab.has_focus(),
+                panes,
+            }
+        })
+        .collect();
+
+    Ok(tabs)
+}
+
+/// Read the working directories of the panes of a session from
+/// `zellij action dump-layout` output. The dump has no pane ids, so panes
+/// are numbered in the order they appear across all tabs.
+fn parse_pane_cwds(kdl: &str) -> ZellijResult<Vec<(u32, PathBuf)>> {
+    Ok(parse_layout_tabs(kdl)?
+        .into_iter()
+        .flat_map(|tab| tab.panes)
+        .enumerate()
+        .filter_map(|(id, pane)| Some((id as u32, pane.cwd?)))
+        .collect())
+}
+
+/// Working directory of a node, its `cwd` resolved against the one it
+/// inherits from its parent
+fn node_cwd(node: &LayoutNode, parent: Option<&Path>) -> Option<PathBuf> {
+    match (node.prop("cwd"), parent) {
+        (Some(cwd), Some(parent)) => Some(parent.join(cwd)),
+        (Some(cwd), None) => Some(PathBuf::from(cwd)),
+        (None, parent) => parent.map(Path::to_path_buf),
+    }
+}
+
+fn collect_layout_panes(nodes: &[LayoutNode], cwd: Option<&Path>, panes: &mut Vec<Pane>) {
+    for node in nodes {
+        match node.name.as_str() {
+            "pane" if node.children.iter().any(|child| child.name == "pane") => {
+                let cwd = node_cwd(node, cwd);
+                collect_layout_panes(&node.children, cwd.as_deref(), panes);
+            }
+            "pane" => {
+                let is_plugin = node.children.iter().any(|child| child.name == "plugin");
+                panes.push(Pane {
+                    id: panes.len() as u32,
+                    name: node.prop("name"),
+                    is_focused: node.has_focus(),
+                    is_plugin,
+                    // Plugins don't run in a directory
+                    cwd: if is_plugin { None } else { node_cwd(node, cwd) },
+                });
+            }
+            "floating_panes" => collect_layout_panes(&node.children, cwd, panes),
+            _ => {}
+        }
+    }
+}
+
+/// The name and working directory a tab was opened with
+pub type TabSpec = (Option<String>, Option<PathBuf>);
+
 #[derive(Default)]
 pub struct MockZellijClient {
     sessions: RefCell<HashMap<String, bool>>,
+    exited_sessions: RefCell<HashSet<String>>,
     tabs: RefCell<Vec<Tab>>,
+    pane_cwds: RefCell<Vec<(u32, PathBuf)>>,
+    // Command lines run in new panes, by session
+    commands: RefCell<HashMap<String, Vec<Vec<String>>>>,
+    // Names and working directories of tabs opened in other sessions
+    tabs_in: RefCell<HashMap<String, Vec<TabSpec>>>,
+    // Working directories tabs were created with, by position
+    tab_cwds: RefCell<HashMap<u32, PathBuf>>,
+    // Positions of tabs whose floating panes are shown
+    floating_tabs: RefCell<HashSet<u32>>,
     current_session: RefCell<Option<String>>,
+    session_options: RefCell<HashMap<String, ZellijOptions>>,
+    created: RefCell<HashMap<String, Duration>>,
+    // None simulates zellij not being installed
+    version: RefCell<Option<String>>,
+    attached: Cell<bool>,
 }
 
+/// Version reported by a default [`MockZellijClient`]
+pub const MOCK_ZELLIJ_VERSION: &str = "zellij 0.41.2";
+
 impl MockZellijClient {
     pub fn new() -> Self {
         Self {
             sessions: RefCell::new(HashMap::new()),
+            exited_sessions: RefCell::new(HashSet::new()),
             tabs: RefCell::new(Vec::new()),
+            pane_cwds: RefCell::new(Vec::new()),
+            commands: RefCell::new(HashMap::new()),
+            tabs_in: RefCell::new(HashMap::new()),
+            tab_cwds: RefCell::new(HashMap::new()),
+            floating_tabs: RefCell::new(HashSet::new()),
             current_session: RefCell::new(None),
+            session_options: RefCell::new(HashMap::new()),
+            created: RefCell::new(HashMap::new()),
+            version: RefCell::new(Some(MOCK_ZELLIJ_VERSION.to_string())),
+            attached: Cell::new(false),
         }
     }
 
+    /// Act as if running inside a zellij session, or not, for testing.
+    /// Mocks start out detached, even with a current session preset.
+    pub fn with_attached(self, attached: bool) -> Self {
+        self.attached.set(attached);
+        self
+    }
+
+    /// Preset the reported version for testing, or `None` to act as if
+    /// zellij isn't installed
+    pub fn with_version(self, version: Option<&str>) -> Self {
+        *self.version.borrow_mut() = version.map(String::from);
+        self
+    }
+
+    /// Working directory a tab was created with, if it was created by this
+    /// mock with one
+    pub fn tab_cwd(&self, position: u32) -> Option<PathBuf> {
+        self.tab_cwds.borrow().get(&position).cloned()
+    }
+
+    /// Whether the tab at `position` has been toggled to show floating panes
+    pub fn is_floating(&self, position: u32) -> bool {
+        self.floating_tabs.borrow().contains(&position)
+    }
+
+    /// Flip the floating flag of the active tab
+    fn toggle_floating(&self) -> ZellijResult<()> {
+        let position = self
+            .tabs
+            .borrow()
+            .iter()
+            .find(|tab| tab.is_active)
+            .map(|tab| tab.position)
+            .ok_or_else(|| ZellijError::CommandExecution("No active tab found".to_string()))?;
+
+        let mut floating = self.floating_tabs.borrow_mut();
+        if !floating.remove(&position) {
+            floating.insert(position);
+        }
+        Ok(())
+    }
+
+    /// Options a session was created with, if it was created by this mock
+    pub fn session_options(&self, session_name: &str) -> Option<ZellijOptions> {
+        self.session_options.borrow().get(session_name).cloned()
+    }
+
+    /// Preset how long ago a session was created for testing
+    pub fn with_created(self, session_name: &str, age: Duration) -> Self {
+        self.created
+            .borrow_mut()
+            .insert(session_name.to_string(), age);
+        self
+    }
+
+    /// Mark preset sessions as exited for testing
+    pub fn with_exited(self, names: &[&str]) -> Self {
+        self.exited_sessions
+            .borrow_mut()
+            .extend(names.iter().map(|n| n.to_string()));
+        self
+    }
+
     /// Preset sessions for testing
     pub fn with_sessions(sessions: HashMap<String, bool>) -> Self {
         let client = Self::new();
@@ -333,6 +1079,44 @@ impl MockZellijClient {
         client
     }
 
+    /// Preset the working directories [`pane_cwds`](ZellijOperations::pane_cwds)
+    /// returns, for testing
+    pub fn with_pane_cwds(self, cwds: Vec<(u32, PathBuf)>) -> Self {
+        *self.pane_cwds.borrow_mut() = cwds;
+        self
+    }
+
+    /// Command lines run in new panes of a session, oldest first
+    pub fn commands(&self, session_name: &str) -> Vec<Vec<String>> {
+        self.commands
+            .borrow()
+            .get(session_name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Names and working directories of the tabs opened in a session with
+    /// [`new_tab_in`](ZellijOperations::new_tab_in), oldest first
+    pub fn tabs_in(&self, session_name: &str) -> Vec<TabSpec> {
+        self.tabs_in
+            .borrow()
+            .get(session_name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn record_command(&self, session_name: &str, command: &str, args: &[&str]) {
+        let line = std::iter::once(command)
+            .chain(args.iter().copied())
+            .map(String::from)
+            .collect();
+        self.commands
+            .borrow_mut()
+            .entry(session_name.to_string())
+            .or_default()
+            .push(line);
+    }
+
     /// Preset tabs for testing
     pub fn with_tabs(tabs: Vec<Tab>) -> Self {
         let client = Self::new();
@@ -344,17 +1128,29 @@ impl MockZellijClient {
 impl ZellijOperations for MockZellijClient {
     fn list_sessions(&self) -> ZellijResult<Vec<Session>> {
         let sessions = self.sessions.borrow();
+        let exited = self.exited_sessions.borrow();
+        let created = self.created.borrow();
         let result = sessions
             .iter()
             .map(|(name, &is_current)| Session {
                 name: name.clone(),
                 is_current,
+                is_exited: exited.contains(name),
+                created: created.get(name).copied(),
             })
             .collect();
 
         Ok(result)
     }
 
+    fn current_session(&self) -> ZellijResult<Option<String>> {
+        Ok(self.current_session.borrow().clone())
+    }
+
+    fn is_attached(&self) -> bool {
+        self.attached.get()
+    }
+
     fn attach_session(&self, session_name: &str) -> ZellijResult<()> {
         let mut sessions = self.sessions.borrow_mut();
 
@@ -372,6 +1168,9 @@ impl ZellijOperations for MockZellijClient {
             *session = false;
         }
 
+        // Attaching resurrects an exited session
+        self.exited_sessions.borrow_mut().remove(session_name);
+
         // Mark the new session as current
         if let Some(session) = sessions.get_mut(session_name) {
             *session = true;
@@ -381,7 +1180,24 @@ impl ZellijOperations for MockZellijClient {
         Ok(())
     }
 
-    fn new_session(&self, session_name: &str, _options: &ZellijOptions) -> ZellijResult<()> {
+    fn attach_session_with(
+        &self,
+        session_name: &str,
+        create: bool,
+        options: &ZellijOptions,
+    ) -> ZellijResult<()> {
+        let exists = self.sessions.borrow().contains_key(session_name);
+        if create && !exists {
+            self.new_session(session_name, options)
+        } else {
+            self.attach_session(session_name)
+        }
+    }
+
+    fn new_session(&self, session_name: &str, options: &ZellijOptions) -> ZellijResult<()> {
+        self.session_options
+            .borrow_mut()
+            .insert(session_name.to_string(), options.clone());
         let mut sessions = self.sessions.borrow_mut();
 
         // Mark the current session as not current
@@ -398,6 +1214,23 @@ impl ZellijOperations for MockZellijClient {
         Ok(())
     }
 
+    fn new_session_detached(
+        &self,
+        session_name: &str,
+        options: &ZellijOptions,
+    ) -> ZellijResult<()> {
+        self.session_options
+            .borrow_mut()
+            .insert(session_name.to_string(), options.clone());
+
+        // The new session runs in the background, so the current one stays
+        self.sessions
+            .borrow_mut()
+            .insert(session_name.to_string(), false);
+
+        Ok(())
+    }
+
     fn kill_session(&self, session_name: &str) -> ZellijResult<()> {
         let mut sessions = self.sessions.borrow_mut();
 
@@ -410,22 +1243,65 @@ impl ZellijOperations for MockZellijClient {
 
         // Remove the session
         sessions.remove(session_name);
+        self.exited_sessions.borrow_mut().remove(session_name);
 
         // If we removed the current session, set current_session to None
-        if let Some(current) = self.current_session.borrow().as_ref()
-            && current == session_name
-        {
-            *self.current_session.borrow_mut() = None;
+        let mut current = self.current_session.borrow_mut();
+        if current.as_deref() == Some(session_name) {
+            *current = None;
+        }
+
+        Ok(())
+    }
+
+    fn rename_session(&self, new_name: &str) -> ZellijResult<()> {
+        let Some(current) = self.current_session.borrow_mut().take() else {
+            return Err(ZellijError::CommandExecution(
+                "Not inside a zellij session".to_string(),
+            ));
+        };
+
+        let mut sessions = self.sessions.borrow_mut();
+        if let Some(is_current) = sessions.remove(&current) {
+            sessions.insert(new_name.to_string(), is_current);
+        }
+        *self.current_session.borrow_mut() = Some(new_name.to_string());
+
+        Ok(())
+    }
+
+    fn detach(&self) -> ZellijResult<()> {
+        let Some(current) = self.current_session.borrow_mut().take() else {
+            return Err(ZellijError::CommandExecution(
+                "Not inside a zellij session".to_string(),
+            ));
+        };
+
+        if let Some(is_current) = self.sessions.borrow_mut().get_mut(&current) {
+            *is_current = false;
         }
 
         Ok(())
     }
 
+    fn switch_session(&self, session_name: &str) -> ZellijResult<()> {
+        if self.current_session.borrow().is_none() {
+            return Err(ZellijError::CommandExecution(
+                "Not inside a zellij session".to_string(),
+            ));
+        }
+        self.attach_session(session_name)
+    }
+
     fn list_tabs(&self) -> ZellijResult<Vec<Tab>> {
         Ok(self.tabs.borrow().clone())
     }
 
-    fn new_tab(&self, name: Option<&str>) -> ZellijResult<()> {
+    fn pane_cwds(&self) -> ZellijResult<Vec<(u32, PathBuf)>> {
+        Ok(self.pane_cwds.borrow().clone())
+    }
+
+    fn new_tab(&self, name: Option<&str>, cwd: Option<&Path>) -> ZellijResult<()> {
         let mut tabs = self.tabs.borrow_mut();
 
         // Set all existing tabs to not active
@@ -435,6 +1311,11 @@ impl ZellijOperations for MockZellijClient {
 
         // Create a new tab and set it as active
         let position = tabs.len() as u32;
+        if let Some(cwd) = cwd {
+            self.tab_cwds
+                .borrow_mut()
+                .insert(position, cwd.to_path_buf());
+        }
         tabs.push(Tab {
             position,
             name: name.map(String::from),
@@ -445,6 +1326,26 @@ impl ZellijOperations for MockZellijClient {
         Ok(())
     }
 
+    fn new_tab_in(
+        &self,
+        session_name: &str,
+        name: Option<&str>,
+        cwd: Option<&Path>,
+    ) -> ZellijResult<()> {
+        if !self.sessions.borrow().contains_key(session_name) {
+            return Err(ZellijError::CommandExecution(format!(
+                "Session '{}' not found",
+                session_name
+            )));
+        }
+        self.tabs_in
+            .borrow_mut()
+            .entry(session_name.to_string())
+            .or_default()
+            .push((name.map(String::from), cwd.map(Path::to_path_buf)));
+        Ok(())
+    }
+
     fn rename_tab(&self, name: &str) -> ZellijResult<()> {
         let mut tabs = self.tabs.borrow_mut();
 
@@ -489,7 +1390,805 @@ impl ZellijOperations for MockZellijClient {
         }
     }
 
-    fn run_command(&self, _command: &str, _args: &[&str]) -> ZellijResult<()> {
+    fn go_to_tab(&self, session_name: &str, tab: &str) -> ZellijResult<()> {
+        if !self.sessions.borrow().contains_key(session_name) {
+            return Err(ZellijError::CommandExecution(format!(
+                "Session '{}' not found",
+                session_name
+            )));
+        }
+
+        // The mock's tabs stand in for the tabs of any session
+        let mut tabs = self.tabs.borrow_mut();
+        let target = match tab.parse::<u32>() {
+            Ok(number) => tabs.iter().position(|t| t.position + 1 == number),
+            Err(_) => tabs.iter().position(|t| t.name.as_deref() == Some(tab)),
+        }
+        .ok_or_else(|| {
+            ZellijError::CommandExecution(format!("No tab '{}' in session '{}'", tab, session_name))
+        })?;
+
+        for (i, t) in tabs.iter_mut().enumerate() {
+            t.is_active = i == target;
+        }
+        Ok(())
+    }
+
+    fn move_pane_to_tab(&self, tab_position: u32) -> ZellijResult<()> {
+        let mut tabs = self.tabs.borrow_mut();
+
+        let target = tab_position as usize;
+        if target >= tabs.len() {
+            return Err(ZellijError::CommandExecution(format!(
+                "No tab at position {}",
+                tab_position
+            )));
+        }
+
+        let source = tabs
+            .iter()
+            .position(|tab| tab.is_active)
+            .ok_or_else(|| ZellijError::CommandExecution("No active tab found".to_string()))?;
+        if source == target {
+            return Ok(());
+        }
+
+        let pane_index = tabs[source]
+            .panes
+            .iter()
+            .position(|pane| pane.is_focused)
+            .ok_or_else(|| ZellijError::CommandExecution("No focused pane found".to_string()))?;
+        let pane = tabs[source].panes.remove(pane_index);
+
+        // The tab left behind focuses its last pane, if it has any left
+        tabs[source].is_active = false;
+        if let Some(last) = tabs[source].panes.last_mut() {
+            last.is_focused = true;
+        }
+
+        tabs[target].is_active = true;
+        for other in tabs[target].panes.iter_mut() {
+            other.is_focused = false;
+        }
+        tabs[target].panes.push(pane);
+
+        Ok(())
+    }
+
+    fn toggle_floating_panes(&self) -> ZellijResult<()> {
+        self.toggle_floating()
+    }
+
+    fn toggle_pane_embed_or_floating(&self) -> ZellijResult<()> {
+        // Floating a pane shows the floating panes, and embedding it again
+        // hides them, so one flag covers both
+        self.toggle_floating()
+    }
+
+    fn run_command(&self, command: &str, args: &[&str]) -> ZellijResult<()> {
+        if let Some(current) = self.current_session.borrow().as_ref() {
+            self.record_command(current, command, args);
+        }
         Ok(())
     }
+
+    fn run_command_in(&self, session_name: &str, command: &str, args: &[&str]) -> ZellijResult<()> {
+        if !self.sessions.borrow().contains_key(session_name) {
+            return Err(ZellijError::CommandExecution(format!(
+                "Session '{}' not found",
+                session_name
+            )));
+        }
+        self.record_command(session_name, command, args);
+        Ok(())
+    }
+
+    fn version(&self) -> ZellijResult<String> {
+        self.version.borrow().clone().ok_or_else(|| {
+            ZellijError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "zellij not found",
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zesh_proc::{MockRunner, RunOutput};
+
+    #[test]
+    fn test_is_transient() {
+        let starting =
+            ZellijError::CommandExecution("Error: Connection refused (os error 111)".to_string());
+        assert!(starting.is_transient());
+
+        let missing = ZellijError::CommandExecution("Session 'x' not found".to_string());
+        assert!(!missing.is_transient());
+
+        let io = ZellijError::Io(std::io::Error::other("Connection refused"));
+        assert!(!io.is_transient());
+    }
+
+    fn sessions_with_current(name: &str) -> Vec<Session> {
+        vec![Session {
+            name: name.to_string(),
+            is_current: true,
+            is_exited: false,
+            created: None,
+        }]
+    }
+
+    #[test]
+    fn test_current_session_from_env() {
+        let current = current_session_from(Some("from-env".to_string()), || {
+            panic!("sessions should not be listed when the env var is set")
+        })
+        .unwrap();
+        assert_eq!(current, Some("from-env".to_string()));
+    }
+
+    #[test]
+    fn test_current_session_falls_back_to_list() {
+        let current =
+            current_session_from(None, || Ok(sessions_with_current("from-list"))).unwrap();
+        assert_eq!(current, Some("from-list".to_string()));
+
+        // An empty variable is treated as unset
+        let current = current_session_from(Some(String::new()), || {
+            Ok(sessions_with_current("from-list"))
+        })
+        .unwrap();
+        assert_eq!(current, Some("from-list".to_string()));
+
+        let current = current_session_from(None, || Ok(Vec::new())).unwrap();
+        assert_eq!(current, None);
+    }
+
+    #[test]
+    fn test_mock_current_session() {
+        let client = MockZellijClient::with_sessions(HashMap::from([
+            ("a".to_string(), true),
+            ("b".to_string(), false),
+        ]));
+        assert_eq!(client.current_session().unwrap(), Some("a".to_string()));
+
+        client.attach_session("b").unwrap();
+        assert_eq!(client.current_session().unwrap(), Some("b".to_string()));
+
+        assert_eq!(MockZellijClient::new().current_session().unwrap(), None);
+    }
+
+    #[test]
+    fn test_mock_detach() {
+        let client = MockZellijClient::with_sessions(HashMap::from([("a".to_string(), true)]));
+
+        client.detach().unwrap();
+
+        assert_eq!(client.current_session().unwrap(), None);
+        let sessions = client.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert!(!sessions[0].is_current);
+        assert!(client.detach().is_err());
+    }
+
+    #[test]
+    fn test_mock_rename_session() {
+        let client = MockZellijClient::with_sessions(HashMap::from([
+            ("old".to_string(), true),
+            ("other".to_string(), false),
+        ]));
+
+        client.rename_session("new").unwrap();
+
+        assert_eq!(client.current_session().unwrap(), Some("new".to_string()));
+        assert_eq!(
+            sorted_names(client.list_sessions().unwrap()),
+            vec!["new", "other"]
+        );
+    }
+
+    fn pane(id: u32, is_focused: bool) -> Pane {
+        Pane {
+            id,
+            name: None,
+            is_focused,
+            is_plugin: false,
+            cwd: None,
+        }
+    }
+
+    fn two_tabs() -> MockZellijClient {
+        MockZellijClient::with_tabs(vec![
+            Tab {
+                position: 0,
+                name: Some("editor".to_string()),
+                is_active: true,
+                panes: vec![pane(1, false), pane(2, true)],
+            },
+            Tab {
+                position: 1,
+                name: Some("shell".to_string()),
+                is_active: false,
+                panes: vec![pane(3, true)],
+            },
+        ])
+    }
+
+    fn focused(tab: &Tab) -> Vec<u32> {
+        tab.panes
+            .iter()
+            .filter(|p| p.is_focused)
+            .map(|p| p.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_mock_move_pane_to_tab() {
+        let client = two_tabs();
+
+        client.move_pane_to_tab(1).unwrap();
+
+        let tabs = client.list_tabs().unwrap();
+        assert_eq!(tabs[0].panes.len(), 1);
+        assert_eq!(tabs[1].panes.len(), 2);
+        assert_eq!(tabs[1].panes.last().unwrap().id, 2);
+
+        // Focus follows the pane, and each tab keeps exactly one focused pane
+        assert!(!tabs[0].is_active);
+        assert!(tabs[1].is_active);
+        assert_eq!(focused(&tabs[0]), vec![1]);
+        assert_eq!(focused(&tabs[1]), vec![2]);
+    }
+
+    #[test]
+    fn test_mock_move_pane_to_same_tab() {
+        let client = two_tabs();
+
+        client.move_pane_to_tab(0).unwrap();
+
+        assert_eq!(client.list_tabs().unwrap(), two_tabs().list_tabs().unwrap());
+    }
+
+    #[test]
+    fn test_mock_toggle_floating_panes() {
+        let client = two_tabs();
+
+        client.toggle_floating_panes().unwrap();
+        assert!(client.is_floating(0));
+        assert!(!client.is_floating(1));
+
+        client.toggle_floating_panes().unwrap();
+        assert!(!client.is_floating(0));
+    }
+
+    #[test]
+    fn test_mock_toggle_pane_embed_or_floating() {
+        let client = two_tabs();
+
+        client.toggle_pane_embed_or_floating().unwrap();
+        assert!(client.is_floating(0));
+
+        client.toggle_pane_embed_or_floating().unwrap();
+        assert!(!client.is_floating(0));
+    }
+
+    #[test]
+    fn test_mock_toggle_floating_without_tabs() {
+        assert!(MockZellijClient::new().toggle_floating_panes().is_err());
+    }
+
+    #[test]
+    fn test_client_argv() {
+        let client = ZellijClient::new().with_runner(MockRunner::new());
+        let options = ZellijOptions {
+            new_session_with_layout: Some("compact".to_string()),
+            ..ZellijOptions::default()
+        };
+
+        client.list_sessions().unwrap();
+        client.new_session_detached("api", &options).unwrap();
+        client.kill_session("api").unwrap();
+        client.rename_tab("logs").unwrap();
+        client.run_command_in("api", "vim", &["/src/api"]).unwrap();
+        client
+            .new_tab_in("api", Some("docs"), Some(Path::new("/src/api/docs")))
+            .unwrap();
+        client.new_tab(Some("logs"), None).unwrap();
+
+        assert_eq!(
+            client.runner().argvs(),
+            vec![
+                vec!["zellij", "list-sessions", "--no-formatting"],
+                vec![
+                    "zellij",
+                    "--new-session-with-layout",
+                    "compact",
+                    "attach",
+                    "--create-background",
+                    "api"
+                ],
+                vec!["zellij", "kill-session", "api"],
+                vec!["zellij", "action", "rename-tab", "logs"],
+                vec!["zellij", "--session", "api", "run", "--", "vim", "/src/api"],
+                vec![
+                    "zellij",
+                    "--session",
+                    "api",
+                    "action",
+                    "new-tab",
+                    "--name",
+                    "docs",
+                    "--cwd",
+                    "/src/api/docs"
+                ],
+                vec!["zellij", "action", "new-tab", "--name", "logs"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mock_records_commands() {
+        let client = MockZellijClient::with_sessions(HashMap::from([
+            ("a".to_string(), true),
+            ("b".to_string(), false),
+        ]));
+
+        client.run_command("htop", &[]).unwrap();
+        client.run_command_in("b", "vim", &["."]).unwrap();
+
+        assert_eq!(client.commands("a"), vec![vec!["htop"]]);
+        assert_eq!(client.commands("b"), vec![vec!["vim", "."]]);
+        assert!(client.run_command_in("missing", "vim", &[]).is_err());
+    }
+
+    #[test]
+    fn test_mock_records_tabs_in_other_sessions() {
+        let client = MockZellijClient::with_sessions(HashMap::from([("api".to_string(), false)]));
+
+        client
+            .new_tab_in("api", Some("docs"), Some(Path::new("/src/api/docs")))
+            .unwrap();
+        client.new_tab_in("api", None, None).unwrap();
+
+        assert_eq!(
+            client.tabs_in("api"),
+            vec![
+                (
+                    Some("docs".to_string()),
+                    Some(PathBuf::from("/src/api/docs"))
+                ),
+                (None, None),
+            ]
+        );
+        // The current session's tabs are untouched
+        assert!(client.list_tabs().unwrap().is_empty());
+        assert!(client.new_tab_in("missing", None, None).is_err());
+    }
+
+    #[test]
+    fn test_client_go_to_tab_argv() {
+        let runner = MockRunner::new()
+            .with_output(RunOutput::success_with("editor\nlogs\n"))
+            .with_output(RunOutput::success_with(""))
+            .with_output(RunOutput::success_with("editor\nlogs\n"));
+        let client = ZellijClient::new().with_runner(runner);
+
+        client.go_to_tab("api", "logs").unwrap();
+        assert!(client.go_to_tab("api", "3").is_err());
+
+        assert_eq!(
+            client.runner().argvs(),
+            vec![
+                vec!["zellij", "--session", "api", "action", "query-tab-names"],
+                vec![
+                    "zellij",
+                    "--session",
+                    "api",
+                    "action",
+                    "go-to-tab-name",
+                    "logs"
+                ],
+                // A missing tab stops before going anywhere
+                vec!["zellij", "--session", "api", "action", "query-tab-names"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mock_is_attached() {
+        let client = MockZellijClient::with_sessions(HashMap::from([("main".to_string(), true)]));
+        assert!(!client.is_attached());
+        assert!(client.with_attached(true).is_attached());
+    }
+
+    #[test]
+    fn test_mock_go_to_tab() {
+        let client = two_tabs();
+        client
+            .new_session_detached("work", &ZellijOptions::default())
+            .unwrap();
+
+        client.go_to_tab("work", "shell").unwrap();
+        let tabs = client.list_tabs().unwrap();
+        assert!(!tabs[0].is_active);
+        assert!(tabs[1].is_active);
+
+        client.go_to_tab("work", "1").unwrap();
+        assert!(client.list_tabs().unwrap()[0].is_active);
+    }
+
+    #[test]
+    fn test_mock_go_to_missing_tab() {
+        let client = two_tabs();
+        client
+            .new_session_detached("work", &ZellijOptions::default())
+            .unwrap();
+
+        assert!(client.go_to_tab("work", "3").is_err());
+        assert!(client.go_to_tab("work", "logs").is_err());
+        assert!(client.go_to_tab("other", "1").is_err());
+        assert!(client.list_tabs().unwrap()[0].is_active);
+    }
+
+    #[test]
+    fn test_mock_move_pane_to_missing_tab() {
+        let client = two_tabs();
+
+        assert!(client.move_pane_to_tab(2).is_err());
+        assert_eq!(client.list_tabs().unwrap()[0].panes.len(), 2);
+    }
+
+    fn sorted_names(sessions: Vec<Session>) -> Vec<String> {
+        let mut names: Vec<String> = sessions.into_iter().map(|s| s.name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_list_sessions_matching() {
+        let client = MockZellijClient::with_sessions(HashMap::from([
+            ("api-server".to_string(), false),
+            ("API-client".to_string(), false),
+            ("web".to_string(), true),
+        ]));
+
+        assert_eq!(
+            sorted_names(client.list_sessions_matching("api").unwrap()),
+            vec!["API-client", "api-server"]
+        );
+        assert_eq!(
+            sorted_names(client.list_sessions_matching("Server").unwrap()),
+            vec!["api-server"]
+        );
+        assert_eq!(
+            sorted_names(client.list_sessions_matching("").unwrap()).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_list_sessions_matching_no_match() {
+        let client = MockZellijClient::with_sessions(HashMap::from([("web".to_string(), true)]));
+
+        assert!(client.list_sessions_matching("api").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_mock_attach_session_with_existing() {
+        let client = MockZellijClient::with_sessions(HashMap::from([
+            ("work".to_string(), true),
+            ("other".to_string(), false),
+        ]));
+        let options = ZellijOptions {
+            new_session_with_layout: Some("compact".to_string()),
+            ..ZellijOptions::default()
+        };
+
+        client.attach_session_with("other", true, &options).unwrap();
+
+        assert_eq!(client.current_session().unwrap(), Some("other".to_string()));
+        assert_eq!(client.list_sessions().unwrap().len(), 2);
+        // Options only apply to sessions that get created
+        assert!(client.session_options("other").is_none());
+    }
+
+    #[test]
+    fn test_mock_attach_session_with_create_missing() {
+        let client = MockZellijClient::with_sessions(HashMap::from([("work".to_string(), true)]));
+        let options = ZellijOptions {
+            new_session_with_layout: Some("compact".to_string()),
+            ..ZellijOptions::default()
+        };
+
+        assert!(client.attach_session_with("new", false, &options).is_err());
+        assert_eq!(client.list_sessions().unwrap().len(), 1);
+
+        client.attach_session_with("new", true, &options).unwrap();
+
+        assert_eq!(client.current_session().unwrap(), Some("new".to_string()));
+        assert_eq!(
+            client
+                .session_options("new")
+                .unwrap()
+                .new_session_with_layout
+                .as_deref(),
+            Some("compact")
+        );
+    }
+
+    #[test]
+    fn test_list_resurrectable() {
+        let client = MockZellijClient::with_sessions(HashMap::from([
+            ("running".to_string(), true),
+            ("dead".to_string(), false),
+            ("gone".to_string(), false),
+        ]))
+        .with_exited(&["dead", "gone"]);
+
+        let sessions = client.list_resurrectable().unwrap();
+        assert!(sessions.iter().all(|s| s.is_exited));
+        assert_eq!(sorted_names(sessions), vec!["dead", "gone"]);
+    }
+
+    #[test]
+    fn test_list_resurrectable_none_exited() {
+        let client =
+            MockZellijClient::with_sessions(HashMap::from([("running".to_string(), true)]));
+
+        assert!(client.list_resurrectable().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_mock_new_session_detached_keeps_current() {
+        let client = MockZellijClient::with_sessions(HashMap::from([("main".to_string(), true)]));
+
+        client
+            .new_session_detached("background", &ZellijOptions::default())
+            .unwrap();
+
+        let sessions = client.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 2);
+        let background = sessions.iter().find(|s| s.name == "background").unwrap();
+        assert!(!background.is_current);
+        assert_eq!(client.current_session().unwrap(), Some("main".to_string()));
+    }
+
+    #[test]
+    fn test_new_session_command() {
+        let options = ZellijOptions {
+            new_session_with_layout: Some("compact".to_string()),
+            ..Default::default()
+        };
+        let cmd = new_session_command("my project", &options);
+
+        // This is what --print-cmd shows before creating the session
+        assert_eq!(
+            zesh_proc::format_command(&cmd),
+            "zellij --session 'my project' --new-session-with-layout compact"
+        );
+    }
+
+    #[test]
+    fn test_parse_session_list() {
+        let output = "\
+alpha [Created 2h 3m ago] (current)
+beta [Created 10s ago]
+gamma [Created 1day ago] (EXITED - attach to resurrect)
+";
+        let sessions = parse_session_list(output).unwrap();
+        assert_eq!(
+            sessions,
+            vec![
+                Session {
+                    name: "alpha".to_string(),
+                    is_current: true,
+                    is_exited: false,
+                    created: Some(Duration::from_secs(2 * 3_600 + 3 * 60)),
+                },
+                Session {
+                    name: "beta".to_string(),
+                    is_current: false,
+                    is_exited: false,
+                    created: Some(Duration::from_secs(10)),
+                },
+                Session {
+                    name: "gamma".to_string(),
+                    is_current: false,
+                    is_exited: true,
+                    created: Some(Duration::from_secs(86_400)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_session_output_invalid_utf8() {
+        let output = b"caf\xe9 [Created 5s ago] (current)\nbeta [Created 10s ago]\n";
+        let sessions = parse_session_output(output).unwrap();
+
+        let names: Vec<&str> = sessions.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["caf\u{fffd}", "beta"]);
+        assert!(sessions[0].is_current);
+    }
+
+    #[test]
+    fn test_parse_created_ages() {
+        let age = |line: &str| parse_created(line).map(|d| d.as_secs());
+
+        assert_eq!(age("a [Created 0s ago]"), Some(0));
+        assert_eq!(age("a [Created 45s ago]"), Some(45));
+        assert_eq!(age("a [Created 5m 12s ago]"), Some(5 * 60 + 12));
+        assert_eq!(
+            age("a [Created 3days 4h ago]"),
+            Some(3 * 86_400 + 4 * 3_600)
+        );
+        assert_eq!(age("a [Created 2weeks ago]"), Some(2 * 604_800));
+        assert_eq!(
+            age("a [Created 1year 2months ago] (EXITED - attach to resurrect)"),
+            Some(31_557_600 + 2 * 2_630_016)
+        );
+        assert_eq!(age("a [Created 1h 30m 12s 345ms ago]"), Some(5_412));
+    }
+
+    #[test]
+    fn test_parse_created_unknown() {
+        assert_eq!(parse_created("alpha (current)"), None);
+        assert_eq!(parse_created("alpha [Created ago]"), None);
+        assert_eq!(parse_created("alpha [Created a while ago]"), None);
+        assert_eq!(parse_created("alpha [Created 5fortnights ago]"), None);
+    }
+
+    const LAYOUT_DUMP: &str = r#"layout {
+    cwd "/home/user"
+    tab name="editor" hide_floating_panes=true {
+        pane size=1 borderless=true {
+            plugin location="zellij:tab-bar"
+        }
+        pane split_direction="vertical" {
+            pane cwd="src" name="vim"
+            pane cwd="src" size="30%"
+        }
+        pane size=2 borderless=true {
+            plugin location="zellij:status-bar"
+        }
+    }
+    tab name="shell { }" focus=true {
+        pane size=1 borderless=true { plugin location="zellij:tab-bar"; }
+        pane command="htop" focus=true {
+            args "-d" "10"
+        }
+        floating_panes {
+            pane x=10 y=5
+        }
+    }
+    new_tab_template {
+        pane
+    }
+    swap_tiled_layout name="vertical" {
+        tab max_panes=5 {
+            pane
+        }
+    }
+}
+"#;
+
+    #[test]
+    fn test_parse_layout_tabs() {
+        let tabs = parse_layout_tabs(LAYOUT_DUMP).unwrap();
+        assert_eq!(tabs.len(), 2);
+
+        assert_eq!(tabs[0].position, 0);
+        assert_eq!(tabs[0].name.as_deref(), Some("editor"));
+        assert!(!tabs[0].is_active);
+        let plugins: Vec<bool> = tabs[0].panes.iter().map(|p| p.is_plugin).collect();
+        assert_eq!(plugins, vec![true, false, false, true]);
+        assert_eq!(tabs[0].panes[1].name.as_deref(), Some("vim"));
+        assert_eq!(tabs[0].panes[0].cwd, None);
+        assert_eq!(tabs[0].panes[2].cwd, Some(PathBuf::from("/home/user/src")));
+
+        assert_eq!(tabs[1].name.as_deref(), Some("shell { }"));
+        assert!(tabs[1].is_active);
+        assert_eq!(tabs[1].panes.len(), 3);
+        assert!(tabs[1].panes[0].is_plugin);
+        assert!(tabs[1].panes[1].is_focused);
+        assert_eq!(tabs[1].panes[2].id, 2);
+        // Panes without a cwd of their own run in the layout's
+        assert_eq!(tabs[1].panes[1].cwd, Some(PathBuf::from("/home/user")));
+    }
+
+    #[test]
+    fn test_parse_layout_pane_cwds() {
+        let tabs = parse_layout_tabs(
+            r#"layout {
+    tab cwd="/srv" focus=true {
+        pane cwd="api" split_direction="vertical" {
+            pane cwd="tests" focus=true
+            pane cwd="/etc"
+        }
+        pane
+    }
+}
+"#,
+        )
+        .unwrap();
+
+        let cwds: Vec<Option<PathBuf>> = tabs[0].panes.iter().map(|p| p.cwd.clone()).collect();
+        assert_eq!(
+            cwds,
+            vec![
+                Some(PathBuf::from("/srv/api/tests")),
+                Some(PathBuf::from("/etc")),
+                Some(PathBuf::from("/srv")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_pane_cwds() {
+        // Plugins and the tab bar are numbered but have no directory
+        assert_eq!(
+            parse_pane_cwds(LAYOUT_DUMP).unwrap(),
+            vec![
+                (1, PathBuf::from("/home/user/src")),
+                (2, PathBuf::from("/home/user/src")),
+                (5, PathBuf::from("/home/user")),
+                (6, PathBuf::from("/home/user")),
+            ]
+        );
+
+        assert_eq!(
+            parse_pane_cwds("layout {\n    tab {\n        pane\n    }\n}\n").unwrap(),
+            vec![]
+        );
+        assert!(parse_pane_cwds("tab {\n").is_err());
+    }
+
+    #[test]
+    fn test_mock_pane_cwds() {
+        let cwds = vec![(0, PathBuf::from("/src/api")), (3, PathBuf::from("/tmp"))];
+        let client = MockZellijClient::new().with_pane_cwds(cwds.clone());
+
+        assert_eq!(client.pane_cwds().unwrap(), cwds);
+        assert_eq!(MockZellijClient::new().pane_cwds().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_parse_layout_tabs_invalid() {
+        assert!(matches!(
+            parse_layout_tabs("tab {\n"),
+            Err(ZellijError::OutputParsing(_))
+        ));
+        assert!(matches!(
+            parse_layout_tabs("}\n"),
+            Err(ZellijError::OutputParsing(_))
+        ));
+        assert!(matches!(
+            parse_layout_tabs("pane\n"),
+            Err(ZellijError::OutputParsing(_))
+        ));
+    }
+
+    #[test]
+    fn test_mock_exited_sessions() {
+        let client = MockZellijClient::with_sessions(HashMap::from([
+            ("alive".to_string(), false),
+            ("dead".to_string(), false),
+        ]))
+        .with_exited(&["dead"]);
+
+        let sessions = client.list_sessions().unwrap();
+        let dead = sessions.iter().find(|s| s.name == "dead").unwrap();
+        let alive = sessions.iter().find(|s| s.name == "alive").unwrap();
+        assert!(dead.is_exited);
+        assert!(!alive.is_exited);
+
+        // Attaching resurrects the session
+        client.attach_session("dead").unwrap();
+        let sessions = client.list_sessions().unwrap();
+        let dead = sessions.iter().find(|s| s.name == "dead").unwrap();
+        assert!(!dead.is_exited);
+        assert!(dead.is_current);
+    }
 }
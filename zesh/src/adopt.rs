@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use zellij_rs::{ZellijError, ZellijOperations};
+
+use crate::fs::{FsError, FsOperations};
+use crate::store::{SessionStore, StoreError};
+
+#[derive(Debug, Error)]
+pub enum AdoptError {
+    #[error("Zellij error: {0}")]
+    Zellij(#[from] ZellijError),
+
+    #[error("Filesystem error: {0}")]
+    Fs(#[from] FsError),
+
+    #[error("Session store error: {0}")]
+    Store(#[from] StoreError),
+
+    #[error("No session named '{0}'")]
+    NoSuchSession(String),
+}
+
+/// Record the root of a session created outside of zesh, so `zesh root` and
+/// reconnecting from history work for it like for zesh's own sessions. An
+/// existing root is replaced. Returns the canonical root that was recorded.
+pub fn adopt_session<Z: ZellijOperations, F: FsOperations>(
+    zellij: &Z,
+    fs: &F,
+    store: &SessionStore,
+    session: &str,
+    path: &Path,
+) -> Result<PathBuf, AdoptError> {
+    let (root, _) = fs.validate_dir_path(&fs.expand_path(path))?;
+
+    let exists = zellij.list_sessions()?.iter().any(|s| s.name == session)
+        || zellij
+            .list_resurrectable()?
+            .iter()
+            .any(|s| s.name == session);
+    if !exists {
+        return Err(AdoptError::NoSuchSession(session.to_string()));
+    }
+
+    store.record(session, &root)?;
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::tests::MockFs;
+    use std::collections::HashMap;
+    use zellij_rs::MockZellijClient;
+
+    fn zellij() -> MockZellijClient {
+        MockZellijClient::with_sessions(HashMap::from([("scratch".to_string(), true)]))
+    }
+
+    #[test]
+    fn test_adopt_records_root() {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/home/src/api"), "api");
+        let store = SessionStore::new();
+
+        let root =
+            adopt_session(&zellij(), &fs, &store, "scratch", Path::new("~/src/api")).unwrap();
+
+        assert_eq!(root, PathBuf::from("/mock/home/src/api"));
+        assert_eq!(store.lookup("scratch"), Some(root));
+    }
+
+    #[test]
+    fn test_adopt_replaces_root_and_keeps_tags() {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/web"), "web");
+        let store = SessionStore::new();
+        store.record("scratch", Path::new("/mock/old")).unwrap();
+        store.tag("scratch", "work").unwrap();
+
+        adopt_session(&zellij(), &fs, &store, "scratch", Path::new("/mock/web")).unwrap();
+
+        assert_eq!(store.lookup("scratch"), Some(PathBuf::from("/mock/web")));
+        assert_eq!(store.tags("scratch"), vec!["work"]);
+    }
+
+    #[test]
+    fn test_adopt_rejects_missing_or_non_directories() {
+        let fs = MockFs::new();
+        fs.with_file(Path::new("/mock/notes.txt"));
+        let store = SessionStore::new();
+
+        for path in ["/mock/missing", "/mock/notes.txt"] {
+            let result = adopt_session(&zellij(), &fs, &store, "scratch", Path::new(path));
+            assert!(matches!(result, Err(AdoptError::Fs(_))));
+        }
+        assert_eq!(store.lookup("scratch"), None);
+    }
+
+    #[test]
+    fn test_adopt_unknown_session() {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/web"), "web");
+        let store = SessionStore::new();
+
+        let result = adopt_session(&zellij(), &fs, &store, "other", Path::new("/mock/web"));
+
+        assert!(matches!(result, Err(AdoptError::NoSuchSession(name)) if name == "other"));
+        assert_eq!(store.lookup("other"), None);
+    }
+}
@@ -0,0 +1,151 @@
+use zellij_rs::ZellijOperations;
+
+/// The terminal multiplexer zesh manages sessions of.
+///
+/// zellij is the only backend so far, and every [`ZellijOperations`]
+/// implementation is one. The services that create and connect to sessions
+/// are generic over this trait rather than zellij's client, so another
+/// multiplexer like tmux can be added by implementing the same operations:
+/// tabs map to tmux windows and panes to panes.
+pub trait SessionBackend: ZellijOperations {}
+
+impl<T: ZellijOperations> SessionBackend for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clone::{CloneOptions, CloneService};
+    use crate::connection::ConnectService;
+    use crate::fs::tests::MockFs;
+    use std::path::{Path, PathBuf};
+    use zellij_rs::options::ZellijOptions;
+    use zellij_rs::{Session, Tab, ZellijResult};
+    use zesh_git::MockGit;
+    use zox_rs::MockZoxideClient;
+
+    /// A backend with no sessions that accepts everything, standing in for
+    /// a multiplexer other than zellij
+    struct MockBackend;
+
+    impl ZellijOperations for MockBackend {
+        fn list_sessions(&self) -> ZellijResult<Vec<Session>> {
+            Ok(Vec::new())
+        }
+
+        fn is_attached(&self) -> bool {
+            false
+        }
+
+        fn attach_session(&self, _: &str) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn attach_session_with(&self, _: &str, _: bool, _: &ZellijOptions) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn new_session(&self, _: &str, _: &ZellijOptions) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn new_session_detached(&self, _: &str, _: &ZellijOptions) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn kill_session(&self, _: &str) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn rename_session(&self, _: &str) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn detach(&self) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn switch_session(&self, _: &str) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn list_tabs(&self) -> ZellijResult<Vec<Tab>> {
+            Ok(Vec::new())
+        }
+
+        fn pane_cwds(&self) -> ZellijResult<Vec<(u32, PathBuf)>> {
+            Ok(Vec::new())
+        }
+
+        fn new_tab(&self, _: Option<&str>, _: Option<&Path>) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn new_tab_in(&self, _: &str, _: Option<&str>, _: Option<&Path>) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn rename_tab(&self, _: &str) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn close_tab(&self) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn go_to_tab(&self, _: &str, _: &str) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn move_pane_to_tab(&self, _: u32) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn toggle_floating_panes(&self) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn toggle_pane_embed_or_floating(&self) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn run_command(&self, _: &str, _: &[&str]) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn run_command_in(&self, _: &str, _: &str, _: &[&str]) -> ZellijResult<()> {
+            Ok(())
+        }
+
+        fn version(&self) -> ZellijResult<String> {
+            Ok("mock 1.0".to_string())
+        }
+    }
+
+    fn assert_backend<B: SessionBackend>(_: &B) {}
+
+    #[test]
+    fn test_services_work_with_other_backends() {
+        assert_backend(&MockBackend);
+
+        let fs = MockFs::new();
+        // MockGit puts every directory in this repository
+        fs.with_directory(Path::new("/mock/repo/top-level"), "top-level");
+        let connect = ConnectService::new(MockBackend, MockZoxideClient::new(), fs, MockGit::new());
+        let outcome = connect
+            .connect_to_directory("/mock/repo/top-level", &ZellijOptions::default())
+            .unwrap();
+        assert!(outcome.created);
+        assert_eq!(outcome.session, "top-level");
+
+        let git = MockGit::new();
+        let clone = CloneService::new(MockBackend, MockZoxideClient::new(), MockFs::new(), git);
+        let options = CloneOptions {
+            path: Some(PathBuf::from("/src")),
+            force: true,
+            ..CloneOptions::default()
+        };
+        clone
+            .clone_repo("user/web", &options, &ZellijOptions::default())
+            .unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+use std::cell::RefCell;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use zox_rs::{ZoxideEntry, ZoxideOperations, ZoxideResult};
+
+use crate::frecency::matches_keywords;
+
+/// How long a [`CachedZoxide`] reuses a listing by default
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(2);
+
+/// Wraps a zoxide client so that listings and queries made within `ttl` of
+/// each other share one `list()`, instead of spawning zoxide every time,
+/// e.g. for a picker that queries on every keystroke. Queries are matched
+/// against the cached listing the way zoxide matches keywords, in order and
+/// ignoring case. Adding a path drops the cache.
+pub struct CachedZoxide<X: ZoxideOperations> {
+    inner: X,
+    ttl: Duration,
+    cache: RefCell<Option<(Instant, Vec<ZoxideEntry>)>>,
+}
+
+impl<X: ZoxideOperations> CachedZoxide<X> {
+    /// Cache listings of `inner` for [`DEFAULT_CACHE_TTL`]
+    pub fn new(inner: X) -> Self {
+        Self {
+            inner,
+            ttl: DEFAULT_CACHE_TTL,
+            cache: RefCell::new(None),
+        }
+    }
+
+    /// Reuse listings for `ttl` instead
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// The wrapped client
+    pub fn inner(&self) -> &X {
+        &self.inner
+    }
+
+    /// The listing as of `now`, from the cache if it's fresh enough
+    fn list_at(&self, now: Instant) -> ZoxideResult<Vec<ZoxideEntry>> {
+        if let Some((listed, entries)) = &*self.cache.borrow()
+            && now.saturating_duration_since(*listed) < self.ttl
+        {
+            return Ok(entries.clone());
+        }
+
+        let entries = self.inner.list()?;
+        *self.cache.borrow_mut() = Some((now, entries.clone()));
+        Ok(entries)
+    }
+
+    fn query_at(&self, keywords: &[&str], now: Instant) -> ZoxideResult<Vec<ZoxideEntry>> {
+        let mut entries = self.list_at(now)?;
+        entries.retain(|entry| matches_keywords(&entry.path, keywords));
+        entries.sort_by(|a, b| b.score.total_cmp(&a.score));
+        Ok(entries)
+    }
+
+    fn invalidate(&self) {
+        self.cache.borrow_mut().take();
+    }
+}
+
+impl<X: ZoxideOperations> ZoxideOperations for CachedZoxide<X> {
+    fn add<P: AsRef<Path>>(&self, path: P) -> ZoxideResult<()> {
+        self.invalidate();
+        self.inner.add(path)
+    }
+
+    fn add_with_score<P: AsRef<Path>>(&self, path: P, score: f64) -> ZoxideResult<()> {
+        self.invalidate();
+        self.inner.add_with_score(path, score)
+    }
+
+    fn list(&self) -> ZoxideResult<Vec<ZoxideEntry>> {
+        self.list_at(Instant::now())
+    }
+
+    fn query(&self, keywords: &[&str]) -> ZoxideResult<Vec<ZoxideEntry>> {
+        self.query_at(keywords, Instant::now())
+    }
+
+    fn version(&self) -> ZoxideResult<String> {
+        self.inner.version()
+    }
+
+    fn score_for<P: AsRef<Path>>(&self, path: P) -> ZoxideResult<Option<f64>> {
+        let path = path.as_ref();
+        Ok(self
+            .list()?
+            .into_iter()
+            .find(|entry| entry.path == path)
+            .map(|entry| entry.score))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::path::PathBuf;
+    use zox_rs::MockZoxideClient;
+
+    /// Counts the listings and queries that reach zoxide
+    struct CountingZoxide {
+        inner: MockZoxideClient,
+        lists: Cell<usize>,
+        queries: Cell<usize>,
+    }
+
+    impl CountingZoxide {
+        fn new() -> Self {
+            let inner = MockZoxideClient::new();
+            inner.add_with_score("/src/api", 3.0).unwrap();
+            inner.add_with_score("/src/web", 5.0).unwrap();
+            inner.add_with_score("/notes/api-docs", 1.0).unwrap();
+            Self {
+                inner,
+                lists: Cell::new(0),
+                queries: Cell::new(0),
+            }
+        }
+    }
+
+    impl ZoxideOperations for CountingZoxide {
+        fn add<P: AsRef<Path>>(&self, path: P) -> ZoxideResult<()> {
+            self.inner.add(path)
+        }
+
+        fn add_with_score<P: AsRef<Path>>(&self, path: P, score: f64) -> ZoxideResult<()> {
+            self.inner.add_with_score(path, score)
+        }
+
+        fn list(&self) -> ZoxideResult<Vec<ZoxideEntry>> {
+            self.lists.set(self.lists.get() + 1);
+            self.inner.list()
+        }
+
+        fn query(&self, keywords: &[&str]) -> ZoxideResult<Vec<ZoxideEntry>> {
+            self.queries.set(self.queries.get() + 1);
+            self.inner.query(keywords)
+        }
+
+        fn version(&self) -> ZoxideResult<String> {
+            self.inner.version()
+        }
+
+        fn score_for<P: AsRef<Path>>(&self, path: P) -> ZoxideResult<Option<f64>> {
+            self.inner.score_for(path)
+        }
+    }
+
+    fn paths(entries: Vec<ZoxideEntry>) -> Vec<PathBuf> {
+        entries.into_iter().map(|e| e.path).collect()
+    }
+
+    #[test]
+    fn test_queries_within_ttl_share_one_listing() {
+        let cached = CachedZoxide::new(CountingZoxide::new()).with_ttl(Duration::from_secs(2));
+        let start = Instant::now();
+
+        assert_eq!(
+            paths(cached.query_at(&["api"], start).unwrap()),
+            vec![PathBuf::from("/src/api"), PathBuf::from("/notes/api-docs")]
+        );
+        assert_eq!(
+            paths(
+                cached
+                    .query_at(&["src"], start + Duration::from_secs(1))
+                    .unwrap()
+            ),
+            vec![PathBuf::from("/src/web"), PathBuf::from("/src/api")]
+        );
+        assert_eq!(
+            cached
+                .list_at(start + Duration::from_secs(1))
+                .unwrap()
+                .len(),
+            3
+        );
+
+        assert_eq!(cached.inner().lists.get(), 1);
+        assert_eq!(cached.inner().queries.get(), 0);
+    }
+
+    #[test]
+    fn test_listing_again_after_ttl() {
+        let cached = CachedZoxide::new(CountingZoxide::new()).with_ttl(Duration::from_secs(2));
+        let start = Instant::now();
+
+        cached.query_at(&["api"], start).unwrap();
+        cached
+            .query_at(&["api"], start + Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(cached.inner().lists.get(), 2);
+
+        // The new listing is what's reused from then on
+        cached
+            .query_at(&["api"], start + Duration::from_secs(3))
+            .unwrap();
+        assert_eq!(cached.inner().lists.get(), 2);
+    }
+
+    #[test]
+    fn test_adding_drops_the_cache() {
+        let cached = CachedZoxide::new(CountingZoxide::new());
+        let start = Instant::now();
+
+        cached.list_at(start).unwrap();
+        cached.add("/src/new").unwrap();
+
+        assert_eq!(cached.list_at(start).unwrap().len(), 4);
+        assert_eq!(cached.inner().lists.get(), 2);
+    }
+}
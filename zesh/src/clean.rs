@@ -0,0 +1,323 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use thiserror::Error;
+use zellij_rs::{ZellijError, ZellijOperations};
+
+use crate::frecency::{FrecencyError, FrecencyStore};
+use crate::fs::{FsOperations, RealFs};
+use crate::history::{History, HistoryEntry, HistoryError};
+use crate::prompt::{Prompt, StdinPrompt};
+use crate::store::{SessionStore, StoreError};
+
+#[derive(Debug, Error)]
+pub enum CleanError {
+    #[error("Zellij error: {0}")]
+    Zellij(#[from] ZellijError),
+
+    #[error("Session store error: {0}")]
+    Store(#[from] StoreError),
+
+    #[error("History error: {0}")]
+    History(#[from] HistoryError),
+
+    #[error("Frecency store error: {0}")]
+    Frecency(#[from] FrecencyError),
+}
+
+/// State zesh kept that no longer points at anything
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StaleEntries {
+    /// Recorded sessions zellij neither runs nor can resurrect
+    pub sessions: Vec<String>,
+    /// History entries for directories that were deleted
+    pub history: Vec<HistoryEntry>,
+    /// Ranked directories that were deleted
+    pub directories: Vec<PathBuf>,
+}
+
+impl StaleEntries {
+    /// How many entries are stale
+    pub fn len(&self) -> usize {
+        self.sessions.len() + self.history.len() + self.directories.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// One line describing each stale entry
+    pub fn lines(&self) -> Vec<String> {
+        let sessions = self
+            .sessions
+            .iter()
+            .map(|name| format!("session '{}' no longer exists", name));
+        let history = self.history.iter().map(|entry| {
+            let path = entry.path.clone().unwrap_or_default();
+            format!(
+                "history entry '{}' points at deleted {}",
+                entry.name,
+                path.display()
+            )
+        });
+        let directories = self
+            .directories
+            .iter()
+            .map(|path| format!("ranked directory {} was deleted", path.display()));
+        sessions.chain(history).chain(directories).collect()
+    }
+}
+
+/// Service for finding and removing stale entries from the session store,
+/// the history and zesh's frecency store
+pub struct CleanService<Z, F = RealFs, P = StdinPrompt>
+where
+    Z: ZellijOperations,
+    F: FsOperations,
+    P: Prompt,
+{
+    zellij: Z,
+    fs: F,
+    prompt: P,
+    store: SessionStore,
+    history: History,
+    frecency: FrecencyStore,
+}
+
+impl<Z: ZellijOperations> CleanService<Z> {
+    /// Create a new clean service
+    pub fn new(zellij: Z) -> Self {
+        Self {
+            zellij,
+            fs: RealFs::new(),
+            prompt: StdinPrompt::default(),
+            store: SessionStore::new(),
+            history: History::new(),
+            frecency: FrecencyStore::new(),
+        }
+    }
+}
+
+impl<Z, F, P> CleanService<Z, F, P>
+where
+    Z: ZellijOperations,
+    F: FsOperations,
+    P: Prompt,
+{
+    /// Clean the given session store
+    pub fn with_store(mut self, store: SessionStore) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Clean the given history
+    pub fn with_history(mut self, history: History) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Clean the given frecency store
+    pub fn with_frecency(mut self, frecency: FrecencyStore) -> Self {
+        self.frecency = frecency;
+        self
+    }
+
+    /// Use the given filesystem to check directories still exist
+    pub fn with_fs<F2: FsOperations>(self, fs: F2) -> CleanService<Z, F2, P> {
+        CleanService {
+            zellij: self.zellij,
+            fs,
+            prompt: self.prompt,
+            store: self.store,
+            history: self.history,
+            frecency: self.frecency,
+        }
+    }
+
+    /// Use the given prompt to confirm removing entries
+    pub fn with_prompt<P2: Prompt>(self, prompt: P2) -> CleanService<Z, F, P2> {
+        CleanService {
+            zellij: self.zellij,
+            fs: self.fs,
+            prompt,
+            store: self.store,
+            history: self.history,
+            frecency: self.frecency,
+        }
+    }
+
+    /// Find the stale entries, without removing anything
+    pub fn find_stale(&self) -> Result<StaleEntries, CleanError> {
+        let mut known: HashSet<String> = self
+            .zellij
+            .list_sessions()?
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        known.extend(
+            self.zellij
+                .list_resurrectable()?
+                .into_iter()
+                .map(|s| s.name),
+        );
+
+        let sessions = self
+            .store
+            .names()
+            .into_iter()
+            .filter(|name| !known.contains(name))
+            .collect();
+
+        // Entries without a path are for sessions zesh didn't know the
+        // directory of, there's nothing to check
+        let history = self
+            .history
+            .recent()
+            .into_iter()
+            .filter(|entry| entry.path.as_ref().is_some_and(|p| !self.fs.exists(p)))
+            .collect();
+
+        let directories = self
+            .frecency
+            .paths()
+            .into_iter()
+            .filter(|path| !self.fs.exists(path))
+            .collect();
+
+        Ok(StaleEntries {
+            sessions,
+            history,
+            directories,
+        })
+    }
+
+    /// Ask to remove the given stale entries, and remove them if the user
+    /// agrees. Returns false if they didn't.
+    pub fn clean(&self, stale: &StaleEntries) -> Result<bool, CleanError> {
+        if stale.is_empty() {
+            return Ok(true);
+        }
+        if !self
+            .prompt
+            .confirm(&format!("Remove {} stale entries?", stale.len()))
+        {
+            return Ok(false);
+        }
+
+        for name in &stale.sessions {
+            self.store.remove(name)?;
+        }
+        if !stale.history.is_empty() {
+            self.history
+                .retain(|entry| !stale.history.contains(entry))?;
+        }
+        for path in &stale.directories {
+            self.frecency.remove(path)?;
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::tests::MockFs;
+    use crate::prompt::tests::MockPrompt;
+    use std::collections::HashMap;
+    use std::path::Path;
+    use zellij_rs::MockZellijClient;
+    use zox_rs::ZoxideOperations;
+
+    /// A store with a running session, an exited one and one that's gone,
+    /// and history and rankings for a directory that exists and one that
+    /// was deleted
+    fn stale_service<P: Prompt>(prompt: P) -> CleanService<MockZellijClient, MockFs, P> {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([
+            ("api".to_string(), false),
+            ("dead".to_string(), false),
+        ]))
+        .with_exited(&["dead"]);
+
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/src/api"), "api");
+
+        let store = SessionStore::new();
+        for name in ["api", "dead", "gone"] {
+            store.record(name, &Path::new("/src").join(name)).unwrap();
+        }
+
+        let history = History::new();
+        history.record("api", Some(Path::new("/src/api"))).unwrap();
+        history
+            .record("gone", Some(Path::new("/src/gone")))
+            .unwrap();
+        history.record("remote", None).unwrap();
+
+        let frecency = FrecencyStore::new();
+        frecency.add("/src/api").unwrap();
+        frecency.add("/src/gone").unwrap();
+
+        CleanService::new(zellij)
+            .with_fs(fs)
+            .with_store(store)
+            .with_history(history)
+            .with_frecency(frecency)
+            .with_prompt(prompt)
+    }
+
+    #[test]
+    fn test_find_stale() {
+        let service = stale_service(MockPrompt::yes());
+
+        let stale = service.find_stale().unwrap();
+
+        assert_eq!(stale.sessions, vec!["gone"]);
+        let history: Vec<&str> = stale.history.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(history, vec!["gone"]);
+        assert_eq!(stale.directories, vec![PathBuf::from("/src/gone")]);
+        assert_eq!(stale.len(), 3);
+        assert_eq!(stale.lines()[0], "session 'gone' no longer exists");
+    }
+
+    #[test]
+    fn test_clean_removes_stale_entries() {
+        let service = stale_service(MockPrompt::yes());
+
+        let stale = service.find_stale().unwrap();
+        assert!(service.clean(&stale).unwrap());
+
+        assert_eq!(service.store.names(), vec!["api", "dead"]);
+        let history: Vec<String> = service
+            .history
+            .recent()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(history, vec!["remote", "api"]);
+        assert_eq!(service.frecency.paths(), vec![PathBuf::from("/src/api")]);
+        assert!(service.find_stale().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_clean_declined_keeps_everything() {
+        let service = stale_service(MockPrompt::no());
+
+        let stale = service.find_stale().unwrap();
+        assert!(!service.clean(&stale).unwrap());
+
+        assert_eq!(
+            service.prompt.questions.borrow().as_slice(),
+            ["Remove 3 stale entries?"]
+        );
+        assert_eq!(service.find_stale().unwrap(), stale);
+    }
+
+    #[test]
+    fn test_clean_nothing_stale_does_not_ask() {
+        let service = CleanService::new(MockZellijClient::new()).with_prompt(MockPrompt::no());
+
+        let stale = service.find_stale().unwrap();
+        assert!(stale.is_empty());
+        assert!(service.clean(&stale).unwrap());
+        assert!(service.prompt.questions.borrow().is_empty());
+    }
+}
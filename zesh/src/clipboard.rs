@@ -0,0 +1,107 @@
+use std::io;
+use std::process::{Command, Stdio};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClipboardError {
+    #[error("No clipboard tool found, install one of: {0}")]
+    NoTool(String),
+
+    #[error("Failed to read the clipboard: {0}")]
+    Failed(String),
+
+    #[error("The clipboard is empty")]
+    Empty,
+}
+
+/// Trait for reading the system clipboard
+pub trait Clipboard {
+    /// The text on the clipboard
+    fn read(&self) -> Result<String, ClipboardError>;
+}
+
+/// Default implementation that runs the platform's clipboard tool
+#[derive(Copy, Clone, Default)]
+pub struct SystemClipboard;
+
+impl SystemClipboard {
+    /// The commands that print the clipboard, tried in order until one of
+    /// them is installed
+    fn commands() -> Vec<(&'static str, Vec<&'static str>)> {
+        if cfg!(target_os = "macos") {
+            vec![("pbpaste", vec![])]
+        } else if cfg!(windows) {
+            vec![(
+                "powershell",
+                vec!["-NoProfile", "-Command", "Get-Clipboard"],
+            )]
+        } else {
+            vec![
+                ("wl-paste", vec!["--no-newline"]),
+                ("xclip", vec!["-selection", "clipboard", "-o"]),
+                ("xsel", vec!["--clipboard", "--output"]),
+            ]
+        }
+    }
+}
+
+impl Clipboard for SystemClipboard {
+    fn read(&self) -> Result<String, ClipboardError> {
+        let commands = Self::commands();
+        for (program, args) in &commands {
+            let output = match Command::new(program)
+                .args(args)
+                .stdin(Stdio::null())
+                .output()
+            {
+                Ok(output) => output,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(ClipboardError::Failed(format!("{}: {}", program, e))),
+            };
+
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(ClipboardError::Failed(format!(
+                    "{} exited with {}: {}",
+                    program,
+                    output.status,
+                    stderr.trim()
+                )));
+            }
+            return String::from_utf8(output.stdout)
+                .map_err(|_| ClipboardError::Failed("not UTF-8 text".to_string()));
+        }
+
+        let programs: Vec<&str> = commands.iter().map(|(program, _)| *program).collect();
+        Err(ClipboardError::NoTool(programs.join(", ")))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// A mock clipboard holding fixed text
+    #[derive(Default)]
+    pub struct MockClipboard {
+        contents: String,
+    }
+
+    impl MockClipboard {
+        pub fn with(contents: &str) -> Self {
+            Self {
+                contents: contents.to_string(),
+            }
+        }
+
+        pub fn empty() -> Self {
+            Self::default()
+        }
+    }
+
+    impl Clipboard for MockClipboard {
+        fn read(&self) -> Result<String, ClipboardError> {
+            Ok(self.contents.clone())
+        }
+    }
+}
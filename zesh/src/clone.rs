@@ -1,9 +1,20 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
 use thiserror::Error;
 use zesh_git::{Git, GitError};
+use zesh_proc::{InterruptGuard, ProcessRunner, SystemRunner};
 
-use crate::fs::{FsError, FsOperations};
-use zellij_rs::{ZellijError, ZellijOperations, options::ZellijOptions};
+use crate::backend::SessionBackend;
+use crate::clipboard::{Clipboard, ClipboardError};
+use crate::config::{CollisionPolicy, Config, DEFAULT_GIT_HOST};
+use crate::connection::{Target, next_available_name};
+use crate::fs::{FsError, FsOperations, RealFs, is_under_allowed_roots};
+use crate::layout::{LayoutError, validate_layout};
+use crate::output::Printer;
+use crate::store::SessionStore;
+use crate::template::LayoutTemplate;
+use zellij_rs::{ZellijError, options::ZellijOptions};
 use zox_rs::{ZoxideError, ZoxideOperations};
 
 #[derive(Debug, Error)]
@@ -20,30 +31,105 @@ pub enum CloneError {
     #[error("Filesystem error: {0}")]
     Fs(#[from] FsError),
 
+    #[error("{0}")]
+    Layout(#[from] LayoutError),
+
     #[error("Could not parse repository name from URL")]
     InvalidRepoUrl,
 
+    #[error("Clipboard error: {0}")]
+    Clipboard(#[from] ClipboardError),
+
+    #[error("The clipboard doesn't hold a repository URL: '{0}'")]
+    NotARepoUrl(String),
+
     #[error("Invalid path: {0}")]
     InvalidPath(String),
+
+    #[error(
+        "Refusing to clone into {0}, which is inside the git repository at {1} (use --force to clone anyway)"
+    )]
+    NestedRepo(String, String),
+
+    #[error("Session '{0}' already exists")]
+    SessionExists(String),
+
+    #[error("Clone interrupted")]
+    Interrupted,
+
+    #[error("Not inside a zellij session, which opening a tab needs")]
+    NoSession,
+
+    #[error("Refusing to clone into {0}, which isn't under any of the allowed_roots")]
+    OutsideAllowedRoots(String),
+
+    #[error("No editor to open the clone in: set `editor` in the config or $EDITOR")]
+    NoEditor,
+}
+
+/// Layout file picked up from the root of a cloned repository
+pub const REPO_LAYOUT_FILE: &str = ".zesh-layout.kdl";
+
+/// Per-invocation options for [`CloneService::clone_repo`]
+#[derive(Debug, Clone, Default)]
+pub struct CloneOptions {
+    /// Custom session name, defaults to the repo name
+    pub name: Option<String>,
+
+    /// Directory to clone into, defaults to [`Config::clone_root`] or the
+    /// current directory
+    pub path: Option<PathBuf>,
+
+    /// Clone even if the target is inside an existing git repository
+    pub force: bool,
+
+    /// Ignore the repository's [`REPO_LAYOUT_FILE`]
+    pub no_repo_layout: bool,
+
+    /// Create the session in the background instead of attaching to it
+    pub no_attach: bool,
+
+    /// How many times to retry a clone that failed on a network error
+    pub retries: u32,
+
+    /// Open the clone in a session of its own or a tab of the current one
+    pub target: Target,
+
+    /// Make a bare clone in `<repo>.git` and open a worktree of its default
+    /// branch in `<repo>/<branch>` instead of a regular checkout
+    pub bare: bool,
+
+    /// Lay out a new session with a built-in template instead of the
+    /// repository's layout file or the layout in the zellij options
+    pub template: Option<LayoutTemplate>,
+
+    /// Open [`Config::editor`] at the clone root in a new pane of a freshly
+    /// created session or tab
+    pub edit: bool,
 }
 
 /// Service for cloning git repositories and setting up zellij sessions
-pub struct CloneService<Z, X, F, G>
+pub struct CloneService<Z, X, F, G, R = SystemRunner>
 where
-    Z: ZellijOperations,
+    Z: SessionBackend,
     X: ZoxideOperations,
     F: FsOperations,
     G: Git,
+    R: ProcessRunner,
 {
     zellij: Z,
     zoxide: X,
     fs: F,
     git: G,
+    runner: R,
+    store: SessionStore,
+    config: Config,
+    printer: Printer,
 }
 
 impl<Z, X, F, G> CloneService<Z, X, F, G>
 where
-    Z: ZellijOperations,
+    Z: SessionBackend,
     X: ZoxideOperations,
     F: FsOperations,
     G: Git,
@@ -54,150 +140,1923 @@ where
             zoxide,
             fs,
             git,
+            runner: SystemRunner::default(),
+            store: SessionStore::new(),
+            config: Config::default(),
+            printer: Printer::default(),
+        }
+    }
+}
+
+impl<Z, X, F, G, R> CloneService<Z, X, F, G, R>
+where
+    Z: SessionBackend,
+    X: ZoxideOperations,
+    F: FsOperations,
+    G: Git,
+    R: ProcessRunner,
+{
+    /// Use the given user configuration
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Use the given store to remember session roots
+    pub fn with_store(mut self, store: SessionStore) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Print informational messages with the given printer
+    pub fn with_printer(mut self, printer: Printer) -> Self {
+        self.printer = printer;
+        self
+    }
+
+    /// Use the given runner for hook commands
+    pub fn with_runner<R2: ProcessRunner>(self, runner: R2) -> CloneService<Z, X, F, G, R2> {
+        CloneService {
+            zellij: self.zellij,
+            zoxide: self.zoxide,
+            fs: self.fs,
+            git: self.git,
+            runner,
+            store: self.store,
+            config: self.config,
+            printer: self.printer,
+        }
+    }
+
+    /// The URL to clone `input` from. A relative path to a local repository
+    /// like `vendor/lib` looks just like `user/repo` shorthand, so one that
+    /// exists is cloned from instead, made absolute since git runs in the
+    /// clone's parent directory. Anything else is expanded as shorthand.
+    fn resolve_repo_url(&self, input: &str) -> Result<String, CloneError> {
+        let path = Path::new(input.trim());
+        if path.is_relative()
+            && !input.contains(':')
+            && let Ok(local) = self.fs.logical_path(&self.fs.expand_path(path))
+            && self.fs.is_dir(&local)
+            && let Some(local) = local.to_str()
+        {
+            return Ok(local.to_string());
+        }
+        Ok(normalize_repo_url_with_host(
+            input,
+            &self.config.default_host,
+        ))
+    }
+
+    /// Clone a git repository and create a zellij session or tab for it
+    pub fn clone_repo(
+        &self,
+        repo_url: &str,
+        options: &CloneOptions,
+        zellij_options: &ZellijOptions,
+    ) -> Result<(), CloneError> {
+        let repo_url = &self.resolve_repo_url(repo_url)?;
+        let repo_name = extract_repo_name(repo_url)?;
+        let name = options.name.as_deref().unwrap_or(repo_name);
+
+        // Check everything that can fail before cloning, so it fails without
+        // leaving a clone behind
+        let editor = if options.edit {
+            Some(self.editor()?)
+        } else {
+            None
+        };
+        if options.target == Target::Session
+            && options.template.is_none()
+            && let Some(layout) = &zellij_options.new_session_with_layout
+        {
+            validate_layout(&self.fs, layout)?;
+        }
+        let session = match options.target {
+            Target::Session => Some(self.settle_session_name(name)?),
+            Target::Tab => {
+                if self.zellij.current_session()?.is_none() {
+                    return Err(CloneError::NoSession);
+                }
+                None
+            }
+        };
+
+        let (parent_dir, in_clone_root) = match (&options.path, &self.config.clone_root) {
+            (Some(path), _) => (path.clone(), false),
+            (None, Some(root)) => {
+                let mut dir = self.fs.expand_path(root);
+                if self.config.clone_by_host
+                    && let Some(owner_dir) = repo_owner_dir(repo_url)
+                {
+                    dir.push(owner_dir);
+                }
+                (dir, true)
+            }
+            (None, None) => (self.current_dir_or_home()?, false),
+        };
+
+        let clone_dir = if options.bare {
+            format!("{}.git", repo_name)
+        } else {
+            repo_name.to_string()
+        };
+        let clone_path = parent_dir.join(&clone_dir);
+        if !is_under_allowed_roots(&self.fs, &clone_path, &self.config.allowed_roots) {
+            return Err(CloneError::OutsideAllowedRoots(
+                clone_path.display().to_string(),
+            ));
+        }
+        if in_clone_root {
+            // git runs in the parent, so it has to exist
+            self.fs.ensure_writable_dir(&parent_dir)?;
+        }
+        let parent_dir_str = parent_dir
+            .to_str()
+            .ok_or_else(|| CloneError::InvalidPath(parent_dir.display().to_string()))?;
+
+        // Cloning inside another repo is usually a mistake
+        if let Ok((true, enclosing_repo)) = self.git.show_top_level(parent_dir_str) {
+            if !options.force {
+                return Err(CloneError::NestedRepo(
+                    clone_path.display().to_string(),
+                    enclosing_repo,
+                ));
+            }
+            self.printer.warn(format_args!(
+                "cloning into {}, which is inside the git repository at {}",
+                clone_path.display(),
+                enclosing_repo
+            ));
+        }
+
+        self.printer.info(format_args!(
+            "Cloning {} into {}...",
+            repo_url,
+            clone_path.display()
+        ));
+        // Only a directory this run creates is removed after Ctrl-C
+        let created = !self.fs.exists(&clone_path);
+        let result = {
+            let _guard = InterruptGuard::new();
+            self.clone_with_retry(
+                repo_url,
+                parent_dir_str,
+                &clone_dir,
+                options.bare,
+                options.retries,
+            )
+        };
+        if result.is_err() && self.runner.interrupted() {
+            if created {
+                self.printer.info(format_args!(
+                    "Removing partial clone {}",
+                    clone_path.display()
+                ));
+                self.fs.remove_dir_all(&clone_path)?;
+            }
+            return Err(CloneError::Interrupted);
+        }
+        result?;
+
+        let clone_path = if options.bare {
+            self.add_default_worktree(&clone_path, &parent_dir.join(repo_name))?
+        } else {
+            // Only informational, so a branch git can't name isn't an error
+            if let Some(clone_path_str) = clone_path.to_str()
+                && let Ok(branch) = self.git.default_branch(clone_path_str)
+            {
+                self.printer
+                    .info(format_args!("Checked out branch '{}'", branch));
+            }
+            clone_path
+        };
+
+        match &session {
+            Some((session_name, true)) => self.printer.info(format_args!(
+                "Session '{}' already exists, using it",
+                session_name
+            )),
+            Some((session_name, false)) => self.printer.info(format_args!(
+                "Creating new session '{}' at {}",
+                session_name,
+                clone_path.display()
+            )),
+            None => self.printer.info(format_args!(
+                "Opening new tab '{}' at {}",
+                name,
+                clone_path.display()
+            )),
+        }
+
+        self.fs.set_current_dir(&clone_path)?;
+
+        if let Some(command) = &self.config.post_clone {
+            self.run_post_clone(command)?;
+        }
+
+        match &session {
+            Some((session_name, attach_existing)) => self.open_session(
+                session_name,
+                *attach_existing,
+                &clone_path,
+                options,
+                zellij_options,
+                editor.as_deref(),
+            )?,
+            None => {
+                self.zellij.new_tab(Some(name), Some(&clone_path))?;
+                if let Some(editor) = &editor {
+                    self.open_editor(None, editor, &clone_path)?;
+                }
+            }
+        }
+
+        self.zoxide.add(&clone_path)?;
+
+        Ok(())
+    }
+
+    /// Check out the default branch of a bare clone in a worktree under
+    /// `worktrees_dir`, returning the worktree's path
+    fn add_default_worktree(
+        &self,
+        bare_path: &Path,
+        worktrees_dir: &Path,
+    ) -> Result<PathBuf, CloneError> {
+        let path_str = |path: &Path| {
+            path.to_str()
+                .map(String::from)
+                .ok_or_else(|| CloneError::InvalidPath(path.display().to_string()))
+        };
+
+        let bare_path_str = path_str(bare_path)?;
+        let branch = self.git.default_branch(&bare_path_str)?;
+        let worktree = worktrees_dir.join(&branch);
+        self.printer.info(format_args!(
+            "Adding worktree for branch '{}' at {}",
+            branch,
+            worktree.display()
+        ));
+        self.git
+            .worktree_add(&bare_path_str, &path_str(&worktree)?, &branch)?;
+        Ok(worktree)
+    }
+
+    /// Settle what to do about a taken session name, returning the name to
+    /// use and whether it's an existing session to attach to
+    fn settle_session_name(&self, name: &str) -> Result<(String, bool), CloneError> {
+        let policy = self.config.collision_policy;
+        let exists = self.zellij.list_sessions()?.iter().any(|s| s.name == name);
+        match (exists, policy) {
+            (true, CollisionPolicy::Error) => Err(CloneError::SessionExists(name.to_string())),
+            (true, CollisionPolicy::Suffix) => {
+                Ok((next_available_name(&self.zellij, name)?, false))
+            }
+            (exists, _) => Ok((name.to_string(), exists)),
+        }
+    }
+
+    /// Create the session for a clone, or attach to the existing one
+    fn open_session(
+        &self,
+        session_name: &str,
+        attach_existing: bool,
+        clone_path: &Path,
+        options: &CloneOptions,
+        zellij_options: &ZellijOptions,
+        editor: Option<&[String]>,
+    ) -> Result<(), CloneError> {
+        // The existing session keeps its own root
+        if !attach_existing && let Err(e) = self.store.record(session_name, clone_path) {
+            self.printer
+                .warn(format_args!("failed to record session root: {}", e));
+        }
+
+        let zellij_options = match options.template {
+            Some(template) => template.apply(&self.fs, session_name, zellij_options)?,
+            None => self.with_repo_layout(clone_path, options, zellij_options),
+        };
+        if attach_existing {
+            if editor.is_some() {
+                self.printer.info(format_args!(
+                    "Not opening the editor in existing session '{}'",
+                    session_name
+                ));
+            }
+            if !options.no_attach {
+                self.zellij.attach_session(session_name)?;
+            }
+        } else {
+            let tabs = self.config.auto_tab_dirs(&self.fs, clone_path);
+            if editor.is_none() && tabs.is_empty() && !options.no_attach {
+                self.zellij.new_session(session_name, &zellij_options)?;
+                return Ok(());
+            }
+            // Attaching to a new session doesn't return until we detach, so
+            // it's created in the background to open tabs and the editor in
+            // it first
+            self.zellij
+                .new_session_detached(session_name, &zellij_options)?;
+            for (name, dir) in &tabs {
+                self.zellij
+                    .new_tab_in(session_name, Some(name), Some(dir))?;
+            }
+            if let Some(editor) = editor {
+                self.open_editor(Some(session_name), editor, clone_path)?;
+            }
+            if !options.no_attach {
+                self.zellij.attach_session(session_name)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Clone, retrying with exponential backoff while git fails on what looks
+    /// like a network error. Other failures, such as bad credentials, are
+    /// returned immediately.
+    fn clone_with_retry(
+        &self,
+        repo_url: &str,
+        parent_dir: &str,
+        dir: &str,
+        bare: bool,
+        retries: u32,
+    ) -> Result<(), CloneError> {
+        let mut backoff = Duration::from_millis(self.config.clone_backoff_ms);
+        let mut retry = 0;
+
+        loop {
+            let result = if bare {
+                self.git.clone_bare(repo_url, parent_dir, dir)
+            } else {
+                self.git.clone(repo_url, parent_dir, dir)
+            };
+            match result {
+                Err(e) if e.is_transient() && retry < retries && !self.runner.interrupted() => {
+                    retry += 1;
+                    self.printer.warn(format_args!(
+                        "clone failed, retrying in {:.1}s ({}/{})",
+                        backoff.as_secs_f64(),
+                        retry,
+                        retries
+                    ));
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                }
+                result => {
+                    result?;
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Use the repository's layout file for the session, unless it's disabled
+    /// or a layout was passed explicitly
+    fn with_repo_layout(
+        &self,
+        clone_path: &Path,
+        options: &CloneOptions,
+        zellij_options: &ZellijOptions,
+    ) -> ZellijOptions {
+        let mut zellij_options = zellij_options.clone();
+        if options.no_repo_layout || zellij_options.new_session_with_layout.is_some() {
+            return zellij_options;
+        }
+
+        let layout = clone_path.join(REPO_LAYOUT_FILE);
+        if self.fs.exists(&layout) {
+            self.printer
+                .info(format_args!("Using layout {}", layout.display()));
+            zellij_options.new_session_with_layout = Some(layout.display().to_string());
+        }
+        zellij_options
+    }
+
+    /// The command line `--edit` runs, split into words
+    fn editor(&self) -> Result<Vec<String>, CloneError> {
+        let editor = self.config.editor.as_deref().unwrap_or_default();
+        let words: Vec<String> = editor.split_whitespace().map(String::from).collect();
+        if words.is_empty() {
+            return Err(CloneError::NoEditor);
+        }
+        Ok(words)
+    }
+
+    /// Open the clone root in the editor, in a new pane of `session` or of
+    /// the current session
+    fn open_editor(
+        &self,
+        session: Option<&str>,
+        editor: &[String],
+        clone_path: &Path,
+    ) -> Result<(), CloneError> {
+        let path = clone_path
+            .to_str()
+            .ok_or_else(|| CloneError::InvalidPath(clone_path.display().to_string()))?;
+        let mut args: Vec<&str> = editor[1..].iter().map(String::as_str).collect();
+        args.push(path);
+
+        match session {
+            Some(session) => self.zellij.run_command_in(session, &editor[0], &args)?,
+            None => self.zellij.run_command(&editor[0], &args)?,
+        }
+        Ok(())
+    }
+
+    /// The current directory, or the home directory with a warning if it
+    /// was deleted, see [`FsOperations::current_dir_or_home`]
+    fn current_dir_or_home(&self) -> Result<PathBuf, CloneError> {
+        let (dir, fallback) = self.fs.current_dir_or_home()?;
+        if let Some(e) = fallback {
+            self.printer.warn(format_args!(
+                "can't read the current directory ({}), using {} instead",
+                e,
+                dir.display()
+            ));
+        }
+        Ok(dir)
+    }
+
+    /// Run the post-clone hook in the current directory. A failing hook
+    /// shouldn't cost the user their clone, so failures are only reported.
+    fn run_post_clone(&self, command: &str) -> Result<(), CloneError> {
+        let cwd = self.fs.current_dir()?;
+        self.printer
+            .info(format_args!("Running post-clone hook: {}", command));
+        match self.runner.run_shell(command, &cwd) {
+            Ok(output) if !output.success() => self.printer.warn(format_args!(
+                "post-clone hook '{}' failed: exited with {}",
+                command,
+                output
+                    .code
+                    .map_or("a signal".to_string(), |code| format!("status {}", code))
+            )),
+            Ok(_) => {}
+            Err(e) => self
+                .printer
+                .warn(format_args!("post-clone hook '{}' failed: {}", command, e)),
+        }
+        Ok(())
+    }
+}
+
+/// Expand repository shorthand into a full clone URL, using github.com for
+/// `user/repo`. See [`normalize_repo_url_with_host`].
+pub fn normalize_repo_url(input: &str) -> String {
+    normalize_repo_url_with_host(input, DEFAULT_GIT_HOST)
+}
+
+/// Expand repository shorthand into a full clone URL:
+///
+/// - `user/repo` becomes `https://<default_host>/user/repo`
+/// - `gh:user/repo` becomes `https://github.com/user/repo`
+/// - `gl:user/repo` becomes `https://gitlab.com/user/repo`
+///
+/// Anything else, such as full https/ssh URLs or local paths, is returned
+/// untouched.
+pub fn normalize_repo_url_with_host(input: &str, default_host: &str) -> String {
+    let input = input.trim();
+
+    let (host, repo) = if let Some(repo) = input.strip_prefix("gh:") {
+        ("github.com", repo)
+    } else if let Some(repo) = input.strip_prefix("gl:") {
+        ("gitlab.com", repo)
+    } else {
+        (default_host, input)
+    };
+
+    if is_shorthand(repo) {
+        format!("https://{}/{}", host, repo)
+    } else {
+        input.to_string()
+    }
+}
+
+/// Read the repository URL for `zesh clone --clipboard` from the clipboard,
+/// expanding shorthand like a URL given on the command line. Anything that
+/// isn't a single URL, scp-like ssh address or shorthand is rejected, so
+/// whatever text was copied last isn't cloned by accident.
+pub fn repo_url_from_clipboard<C: Clipboard>(
+    clipboard: &C,
+    default_host: &str,
+) -> Result<String, CloneError> {
+    let contents = clipboard.read()?;
+    let contents = contents.trim();
+    if contents.is_empty() {
+        return Err(ClipboardError::Empty.into());
+    }
+
+    let url = normalize_repo_url_with_host(contents, default_host);
+    if !is_repo_url(&url) {
+        return Err(CloneError::NotARepoUrl(contents.to_string()));
+    }
+    Ok(url)
+}
+
+/// Whether a normalized URL looks like something git can clone remotely:
+/// `scheme://host/path` or `user@host:path`, naming a repository
+fn is_repo_url(url: &str) -> bool {
+    if url.contains(char::is_whitespace) {
+        return false;
+    }
+
+    let path = match url.split_once("://") {
+        Some((scheme, rest)) => {
+            if !matches!(scheme, "https" | "http" | "ssh" | "git") {
+                return false;
+            }
+            match rest.split_once('/') {
+                Some((host, path)) if !host.is_empty() => path,
+                _ => return false,
+            }
+        }
+        None => match url.split_once(':') {
+            Some((user_host, path)) if user_host.contains('@') => path,
+            _ => return false,
+        },
+    };
+    !path.is_empty() && extract_repo_name(url).is_ok()
+}
+
+/// Check if the input looks like `user/repo`, rather than a URL, an scp-like
+/// ssh address (`git@host:user/repo`) or a path
+fn is_shorthand(input: &str) -> bool {
+    if input.contains(':') || input.starts_with(['/', '.', '~']) {
+        return false;
+    }
+
+    let mut parts = input.split('/');
+    matches!(
+        (parts.next(), parts.next(), parts.next()),
+        (Some(user), Some(repo), None) if !user.is_empty() && !repo.is_empty()
+    )
+}
+
+/// The `<host>/<owner>` directory a clone goes in with `clone_by_host`, e.g.
+/// `github.com/user` for both `https://github.com/user/repo` and
+/// `git@github.com:user/repo.git`. Local paths have no host and give `None`.
+fn repo_owner_dir(url: &str) -> Option<PathBuf> {
+    let (host, path) = match url.split_once("://") {
+        Some((_, rest)) => {
+            let (authority, path) = rest.split_once('/')?;
+            // Drop credentials and ports, as in `ssh://git@host:22/user/repo`
+            let host = authority.rsplit('@').next()?.split(':').next()?;
+            (host, path)
+        }
+        // scp-like `git@host:user/repo`
+        None => {
+            let (authority, path) = url.split_once(':')?;
+            if authority.contains('/') {
+                return None;
+            }
+            (authority.rsplit('@').next()?, path)
+        }
+    };
+    if host.is_empty() {
+        return None;
+    }
+
+    let owner = path
+        .trim_matches('/')
+        .rsplit_once('/')
+        .map_or("", |(owner, _)| owner);
+    let mut dir = PathBuf::from(host);
+    for part in owner.split('/').filter(|part| !part.is_empty()) {
+        // Keep clones inside the clone root
+        if part == ".." || part == "." {
+            return None;
+        }
+        dir.push(part);
+    }
+    Some(dir)
+}
+
+/// Parse `zesh clone --path`, see [`validate_clone_path`]
+pub fn parse_clone_path(arg: &str) -> Result<PathBuf, String> {
+    validate_clone_path(&RealFs::new(), arg)
+}
+
+/// Check a path to clone into before cloning. It doesn't have to exist yet,
+/// but neither it nor any of its parents can be a file, and it can't be
+/// empty or contain NUL bytes.
+pub fn validate_clone_path<F: FsOperations>(fs: &F, arg: &str) -> Result<PathBuf, String> {
+    if arg.trim().is_empty() {
+        return Err("path is empty".to_string());
+    }
+    if arg.contains('\0') {
+        return Err("path contains a NUL byte".to_string());
+    }
+
+    let path = fs.expand_path(Path::new(arg));
+    let file = path
+        .ancestors()
+        .filter(|p| !p.as_os_str().is_empty())
+        .find(|p| fs.exists(p) && !fs.is_dir(p));
+    match file {
+        Some(file) if file == path => Err(format!("{} is a file, not a directory", arg)),
+        Some(file) => Err(format!(
+            "{} is inside {}, which is a file",
+            arg,
+            file.display()
+        )),
+        None => Ok(PathBuf::from(arg)),
+    }
+}
+
+/// Extract repository name from URL
+pub fn extract_repo_name(url: &str) -> Result<&str, CloneError> {
+    let url = url.trim_end_matches(".git");
+    url.rsplit('/')
+        .next()
+        .filter(|s| !s.is_empty())
+        .ok_or(CloneError::InvalidRepoUrl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::tests::MockClipboard;
+    use crate::fs::tests::MockFs;
+    use crate::template::tests::assert_valid_kdl;
+    use clap::ValueEnum;
+    use std::cell::Cell;
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use zellij_rs::{MockZellijClient, ZellijError, ZellijOperations};
+    use zesh_git::{GitError, MockGit};
+    use zesh_proc::{MockRunner, RunOutput};
+    use zox_rs::{MockZoxideClient, ZoxideError};
+
+    struct TestGit {
+        should_fail: bool,
+        branch: &'static str,
+    }
+
+    impl TestGit {
+        fn success() -> Self {
+            Self {
+                should_fail: false,
+                branch: "main",
+            }
+        }
+
+        fn failing() -> Self {
+            Self {
+                should_fail: true,
+                branch: "main",
+            }
+        }
+
+        fn on_branch(branch: &'static str) -> Self {
+            Self {
+                branch,
+                ..Self::success()
+            }
+        }
+    }
+
+    impl Git for TestGit {
+        fn show_top_level(&self, _name: &str) -> Result<(bool, String), GitError> {
+            Ok((false, String::new()))
+        }
+
+        fn is_inside_work_tree(&self, _name: &str) -> Result<bool, GitError> {
+            Ok(false)
+        }
+
+        fn git_common_dir(&self, _name: &str) -> Result<(bool, String), GitError> {
+            Ok((false, String::new()))
+        }
+
+        fn clone(&self, _url: &str, _cmd_dir: &str, _dir: &str) -> Result<String, GitError> {
+            if self.should_fail {
+                Err(GitError::CommandError("clone failed".to_string()))
+            } else {
+                Ok("Clone successful".to_string())
+            }
+        }
+
+        fn clone_bare(&self, url: &str, cmd_dir: &str, dir: &str) -> Result<String, GitError> {
+            self.clone(url, cmd_dir, dir)
+        }
+
+        fn worktree_add(&self, _repo: &str, _path: &str, _branch: &str) -> Result<(), GitError> {
+            Ok(())
+        }
+
+        fn version(&self) -> Result<String, GitError> {
+            Ok("git version 2.43.0".to_string())
+        }
+
+        fn default_branch(&self, _url_or_dir: &str) -> Result<String, GitError> {
+            Ok(self.branch.to_string())
         }
     }
 
-    /// Clone a git repository and create a zellij session for it
-    pub fn clone_repo(
-        &self,
-        repo_url: &str,
-        name: Option<&str>,
-        path: Option<&PathBuf>,
-        zellij_options: &ZellijOptions,
-    ) -> Result<(), CloneError> {
-        let repo_name = extract_repo_name(repo_url)?;
-        let session_name = name.unwrap_or(repo_name);
+    /// Git whose clone fails with `stderr` a number of times before working
+    struct FlakyGit {
+        failures: Cell<u32>,
+        stderr: &'static str,
+        calls: Cell<u32>,
+    }
+
+    impl FlakyGit {
+        fn new(failures: u32, stderr: &'static str) -> Self {
+            Self {
+                failures: Cell::new(failures),
+                stderr,
+                calls: Cell::new(0),
+            }
+        }
+    }
+
+    impl Git for FlakyGit {
+        fn show_top_level(&self, _name: &str) -> Result<(bool, String), GitError> {
+            Ok((false, "fatal: not a git repository".to_string()))
+        }
+
+        fn is_inside_work_tree(&self, _name: &str) -> Result<bool, GitError> {
+            Ok(false)
+        }
+
+        fn git_common_dir(&self, _name: &str) -> Result<(bool, String), GitError> {
+            Ok((false, "fatal: not a git repository".to_string()))
+        }
+
+        fn clone(&self, _url: &str, _cmd_dir: &str, _dir: &str) -> Result<String, GitError> {
+            self.calls.set(self.calls.get() + 1);
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err(GitError::CommandError(self.stderr.to_string()));
+            }
+            Ok(String::new())
+        }
+
+        fn clone_bare(&self, url: &str, cmd_dir: &str, dir: &str) -> Result<String, GitError> {
+            self.clone(url, cmd_dir, dir)
+        }
+
+        fn worktree_add(&self, _repo: &str, _path: &str, _branch: &str) -> Result<(), GitError> {
+            Ok(())
+        }
+
+        fn version(&self) -> Result<String, GitError> {
+            Ok("git version 2.43.0".to_string())
+        }
+
+        fn default_branch(&self, _url_or_dir: &str) -> Result<String, GitError> {
+            Ok("main".to_string())
+        }
+    }
+
+    const NETWORK_ERROR: &str = "fatal: unable to access 'https://github.com/user/my-repo.git/': Could not resolve host: github.com";
+
+    fn flaky_service(
+        git: FlakyGit,
+    ) -> CloneService<MockZellijClient, MockZoxideClient, MockFs, FlakyGit> {
+        CloneService::new(
+            MockZellijClient::new(),
+            MockZoxideClient::new(),
+            MockFs::new(),
+            git,
+        )
+        .with_config(Config {
+            clone_backoff_ms: 0,
+            ..Config::default()
+        })
+    }
+
+    fn with_retries(retries: u32) -> CloneOptions {
+        CloneOptions {
+            retries,
+            ..in_parent()
+        }
+    }
+
+    #[test]
+    fn test_clone_repo_retries_network_errors() {
+        let service =
+            flaky_service(FlakyGit::new(2, NETWORK_ERROR)).with_printer(Printer::capturing(false));
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &with_retries(3),
+            &ZellijOptions::default(),
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(service.git.calls.get(), 3);
+        let retries: Vec<_> = service
+            .printer
+            .captured()
+            .into_iter()
+            .filter(|line| line.starts_with("Warning: clone failed"))
+            .collect();
+        assert_eq!(
+            retries,
+            vec![
+                "Warning: clone failed, retrying in 0.0s (1/3)",
+                "Warning: clone failed, retrying in 0.0s (2/3)",
+            ]
+        );
+        assert_eq!(service.zellij.list_sessions().unwrap().len(), 1);
+    }
+
+    fn restricted_service(
+        allowed_roots: &[&str],
+    ) -> CloneService<MockZellijClient, MockZoxideClient, MockFs, FlakyGit> {
+        flaky_service(FlakyGit::new(0, NETWORK_ERROR)).with_config(Config {
+            allowed_roots: allowed_roots.iter().map(PathBuf::from).collect(),
+            ..Config::default()
+        })
+    }
+
+    #[test]
+    fn test_clone_repo_under_allowed_root() {
+        for root in ["/mock", "/mock/parent", "/mock/parent/my-repo"] {
+            let service = restricted_service(&["/elsewhere", root]);
+
+            service
+                .clone_repo(
+                    "https://github.com/user/my-repo.git",
+                    &in_parent(),
+                    &ZellijOptions::default(),
+                )
+                .unwrap();
+
+            assert_eq!(service.git.calls.get(), 1, "root {}", root);
+        }
+    }
+
+    #[test]
+    fn test_clone_repo_outside_allowed_roots() {
+        let service = restricted_service(&["/mock/src"]);
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &in_parent(),
+            &ZellijOptions::default(),
+        );
+
+        // Nothing was cloned or created
+        assert!(
+            matches!(result, Err(CloneError::OutsideAllowedRoots(path)) if path == "/mock/parent/my-repo")
+        );
+        assert_eq!(service.git.calls.get(), 0);
+        assert!(service.zellij.list_sessions().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_clone_repo_with_malformed_layout() {
+        let service = flaky_service(FlakyGit::new(0, NETWORK_ERROR));
+        service
+            .fs
+            .with_file_contents(Path::new("/layouts/bad.kdl"), "layout {\n");
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &in_parent(),
+            &ZellijOptions {
+                new_session_with_layout: Some("/layouts/bad.kdl".to_string()),
+                ..ZellijOptions::default()
+            },
+        );
+
+        // The layout is checked before anything is cloned
+        assert!(matches!(
+            result,
+            Err(CloneError::Layout(LayoutError::Malformed(..)))
+        ));
+        assert_eq!(service.git.calls.get(), 0);
+    }
+
+    #[test]
+    fn test_clone_repo_gives_up_after_retries() {
+        let service = flaky_service(FlakyGit::new(3, NETWORK_ERROR));
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &with_retries(2),
+            &ZellijOptions::default(),
+        );
+
+        assert!(matches!(result, Err(CloneError::Git(_))));
+        assert_eq!(service.git.calls.get(), 3);
+        assert!(service.zellij.list_sessions().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_clone_repo_does_not_retry_auth_errors() {
+        let service = flaky_service(FlakyGit::new(
+            1,
+            "fatal: Authentication failed for 'https://github.com/user/my-repo.git/'",
+        ));
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &with_retries(3),
+            &ZellijOptions::default(),
+        );
+
+        assert!(matches!(result, Err(CloneError::Git(_))));
+        assert_eq!(service.git.calls.get(), 1);
+    }
+
+    #[test]
+    fn test_clone_repo_no_retries_by_default() {
+        let service = flaky_service(FlakyGit::new(1, NETWORK_ERROR));
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &in_parent(),
+            &ZellijOptions::default(),
+        );
+
+        assert!(result.is_err());
+        assert_eq!(service.git.calls.get(), 1);
+    }
+
+    fn create_service(
+        git: TestGit,
+    ) -> CloneService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
+        let zellij = MockZellijClient::new();
+        let zoxide = MockZoxideClient::new();
+        let fs = MockFs::new();
+        CloneService::new(zellij, zoxide, fs, git)
+    }
+
+    /// Options that clone into the mock parent directory
+    fn in_parent() -> CloneOptions {
+        CloneOptions {
+            path: Some(PathBuf::from("/mock/parent")),
+            ..CloneOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_clone_path() {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/src"), "src");
+        fs.with_file(Path::new("/mock/notes.txt"));
+
+        assert_eq!(
+            validate_clone_path(&fs, "/mock/src").unwrap(),
+            PathBuf::from("/mock/src")
+        );
+        // Missing directories are created by the clone
+        assert!(validate_clone_path(&fs, "/mock/src/new/dir").is_ok());
+        assert!(validate_clone_path(&fs, "relative/dir").is_ok());
+
+        assert_eq!(
+            validate_clone_path(&fs, "/mock/notes.txt").unwrap_err(),
+            "/mock/notes.txt is a file, not a directory"
+        );
+        assert_eq!(
+            validate_clone_path(&fs, "/mock/notes.txt/sub").unwrap_err(),
+            "/mock/notes.txt/sub is inside /mock/notes.txt, which is a file"
+        );
+        assert!(validate_clone_path(&fs, "").is_err());
+        assert!(validate_clone_path(&fs, "  ").is_err());
+        assert!(validate_clone_path(&fs, "/mock/a\0b").is_err());
+    }
+
+    #[test]
+    fn test_extract_repo_name_https() {
+        let name = extract_repo_name("https://github.com/user/my-repo.git").unwrap();
+        assert_eq!(name, "my-repo");
+    }
+
+    #[test]
+    fn test_extract_repo_name_https_no_git_suffix() {
+        let name = extract_repo_name("https://github.com/user/my-repo").unwrap();
+        assert_eq!(name, "my-repo");
+    }
+
+    #[test]
+    fn test_extract_repo_name_ssh() {
+        let name = extract_repo_name("git@github.com:user/my-repo.git").unwrap();
+        assert_eq!(name, "my-repo");
+    }
+
+    #[test]
+    fn test_extract_repo_name_trailing_slash() {
+        let result = extract_repo_name("/");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_normalize_repo_url_shorthand() {
+        assert_eq!(
+            normalize_repo_url("user/my-repo"),
+            "https://github.com/user/my-repo"
+        );
+        assert_eq!(
+            normalize_repo_url("gh:user/my-repo"),
+            "https://github.com/user/my-repo"
+        );
+        assert_eq!(
+            normalize_repo_url("gl:user/my-repo"),
+            "https://gitlab.com/user/my-repo"
+        );
+    }
+
+    #[test]
+    fn test_normalize_repo_url_custom_host() {
+        assert_eq!(
+            normalize_repo_url_with_host("user/my-repo", "git.example.com"),
+            "https://git.example.com/user/my-repo"
+        );
+        // Explicit prefixes win over the default host
+        assert_eq!(
+            normalize_repo_url_with_host("gh:user/my-repo", "git.example.com"),
+            "https://github.com/user/my-repo"
+        );
+    }
+
+    #[test]
+    fn test_normalize_repo_url_leaves_full_urls() {
+        for url in [
+            "https://github.com/user/my-repo.git",
+            "http://example.com/user/my-repo",
+            "git@github.com:user/my-repo.git",
+            "ssh://git@github.com/user/my-repo.git",
+            "/local/path/my-repo",
+            "./my-repo",
+            "~/src/my-repo",
+            "my-repo",
+            "a/b/c",
+        ] {
+            assert_eq!(normalize_repo_url(url), url);
+        }
+    }
+
+    #[test]
+    fn test_repo_url_from_clipboard() {
+        for (contents, url) in [
+            (
+                "https://github.com/user/my-repo.git\n",
+                "https://github.com/user/my-repo.git",
+            ),
+            (
+                "  git@github.com:user/my-repo.git  ",
+                "git@github.com:user/my-repo.git",
+            ),
+            ("user/my-repo\r\n", "https://git.example.com/user/my-repo"),
+            ("gl:user/my-repo", "https://gitlab.com/user/my-repo"),
+        ] {
+            let clipboard = MockClipboard::with(contents);
+            assert_eq!(
+                repo_url_from_clipboard(&clipboard, "git.example.com").unwrap(),
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_repo_url_from_empty_clipboard() {
+        for clipboard in [MockClipboard::empty(), MockClipboard::with(" \n")] {
+            let result = repo_url_from_clipboard(&clipboard, DEFAULT_GIT_HOST);
+            assert!(matches!(
+                result,
+                Err(CloneError::Clipboard(ClipboardError::Empty))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_repo_url_from_clipboard_rejects_other_text() {
+        for contents in [
+            "my-repo",
+            "meeting notes for tomorrow",
+            "https://github.com/user/a https://github.com/user/b",
+            "/local/path/my-repo",
+            "mailto:user@example.com",
+            "https://",
+        ] {
+            let result = repo_url_from_clipboard(&MockClipboard::with(contents), DEFAULT_GIT_HOST);
+            assert!(
+                matches!(result, Err(CloneError::NotARepoUrl(ref text)) if text == contents),
+                "expected '{}' to be rejected",
+                contents
+            );
+        }
+    }
+
+    #[test]
+    fn test_extract_repo_name_after_normalization() {
+        for input in [
+            "user/my-repo",
+            "user/my-repo.git",
+            "gh:user/my-repo",
+            "git@github.com:user/my-repo.git",
+            "https://github.com/user/my-repo",
+        ] {
+            let url = normalize_repo_url(input);
+            assert_eq!(extract_repo_name(&url).unwrap(), "my-repo");
+        }
+    }
+
+    #[test]
+    fn test_clone_repo_shorthand() {
+        let service = create_service(TestGit::success());
+
+        let result = service.clone_repo("user/my-repo", &in_parent(), &ZellijOptions::default());
+
+        assert!(result.is_ok());
+
+        let sessions = service.zellij.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "my-repo");
+    }
+
+    #[test]
+    fn test_clone_repo_runs_post_clone_hook_in_clone_path() {
+        let service = create_service(TestGit::success())
+            .with_config(Config {
+                post_clone: Some("npm install".to_string()),
+                ..Config::default()
+            })
+            .with_runner(MockRunner::new());
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &in_parent(),
+            &ZellijOptions::default(),
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(
+            service.runner.shell_commands(),
+            vec![(
+                "npm install".to_string(),
+                PathBuf::from("/mock/parent/my-repo")
+            )]
+        );
+    }
+
+    #[test]
+    fn test_clone_repo_uses_repo_layout_file() {
+        let service = create_service(TestGit::success());
+        service
+            .fs
+            .with_file(Path::new("/mock/parent/my-repo/.zesh-layout.kdl"));
+
+        service
+            .clone_repo(
+                "https://github.com/user/my-repo.git",
+                &in_parent(),
+                &ZellijOptions::default(),
+            )
+            .unwrap();
+
+        let options = service.zellij.session_options("my-repo").unwrap();
+        assert_eq!(
+            options.new_session_with_layout,
+            Some("/mock/parent/my-repo/.zesh-layout.kdl".to_string())
+        );
+    }
+
+    #[test]
+    fn test_clone_repo_with_template() {
+        for template in LayoutTemplate::value_variants() {
+            let service = create_service(TestGit::success());
+            // The template wins over the repository's layout
+            service
+                .fs
+                .with_file(Path::new("/mock/parent/my-repo/.zesh-layout.kdl"));
+
+            service
+                .clone_repo(
+                    "https://github.com/user/my-repo.git",
+                    &CloneOptions {
+                        template: Some(*template),
+                        ..in_parent()
+                    },
+                    &ZellijOptions::default(),
+                )
+                .unwrap();
+
+            let options = service.zellij.session_options("my-repo").unwrap();
+            let layout = options.new_session_with_layout.unwrap();
+            let kdl = service.fs.read_to_string(Path::new(&layout)).unwrap();
+            assert_valid_kdl(&kdl);
+            assert_eq!(kdl, template.kdl());
+        }
+    }
+
+    fn editing_service(
+        zellij: MockZellijClient,
+        editor: Option<&str>,
+    ) -> CloneService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
+        CloneService::new(
+            zellij,
+            MockZoxideClient::new(),
+            MockFs::new(),
+            TestGit::success(),
+        )
+        .with_config(Config {
+            editor: editor.map(String::from),
+            ..Config::default()
+        })
+    }
+
+    fn editing() -> CloneOptions {
+        CloneOptions {
+            edit: true,
+            ..in_parent()
+        }
+    }
+
+    #[test]
+    fn test_clone_repo_edit_opens_editor_in_new_session() {
+        let service = editing_service(MockZellijClient::new(), Some("nvim"));
+
+        service
+            .clone_repo(
+                "https://github.com/user/my-repo.git",
+                &editing(),
+                &ZellijOptions::default(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            service.zellij.commands("my-repo"),
+            vec![vec!["nvim", "/mock/parent/my-repo"]]
+        );
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("my-repo".to_string())
+        );
+    }
+
+    #[test]
+    fn test_clone_repo_edit_keeps_editor_arguments() {
+        let service = editing_service(MockZellijClient::new(), Some("code -w"));
+
+        service
+            .clone_repo(
+                "https://github.com/user/my-repo.git",
+                &CloneOptions {
+                    no_attach: true,
+                    ..editing()
+                },
+                &ZellijOptions::default(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            service.zellij.commands("my-repo"),
+            vec![vec!["code", "-w", "/mock/parent/my-repo"]]
+        );
+        assert_eq!(service.zellij.current_session().unwrap(), None);
+    }
+
+    #[test]
+    fn test_clone_repo_opens_auto_tabs() {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/parent"), "parent");
+        let service = CloneService::new(
+            MockZellijClient::new(),
+            MockZoxideClient::new(),
+            fs,
+            TestGit::success(),
+        )
+        .with_config(Config {
+            auto_tabs: vec!["frontend".into(), "backend".into()],
+            editor: Some("nvim".to_string()),
+            ..Config::default()
+        });
+        // What the clone checks out
+        service
+            .fs
+            .with_directory(Path::new("/mock/parent/my-repo/backend"), "backend");
+
+        service
+            .clone_repo(
+                "https://github.com/user/my-repo.git",
+                &editing(),
+                &ZellijOptions::default(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            service.zellij.tabs_in("my-repo"),
+            vec![(
+                Some("backend".to_string()),
+                Some(PathBuf::from("/mock/parent/my-repo/backend"))
+            )]
+        );
+        assert_eq!(
+            service.zellij.commands("my-repo"),
+            vec![vec!["nvim", "/mock/parent/my-repo"]]
+        );
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("my-repo".to_string())
+        );
+    }
+
+    #[test]
+    fn test_clone_repo_edit_without_editor() {
+        let service = editing_service(MockZellijClient::new(), Some("  "));
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &editing(),
+            &ZellijOptions::default(),
+        );
+
+        // It fails before cloning
+        assert!(matches!(result, Err(CloneError::NoEditor)));
+        assert!(!service.fs.exists(Path::new("/mock/parent/my-repo")));
+        assert!(service.zellij.list_sessions().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_clone_repo_edit_skips_existing_session() {
+        let zellij =
+            MockZellijClient::with_sessions(HashMap::from([("my-repo".to_string(), false)]));
+        let service = editing_service(zellij, Some("nvim"));
+
+        service
+            .clone_repo(
+                "https://github.com/user/my-repo.git",
+                &editing(),
+                &ZellijOptions::default(),
+            )
+            .unwrap();
+
+        assert!(service.zellij.commands("my-repo").is_empty());
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("my-repo".to_string())
+        );
+    }
+
+    #[test]
+    fn test_clone_repo_edit_as_tab() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([("work".to_string(), true)]));
+        let service = editing_service(zellij, Some("nvim"));
+
+        service
+            .clone_repo(
+                "https://github.com/user/my-repo.git",
+                &CloneOptions {
+                    target: Target::Tab,
+                    ..editing()
+                },
+                &ZellijOptions::default(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            service.zellij.commands("work"),
+            vec![vec!["nvim", "/mock/parent/my-repo"]]
+        );
+    }
+
+    #[test]
+    fn test_clone_repo_without_repo_layout_file() {
+        let service = create_service(TestGit::success());
+
+        service
+            .clone_repo(
+                "https://github.com/user/my-repo.git",
+                &in_parent(),
+                &ZellijOptions::default(),
+            )
+            .unwrap();
+
+        let options = service.zellij.session_options("my-repo").unwrap();
+        assert_eq!(options.new_session_with_layout, None);
+    }
+
+    #[test]
+    fn test_clone_repo_repo_layout_disabled_or_overridden() {
+        let service = create_service(TestGit::success());
+        service
+            .fs
+            .with_file(Path::new("/mock/parent/my-repo/.zesh-layout.kdl"));
+
+        let disabled = CloneOptions {
+            name: Some("disabled".to_string()),
+            no_repo_layout: true,
+            ..in_parent()
+        };
+        service
+            .clone_repo(
+                "https://github.com/user/my-repo.git",
+                &disabled,
+                &ZellijOptions::default(),
+            )
+            .unwrap();
+        let options = service.zellij.session_options("disabled").unwrap();
+        assert_eq!(options.new_session_with_layout, None);
+
+        // An explicit layout wins over the repo's
+        let explicit = ZellijOptions {
+            new_session_with_layout: Some("compact".to_string()),
+            ..ZellijOptions::default()
+        };
+        service
+            .clone_repo(
+                "https://github.com/user/my-repo.git",
+                &in_parent(),
+                &explicit,
+            )
+            .unwrap();
+        let options = service.zellij.session_options("my-repo").unwrap();
+        assert_eq!(options.new_session_with_layout, Some("compact".to_string()));
+    }
+
+    #[test]
+    fn test_clone_repo_prints_progress() {
+        let service = create_service(TestGit::success()).with_printer(Printer::capturing(false));
+
+        service
+            .clone_repo(
+                "https://github.com/user/my-repo.git",
+                &in_parent(),
+                &ZellijOptions::default(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            service.printer.captured(),
+            vec![
+                "Cloning https://github.com/user/my-repo.git into /mock/parent/my-repo..."
+                    .to_string(),
+                "Checked out branch 'main'".to_string(),
+                "Creating new session 'my-repo' at /mock/parent/my-repo".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clone_repo_prints_master_branch() {
+        let service =
+            create_service(TestGit::on_branch("master")).with_printer(Printer::capturing(false));
+
+        service
+            .clone_repo(
+                "https://github.com/user/my-repo.git",
+                &in_parent(),
+                &ZellijOptions::default(),
+            )
+            .unwrap();
+
+        assert!(
+            service
+                .printer
+                .captured()
+                .contains(&"Checked out branch 'master'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_clone_repo_quiet_omits_progress() {
+        let service = create_service(TestGit::success())
+            .with_config(Config {
+                post_clone: Some("npm install".to_string()),
+                ..Config::default()
+            })
+            .with_runner(MockRunner::new())
+            .with_printer(Printer::capturing(true));
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &in_parent(),
+            &ZellijOptions::default(),
+        );
+
+        assert!(result.is_ok());
+        assert!(service.printer.captured().is_empty());
+        // Quiet only silences output, the hook still runs
+        assert_eq!(service.runner.shell_commands().len(), 1);
+    }
+
+    #[test]
+    fn test_clone_repo_without_post_clone_hook() {
+        let service = create_service(TestGit::success()).with_runner(MockRunner::new());
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &in_parent(),
+            &ZellijOptions::default(),
+        );
+
+        assert!(result.is_ok());
+        assert!(service.runner.invocations().is_empty());
+    }
+
+    #[test]
+    fn test_clone_repo_post_clone_failure_is_not_fatal() {
+        let service = create_service(TestGit::success())
+            .with_config(Config {
+                post_clone: Some("false".to_string()),
+                ..Config::default()
+            })
+            .with_runner(MockRunner::new().with_output(RunOutput::with_code(1, "", "")))
+            .with_printer(Printer::capturing(false));
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &in_parent(),
+            &ZellijOptions::default(),
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(service.runner.shell_commands().len(), 1);
+        assert!(service.printer.captured().contains(
+            &"Warning: post-clone hook 'false' failed: exited with status 1".to_string()
+        ));
+
+        // The session is still created
+        let sessions = service.zellij.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "my-repo");
+    }
+
+    #[test]
+    fn test_clone_repo_interrupted_removes_partial_clone() {
+        let service = create_service(TestGit::failing())
+            .with_runner(MockRunner::new().with_interrupted())
+            .with_printer(Printer::capturing(false));
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &in_parent(),
+            &ZellijOptions::default(),
+        );
+
+        assert!(matches!(result, Err(CloneError::Interrupted)));
+        assert_eq!(
+            *service.fs.removed.borrow(),
+            vec![PathBuf::from("/mock/parent/my-repo")]
+        );
+        assert!(service.zellij.list_sessions().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_clone_repo_interrupted_keeps_existing_dir() {
+        let service = create_service(TestGit::failing())
+            .with_runner(MockRunner::new().with_interrupted())
+            .with_printer(Printer::capturing(false));
+        service
+            .fs
+            .with_directory(Path::new("/mock/parent/my-repo"), "my-repo");
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &in_parent(),
+            &ZellijOptions::default(),
+        );
+
+        // zesh didn't create the directory, so it isn't its to remove
+        assert!(matches!(result, Err(CloneError::Interrupted)));
+        assert!(service.fs.removed.borrow().is_empty());
+        assert!(service.fs.exists(Path::new("/mock/parent/my-repo")));
+    }
+
+    #[test]
+    fn test_clone_repo_success() {
+        let service = create_service(TestGit::success());
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &in_parent(),
+            &ZellijOptions::default(),
+        );
+
+        assert!(result.is_ok());
+
+        // Verify session was created
+        let sessions = service.zellij.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "my-repo");
+        assert_eq!(
+            service.store.lookup("my-repo"),
+            Some(PathBuf::from("/mock/parent/my-repo"))
+        );
+    }
+
+    #[test]
+    fn test_resolve_repo_url_prefers_local_paths() {
+        let service = create_service(TestGit::success());
+        service
+            .fs
+            .with_directory(Path::new("/mock/current/vendor/lib"), "lib");
+        service
+            .fs
+            .with_directory(Path::new("/mock/repos/foo"), "foo");
+
+        assert_eq!(
+            service.resolve_repo_url("vendor/lib").unwrap(),
+            "/mock/current/vendor/lib"
+        );
+        assert_eq!(
+            service.resolve_repo_url("../repos/foo").unwrap(),
+            "/mock/repos/foo"
+        );
+        // Without a local directory it's still shorthand
+        assert_eq!(
+            service.resolve_repo_url("user/my-repo").unwrap(),
+            "https://github.com/user/my-repo"
+        );
+    }
 
-        let parent_dir = if let Some(p) = path {
-            p.clone()
-        } else {
-            self.fs.current_dir()?
-        };
+    #[test]
+    fn test_clone_repo_from_deleted_directory() {
+        let service = create_service(TestGit::success()).with_printer(Printer::capturing(false));
+        service.fs.with_deleted_current_dir();
 
-        let clone_path = parent_dir.join(repo_name);
-        let parent_dir_str = parent_dir
-            .to_str()
-            .ok_or_else(|| CloneError::InvalidPath(parent_dir.display().to_string()))?;
+        service
+            .clone_repo(
+                "https://github.com/user/my-repo.git",
+                &CloneOptions::default(),
+                &ZellijOptions::default(),
+            )
+            .unwrap();
 
-        println!("Cloning {} into {}...", repo_url, clone_path.display());
-        self.git.clone(repo_url, parent_dir_str, repo_name)?;
+        // Cloned into the home directory instead of failing
+        assert_eq!(
+            service.store.lookup("my-repo"),
+            Some(PathBuf::from("/mock/home/my-repo"))
+        );
+        assert!(
+            service.printer.captured()[0]
+                .starts_with("Warning: can't read the current directory (")
+        );
+    }
 
-        println!(
-            "Creating new session '{}' at {}",
-            session_name,
-            clone_path.display()
+    #[test]
+    fn test_clone_repo_no_attach_creates_detached_session() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([("work".to_string(), true)]));
+        let service = CloneService::new(
+            zellij,
+            MockZoxideClient::new(),
+            MockFs::new(),
+            TestGit::success(),
         );
 
-        self.fs.set_current_dir(&clone_path)?;
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &CloneOptions {
+                no_attach: true,
+                ..in_parent()
+            },
+            &ZellijOptions::default(),
+        );
 
-        self.zellij.new_session(session_name, zellij_options)?;
+        assert!(result.is_ok());
 
-        self.zoxide.add(&clone_path)?;
+        // The new session exists, but we're still attached to the old one
+        let sessions = service.zellij.list_sessions().unwrap();
+        let cloned = sessions.iter().find(|s| s.name == "my-repo").unwrap();
+        assert!(!cloned.is_current);
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("work".to_string())
+        );
+        assert_eq!(
+            service.store.lookup("my-repo"),
+            Some(PathBuf::from("/mock/parent/my-repo"))
+        );
+    }
 
-        Ok(())
+    fn rooted_service(
+        clone_by_host: bool,
+    ) -> CloneService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
+        CloneService::new(
+            MockZellijClient::new(),
+            MockZoxideClient::new(),
+            MockFs::new(),
+            TestGit::success(),
+        )
+        .with_config(Config {
+            clone_root: Some(PathBuf::from("~/src")),
+            clone_by_host,
+            ..Config::default()
+        })
     }
-}
 
-/// Extract repository name from URL
-pub fn extract_repo_name(url: &str) -> Result<&str, CloneError> {
-    let url = url.trim_end_matches(".git");
-    url.rsplit('/')
-        .next()
-        .filter(|s| !s.is_empty())
-        .ok_or(CloneError::InvalidRepoUrl)
-}
+    #[test]
+    fn test_clone_repo_into_clone_root() {
+        let service = rooted_service(false);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::fs::tests::MockFs;
-    use std::path::{Path, PathBuf};
-    use zellij_rs::{MockZellijClient, ZellijError};
-    use zesh_git::GitError;
-    use zox_rs::{MockZoxideClient, ZoxideError};
+        service
+            .clone_repo(
+                "https://github.com/user/my-repo.git",
+                &CloneOptions::default(),
+                &ZellijOptions::default(),
+            )
+            .unwrap();
 
-    struct TestGit {
-        should_fail: bool,
+        assert_eq!(
+            service.store.lookup("my-repo"),
+            Some(PathBuf::from("/mock/home/src/my-repo"))
+        );
     }
 
-    impl TestGit {
-        fn success() -> Self {
-            Self { should_fail: false }
-        }
+    #[test]
+    fn test_clone_repo_into_host_dir() {
+        let service = rooted_service(true);
 
-        fn failing() -> Self {
-            Self { should_fail: true }
-        }
+        service
+            .clone_repo(
+                "git@gitlab.com:group/sub/my-repo.git",
+                &CloneOptions::default(),
+                &ZellijOptions::default(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            service.store.lookup("my-repo"),
+            Some(PathBuf::from("/mock/home/src/gitlab.com/group/sub/my-repo"))
+        );
     }
 
-    impl Git for TestGit {
-        fn show_top_level(&self, _name: &str) -> Result<(bool, String), GitError> {
-            Ok((false, String::new()))
-        }
+    #[test]
+    fn test_clone_repo_path_overrides_clone_root() {
+        let service = rooted_service(true);
 
-        fn git_common_dir(&self, _name: &str) -> Result<(bool, String), GitError> {
-            Ok((false, String::new()))
-        }
+        service
+            .clone_repo(
+                "https://github.com/user/my-repo.git",
+                &in_parent(),
+                &ZellijOptions::default(),
+            )
+            .unwrap();
 
-        fn clone(&self, _url: &str, _cmd_dir: &str, _dir: &str) -> Result<String, GitError> {
-            if self.should_fail {
-                Err(GitError::CommandError("clone failed".to_string()))
-            } else {
-                Ok("Clone successful".to_string())
-            }
-        }
+        assert_eq!(
+            service.store.lookup("my-repo"),
+            Some(PathBuf::from("/mock/parent/my-repo"))
+        );
     }
 
-    fn create_service(
-        git: TestGit,
-    ) -> CloneService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
-        let zellij = MockZellijClient::new();
-        let zoxide = MockZoxideClient::new();
-        let fs = MockFs::new();
-        CloneService::new(zellij, zoxide, fs, git)
+    #[test]
+    fn test_repo_owner_dir() {
+        let dir = |url| repo_owner_dir(url).map(|d| d.display().to_string());
+
+        assert_eq!(
+            dir("https://github.com/user/repo").as_deref(),
+            Some("github.com/user")
+        );
+        assert_eq!(
+            dir("git@github.com:user/repo.git").as_deref(),
+            Some("github.com/user")
+        );
+        assert_eq!(
+            dir("ssh://git@example.com:2222/team/repo.git").as_deref(),
+            Some("example.com/team")
+        );
+        assert_eq!(dir("file:///srv/git/repo"), None);
+        assert_eq!(dir("/srv/git/repo"), None);
+        assert_eq!(dir("https://evil.com/../../repo"), None);
     }
 
     #[test]
-    fn test_extract_repo_name_https() {
-        let name = extract_repo_name("https://github.com/user/my-repo.git").unwrap();
-        assert_eq!(name, "my-repo");
+    fn test_clone_repo_as_tab() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([("work".to_string(), true)]));
+        let service = CloneService::new(
+            zellij,
+            MockZoxideClient::new(),
+            MockFs::new(),
+            TestGit::success(),
+        );
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &CloneOptions {
+                target: Target::Tab,
+                ..in_parent()
+            },
+            &ZellijOptions::default(),
+        );
+
+        assert!(result.is_ok());
+
+        // A tab opened in the clone, and no session was created
+        let tabs = service.zellij.list_tabs().unwrap();
+        assert_eq!(tabs.len(), 1);
+        assert_eq!(tabs[0].name.as_deref(), Some("my-repo"));
+        assert_eq!(
+            service.zellij.tab_cwd(0),
+            Some(PathBuf::from("/mock/parent/my-repo"))
+        );
+        let sessions = service.zellij.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert!(service.zellij.session_options("my-repo").is_none());
+        assert_eq!(service.store.lookup("my-repo"), None);
+        assert_eq!(
+            service.zoxide.list().unwrap()[0].path,
+            PathBuf::from("/mock/parent/my-repo")
+        );
     }
 
     #[test]
-    fn test_extract_repo_name_https_no_git_suffix() {
-        let name = extract_repo_name("https://github.com/user/my-repo").unwrap();
-        assert_eq!(name, "my-repo");
+    fn test_clone_repo_as_tab_outside_session() {
+        let service = CloneService::new(
+            MockZellijClient::new(),
+            MockZoxideClient::new(),
+            MockFs::new(),
+            TestGit::failing(),
+        );
+
+        // Fails before cloning, or the failing git would be reported
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &CloneOptions {
+                target: Target::Tab,
+                ..in_parent()
+            },
+            &ZellijOptions::default(),
+        );
+
+        assert!(matches!(result, Err(CloneError::NoSession)));
+        assert!(service.zellij.list_tabs().unwrap().is_empty());
     }
 
-    #[test]
-    fn test_extract_repo_name_ssh() {
-        let name = extract_repo_name("git@github.com:user/my-repo.git").unwrap();
-        assert_eq!(name, "my-repo");
+    fn colliding_service(
+        policy: CollisionPolicy,
+    ) -> CloneService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
+        let zellij =
+            MockZellijClient::with_sessions(HashMap::from([("my-repo".to_string(), false)]));
+        let store = SessionStore::new();
+        store.record("my-repo", Path::new("/src/my-repo")).unwrap();
+        CloneService::new(
+            zellij,
+            MockZoxideClient::new(),
+            MockFs::new(),
+            TestGit::success(),
+        )
+        .with_store(store)
+        .with_config(Config {
+            collision_policy: policy,
+            ..Config::default()
+        })
     }
 
     #[test]
-    fn test_extract_repo_name_trailing_slash() {
-        let result = extract_repo_name("/");
-        assert!(result.is_err());
+    fn test_clone_repo_collision_attach() {
+        let service = colliding_service(CollisionPolicy::Attach);
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &in_parent(),
+            &ZellijOptions::default(),
+        );
+
+        assert!(result.is_ok());
+        let sessions = service.zellij.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert!(sessions[0].is_current);
+        // The existing session keeps its root
+        assert_eq!(
+            service.store.lookup("my-repo"),
+            Some(PathBuf::from("/src/my-repo"))
+        );
     }
 
     #[test]
-    fn test_clone_repo_success() {
-        let service = create_service(TestGit::success());
+    fn test_clone_repo_collision_suffix() {
+        let service = colliding_service(CollisionPolicy::Suffix);
 
         let result = service.clone_repo(
             "https://github.com/user/my-repo.git",
-            None,
-            Some(&PathBuf::from("/mock/parent")),
+            &in_parent(),
             &ZellijOptions::default(),
         );
 
         assert!(result.is_ok());
+        assert_eq!(service.zellij.list_sessions().unwrap().len(), 2);
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("my-repo-2".to_string())
+        );
+        assert_eq!(
+            service.store.lookup("my-repo-2"),
+            Some(PathBuf::from("/mock/parent/my-repo"))
+        );
+    }
 
-        // Verify session was created
-        let sessions = service.zellij.list_sessions().unwrap();
-        assert_eq!(sessions.len(), 1);
-        assert_eq!(sessions[0].name, "my-repo");
+    #[test]
+    fn test_clone_repo_collision_error() {
+        let service =
+            colliding_service(CollisionPolicy::Error).with_printer(Printer::capturing(false));
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &in_parent(),
+            &ZellijOptions::default(),
+        );
+
+        assert!(matches!(result, Err(CloneError::SessionExists(name)) if name == "my-repo"));
+        // Fails before cloning anything
+        assert!(service.printer.captured().is_empty());
+        assert_eq!(service.zellij.current_session().unwrap(), None);
     }
 
     #[test]
@@ -206,8 +2065,10 @@ mod tests {
 
         let result = service.clone_repo(
             "https://github.com/user/my-repo.git",
-            Some("custom-session"),
-            Some(&PathBuf::from("/mock/parent")),
+            &CloneOptions {
+                name: Some("custom-session".to_string()),
+                ..in_parent()
+            },
             &ZellijOptions::default(),
         );
 
@@ -225,8 +2086,7 @@ mod tests {
 
         let result = service.clone_repo(
             "https://github.com/user/my-repo.git",
-            None,
-            None,
+            &CloneOptions::default(),
             &ZellijOptions::default(),
         );
 
@@ -243,8 +2103,7 @@ mod tests {
 
         let result = service.clone_repo(
             "https://github.com/user/my-repo.git",
-            None,
-            Some(&PathBuf::from("/mock/parent")),
+            &in_parent(),
             &ZellijOptions::default(),
         );
 
@@ -260,12 +2119,7 @@ mod tests {
     fn test_clone_repo_invalid_url() {
         let service = create_service(TestGit::success());
 
-        let result = service.clone_repo(
-            "/",
-            None,
-            Some(&PathBuf::from("/mock/parent")),
-            &ZellijOptions::default(),
-        );
+        let result = service.clone_repo("/", &in_parent(), &ZellijOptions::default());
 
         assert!(result.is_err());
         assert!(matches!(result, Err(CloneError::InvalidRepoUrl)));
@@ -279,30 +2133,86 @@ mod tests {
             fn list_sessions(&self) -> zellij_rs::ZellijResult<Vec<zellij_rs::Session>> {
                 Err(ZellijError::CommandExecution("Command failed".to_string()))
             }
+            fn is_attached(&self) -> bool {
+                false
+            }
             fn attach_session(&self, _: &str) -> zellij_rs::ZellijResult<()> {
                 Err(ZellijError::CommandExecution("Command failed".to_string()))
             }
+            fn attach_session_with(
+                &self,
+                _: &str,
+                _: bool,
+                _: &ZellijOptions,
+            ) -> zellij_rs::ZellijResult<()> {
+                Err(ZellijError::CommandExecution("Command failed".to_string()))
+            }
+            fn switch_session(&self, _: &str) -> zellij_rs::ZellijResult<()> {
+                Err(ZellijError::CommandExecution("Command failed".to_string()))
+            }
             fn new_session(&self, _: &str, _: &ZellijOptions) -> zellij_rs::ZellijResult<()> {
                 Err(ZellijError::CommandExecution("Command failed".to_string()))
             }
+            fn new_session_detached(
+                &self,
+                _: &str,
+                _: &ZellijOptions,
+            ) -> zellij_rs::ZellijResult<()> {
+                Err(ZellijError::CommandExecution("Command failed".to_string()))
+            }
             fn kill_session(&self, _: &str) -> zellij_rs::ZellijResult<()> {
                 Err(ZellijError::CommandExecution("Command failed".to_string()))
             }
+            fn rename_session(&self, _: &str) -> zellij_rs::ZellijResult<()> {
+                Err(ZellijError::CommandExecution("Command failed".to_string()))
+            }
+            fn detach(&self) -> zellij_rs::ZellijResult<()> {
+                Err(ZellijError::CommandExecution("Command failed".to_string()))
+            }
             fn list_tabs(&self) -> zellij_rs::ZellijResult<Vec<zellij_rs::Tab>> {
                 Err(ZellijError::CommandExecution("Command failed".to_string()))
             }
-            fn new_tab(&self, _: Option<&str>) -> zellij_rs::ZellijResult<()> {
+            fn pane_cwds(&self) -> zellij_rs::ZellijResult<Vec<(u32, PathBuf)>> {
+                Err(ZellijError::CommandExecution("Command failed".to_string()))
+            }
+            fn new_tab(&self, _: Option<&str>, _: Option<&Path>) -> zellij_rs::ZellijResult<()> {
+                Err(ZellijError::CommandExecution("Command failed".to_string()))
+            }
+            fn new_tab_in(
+                &self,
+                _: &str,
+                _: Option<&str>,
+                _: Option<&Path>,
+            ) -> zellij_rs::ZellijResult<()> {
                 Err(ZellijError::CommandExecution("Command failed".to_string()))
             }
             fn rename_tab(&self, _: &str) -> zellij_rs::ZellijResult<()> {
                 Err(ZellijError::CommandExecution("Command failed".to_string()))
             }
+            fn go_to_tab(&self, _: &str, _: &str) -> zellij_rs::ZellijResult<()> {
+                Err(ZellijError::CommandExecution("Command failed".to_string()))
+            }
             fn close_tab(&self) -> zellij_rs::ZellijResult<()> {
                 Err(ZellijError::CommandExecution("Command failed".to_string()))
             }
+            fn move_pane_to_tab(&self, _: u32) -> zellij_rs::ZellijResult<()> {
+                Err(ZellijError::CommandExecution("Command failed".to_string()))
+            }
+            fn toggle_floating_panes(&self) -> zellij_rs::ZellijResult<()> {
+                Err(ZellijError::CommandExecution("Command failed".to_string()))
+            }
+            fn toggle_pane_embed_or_floating(&self) -> zellij_rs::ZellijResult<()> {
+                Err(ZellijError::CommandExecution("Command failed".to_string()))
+            }
             fn run_command(&self, _: &str, _: &[&str]) -> zellij_rs::ZellijResult<()> {
                 Err(ZellijError::CommandExecution("Command failed".to_string()))
             }
+            fn run_command_in(&self, _: &str, _: &str, _: &[&str]) -> zellij_rs::ZellijResult<()> {
+                Err(ZellijError::CommandExecution("Command failed".to_string()))
+            }
+            fn version(&self) -> zellij_rs::ZellijResult<String> {
+                Err(ZellijError::CommandExecution("Command failed".to_string()))
+            }
         }
 
         let service = CloneService::new(
@@ -314,8 +2224,7 @@ mod tests {
 
         let result = service.clone_repo(
             "https://github.com/user/my-repo.git",
-            None,
-            Some(&PathBuf::from("/mock/parent")),
+            &in_parent(),
             &ZellijOptions::default(),
         );
 
@@ -330,12 +2239,24 @@ mod tests {
             fn add<P: AsRef<Path>>(&self, _: P) -> zox_rs::ZoxideResult<()> {
                 Err(ZoxideError::CommandExecution("Command failed".to_string()))
             }
+            fn add_with_score<P: AsRef<Path>>(&self, _: P, _: f64) -> zox_rs::ZoxideResult<()> {
+                Err(ZoxideError::CommandExecution("Command failed".to_string()))
+            }
             fn list(&self) -> zox_rs::ZoxideResult<Vec<zox_rs::ZoxideEntry>> {
                 Err(ZoxideError::CommandExecution("Command failed".to_string()))
             }
             fn query(&self, _: &[&str]) -> zox_rs::ZoxideResult<Vec<zox_rs::ZoxideEntry>> {
                 Err(ZoxideError::CommandExecution("Command failed".to_string()))
             }
+            fn version(&self) -> zox_rs::ZoxideResult<String> {
+                Err(ZoxideError::CommandExecution("Command failed".to_string()))
+            }
+            fn score_for<P: AsRef<std::path::Path>>(
+                &self,
+                _: P,
+            ) -> zox_rs::ZoxideResult<Option<f64>> {
+                Err(ZoxideError::CommandExecution("Command failed".to_string()))
+            }
         }
 
         let service = CloneService::new(
@@ -347,12 +2268,105 @@ mod tests {
 
         let result = service.clone_repo(
             "https://github.com/user/my-repo.git",
-            None,
-            Some(&PathBuf::from("/mock/parent")),
+            &in_parent(),
             &ZellijOptions::default(),
         );
 
         assert!(result.is_err());
         assert!(matches!(result, Err(CloneError::Zoxide(_))));
     }
+
+    #[test]
+    fn test_clone_repo_inside_existing_repo_errors() {
+        // MockGit reports every directory as part of /mock/repo/top-level
+        let service = CloneService::new(
+            MockZellijClient::new(),
+            MockZoxideClient::new(),
+            MockFs::new(),
+            MockGit::new(),
+        );
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &in_parent(),
+            &ZellijOptions::default(),
+        );
+
+        match result {
+            Err(CloneError::NestedRepo(path, repo)) => {
+                assert_eq!(path, "/mock/parent/my-repo");
+                assert_eq!(repo, "/mock/repo/top-level");
+            }
+            _ => panic!("Expected CloneError::NestedRepo"),
+        }
+
+        // Nothing was created
+        let sessions = service.zellij.list_sessions().unwrap();
+        assert!(sessions.is_empty());
+    }
+
+    #[test]
+    fn test_clone_repo_inside_existing_repo_with_force() {
+        let service = CloneService::new(
+            MockZellijClient::new(),
+            MockZoxideClient::new(),
+            MockFs::new(),
+            MockGit::new(),
+        )
+        .with_printer(Printer::capturing(false));
+
+        let result = service.clone_repo(
+            "https://github.com/user/my-repo.git",
+            &CloneOptions {
+                force: true,
+                ..in_parent()
+            },
+            &ZellijOptions::default(),
+        );
+
+        assert!(result.is_ok());
+        assert!(
+            service.printer.captured()[0]
+                .starts_with("Warning: cloning into /mock/parent/my-repo, which is inside")
+        );
+        let sessions = service.zellij.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "my-repo");
+    }
+
+    #[test]
+    fn test_clone_repo_bare_with_worktree() {
+        let service = CloneService::new(
+            MockZellijClient::new(),
+            MockZoxideClient::new(),
+            MockFs::new(),
+            MockGit::new(),
+        );
+
+        service
+            .clone_repo(
+                "https://github.com/user/my-repo.git",
+                &CloneOptions {
+                    bare: true,
+                    force: true,
+                    ..in_parent()
+                },
+                &ZellijOptions::default(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            service.git.calls(),
+            vec![
+                "clone --bare https://github.com/user/my-repo.git my-repo.git",
+                "-C /mock/parent/my-repo.git worktree add /mock/parent/my-repo/main main",
+            ]
+        );
+
+        // The session and zoxide entry are for the worktree
+        let worktree = Path::new("/mock/parent/my-repo/main");
+        assert_eq!(service.fs.current_dir().unwrap(), worktree);
+        assert_eq!(service.zellij.list_sessions().unwrap()[0].name, "my-repo");
+        assert!(service.zoxide.score_for(worktree).unwrap().is_some());
+    }
 }
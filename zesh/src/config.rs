@@ -0,0 +1,661 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+use zesh_git::Git;
+
+use crate::fs::{FsError, FsOperations};
+
+/// Default number of seconds to wait for a zellij, zoxide or git command
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Default number of attempts when attaching to a session that is starting up
+pub const DEFAULT_ATTACH_ATTEMPTS: u32 = 3;
+
+/// Default delay before the first attach retry, in milliseconds
+pub const DEFAULT_ATTACH_BACKOFF_MS: u64 = 100;
+
+/// Default delay before the first `zesh clone --retries` retry, in milliseconds
+pub const DEFAULT_CLONE_BACKOFF_MS: u64 = 1000;
+
+/// Default host used to expand `user/repo` shorthand in `zesh clone`
+pub const DEFAULT_GIT_HOST: &str = "github.com";
+
+/// Per-project config file, merged over the user's config
+pub const REPO_CONFIG_FILE: &str = ".zesh.toml";
+
+/// Environment variable selecting a config profile, like `--profile`
+pub const PROFILE_ENV: &str = "ZESH_PROFILE";
+
+/// What to do when a session zesh is about to create already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollisionPolicy {
+    /// Attach to the existing session
+    #[default]
+    Attach,
+    /// Create a new session with a numeric suffix, like `project-2`
+    Suffix,
+    /// Fail without touching the existing session
+    Error,
+}
+
+/// Where zesh gets its ranked directories from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DirectoryBackend {
+    /// zoxide's database
+    #[default]
+    Zoxide,
+    /// zesh's own [`FrecencyStore`](crate::frecency::FrecencyStore), which
+    /// leaves zoxide's database alone
+    Zesh,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file {0}: {1}")]
+    Read(String, std::io::Error),
+
+    #[error("Failed to parse config file: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("Failed to read config file: {0}")]
+    Fs(#[from] FsError),
+
+    #[error("No [profiles.{0}] in the config file")]
+    UnknownProfile(String),
+}
+
+/// User configuration, read from `<config dir>/zesh/config.toml`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Seconds to wait for non-interactive zellij, zoxide and git commands.
+    /// `0` disables the timeout.
+    pub timeout: u64,
+
+    /// Host used to expand `user/repo` shorthand when cloning
+    pub default_host: String,
+
+    /// Shell command run in a freshly cloned repository before its session
+    /// is created, e.g. `npm install`
+    pub post_clone: Option<String>,
+
+    /// How many times to try attaching to a session that is still starting up
+    pub attach_attempts: u32,
+
+    /// Milliseconds to wait before the first attach retry, doubling after
+    /// every failed attempt
+    pub attach_backoff_ms: u64,
+
+    /// What to do when a session that is about to be created already exists
+    pub collision_policy: CollisionPolicy,
+
+    /// Milliseconds to wait before retrying a clone that failed on a network
+    /// error, doubling after every failed attempt
+    pub clone_backoff_ms: u64,
+
+    /// Directory to clone into when no path is given, instead of the
+    /// current directory. A leading `~` is expanded.
+    pub clone_root: Option<PathBuf>,
+
+    /// Clone into `<clone_root>/<host>/<owner>/<repo>` instead of
+    /// `<clone_root>/<repo>`
+    pub clone_by_host: bool,
+
+    /// Glob patterns of directories to leave out of zoxide listings and
+    /// `zesh up --glob`, e.g. `node_modules` or `~/src/archive/*`
+    pub ignore: Vec<String>,
+
+    /// Rank directories with zoxide or with zesh's own store
+    pub directory_backend: DirectoryBackend,
+
+    /// File zesh's own store is kept in, defaulting to one in the state
+    /// dir. A project's `.zesh.toml` can point this elsewhere to keep
+    /// rankings of its own. A leading `~` is expanded.
+    pub frecency_file: Option<PathBuf>,
+
+    /// Name and root sessions after the directory a symlink points to. When
+    /// off, a directory reached through a symlink keeps the path it was
+    /// given by.
+    pub follow_symlinks: bool,
+
+    /// Editor `zesh clone --edit` opens the clone in, e.g. `nvim` or
+    /// `code -w`. Defaults to `$EDITOR`.
+    pub editor: Option<String>,
+
+    /// Only create sessions and clones for directories under one of these,
+    /// e.g. `~/src`. Empty allows any directory.
+    pub allowed_roots: Vec<PathBuf>,
+
+    /// Subdirectories of a project to open a named tab in when its session
+    /// is created, e.g. `["frontend", "backend"]`. Ones a project doesn't
+    /// have are skipped.
+    pub auto_tabs: Vec<String>,
+
+    /// Connect to a session by a prefix of its name, like `zesh cn proj` for
+    /// `project`, when it's the only session starting with it. This wins
+    /// over directories and zoxide, so it's off by default.
+    pub attach_by_prefix: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT_SECS,
+            default_host: DEFAULT_GIT_HOST.to_string(),
+            post_clone: None,
+            attach_attempts: DEFAULT_ATTACH_ATTEMPTS,
+            attach_backoff_ms: DEFAULT_ATTACH_BACKOFF_MS,
+            collision_policy: CollisionPolicy::default(),
+            clone_backoff_ms: DEFAULT_CLONE_BACKOFF_MS,
+            clone_root: None,
+            clone_by_host: false,
+            ignore: Vec::new(),
+            directory_backend: DirectoryBackend::default(),
+            frecency_file: None,
+            follow_symlinks: true,
+            editor: None,
+            allowed_roots: Vec::new(),
+            auto_tabs: Vec::new(),
+            attach_by_prefix: false,
+        }
+    }
+}
+
+impl Config {
+    /// Location of the user's config file, if a config dir is known
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("zesh").join("config.toml"))
+    }
+
+    /// Load the user's config file, falling back to defaults if it doesn't exist
+    pub fn load() -> Result<Self, ConfigError> {
+        match Self::path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Load config from a specific file
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::Read(path.display().to_string(), e))?;
+        Self::parse(&contents)
+    }
+
+    /// Parse config from TOML
+    pub fn parse(contents: &str) -> Result<Self, ConfigError> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// The [`auto_tabs`](Config::auto_tabs) that are directories under
+    /// `root`, with the directory each tab opens in. Entries the project
+    /// doesn't have are skipped.
+    pub fn auto_tab_dirs<F: FsOperations>(&self, fs: &F, root: &Path) -> Vec<(String, PathBuf)> {
+        self.auto_tabs
+            .iter()
+            .map(|name| (name.clone(), root.join(name)))
+            .filter(|(_, dir)| fs.is_dir(dir))
+            .collect()
+    }
+
+    /// Load the user's config with the nearest [`REPO_CONFIG_FILE`] for `path`
+    /// merged over it, see [`Config::find_repo_config`]. Settings the repo
+    /// file leaves out keep their user (or default) values.
+    ///
+    /// With a `profile`, the user config's `[profiles.<profile>]` table is
+    /// merged over the rest of it first, e.g. to clone work repositories
+    /// somewhere else. The repo file still wins over the profile.
+    pub fn load_for_path<F: FsOperations, G: Git>(
+        path: &Path,
+        profile: Option<&str>,
+        fs: &F,
+        git: &G,
+    ) -> Result<Self, ConfigError> {
+        Self::load_layered(Self::path().as_deref(), profile, path, fs, git)
+    }
+
+    fn load_layered<F: FsOperations, G: Git>(
+        global: Option<&Path>,
+        profile: Option<&str>,
+        path: &Path,
+        fs: &F,
+        git: &G,
+    ) -> Result<Self, ConfigError> {
+        let mut table: toml::Table = match global {
+            Some(global) if fs.exists(global) => toml::from_str(&fs.read_to_string(global)?)?,
+            _ => toml::Table::new(),
+        };
+
+        let mut profiles = match table.remove("profiles") {
+            Some(toml::Value::Table(profiles)) => profiles,
+            _ => toml::Table::new(),
+        };
+        if let Some(profile) = profile {
+            match profiles.remove(profile) {
+                Some(toml::Value::Table(overrides)) => table.extend(overrides),
+                _ => return Err(ConfigError::UnknownProfile(profile.to_string())),
+            }
+        }
+
+        if let Some(repo_config) = Self::find_repo_config(path, fs, git) {
+            let repo_table: toml::Table = toml::from_str(&fs.read_to_string(&repo_config)?)?;
+            table.extend(repo_table);
+        }
+
+        Ok(toml::Value::Table(table).try_into()?)
+    }
+
+    /// Find the [`REPO_CONFIG_FILE`] closest to `path`, looking in `path` and
+    /// its parents up to the root of the git repository it's in. Outside a
+    /// repository only `path` itself is checked.
+    pub fn find_repo_config<F: FsOperations, G: Git>(
+        path: &Path,
+        fs: &F,
+        git: &G,
+    ) -> Option<PathBuf> {
+        let top_level = match path.to_str().map(|p| git.show_top_level(p)) {
+            Some(Ok((true, root))) => Some(PathBuf::from(root)),
+            _ => None,
+        }
+        .filter(|root| path.starts_with(root));
+
+        for dir in path.ancestors() {
+            let candidate = dir.join(REPO_CONFIG_FILE);
+            if fs.exists(&candidate) {
+                return Some(candidate);
+            }
+            // Stop at the repo root, and don't walk up at all outside a repo
+            if top_level.as_deref().is_none_or(|root| dir == root) {
+                break;
+            }
+        }
+
+        None
+    }
+
+    /// The subprocess timeout, or `None` if disabled
+    pub fn timeout(&self) -> Option<Duration> {
+        (self.timeout > 0).then(|| Duration::from_secs(self.timeout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::tests::MockFs;
+    use zesh_git::{GitError, MockGit};
+
+    /// MockGit puts everything in the repository at this root
+    const REPO: &str = "/mock/repo/top-level";
+
+    struct NoRepo;
+
+    impl Git for NoRepo {
+        fn show_top_level(&self, _: &str) -> Result<(bool, String), GitError> {
+            Ok((false, "fatal: not a git repository".to_string()))
+        }
+
+        fn is_inside_work_tree(&self, _: &str) -> Result<bool, GitError> {
+            Ok(false)
+        }
+
+        fn git_common_dir(&self, _: &str) -> Result<(bool, String), GitError> {
+            Ok((false, "fatal: not a git repository".to_string()))
+        }
+
+        fn clone(&self, _: &str, _: &str, _: &str) -> Result<String, GitError> {
+            unimplemented!()
+        }
+
+        fn clone_bare(&self, _: &str, _: &str, _: &str) -> Result<String, GitError> {
+            unimplemented!()
+        }
+
+        fn worktree_add(&self, _: &str, _: &str, _: &str) -> Result<(), GitError> {
+            unimplemented!()
+        }
+
+        fn version(&self) -> Result<String, GitError> {
+            Ok("git version 2.43.0".to_string())
+        }
+
+        fn default_branch(&self, _: &str) -> Result<String, GitError> {
+            unimplemented!()
+        }
+    }
+
+    fn repo_config(dir: &str) -> PathBuf {
+        Path::new(dir).join(REPO_CONFIG_FILE)
+    }
+
+    #[test]
+    fn test_find_repo_config_in_target_dir() {
+        let fs = MockFs::new();
+        let target = format!("{}/crates/api", REPO);
+        fs.with_file(&repo_config(&target));
+        fs.with_file(&repo_config(REPO));
+
+        // The closest file wins
+        assert_eq!(
+            Config::find_repo_config(Path::new(&target), &fs, &MockGit::new()),
+            Some(repo_config(&target))
+        );
+    }
+
+    #[test]
+    fn test_find_repo_config_in_ancestor() {
+        let fs = MockFs::new();
+        fs.with_file(&repo_config(&format!("{}/crates", REPO)));
+
+        let target = format!("{}/crates/api/src", REPO);
+        assert_eq!(
+            Config::find_repo_config(Path::new(&target), &fs, &MockGit::new()),
+            Some(repo_config(&format!("{}/crates", REPO)))
+        );
+    }
+
+    #[test]
+    fn test_find_repo_config_at_repo_root() {
+        let fs = MockFs::new();
+        fs.with_file(&repo_config(REPO));
+
+        let target = format!("{}/crates/api", REPO);
+        assert_eq!(
+            Config::find_repo_config(Path::new(&target), &fs, &MockGit::new()),
+            Some(repo_config(REPO))
+        );
+    }
+
+    #[test]
+    fn test_find_repo_config_stops_at_repo_root() {
+        let fs = MockFs::new();
+        fs.with_file(&repo_config("/mock/repo"));
+
+        let target = format!("{}/crates/api", REPO);
+        assert_eq!(
+            Config::find_repo_config(Path::new(&target), &fs, &MockGit::new()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_find_repo_config_outside_repo() {
+        let fs = MockFs::new();
+        fs.with_file(&repo_config("/mock/projects"));
+        fs.with_file(&repo_config("/mock/projects/notes"));
+
+        assert_eq!(
+            Config::find_repo_config(Path::new("/mock/projects/notes"), &fs, &NoRepo),
+            Some(repo_config("/mock/projects/notes"))
+        );
+        // Without a repo to bound the search, parents aren't checked
+        assert_eq!(
+            Config::find_repo_config(Path::new("/mock/projects/notes/src"), &fs, &NoRepo),
+            None
+        );
+    }
+
+    #[test]
+    fn test_load_layered_merges_repo_config_over_global() {
+        let fs = MockFs::new();
+        let global = Path::new("/mock/config/zesh/config.toml");
+        fs.with_file_contents(global, "timeout = 5\ndefault_host = \"gitlab.com\"");
+        fs.with_file_contents(
+            &repo_config(REPO),
+            "default_host = \"git.example.com\"\ncollision_policy = \"suffix\"",
+        );
+
+        let config = Config::load_layered(
+            Some(global),
+            None,
+            &Path::new(REPO).join("src"),
+            &fs,
+            &MockGit::new(),
+        )
+        .unwrap();
+
+        assert_eq!(config.timeout, 5);
+        assert_eq!(config.default_host, "git.example.com");
+        assert_eq!(config.collision_policy, CollisionPolicy::Suffix);
+        assert_eq!(config.attach_attempts, DEFAULT_ATTACH_ATTEMPTS);
+    }
+
+    #[test]
+    fn test_load_layered_without_any_files() {
+        let config = Config::load_layered(
+            None,
+            None,
+            Path::new("/mock/elsewhere"),
+            &MockFs::new(),
+            &NoRepo,
+        )
+        .unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn test_load_layered_invalid_repo_config() {
+        let fs = MockFs::new();
+        fs.with_file_contents(&repo_config(REPO), "timeout = \"soon\"");
+
+        let result = Config::load_layered(None, None, Path::new(REPO), &fs, &MockGit::new());
+        assert!(matches!(result, Err(ConfigError::Parse(_))));
+    }
+
+    const PROFILES: &str = r#"
+default_host = "github.com"
+clone_root = "~/src"
+timeout = 5
+
+[profiles.work]
+default_host = "git.work.example.com"
+clone_root = "~/work"
+
+[profiles.personal]
+clone_root = "~/code"
+clone_by_host = true
+"#;
+
+    fn load_profile(profile: Option<&str>) -> Result<Config, ConfigError> {
+        let fs = MockFs::new();
+        let global = Path::new("/mock/config/zesh/config.toml");
+        fs.with_file_contents(global, PROFILES);
+        Config::load_layered(
+            Some(global),
+            profile,
+            Path::new("/mock/elsewhere"),
+            &fs,
+            &NoRepo,
+        )
+    }
+
+    #[test]
+    fn test_load_profile() {
+        let work = load_profile(Some("work")).unwrap();
+        assert_eq!(work.default_host, "git.work.example.com");
+        assert_eq!(work.clone_root, Some(PathBuf::from("~/work")));
+        assert!(!work.clone_by_host);
+        assert_eq!(work.timeout, 5);
+
+        let personal = load_profile(Some("personal")).unwrap();
+        assert_eq!(personal.default_host, "github.com");
+        assert_eq!(personal.clone_root, Some(PathBuf::from("~/code")));
+        assert!(personal.clone_by_host);
+    }
+
+    #[test]
+    fn test_load_without_profile_ignores_profiles() {
+        let config = load_profile(None).unwrap();
+        assert_eq!(config.default_host, "github.com");
+        assert_eq!(config.clone_root, Some(PathBuf::from("~/src")));
+        assert!(!config.clone_by_host);
+    }
+
+    #[test]
+    fn test_load_unknown_profile() {
+        let result = load_profile(Some("school"));
+        assert!(matches!(result, Err(ConfigError::UnknownProfile(name)) if name == "school"));
+    }
+
+    #[test]
+    fn test_repo_config_wins_over_profile() {
+        let fs = MockFs::new();
+        let global = Path::new("/mock/config/zesh/config.toml");
+        fs.with_file_contents(global, PROFILES);
+        fs.with_file_contents(&repo_config(REPO), "clone_root = \"~/repo\"");
+
+        let config = Config::load_layered(
+            Some(global),
+            Some("work"),
+            Path::new(REPO),
+            &fs,
+            &MockGit::new(),
+        )
+        .unwrap();
+
+        assert_eq!(config.clone_root, Some(PathBuf::from("~/repo")));
+        assert_eq!(config.default_host, "git.work.example.com");
+    }
+
+    #[test]
+    fn test_default_timeout() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config, Config::default());
+        assert_eq!(
+            config.timeout(),
+            Some(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
+        );
+    }
+
+    #[test]
+    fn test_parse_timeout() {
+        let config = Config::parse("timeout = 5").unwrap();
+        assert_eq!(config.timeout(), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_zero_timeout_disables() {
+        let config = Config::parse("timeout = 0").unwrap();
+        assert_eq!(config.timeout(), None);
+    }
+
+    #[test]
+    fn test_parse_default_host() {
+        let config = Config::parse("default_host = \"gitlab.com\"").unwrap();
+        assert_eq!(config.default_host, "gitlab.com");
+        assert_eq!(config.timeout, DEFAULT_TIMEOUT_SECS);
+    }
+
+    #[test]
+    fn test_parse_post_clone() {
+        let config = Config::parse("post_clone = \"npm install\"").unwrap();
+        assert_eq!(config.post_clone.as_deref(), Some("npm install"));
+    }
+
+    #[test]
+    fn test_parse_clone_root() {
+        let config = Config::parse("clone_root = \"~/src\"\nclone_by_host = true").unwrap();
+        assert_eq!(config.clone_root, Some(PathBuf::from("~/src")));
+        assert!(config.clone_by_host);
+
+        let config = Config::default();
+        assert_eq!(config.clone_root, None);
+        assert!(!config.clone_by_host);
+    }
+
+    #[test]
+    fn test_parse_ignore() {
+        let config = Config::parse("ignore = [\"node_modules\", \"~/src/archive/*\"]").unwrap();
+        assert_eq!(config.ignore, vec!["node_modules", "~/src/archive/*"]);
+        assert!(Config::default().ignore.is_empty());
+    }
+
+    #[test]
+    fn test_parse_collision_policy() {
+        assert_eq!(Config::default().collision_policy, CollisionPolicy::Attach);
+        let config = Config::parse("collision_policy = \"suffix\"").unwrap();
+        assert_eq!(config.collision_policy, CollisionPolicy::Suffix);
+        let config = Config::parse("collision_policy = \"error\"").unwrap();
+        assert_eq!(config.collision_policy, CollisionPolicy::Error);
+        assert!(Config::parse("collision_policy = \"replace\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_directory_backend() {
+        assert_eq!(
+            Config::default().directory_backend,
+            DirectoryBackend::Zoxide
+        );
+        let config =
+            Config::parse("directory_backend = \"zesh\"\nfrecency_file = \"~/work.json\"").unwrap();
+        assert_eq!(config.directory_backend, DirectoryBackend::Zesh);
+        assert_eq!(config.frecency_file, Some(PathBuf::from("~/work.json")));
+        assert!(Config::parse("directory_backend = \"fasd\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_allowed_roots() {
+        assert!(Config::default().allowed_roots.is_empty());
+        let config = Config::parse("allowed_roots = [\"~/src\", \"/work\"]").unwrap();
+        assert_eq!(
+            config.allowed_roots,
+            vec![PathBuf::from("~/src"), PathBuf::from("/work")]
+        );
+    }
+
+    #[test]
+    fn test_parse_auto_tabs() {
+        assert!(Config::default().auto_tabs.is_empty());
+        let config = Config::parse("auto_tabs = [\"frontend\", \"backend\"]").unwrap();
+        assert_eq!(config.auto_tabs, vec!["frontend", "backend"]);
+    }
+
+    #[test]
+    fn test_auto_tab_dirs() {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/src/app/frontend"), "frontend");
+        fs.with_file_contents(Path::new("/src/app/docs"), "not a directory");
+        let config = Config {
+            auto_tabs: vec!["frontend".into(), "docs".into(), "backend".into()],
+            ..Config::default()
+        };
+
+        assert_eq!(
+            config.auto_tab_dirs(&fs, Path::new("/src/app")),
+            vec![("frontend".to_string(), PathBuf::from("/src/app/frontend"))]
+        );
+    }
+
+    #[test]
+    fn test_parse_attach_by_prefix() {
+        assert!(!Config::default().attach_by_prefix);
+        assert!(
+            Config::parse("attach_by_prefix = true")
+                .unwrap()
+                .attach_by_prefix
+        );
+    }
+
+    #[test]
+    fn test_parse_editor() {
+        assert_eq!(Config::default().editor, None);
+        let config = Config::parse("editor = \"code -w\"").unwrap();
+        assert_eq!(config.editor.as_deref(), Some("code -w"));
+    }
+
+    #[test]
+    fn test_parse_follow_symlinks() {
+        assert!(Config::default().follow_symlinks);
+        let config = Config::parse("follow_symlinks = false").unwrap();
+        assert!(!config.follow_symlinks);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let result = Config::parse("timeout = \"soon\"");
+        assert!(matches!(result, Err(ConfigError::Parse(_))));
+    }
+}
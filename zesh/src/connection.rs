@@ -1,9 +1,19 @@
+use std::io::BufRead;
 use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
 use thiserror::Error;
 use zesh_git::{Git, GitError};
 
-use crate::fs::{FsError, FsOperations};
-use zellij_rs::{Session, ZellijError, ZellijOperations, options::ZellijOptions};
+use crate::backend::SessionBackend;
+use crate::config::{CollisionPolicy, Config};
+use crate::fs::{FsError, FsOperations, is_under_allowed_roots};
+use crate::history::History;
+use crate::layout::{LayoutError, validate_layout};
+use crate::output::Printer;
+use crate::store::SessionStore;
+use crate::template::LayoutTemplate;
+use zellij_rs::{Session, ZellijError, ZellijOperations, ZellijResult, options::ZellijOptions};
 use zox_rs::{ZoxideError, ZoxideOperations};
 
 #[derive(Debug, Error)]
@@ -20,17 +30,158 @@ pub enum ConnectError {
     #[error("Git error: {0}")]
     Git(#[from] GitError),
 
+    #[error("{0}")]
+    Layout(#[from] LayoutError),
+
     #[error("No matching sessions or directories found for '{0}'")]
     NoMatch(String),
 
+    #[error("Already attached to session '{0}'")]
+    AlreadyAttached(String),
+
+    #[error("Session '{0}' already exists")]
+    SessionExists(String),
+
+    #[error("Not inside a zellij session, which opening a tab needs")]
+    NoSession,
+
+    #[error("Refusing to open {0}, which isn't under any of the allowed_roots")]
+    OutsideAllowedRoots(String),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
     #[error("Other error: {0}")]
     Other(String),
 }
 
+/// Where to open a directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Target {
+    /// A zellij session of its own
+    #[default]
+    Session,
+    /// A new tab in the session we're running inside of
+    Tab,
+}
+
+/// What [`ConnectService::connect`] tries first when a name could be a
+/// session, a directory or a zoxide query. The others follow in the default
+/// order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Prefer {
+    /// An existing session with that name
+    #[default]
+    Session,
+    /// A directory at that path
+    Dir,
+    /// The best zoxide match for the name
+    Zoxide,
+}
+
+impl Prefer {
+    /// The order to try sessions, directories and zoxide in
+    fn order(self) -> [Prefer; 3] {
+        match self {
+            Prefer::Session => [Prefer::Session, Prefer::Dir, Prefer::Zoxide],
+            Prefer::Dir => [Prefer::Dir, Prefer::Session, Prefer::Zoxide],
+            Prefer::Zoxide => [Prefer::Zoxide, Prefer::Session, Prefer::Dir],
+        }
+    }
+}
+
+/// The session a connection ended up in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectOutcome {
+    /// Name of the session, which can differ from the one asked for when
+    /// the collision policy picked a suffixed name. With [`Target::Tab`],
+    /// the session the tab was opened in.
+    pub session: String,
+    /// Whether the session (or tab) was created, rather than an existing
+    /// session attached to
+    pub created: bool,
+}
+
+impl ConnectOutcome {
+    fn created(session: &str) -> Self {
+        Self {
+            session: session.to_string(),
+            created: true,
+        }
+    }
+
+    fn attached(session: &str) -> Self {
+        Self {
+            session: session.to_string(),
+            created: false,
+        }
+    }
+}
+
+/// Whether a directory has a session, see [`ConnectService::status`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirStatus {
+    /// The name of the directory's session, whether or not it exists
+    pub session: String,
+    /// The directory, as its session would be rooted at
+    pub path: PathBuf,
+    /// The session exists, running or exited
+    pub exists: bool,
+    /// The session exists but is no longer running
+    pub exited: bool,
+    /// We're in the session right now
+    pub attached: bool,
+}
+
+/// How [`ConnectService::connect`] would interpret a name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveKind {
+    /// A running or exited session with that name
+    ExistingSession,
+    /// A directory at that path
+    Directory(PathBuf),
+    /// The best zoxide match for the name
+    ZoxideMatch(PathBuf),
+    NoMatch,
+}
+
+/// Options for connecting
+#[derive(Debug, Clone, Default)]
+pub struct ConnectOptions {
+    /// Create a new session with a suffixed name (`project-2`) instead of
+    /// attaching when the session name is already taken, whatever the
+    /// configured [`CollisionPolicy`]
+    pub force_new: bool,
+
+    /// Open directories in a session of their own or a tab of the current
+    /// one. Session names are only looked up for [`Target::Session`].
+    pub target: Target,
+
+    /// Make sure the session exists, creating it in the background if it
+    /// doesn't, but stay in the current session instead of attaching
+    pub keep_current: bool,
+
+    /// Lay out new sessions with a built-in template instead of the layout
+    /// in the zellij options
+    pub template: Option<LayoutTemplate>,
+
+    /// Whether a name is tried as a session, a directory or a zoxide query
+    /// first
+    pub prefer: Prefer,
+
+    /// Mark new sessions to be killed when detaching with
+    /// `zesh detach --kill`, for throwaway sessions
+    pub kill_on_detach: bool,
+
+    /// Resolve relative paths against the working directory of the focused
+    /// pane instead of the session root, when running inside a session
+    pub pane_cwd: bool,
+}
+
 /// Connect service handles connecting to zellij sessions, directories, or zoxide entries
 pub struct ConnectService<Z, X, F, G>
 where
-    Z: ZellijOperations,
+    Z: SessionBackend,
     X: ZoxideOperations,
     F: FsOperations,
     G: Git,
@@ -39,11 +190,16 @@ where
     zoxide: X,
     fs: F,
     git: G,
+    store: SessionStore,
+    history: History,
+    config: Config,
+    options: ConnectOptions,
+    printer: Printer,
 }
 
 impl<Z, X, F, G> ConnectService<Z, X, F, G>
 where
-    Z: ZellijOperations,
+    Z: SessionBackend,
     X: ZoxideOperations,
     F: FsOperations,
     G: Git,
@@ -55,99 +211,374 @@ where
             zoxide,
             fs,
             git,
+            store: SessionStore::new(),
+            history: History::new(),
+            config: Config::default(),
+            options: ConnectOptions::default(),
+            printer: Printer::default(),
         }
     }
 
-    /// Connect to a session by name, or a directory by path or zoxide query
-    pub fn connect(&self, name: &str, options: &ZellijOptions) -> Result<(), ConnectError> {
-        // First try to connect to an existing zellij session
-        match self.connect_to_session(name) {
-            Ok(_) => return Ok(()),
-            Err(ConnectError::NoMatch(_)) => {}
-            Err(e) => return Err(e),
+    /// Use the given user configuration
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Use the given store to remember session roots
+    pub fn with_store(mut self, store: SessionStore) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Use the given history to remember connections
+    pub fn with_history(mut self, history: History) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Use the given connect options
+    pub fn with_options(mut self, options: ConnectOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Print informational messages with the given printer
+    pub fn with_printer(mut self, printer: Printer) -> Self {
+        self.printer = printer;
+        self
+    }
+
+    /// The ranked directories connecting looks keywords up in
+    pub fn zoxide(&self) -> &X {
+        &self.zoxide
+    }
+
+    /// Connect using the keywords given on the command line. A single
+    /// keyword can still be a session name or a path; several keywords are
+    /// always passed to zoxide together.
+    pub fn connect_keywords(
+        &self,
+        keywords: &[&str],
+        options: &ZellijOptions,
+    ) -> Result<ConnectOutcome, ConnectError> {
+        match keywords {
+            [name] => match split_session_tab(name) {
+                Some((session, tab)) if self.session_exists(session)? => {
+                    self.connect_to_tab(session, tab)
+                }
+                _ => self.connect(name, options),
+            },
+            _ => self.connect_via_zoxide(keywords, options),
+        }
+    }
+
+    /// Connect to a session by name, or a directory by path or zoxide query.
+    /// By default an existing session wins, then a directory, then zoxide;
+    /// [`ConnectOptions::prefer`] moves one of them to the front. With
+    /// `attach_by_prefix`, a session can be named by a unique prefix.
+    pub fn connect(
+        &self,
+        name: &str,
+        options: &ZellijOptions,
+    ) -> Result<ConnectOutcome, ConnectError> {
+        let mut last_error = ConnectError::NoMatch(name.to_string());
+        for source in self.options.prefer.order() {
+            let result = match source {
+                Prefer::Session if self.skip_sessions() => continue,
+                Prefer::Session => self.connect_to_session(&self.expand_session_name(name)?),
+                Prefer::Dir => self.connect_to_directory(name, options),
+                Prefer::Zoxide => self.connect_via_zoxide(&[name], options),
+            };
+            match (source, result) {
+                (_, Ok(outcome)) => return Ok(outcome),
+                // Whatever the name is, connecting to it can't work
+                (
+                    _,
+                    Err(
+                        e @ (ConnectError::AlreadyAttached(_)
+                        | ConnectError::SessionExists(_)
+                        | ConnectError::NoSession
+                        | ConnectError::OutsideAllowedRoots(_)
+                        | ConnectError::Layout(_)),
+                    ),
+                ) => return Err(e),
+                // Any other reason a path doesn't work means it's no path
+                (Prefer::Dir, Err(e)) | (_, Err(e @ ConnectError::NoMatch(_))) => last_error = e,
+                (_, Err(e)) => return Err(e),
+            }
         }
+        Err(last_error)
+    }
 
-        // Then try if it's a directory path
-        if let Ok(()) = self.connect_to_directory(name, options) {
-            return Ok(());
+    /// Work out what [`connect`](Self::connect) would do with `name`, in the
+    /// same order, without connecting or recording anything
+    pub fn resolve(&self, name: &str) -> Result<ResolveKind, ConnectError> {
+        for source in self.options.prefer.order() {
+            let kind = match source {
+                Prefer::Session if self.skip_sessions() => continue,
+                Prefer::Session => self.resolve_session(&self.expand_session_name(name)?)?,
+                Prefer::Dir => self.resolve_path(name)?,
+                Prefer::Zoxide => self.resolve_zoxide(name)?,
+            };
+            if kind != ResolveKind::NoMatch {
+                return Ok(kind);
+            }
+        }
+        Ok(ResolveKind::NoMatch)
+    }
+
+    /// The directory [`connect`](Self::connect) would open for `name`,
+    /// without connecting or recording anything, for `cd "$(zesh query foo)"`.
+    /// A session resolves to its recorded root, or to the directory the name
+    /// resolves to if zesh didn't create it.
+    pub fn query(&self, name: &str) -> Result<PathBuf, ConnectError> {
+        let kind = match self.resolve(name)? {
+            ResolveKind::ExistingSession => {
+                match self.store.lookup(&self.expand_session_name(name)?) {
+                    Some(root) => return Ok(root),
+                    None => self.resolve_directory(name)?,
+                }
+            }
+            kind => kind,
+        };
+        match kind {
+            ResolveKind::Directory(path) | ResolveKind::ZoxideMatch(path) => Ok(path),
+            ResolveKind::ExistingSession | ResolveKind::NoMatch => {
+                Err(ConnectError::NoMatch(name.to_string()))
+            }
         }
+    }
+
+    /// The session `name` stands for. With `attach_by_prefix`, a name that
+    /// isn't a session but starts exactly one session's name stands for that
+    /// session, like tmux's `attach -t`. Anything else, including ambiguous
+    /// prefixes, is left as it is to be resolved as a directory.
+    fn expand_session_name(&self, name: &str) -> Result<String, ConnectError> {
+        if !self.config.attach_by_prefix || name.is_empty() {
+            return Ok(name.to_string());
+        }
+        let sessions = self.zellij.list_sessions()?;
+        if sessions.iter().any(|s| s.name == name) {
+            return Ok(name.to_string());
+        }
+        let mut matches = sessions.iter().filter(|s| s.name.starts_with(name));
+        Ok(match (matches.next(), matches.next()) {
+            (Some(session), None) => session.name.clone(),
+            _ => name.to_string(),
+        })
+    }
+
+    /// Existing sessions aren't connected to when a new one or a tab was
+    /// asked for
+    fn skip_sessions(&self) -> bool {
+        self.options.force_new || self.options.target != Target::Session
+    }
+
+    /// Like [`resolve`](Self::resolve), but only looking for a session
+    pub fn resolve_session(&self, name: &str) -> Result<ResolveKind, ConnectError> {
+        Ok(if self.session_exists(name)? {
+            ResolveKind::ExistingSession
+        } else {
+            ResolveKind::NoMatch
+        })
+    }
+
+    /// Like [`resolve`](Self::resolve), but only looking for a directory, by
+    /// path and then through zoxide, even if a session has the same name
+    pub fn resolve_directory(&self, name: &str) -> Result<ResolveKind, ConnectError> {
+        match self.resolve_path(name)? {
+            ResolveKind::NoMatch => self.resolve_zoxide(name),
+            kind => Ok(kind),
+        }
+    }
+
+    fn resolve_path(&self, name: &str) -> Result<ResolveKind, ConnectError> {
+        let path = self.fs.expand_path(Path::new(name));
+        Ok(
+            match self
+                .fs
+                .validate_dir_path_with(&self.resolve_relative(&path)?, self.config.follow_symlinks)
+            {
+                Ok((canon_path, _)) => ResolveKind::Directory(canon_path),
+                Err(_) => ResolveKind::NoMatch,
+            },
+        )
+    }
 
-        // Finally try zoxide query
-        self.connect_via_zoxide(name, options)
+    fn resolve_zoxide(&self, name: &str) -> Result<ResolveKind, ConnectError> {
+        Ok(match self.zoxide.query_best(&[name])? {
+            Some(entry) => ResolveKind::ZoxideMatch(entry.path),
+            None => ResolveKind::NoMatch,
+        })
+    }
+
+    /// Reconnect to the `n`th most recent connection, counting from 1. The
+    /// session is attached to if it still exists, and otherwise created
+    /// again in its directory.
+    pub fn connect_from_history(
+        &self,
+        n: usize,
+        options: &ZellijOptions,
+    ) -> Result<ConnectOutcome, ConnectError> {
+        let entry = self
+            .history
+            .get(n)
+            .ok_or_else(|| ConnectError::NoMatch(format!("history entry {}", n)))?;
+
+        match self.connect_to_session(&entry.name) {
+            Err(ConnectError::NoMatch(_)) => {}
+            result => return result,
+        }
+
+        let path = entry.path.ok_or(ConnectError::NoMatch(entry.name))?;
+        let path = path
+            .to_str()
+            .ok_or_else(|| ConnectError::InvalidInput(path.display().to_string()))?;
+        self.connect_to_directory(path, options)
     }
 
     /// Connect to a session by name
-    pub fn connect_to_session(&self, name: &str) -> Result<(), ConnectError> {
+    pub fn connect_to_session(&self, name: &str) -> Result<ConnectOutcome, ConnectError> {
         let sessions = self.zellij.list_sessions()?;
         let session_match = sessions.iter().find(|s| s.name == name);
 
         if let Some(session) = session_match {
-            self.zellij.attach_session(&session.name)?;
-            Ok(())
+            let root = self.store.lookup(&session.name);
+            self.attach(&session.name, root.as_deref())?;
+            Ok(ConnectOutcome::attached(&session.name))
         } else {
             Err(ConnectError::NoMatch(name.to_string()))
         }
     }
 
+    /// Connect to a session and focus one of its tabs, by number or name. The
+    /// tab is focused first since attaching doesn't return until we detach.
+    /// A missing tab only warns, the session is still connected to.
+    pub fn connect_to_tab(
+        &self,
+        session_name: &str,
+        tab: &str,
+    ) -> Result<ConnectOutcome, ConnectError> {
+        if let Err(e) = self.zellij.go_to_tab(session_name, tab) {
+            self.printer
+                .warn(format_args!("could not focus tab '{}': {}", tab, e));
+        }
+
+        if self.zellij.current_session()?.as_deref() == Some(session_name) {
+            return Ok(ConnectOutcome::attached(session_name));
+        }
+        self.connect_to_session(session_name)
+    }
+
     /// Connect to a directory, creating a new session or attaching to an existing one
     pub fn connect_to_directory(
         &self,
         dir: &str,
         options: &ZellijOptions,
-    ) -> Result<(), ConnectError> {
-        let path = PathBuf::from(dir);
+    ) -> Result<ConnectOutcome, ConnectError> {
+        let path = self.fs.expand_path(Path::new(dir));
+        let path = self.resolve_relative(&path)?;
 
-        let (canon_path, _) = self.fs.validate_dir_path(&path)?;
+        // The canonical path, or the path as given if symlinks aren't followed
+        let (dir_path, _) = self
+            .fs
+            .validate_dir_path_with(&path, self.config.follow_symlinks)?;
 
-        let session_name = self.get_session_name_for_path(&canon_path)?;
+        let session_name = self.get_session_name_for_path(&dir_path)?;
+        let outcome = self.attach_or_create(&session_name, &dir_path, options)?;
 
-        let sessions = self.zellij.list_sessions()?;
-        let session_match = sessions.iter().find(|s| s.name == session_name);
+        self.zoxide.add(&dir_path)?;
 
-        if let Some(session) = session_match {
-            self.zellij.attach_session(&session.name)?;
-        } else {
-            self.fs.set_current_dir(&canon_path)?;
-            self.zellij.new_session(&session_name, options)?;
-        }
+        Ok(outcome)
+    }
+
+    /// Whether the directory `dir` has a session zesh would connect to, and
+    /// whether we're in it, telling whether `zesh cn <dir>` would create or
+    /// attach. Nothing is connected to or recorded.
+    pub fn status(&self, dir: &str) -> Result<DirStatus, ConnectError> {
+        let path = self.resolve_relative(&self.fs.expand_path(Path::new(dir)))?;
+        let (path, _) = self
+            .fs
+            .validate_dir_path_with(&path, self.config.follow_symlinks)?;
+        let session = self.get_session_name_for_path(&path)?;
+
+        let found = self
+            .zellij
+            .list_sessions()?
+            .into_iter()
+            .find(|s| s.name == session);
+        let attached = self.zellij.current_session()?.as_deref() == Some(session.as_str());
+        Ok(DirStatus {
+            exists: found.is_some(),
+            exited: found.is_some_and(|s| s.is_exited),
+            attached,
+            session,
+            path,
+        })
+    }
 
-        self.zoxide.add(&canon_path)?;
+    /// Connect to the session `name`, creating it in `cwd` instead of a
+    /// directory derived from the name if it doesn't exist yet. An existing
+    /// session is attached to as it is.
+    pub fn connect_with_cwd(
+        &self,
+        name: &str,
+        cwd: &Path,
+        options: &ZellijOptions,
+    ) -> Result<ConnectOutcome, ConnectError> {
+        let path = self.resolve_relative(&self.fs.expand_path(cwd))?;
+        let (dir_path, _) = self
+            .fs
+            .validate_dir_path_with(&path, self.config.follow_symlinks)?;
+
+        let outcome = self.attach_or_create(name, &dir_path, options)?;
+        if outcome.created {
+            self.zoxide.add(&dir_path)?;
+        }
 
-        Ok(())
+        Ok(outcome)
     }
 
     /// Connect to a directory using zoxide query
     pub fn connect_via_zoxide(
         &self,
-        query: &str,
+        keywords: &[&str],
         options: &ZellijOptions,
-    ) -> Result<(), ConnectError> {
-        let entries = self.zoxide.query(&[query])?;
+    ) -> Result<ConnectOutcome, ConnectError> {
+        let best_match = self
+            .zoxide
+            .query_best(keywords)?
+            .ok_or_else(|| ConnectError::NoMatch(keywords.join(" ")))?;
+        self.connect_to_zoxide_path(&best_match.path, options)
+    }
 
-        if entries.is_empty() {
-            return Err(ConnectError::NoMatch(query.to_string()));
-        }
+    /// Connect to the highest scored zoxide directory
+    pub fn connect_recent(&self, options: &ZellijOptions) -> Result<ConnectOutcome, ConnectError> {
+        let top = self
+            .zoxide
+            .list()?
+            .into_iter()
+            .max_by(|a, b| a.score.total_cmp(&b.score))
+            .ok_or_else(|| ConnectError::NoMatch("recent directories".to_string()))?;
 
-        // Use the highest scored match
-        let best_match = &entries[0];
-        let path = &best_match.path;
+        self.connect_to_zoxide_path(&top.path, options)
+    }
 
+    /// Attach to or create the session for a directory zoxide gave us
+    fn connect_to_zoxide_path(
+        &self,
+        path: &Path,
+        options: &ZellijOptions,
+    ) -> Result<ConnectOutcome, ConnectError> {
         let session_name = self.get_session_name_for_path(path)?;
 
-        // Check if session with this name already exists
-        let sessions = self.zellij.list_sessions()?;
-
-        if sessions.iter().any(|s| s.name == session_name) {
-            self.zellij.attach_session(&session_name)?;
-            return Ok(());
+        let outcome = self.attach_or_create(&session_name, path, options)?;
+        if outcome.created {
+            self.zoxide.add(path)?;
         }
 
-        self.fs.set_current_dir(path)?;
-        self.zellij.new_session(&session_name, options)?;
-
-        self.zoxide.add(path)?;
-
-        Ok(())
+        Ok(outcome)
     }
 
     /// Get a list of active sessions
@@ -155,60 +586,400 @@ where
         Ok(self.zellij.list_sessions()?)
     }
 
-    /// Determine a session name for the given path, checking if it's in a Git repository
-    fn get_session_name_for_path(&self, path: &Path) -> Result<String, ConnectError> {
-        let path_str = path
-            .to_str()
-            .ok_or_else(|| ConnectError::Other("Invalid path".to_string()))?;
-
-        // Try to get the Git repository root
-        match self.git.show_top_level(path_str) {
-            Ok((true, git_root)) => {
-                // Path is in a Git repository
-                let git_root_path = PathBuf::from(&git_root);
-                let git_root_name = self.fs.get_dir_name(&git_root_path)?;
-
-                // If the path is the git root, just use the root name
-                if path == git_root_path {
-                    return Ok(git_root_name);
-                }
+    /// Create a session with the given name in `path`. If the name is taken,
+    /// the collision policy decides between attaching to it, picking a
+    /// suffixed name, or failing.
+    ///
+    /// With [`Target::Tab`] this opens a tab with that name in the current
+    /// session instead.
+    fn attach_or_create(
+        &self,
+        session_name: &str,
+        path: &Path,
+        options: &ZellijOptions,
+    ) -> Result<ConnectOutcome, ConnectError> {
+        if !is_under_allowed_roots(&self.fs, path, &self.config.allowed_roots) {
+            return Err(ConnectError::OutsideAllowedRoots(
+                path.display().to_string(),
+            ));
+        }
+
+        if self.options.target == Target::Tab {
+            let Some(current) = self.zellij.current_session()? else {
+                return Err(ConnectError::NoSession);
+            };
+            self.zellij.new_tab(Some(session_name), Some(path))?;
+            return Ok(ConnectOutcome::created(&current));
+        }
+
+        let policy = if self.options.force_new {
+            CollisionPolicy::Suffix
+        } else {
+            self.config.collision_policy
+        };
 
-                // Get the relative path from the Git root
-                match path.strip_prefix(&git_root_path) {
-                    Ok(rel_path) => {
-                        if rel_path == Path::new("") {
-                            // We're at the git root itself
-                            Ok(git_root_name)
-                        } else {
-                            // We're in a subdirectory
-                            // We have to use '_' because zellij does not
-                            // support '/' in session names
-                            Ok(format!("{}_{}", git_root_name, rel_path.display()))
-                        }
+        let session_name = match policy {
+            CollisionPolicy::Suffix => self.next_available_name(session_name)?,
+            CollisionPolicy::Attach | CollisionPolicy::Error => {
+                let sessions = self.zellij.list_sessions()?;
+                if sessions.iter().any(|s| s.name == session_name) {
+                    if policy == CollisionPolicy::Error {
+                        return Err(ConnectError::SessionExists(session_name.to_string()));
                     }
-                    Err(_) => Ok(self.fs.get_dir_name(path)?), // Fallback to dir name on error
+                    // The session keeps the root it was created in
+                    let root = self.store.lookup(session_name);
+                    self.attach(session_name, Some(root.as_deref().unwrap_or(path)))?;
+                    return Ok(ConnectOutcome::attached(session_name));
+                }
+                session_name.to_string()
+            }
+        };
+
+        // Templates replace the layout, so only a layout that's used is
+        // checked before the session is created
+        if self.options.template.is_none()
+            && let Some(layout) = &options.new_session_with_layout
+        {
+            validate_layout(&self.fs, layout)?;
+        }
+        self.fs.set_current_dir(path)?;
+        self.remember_root(&session_name, path);
+        if self.options.kill_on_detach
+            && let Err(e) = self.store.mark_kill_on_detach(&session_name)
+        {
+            self.printer.warn(format_args!(
+                "failed to mark session to be killed on detach: {}",
+                e
+            ));
+        }
+        let options = match self.options.template {
+            Some(template) => &template.apply(&self.fs, &session_name, options)?,
+            None => options,
+        };
+        let tabs = self.config.auto_tab_dirs(&self.fs, path);
+        if self.options.keep_current || !tabs.is_empty() {
+            // Tabs can only be opened in a running session from outside it,
+            // so a session that gets some is created in the background first
+            self.zellij.new_session_detached(&session_name, options)?;
+            for (name, dir) in &tabs {
+                self.zellij
+                    .new_tab_in(&session_name, Some(name), Some(dir))?;
+            }
+            if !self.options.keep_current {
+                self.attach_with_retry(
+                    &session_name,
+                    Some(path),
+                    false,
+                    &ZellijOptions::default(),
+                )?;
+            }
+        } else {
+            // Creating through attach means a session someone else started
+            // in the meantime is attached to instead of failing
+            self.attach_with_retry(&session_name, Some(path), true, options)?;
+        }
+
+        Ok(ConnectOutcome::created(&session_name))
+    }
+
+    /// Attach to an existing session, unless we're keeping the current one,
+    /// in which case it existing is all that was asked for
+    fn attach(&self, session_name: &str, root: Option<&Path>) -> Result<(), ConnectError> {
+        if self.options.keep_current {
+            return Ok(());
+        }
+        self.attach_with_retry(session_name, root, false, &ZellijOptions::default())
+    }
+
+    fn session_exists(&self, name: &str) -> Result<bool, ConnectError> {
+        Ok(self.zellij.list_sessions()?.iter().any(|s| s.name == name))
+    }
+
+    /// Find a session name that isn't taken yet, see [`next_available_name`]
+    pub fn next_available_name(&self, base: &str) -> Result<String, ConnectError> {
+        Ok(next_available_name(&self.zellij, base)?)
+    }
+
+    /// Attach to a session, retrying with exponential backoff while zellij
+    /// reports it as still starting up. Other errors are returned immediately.
+    /// With `create`, a missing session is created with `options`.
+    ///
+    /// Attaching to the session we're already in makes zellij fail with an
+    /// unhelpful error, so that returns [`ConnectError::AlreadyAttached`]
+    /// without trying. Otherwise the connection to `root` is reported and
+    /// recorded first, as attaching doesn't return until the user detaches.
+    fn attach_with_retry(
+        &self,
+        session_name: &str,
+        root: Option<&Path>,
+        create: bool,
+        options: &ZellijOptions,
+    ) -> Result<(), ConnectError> {
+        if self.zellij.current_session()?.as_deref() == Some(session_name) {
+            return Err(ConnectError::AlreadyAttached(session_name.to_string()));
+        }
+        self.remember_connection(session_name, root);
+
+        let attempts = self.config.attach_attempts.max(1);
+        let mut backoff = Duration::from_millis(self.config.attach_backoff_ms);
+        let mut attempt = 1;
+
+        loop {
+            match self.attach_or_switch(session_name, create, options) {
+                Err(e) if e.is_transient() && attempt < attempts => {
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                    attempt += 1;
                 }
+                result => return Ok(result?),
             }
-            Ok((false, _)) => {
-                // Not in a Git repository, just use the directory name
-                Ok(self.fs.get_dir_name(path)?)
+        }
+    }
+
+    /// Attach to the session, or switch to it when running inside zellij,
+    /// which can't attach from within a session. Switching can't create, so
+    /// a missing session is created in the background first.
+    fn attach_or_switch(
+        &self,
+        session_name: &str,
+        create: bool,
+        options: &ZellijOptions,
+    ) -> ZellijResult<()> {
+        if !self.zellij.is_attached() {
+            return self
+                .zellij
+                .attach_session_with(session_name, create, options);
+        }
+
+        let exists = self
+            .zellij
+            .list_sessions()?
+            .iter()
+            .any(|s| s.name == session_name);
+        if create && !exists {
+            self.zellij.new_session_detached(session_name, options)?;
+        }
+        self.zellij.switch_session(session_name)
+    }
+
+    /// Resolve a relative path against the root of the current session, if
+    /// zesh created it, or the focused pane's directory with
+    /// [`ConnectOptions::pane_cwd`]. Otherwise the path stays relative to
+    /// the working directory.
+    fn resolve_relative(&self, path: &Path) -> Result<PathBuf, ConnectError> {
+        if path.is_absolute() {
+            return Ok(path.to_path_buf());
+        }
+
+        let Some(session) = self.zellij.current_session()? else {
+            return Ok(path.to_path_buf());
+        };
+        let root = if self.options.pane_cwd {
+            self.focused_pane_cwd()?
+                .or_else(|| self.store.lookup(&session))
+        } else {
+            self.store.lookup(&session)
+        };
+
+        Ok(match root {
+            Some(root) => root.join(path),
+            None => path.to_path_buf(),
+        })
+    }
+
+    /// Working directory of the focused pane in the active tab of the
+    /// current session
+    fn focused_pane_cwd(&self) -> Result<Option<PathBuf>, ConnectError> {
+        Ok(self
+            .zellij
+            .list_tabs()?
+            .into_iter()
+            .filter(|tab| tab.is_active)
+            .flat_map(|tab| tab.panes)
+            .find(|pane| pane.is_focused)
+            .and_then(|pane| pane.cwd))
+    }
+
+    /// Remember where a session was created. Failing to do so shouldn't stop
+    /// the user from connecting, so errors are only reported.
+    fn remember_root(&self, session_name: &str, root: &Path) {
+        if let Err(e) = self.store.record(session_name, root) {
+            self.printer
+                .warn(format_args!("failed to record session root: {}", e));
+        }
+    }
+
+    /// Say where we're connecting to and add it to the history. Like the
+    /// session root, failing to record it is only reported.
+    fn remember_connection(&self, session_name: &str, root: Option<&Path>) {
+        match root {
+            Some(root) => self.printer.info(format_args!(
+                "Connected to {} ({})",
+                session_name,
+                root.display()
+            )),
+            None => self
+                .printer
+                .info(format_args!("Connected to {}", session_name)),
+        }
+
+        if let Err(e) = self.history.record(session_name, root) {
+            self.printer
+                .warn(format_args!("failed to record connection history: {}", e));
+        }
+    }
+
+    /// Determine a session name for the given path, see [`session_name_for_path`]
+    fn get_session_name_for_path(&self, path: &Path) -> Result<String, ConnectError> {
+        Ok(session_name_for_path(&self.fs, &self.git, path)?)
+    }
+}
+
+/// Name the session for `path` the way zesh does: the directory's name, or
+/// `<repo>_<subdir>` for subdirectories of a git repository
+pub fn session_name_for_path<F: FsOperations, G: Git>(
+    fs: &F,
+    git: &G,
+    path: &Path,
+) -> Result<String, FsError> {
+    raw_session_name_for_path(fs, git, path).map(|name| sanitize_session_name(&name))
+}
+
+/// Make a session name zellij accepts out of a directory name or a path
+/// relative to a repository root. zellij doesn't support path separators in
+/// session names, so `/` and `\` become `_` whatever the platform, and a
+/// drive root like `C:\` is named after its drive letter.
+pub fn sanitize_session_name(name: &str) -> String {
+    let is_separator = |c: char| c == '/' || c == '\\';
+    let name = match name.split_once(':') {
+        Some((drive, rest))
+            if drive.len() == 1
+                && drive.chars().all(|c| c.is_ascii_alphabetic())
+                && rest.chars().all(is_separator) =>
+        {
+            drive
+        }
+        _ => name,
+    };
+    name.trim_matches(is_separator).replace(is_separator, "_")
+}
+
+fn raw_session_name_for_path<F: FsOperations, G: Git>(
+    fs: &F,
+    git: &G,
+    path: &Path,
+) -> Result<String, FsError> {
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| FsError::Other("Invalid path".to_string()))?;
+
+    // Cheaply rule out plain directories before asking for the root
+    match git.is_inside_work_tree(path_str) {
+        Ok(true) => {}
+        Ok(false) => return fs.get_dir_name(path),
+        Err(e) => {
+            eprintln!("Git error: {}", e);
+            return fs.get_dir_name(path);
+        }
+    }
+
+    // Try to get the Git repository root
+    match git.show_top_level(path_str) {
+        Ok((true, git_root)) => {
+            // Path is in a Git repository
+            let git_root_path = PathBuf::from(&git_root);
+            let git_root_name = fs.get_dir_name(&git_root_path)?;
+
+            // If the path is the git root, just use the root name
+            if path == git_root_path {
+                return Ok(git_root_name);
             }
-            Err(e) => {
-                // Error running git command, log it and fall back to directory name
-                eprintln!("Git error: {}", e);
-                Ok(self.fs.get_dir_name(path)?)
+
+            // Get the relative path from the Git root
+            match path.strip_prefix(&git_root_path) {
+                Ok(rel_path) => {
+                    if rel_path == Path::new("") {
+                        // We're at the git root itself
+                        Ok(git_root_name)
+                    } else {
+                        // We're in a subdirectory, whose separators are
+                        // replaced by sanitize_session_name
+                        Ok(format!("{}_{}", git_root_name, rel_path.display()))
+                    }
+                }
+                Err(_) => fs.get_dir_name(path), // Fallback to dir name on error
             }
         }
+        Ok((false, _)) => {
+            // Not in a Git repository, just use the directory name
+            fs.get_dir_name(path)
+        }
+        Err(e) => {
+            // Error running git command, log it and fall back to directory name
+            eprintln!("Git error: {}", e);
+            fs.get_dir_name(path)
+        }
+    }
+}
+
+/// Find a session name that isn't taken yet, trying `base` first and then
+/// `base-2`, `base-3` and so on
+pub fn next_available_name<Z: ZellijOperations>(zellij: &Z, base: &str) -> ZellijResult<String> {
+    let sessions = zellij.list_sessions()?;
+    let taken = |name: &str| sessions.iter().any(|s| s.name == name);
+
+    if !taken(base) {
+        return Ok(base.to_string());
+    }
+
+    Ok((2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|name| !taken(name))
+        .expect("some suffix is always free"))
+}
+
+/// Split a `session:tab` target at its last `:`, e.g. `api:2` or
+/// `api:logs`. Returns `None` unless both halves are non-empty.
+pub fn split_session_tab(target: &str) -> Option<(&str, &str)> {
+    let (session, tab) = target.rsplit_once(':')?;
+    (!session.is_empty() && !tab.is_empty()).then_some((session, tab))
+}
+
+/// Read a single session name or path from a reader, such as piped stdin.
+/// Blank lines are ignored, but exactly one non-empty line must be present.
+pub fn read_target<R: BufRead>(reader: R) -> Result<String, ConnectError> {
+    let mut targets = Vec::new();
+    for line in reader.lines() {
+        let line = line.map_err(|e| ConnectError::InvalidInput(e.to_string()))?;
+        let line = line.trim();
+        if !line.is_empty() {
+            targets.push(line.to_string());
+        }
+    }
+
+    match targets.len() {
+        1 => Ok(targets.remove(0)),
+        0 => Err(ConnectError::InvalidInput(
+            "expected a session name or path on stdin, got nothing".to_string(),
+        )),
+        n => Err(ConnectError::InvalidInput(format!(
+            "expected a single session name or path on stdin, got {} lines",
+            n
+        ))),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::frecency::{Directories, FrecencyStore};
     use crate::fs::tests::MockFs;
+    use crate::template::tests::assert_valid_kdl;
+    use clap::ValueEnum;
+    use std::io::Cursor;
     use std::path::PathBuf;
     use std::{collections::HashMap, path::Path};
-    use zellij_rs::{MockZellijClient, Session, ZellijError};
+    use zellij_rs::{MockZellijClient, Pane, Session, Tab, ZellijError};
     use zox_rs::{MockZoxideClient, ZoxideEntry, ZoxideError};
 
     fn create_service(
@@ -247,69 +1018,147 @@ mod tests {
             Err(ZellijError::CommandExecution("Command failed".to_string()))
         }
 
+        fn is_attached(&self) -> bool {
+            false
+        }
+
         fn attach_session(&self, _: &str) -> zellij_rs::ZellijResult<()> {
             Err(ZellijError::CommandExecution("Command failed".to_string()))
         }
 
-        fn new_session(&self, _: &str, _: &ZellijOptions) -> zellij_rs::ZellijResult<()> {
+        fn attach_session_with(
+            &self,
+            _: &str,
+            _: bool,
+            _: &ZellijOptions,
+        ) -> zellij_rs::ZellijResult<()> {
             Err(ZellijError::CommandExecution("Command failed".to_string()))
         }
 
-        fn kill_session(&self, _: &str) -> zellij_rs::ZellijResult<()> {
+        fn switch_session(&self, _: &str) -> zellij_rs::ZellijResult<()> {
             Err(ZellijError::CommandExecution("Command failed".to_string()))
         }
 
-        fn list_tabs(&self) -> zellij_rs::ZellijResult<Vec<zellij_rs::Tab>> {
+        fn new_session(&self, _: &str, _: &ZellijOptions) -> zellij_rs::ZellijResult<()> {
             Err(ZellijError::CommandExecution("Command failed".to_string()))
         }
 
-        fn new_tab(&self, _: Option<&str>) -> zellij_rs::ZellijResult<()> {
+        fn new_session_detached(&self, _: &str, _: &ZellijOptions) -> zellij_rs::ZellijResult<()> {
             Err(ZellijError::CommandExecution("Command failed".to_string()))
         }
 
-        fn rename_tab(&self, _: &str) -> zellij_rs::ZellijResult<()> {
+        fn kill_session(&self, _: &str) -> zellij_rs::ZellijResult<()> {
             Err(ZellijError::CommandExecution("Command failed".to_string()))
         }
 
-        fn close_tab(&self) -> zellij_rs::ZellijResult<()> {
+        fn rename_session(&self, _: &str) -> zellij_rs::ZellijResult<()> {
             Err(ZellijError::CommandExecution("Command failed".to_string()))
         }
 
-        fn run_command(&self, _: &str, _: &[&str]) -> zellij_rs::ZellijResult<()> {
+        fn detach(&self) -> zellij_rs::ZellijResult<()> {
             Err(ZellijError::CommandExecution("Command failed".to_string()))
         }
-    }
 
-    // Helper function to create a failing zoxide client
-    struct FailingZoxideClient;
-    impl ZoxideOperations for FailingZoxideClient {
-        fn add<P: AsRef<Path>>(&self, _: P) -> zox_rs::ZoxideResult<()> {
-            Err(ZoxideError::CommandExecution("Command failed".to_string()))
+        fn list_tabs(&self) -> zellij_rs::ZellijResult<Vec<zellij_rs::Tab>> {
+            Err(ZellijError::CommandExecution("Command failed".to_string()))
         }
 
-        fn list(&self) -> zox_rs::ZoxideResult<Vec<ZoxideEntry>> {
-            Err(ZoxideError::CommandExecution("Command failed".to_string()))
+        fn pane_cwds(&self) -> zellij_rs::ZellijResult<Vec<(u32, PathBuf)>> {
+            Err(ZellijError::CommandExecution("Command failed".to_string()))
         }
 
-        fn query(&self, _: &[&str]) -> zox_rs::ZoxideResult<Vec<ZoxideEntry>> {
-            Err(ZoxideError::CommandExecution("Command failed".to_string()))
+        fn new_tab(&self, _: Option<&str>, _: Option<&Path>) -> zellij_rs::ZellijResult<()> {
+            Err(ZellijError::CommandExecution("Command failed".to_string()))
         }
-    }
 
-    // Helper function to create a failing filesystem
-    struct FailingFs;
-    impl FsOperations for FailingFs {
-        fn exists(&self, _: &Path) -> bool {
-            false
+        fn new_tab_in(
+            &self,
+            _: &str,
+            _: Option<&str>,
+            _: Option<&Path>,
+        ) -> zellij_rs::ZellijResult<()> {
+            Err(ZellijError::CommandExecution("Command failed".to_string()))
         }
 
-        fn is_dir(&self, _: &Path) -> bool {
-            false
+        fn rename_tab(&self, _: &str) -> zellij_rs::ZellijResult<()> {
+            Err(ZellijError::CommandExecution("Command failed".to_string()))
         }
 
-        fn canonicalize(&self, _: &Path) -> Result<PathBuf, FsError> {
-            Err(FsError::Canonicalize(std::io::Error::other(
-                "Failed to canonicalize",
+        fn go_to_tab(&self, _: &str, _: &str) -> zellij_rs::ZellijResult<()> {
+            Err(ZellijError::CommandExecution("Command failed".to_string()))
+        }
+
+        fn close_tab(&self) -> zellij_rs::ZellijResult<()> {
+            Err(ZellijError::CommandExecution("Command failed".to_string()))
+        }
+
+        fn move_pane_to_tab(&self, _: u32) -> zellij_rs::ZellijResult<()> {
+            Err(ZellijError::CommandExecution("Command failed".to_string()))
+        }
+
+        fn toggle_floating_panes(&self) -> zellij_rs::ZellijResult<()> {
+            Err(ZellijError::CommandExecution("Command failed".to_string()))
+        }
+
+        fn toggle_pane_embed_or_floating(&self) -> zellij_rs::ZellijResult<()> {
+            Err(ZellijError::CommandExecution("Command failed".to_string()))
+        }
+
+        fn run_command(&self, _: &str, _: &[&str]) -> zellij_rs::ZellijResult<()> {
+            Err(ZellijError::CommandExecution("Command failed".to_string()))
+        }
+
+        fn run_command_in(&self, _: &str, _: &str, _: &[&str]) -> zellij_rs::ZellijResult<()> {
+            Err(ZellijError::CommandExecution("Command failed".to_string()))
+        }
+
+        fn version(&self) -> zellij_rs::ZellijResult<String> {
+            Err(ZellijError::CommandExecution("Command failed".to_string()))
+        }
+    }
+
+    // Helper function to create a failing zoxide client
+    struct FailingZoxideClient;
+    impl ZoxideOperations for FailingZoxideClient {
+        fn add<P: AsRef<Path>>(&self, _: P) -> zox_rs::ZoxideResult<()> {
+            Err(ZoxideError::CommandExecution("Command failed".to_string()))
+        }
+
+        fn add_with_score<P: AsRef<Path>>(&self, _: P, _: f64) -> zox_rs::ZoxideResult<()> {
+            Err(ZoxideError::CommandExecution("Command failed".to_string()))
+        }
+
+        fn list(&self) -> zox_rs::ZoxideResult<Vec<ZoxideEntry>> {
+            Err(ZoxideError::CommandExecution("Command failed".to_string()))
+        }
+
+        fn query(&self, _: &[&str]) -> zox_rs::ZoxideResult<Vec<ZoxideEntry>> {
+            Err(ZoxideError::CommandExecution("Command failed".to_string()))
+        }
+
+        fn version(&self) -> zox_rs::ZoxideResult<String> {
+            Err(ZoxideError::CommandExecution("Command failed".to_string()))
+        }
+
+        fn score_for<P: AsRef<Path>>(&self, _: P) -> zox_rs::ZoxideResult<Option<f64>> {
+            Err(ZoxideError::CommandExecution("Command failed".to_string()))
+        }
+    }
+
+    // Helper function to create a failing filesystem
+    struct FailingFs;
+    impl FsOperations for FailingFs {
+        fn exists(&self, _: &Path) -> bool {
+            false
+        }
+
+        fn is_dir(&self, _: &Path) -> bool {
+            false
+        }
+
+        fn canonicalize(&self, _: &Path) -> Result<PathBuf, FsError> {
+            Err(FsError::Canonicalize(std::io::Error::other(
+                "Failed to canonicalize",
             )))
         }
 
@@ -324,6 +1173,30 @@ mod tests {
         fn current_dir(&self) -> Result<PathBuf, FsError> {
             Err(FsError::Other("Failed to get current dir".to_string()))
         }
+
+        fn home_dir(&self) -> Result<PathBuf, FsError> {
+            Err(FsError::NoHomeDir)
+        }
+
+        fn ensure_writable_dir(&self, path: &Path) -> Result<(), FsError> {
+            Err(FsError::Other(format!("{} is read-only", path.display())))
+        }
+
+        fn read_to_string(&self, path: &Path) -> Result<String, FsError> {
+            Err(FsError::PathNotFound(path.display().to_string()))
+        }
+
+        fn write(&self, path: &Path, _: &str) -> Result<(), FsError> {
+            Err(FsError::Other(format!("{} is read-only", path.display())))
+        }
+
+        fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, FsError> {
+            Err(FsError::PathNotFound(path.display().to_string()))
+        }
+
+        fn remove_dir_all(&self, path: &Path) -> Result<(), FsError> {
+            Err(FsError::Other(format!("{} is read-only", path.display())))
+        }
     }
 
     #[test]
@@ -395,423 +1268,1506 @@ mod tests {
     }
 
     #[test]
-    fn test_connect_to_directory_existing_session() {
-        // Setup test directory and existing session with same name
-        let dir_path = PathBuf::from("/mock/project");
-        let mut sessions = HashMap::new();
-        sessions.insert("project".to_string(), false);
+    fn test_connect_with_template_creates_session_with_layout() {
+        for template in LayoutTemplate::value_variants() {
+            let service = create_service(
+                None,
+                None,
+                Some(vec![(
+                    PathBuf::from("/mock/project"),
+                    "project".to_string(),
+                )]),
+            )
+            .with_options(ConnectOptions {
+                template: Some(*template),
+                ..ConnectOptions::default()
+            });
+
+            service
+                .connect_to_directory("/mock/project", &ZellijOptions::default())
+                .unwrap();
+
+            let options = service.zellij.session_options("project").unwrap();
+            let layout = options.new_session_with_layout.unwrap();
+            let kdl = service.fs.read_to_string(Path::new(&layout)).unwrap();
+            assert_valid_kdl(&kdl);
+            assert_eq!(kdl, template.kdl());
+        }
+    }
 
+    #[test]
+    fn test_connect_with_template_keeps_existing_session() {
         let service = create_service(
-            Some(sessions),
+            Some(HashMap::from([("project".to_string(), false)])),
             None,
-            Some(vec![(dir_path.clone(), "project".to_string())]),
-        );
+            Some(vec![(
+                PathBuf::from("/mock/project"),
+                "project".to_string(),
+            )]),
+        )
+        .with_options(ConnectOptions {
+            template: Some(LayoutTemplate::Tabs),
+            ..ConnectOptions::default()
+        });
+
+        service
+            .connect_to_directory("/mock/project", &ZellijOptions::default())
+            .unwrap();
 
-        // Test connecting to directory that already has a session
-        let result = service.connect_to_directory("/mock/project", &ZellijOptions::default());
-        assert!(result.is_ok());
+        // Attaching doesn't lay the session out again
+        assert!(service.zellij.session_options("project").is_none());
+    }
 
-        // After connection, should attach to existing session
-        let sessions = service.list_sessions().unwrap();
-        assert_eq!(sessions.len(), 1);
-        assert_eq!(sessions[0].name, "project");
-        assert!(sessions[0].is_current);
+    fn restricted_service(
+        allowed_roots: &[&str],
+    ) -> ConnectService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
+        create_service(
+            None,
+            None,
+            Some(vec![
+                (PathBuf::from("/mock/src"), "src".to_string()),
+                (PathBuf::from("/mock/src/api"), "api".to_string()),
+                (PathBuf::from("/mock/src-old"), "src-old".to_string()),
+            ]),
+        )
+        .with_config(Config {
+            allowed_roots: allowed_roots.iter().map(PathBuf::from).collect(),
+            ..Config::default()
+        })
     }
 
     #[test]
-    fn test_connect_to_directory_invalid_path() {
-        let service = create_service(None, None, None);
+    fn test_connect_under_allowed_root() {
+        let service = restricted_service(&["/mock/src"]);
 
-        // Test with non-existent path
-        let result = service.connect_to_directory("/mock/non-existent", &ZellijOptions::default());
-        assert!(result.is_err());
-        if let Err(ConnectError::Fs(_)) = result {
-            // Expected error
-        } else {
-            panic!("Expected ConnectError::Fs");
-        }
+        service
+            .connect_to_directory("/mock/src/api", &ZellijOptions::default())
+            .unwrap();
+        // The root itself is allowed too
+        service
+            .connect_to_directory("/mock/src", &ZellijOptions::default())
+            .unwrap();
 
-        // Setup a file path (not a directory)
-        let fs = MockFs::new();
-        fs.with_file(&PathBuf::from("/mock/file.txt"));
-        let service = ConnectService::new(
-            MockZellijClient::new(),
-            MockZoxideClient::new(),
-            fs,
-            TestGit::new(false, "./"),
+        assert_eq!(service.list_sessions().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_connect_outside_allowed_roots() {
+        let service = restricted_service(&["/mock/src"]);
+        service.fs.set_current_dir(Path::new("/mock")).unwrap();
+
+        let result = service.connect_to_directory("/mock/src-old", &ZellijOptions::default());
+
+        assert!(
+            matches!(result, Err(ConnectError::OutsideAllowedRoots(path)) if path == "/mock/src-old")
         );
+        assert!(service.list_sessions().unwrap().is_empty());
+        assert_eq!(service.fs.current_dir().unwrap(), PathBuf::from("/mock"));
+        assert_eq!(service.store.lookup("src-old"), None);
+
+        // connect() says why rather than that nothing matched
+        let result = service.connect("/mock/src-old", &ZellijOptions::default());
+        assert!(matches!(result, Err(ConnectError::OutsideAllowedRoots(_))));
+        assert!(service.list_sessions().unwrap().is_empty());
+    }
 
-        // Test with a file path instead of directory
-        let result = service.connect_to_directory("/mock/file.txt", &ZellijOptions::default());
-        assert!(result.is_err());
-        if let Err(ConnectError::Fs(_)) = result {
-            // Expected error
-        } else {
-            panic!("Expected ConnectError::Fs");
-        }
+    fn auto_tabs_service(
+        auto_tabs: &[&str],
+    ) -> ConnectService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
+        create_service(
+            None,
+            None,
+            Some(vec![
+                (PathBuf::from("/mock/app"), "app".to_string()),
+                (PathBuf::from("/mock/app/frontend"), "frontend".to_string()),
+                (PathBuf::from("/mock/app/backend"), "backend".to_string()),
+            ]),
+        )
+        .with_config(Config {
+            auto_tabs: auto_tabs.iter().map(|s| s.to_string()).collect(),
+            ..Config::default()
+        })
     }
 
     #[test]
-    fn test_connect_to_directory_error_handling() {
-        // Setup test with failing filesystem
-        let zellij = MockZellijClient::new();
-        let zoxide = MockZoxideClient::new();
-        let fs = FailingFs;
-        let service = ConnectService::new(zellij, zoxide, fs, TestGit::new(false, "./"));
+    fn test_connect_opens_auto_tabs() {
+        let service = auto_tabs_service(&["frontend", "docs", "backend"]);
 
-        let result = service.connect_to_directory("/any/path", &ZellijOptions::default());
-        assert!(result.is_err());
-        if let Err(ConnectError::Fs(_)) = result {
-            // Expected error
-        } else {
-            panic!("Expected ConnectError::Fs");
-        }
+        let outcome = service
+            .connect_to_directory("/mock/app", &ZellijOptions::default())
+            .unwrap();
 
-        // Setup test with working filesystem but failing zellij
-        let zellij = FailingZellijClient;
-        let zoxide = MockZoxideClient::new();
-        let fs = MockFs::new();
-        fs.with_directory(&PathBuf::from("/mock/project"), "project");
-        let service = ConnectService::new(zellij, zoxide, fs, TestGit::new(false, "./"));
+        assert!(outcome.created);
+        // The missing docs directory gets no tab
+        assert_eq!(
+            service.zellij.tabs_in("app"),
+            vec![
+                (
+                    Some("frontend".to_string()),
+                    Some(PathBuf::from("/mock/app/frontend"))
+                ),
+                (
+                    Some("backend".to_string()),
+                    Some(PathBuf::from("/mock/app/backend"))
+                ),
+            ]
+        );
+        assert_eq!(
+            service.zellij.current_session().unwrap().as_deref(),
+            Some("app")
+        );
+    }
 
-        let result = service.connect_to_directory("/mock/project", &ZellijOptions::default());
-        assert!(result.is_err());
-        if let Err(ConnectError::Zellij(_)) = result {
-            // Expected error
-        } else {
-            panic!("Expected ConnectError::Zellij");
-        }
+    #[test]
+    fn test_auto_tabs_only_for_new_sessions() {
+        let service = auto_tabs_service(&["frontend"]);
+        service
+            .zellij
+            .new_session_detached("app", &ZellijOptions::default())
+            .unwrap();
 
-        // Setup test with working filesystem and zellij but failing zoxide
-        let zellij = MockZellijClient::new();
-        let zoxide = FailingZoxideClient;
-        let fs = MockFs::new();
-        fs.with_directory(&PathBuf::from("/mock/project"), "project");
-        let service = ConnectService::new(zellij, zoxide, fs, TestGit::new(false, "./"));
+        let outcome = service
+            .connect_to_directory("/mock/app", &ZellijOptions::default())
+            .unwrap();
 
-        let result = service.connect_to_directory("/mock/project", &ZellijOptions::default());
-        assert!(result.is_err());
-        if let Err(ConnectError::Zoxide(_)) = result {
-            // Expected error
-        } else {
-            panic!("Expected ConnectError::Zoxide");
-        }
+        assert!(!outcome.created);
+        assert!(service.zellij.tabs_in("app").is_empty());
     }
 
     #[test]
-    fn test_connect_to_directory_with_special_chars() {
-        // Test directory with spaces and special characters
-        let dir_path = PathBuf::from("/mock/special project-name!");
-        let service = create_service(
-            None,
-            None,
+    fn test_no_auto_tabs_when_none_exist() {
+        let service = auto_tabs_service(&["docs"]);
+
+        service
+            .connect_to_directory("/mock/app", &ZellijOptions::default())
+            .unwrap();
+
+        assert!(service.zellij.tabs_in("app").is_empty());
+        assert_eq!(service.list_sessions().unwrap().len(), 1);
+    }
+
+    fn prefix_service(
+        sessions: &[&str],
+        attach_by_prefix: bool,
+    ) -> ConnectService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
+        create_service(
+            Some(sessions.iter().map(|s| (s.to_string(), false)).collect()),
+            Some(HashMap::from([(PathBuf::from("/mock/src/proj-docs"), 5.0)])),
             Some(vec![(
-                dir_path.clone(),
-                "special project-name!".to_string(),
+                PathBuf::from("/mock/src/proj-docs"),
+                "proj-docs".to_string(),
             )]),
+        )
+        .with_config(Config {
+            attach_by_prefix,
+            ..Config::default()
+        })
+    }
+
+    #[test]
+    fn test_connect_by_unique_prefix() {
+        let service = prefix_service(&["project", "web"], true);
+
+        let outcome = service.connect("proj", &ZellijOptions::default()).unwrap();
+
+        assert_eq!(outcome, ConnectOutcome::attached("project"));
+        assert_eq!(
+            service.zellij.current_session().unwrap().as_deref(),
+            Some("project")
+        );
+        assert_eq!(
+            service.resolve("proj").unwrap(),
+            ResolveKind::ExistingSession
         );
+    }
 
-        let result =
-            service.connect_to_directory("/mock/special project-name!", &ZellijOptions::default());
-        assert!(result.is_ok());
+    #[test]
+    fn test_connect_by_ambiguous_prefix() {
+        let service = prefix_service(&["project", "proposal"], true);
 
-        // Verify session was created with special characters
-        let sessions = service.list_sessions().unwrap();
-        assert_eq!(sessions.len(), 1);
-        assert_eq!(sessions[0].name, "special project-name!");
+        let outcome = service.connect("pro", &ZellijOptions::default()).unwrap();
+
+        // Neither session, zoxide gets the name instead
+        assert_eq!(outcome, ConnectOutcome::created("proj-docs"));
+        assert_eq!(service.list_sessions().unwrap().len(), 3);
     }
 
     #[test]
-    fn test_connect_via_zoxide_single_match() {
-        // Setup zoxide with a single matching path
-        let mut path_scores = HashMap::new();
-        path_scores.insert(PathBuf::from("/mock/zoxide-dir"), 10.0);
+    fn test_connect_by_prefix_prefers_exact_match() {
+        let service = prefix_service(&["proj", "project"], true);
+
+        let outcome = service.connect("proj", &ZellijOptions::default()).unwrap();
+
+        assert_eq!(outcome, ConnectOutcome::attached("proj"));
+    }
+
+    #[test]
+    fn test_connect_by_prefix_is_opt_in() {
+        let service = prefix_service(&["project"], false);
+
+        let outcome = service.connect("proj", &ZellijOptions::default()).unwrap();
 
+        assert_eq!(outcome, ConnectOutcome::created("proj-docs"));
+    }
+
+    fn status_service(
+        sessions: HashMap<String, bool>,
+    ) -> ConnectService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
         let service = create_service(
+            Some(sessions),
             None,
-            Some(path_scores),
             Some(vec![(
-                PathBuf::from("/mock/zoxide-dir"),
-                "zoxide-dir".to_string(),
+                PathBuf::from("/mock/project"),
+                "project".to_string(),
             )]),
         );
+        service
+            .fs
+            .set_current_dir(Path::new("/mock/project"))
+            .unwrap();
+        service
+    }
 
-        // Test connecting via zoxide query that matches single entry
-        let result = service.connect_via_zoxide("zoxide", &ZellijOptions::default());
-        assert!(result.is_ok());
+    #[test]
+    fn test_status_in_session() {
+        let service = status_service(HashMap::from([("project".to_string(), false)]));
+        service.zellij.attach_session("project").unwrap();
+
+        let status = service.status(".").unwrap();
+
+        assert_eq!(
+            status,
+            DirStatus {
+                session: "project".to_string(),
+                path: PathBuf::from("/mock/project"),
+                exists: true,
+                exited: false,
+                attached: true,
+            }
+        );
+    }
 
-        // After connection, should have a new session with the directory name
-        let sessions = service.list_sessions().unwrap();
-        assert_eq!(sessions.len(), 1);
-        assert_eq!(sessions[0].name, "zoxide-dir");
+    #[test]
+    fn test_status_session_exists_detached() {
+        let service = status_service(HashMap::from([
+            ("project".to_string(), false),
+            ("other".to_string(), false),
+        ]));
+        service.zellij.attach_session("other").unwrap();
+
+        let status = service.status(".").unwrap();
+
+        assert!(status.exists);
+        assert!(!status.attached);
+        assert_eq!(status.session, "project");
     }
 
     #[test]
-    fn test_connect_via_zoxide_multiple_matches() {
-        // Setup zoxide with multiple matching paths
-        let mut path_scores = HashMap::new();
-        path_scores.insert(PathBuf::from("/mock/best-match"), 20.0);
-        path_scores.insert(PathBuf::from("/mock/second-match"), 10.0);
+    fn test_status_without_session() {
+        let service = status_service(HashMap::new());
+
+        let status = service.status("/mock/project").unwrap();
+
+        assert!(!status.exists);
+        assert!(!status.attached);
+        assert_eq!(status.session, "project");
+        // Asking doesn't create anything
+        assert!(service.list_sessions().unwrap().is_empty());
+        assert!(service.status("/mock/missing").is_err());
+    }
 
+    #[test]
+    fn test_connect_checks_layout_file() {
         let service = create_service(
+            Some(HashMap::from([("existing".to_string(), false)])),
             None,
-            Some(path_scores),
             Some(vec![
-                (PathBuf::from("/mock/best-match"), "best-match".to_string()),
-                (
-                    PathBuf::from("/mock/second-match"),
-                    "second-match".to_string(),
-                ),
+                (PathBuf::from("/mock/project"), "project".to_string()),
+                (PathBuf::from("/mock/existing"), "existing".to_string()),
             ]),
         );
+        let missing = ZellijOptions {
+            new_session_with_layout: Some("/mock/layouts/missing.kdl".to_string()),
+            ..ZellijOptions::default()
+        };
 
-        // Test connecting via zoxide query that matches multiple entries
-        let result = service.connect_via_zoxide("match", &ZellijOptions::default());
-        assert!(result.is_ok());
+        let result = service.connect_to_directory("/mock/project", &missing);
+        assert!(matches!(
+            result,
+            Err(ConnectError::Layout(LayoutError::NotFound(_)))
+        ));
+        assert!(service.zellij.session_options("project").is_none());
+        // connect() says why rather than that nothing matched
+        assert!(matches!(
+            service.connect("/mock/project", &missing),
+            Err(ConnectError::Layout(LayoutError::NotFound(_)))
+        ));
+        assert!(service.zellij.session_options("project").is_none());
+
+        // Attaching to an existing session doesn't use the layout
+        service
+            .connect_to_directory("/mock/existing", &missing)
+            .unwrap();
 
-        // Should connect to highest scored match
-        let sessions = service.list_sessions().unwrap();
-        assert_eq!(sessions.len(), 1);
-        assert_eq!(sessions[0].name, "best-match");
+        service.fs.with_file_contents(
+            Path::new("/mock/layouts/dev.kdl"),
+            "layout {\n    pane\n}\n",
+        );
+        let valid = ZellijOptions {
+            new_session_with_layout: Some("/mock/layouts/dev.kdl".to_string()),
+            ..ZellijOptions::default()
+        };
+        service
+            .connect_to_directory("/mock/project", &valid)
+            .unwrap();
+        assert!(service.zellij.session_options("project").is_some());
     }
 
     #[test]
-    fn test_connect_via_zoxide_existing_session() {
-        // Setup zoxide path and existing session with same name
-        let mut path_scores = HashMap::new();
-        path_scores.insert(PathBuf::from("/mock/existing"), 10.0);
-
-        let mut sessions = HashMap::new();
-        sessions.insert("existing".to_string(), false);
-
+    fn test_connect_outcome_created_then_attached() {
         let service = create_service(
-            Some(sessions),
-            Some(path_scores),
+            None,
+            None,
             Some(vec![(
-                PathBuf::from("/mock/existing"),
-                "existing".to_string(),
+                PathBuf::from("/mock/project"),
+                "project".to_string(),
             )]),
         );
 
-        // Test connecting via zoxide when session already exists
-        let result = service.connect_via_zoxide("existing", &ZellijOptions::default());
-        assert!(result.is_ok());
+        let outcome = service
+            .connect("/mock/project", &ZellijOptions::default())
+            .unwrap();
+        assert_eq!(
+            outcome,
+            ConnectOutcome {
+                session: "project".to_string(),
+                created: true
+            }
+        );
 
-        // Should attach to existing session
-        let sessions = service.list_sessions().unwrap();
-        assert_eq!(sessions.len(), 1);
-        assert_eq!(sessions[0].name, "existing");
-        assert!(sessions[0].is_current);
+        service.zellij.detach().unwrap();
+        assert_eq!(
+            service
+                .connect("project", &ZellijOptions::default())
+                .unwrap(),
+            ConnectOutcome::attached("project")
+        );
+        // Through the directory, the existing session is attached to too
+        service.zellij.detach().unwrap();
+        assert_eq!(
+            service
+                .connect_to_directory("/mock/project", &ZellijOptions::default())
+                .unwrap(),
+            ConnectOutcome::attached("project")
+        );
     }
 
     #[test]
-    fn test_connect_via_zoxide_no_matches() {
-        // Setup empty zoxide database
-        let service = create_service(None, None, None);
+    fn test_connect_with_cwd_creates_session_in_cwd() {
+        let service = create_service(
+            None,
+            None,
+            Some(vec![
+                (PathBuf::from("/mock/api"), "api".to_string()),
+                (PathBuf::from("/mock/work/api-v2"), "api-v2".to_string()),
+            ]),
+        );
 
-        // Test connecting via zoxide with no matches
-        let result = service.connect_via_zoxide("non-existent", &ZellijOptions::default());
-        assert!(result.is_err());
-        if let Err(ConnectError::NoMatch(query)) = result {
-            assert_eq!(query, "non-existent");
-        } else {
-            panic!("Expected ConnectError::NoMatch");
-        }
+        service
+            .connect_with_cwd(
+                "api",
+                Path::new("/mock/work/api-v2"),
+                &ZellijOptions::default(),
+            )
+            .unwrap();
+
+        // Named after the argument, but started in the override
+        let sessions = service.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "api");
+        assert_eq!(
+            service.fs.current_dir().unwrap(),
+            PathBuf::from("/mock/work/api-v2")
+        );
+        assert_eq!(
+            service.store.lookup("api"),
+            Some(PathBuf::from("/mock/work/api-v2"))
+        );
     }
 
     #[test]
-    fn test_connect_via_zoxide_error_handling() {
-        // Setup failing zoxide client
-        let zellij = MockZellijClient::new();
-        let zoxide = FailingZoxideClient;
-        let fs = MockFs::new();
-        let service = ConnectService::new(zellij, zoxide, fs, TestGit::new(false, "./"));
+    fn test_connect_with_cwd_attaches_to_existing_session() {
+        let service = create_service(
+            Some(HashMap::from([("api".to_string(), false)])),
+            None,
+            Some(vec![(
+                PathBuf::from("/mock/work/api-v2"),
+                "api-v2".to_string(),
+            )]),
+        );
+        service.fs.set_current_dir(Path::new("/mock")).unwrap();
+
+        service
+            .connect_with_cwd(
+                "api",
+                Path::new("/mock/work/api-v2"),
+                &ZellijOptions::default(),
+            )
+            .unwrap();
 
-        let result = service.connect_via_zoxide("query", &ZellijOptions::default());
-        assert!(result.is_err());
-        if let Err(ConnectError::Zoxide(_)) = result {
-            // Expected error
-        } else {
-            panic!("Expected ConnectError::Zoxide");
-        }
+        // The override only applies when creating
+        assert_eq!(service.fs.current_dir().unwrap(), PathBuf::from("/mock"));
+        assert_eq!(service.store.lookup("api"), None);
+        assert!(service.list_sessions().unwrap()[0].is_current);
     }
 
     #[test]
-    fn test_connect_success_scenarios() {
-        // Setup for various connection types
-        let mut sessions = HashMap::new();
-        sessions.insert("existing-session".to_string(), false);
+    fn test_connect_with_invalid_cwd() {
+        let service = create_service(None, None, None);
 
-        let mut path_scores = HashMap::new();
-        path_scores.insert(PathBuf::from("/mock/zoxide-match"), 10.0);
+        let result =
+            service.connect_with_cwd("api", Path::new("/mock/missing"), &ZellijOptions::default());
 
+        assert!(matches!(result, Err(ConnectError::Fs(_))));
+        assert!(service.list_sessions().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_connect_kill_on_detach_marks_new_sessions_only() {
         let service = create_service(
-            Some(sessions),
-            Some(path_scores),
+            Some(HashMap::from([("existing".to_string(), false)])),
+            None,
             Some(vec![
-                (PathBuf::from("/mock/dir-path"), "dir-path".to_string()),
-                (
-                    PathBuf::from("/mock/zoxide-match"),
-                    "zoxide-match".to_string(),
-                ),
+                (PathBuf::from("/mock/project"), "project".to_string()),
+                (PathBuf::from("/mock/existing"), "existing".to_string()),
             ]),
-        );
-
-        // 1. Test connect to existing session
-        let result = service.connect("existing-session", &ZellijOptions::default());
-        assert!(result.is_ok());
-        let sessions = service.list_sessions().unwrap();
-        assert!(
-            sessions
-                .iter()
-                .any(|s| s.name == "existing-session" && s.is_current)
-        );
-
-        // 2. Test connect to directory path
-        let result = service.connect("/mock/dir-path", &ZellijOptions::default());
-        assert!(result.is_ok());
-        let sessions = service.list_sessions().unwrap();
-        assert!(
-            sessions
-                .iter()
-                .any(|s| s.name == "dir-path" && s.is_current)
-        );
+        )
+        .with_options(ConnectOptions {
+            kill_on_detach: true,
+            ..ConnectOptions::default()
+        });
+
+        service
+            .connect_to_directory("/mock/project", &ZellijOptions::default())
+            .unwrap();
+        service
+            .connect_to_directory("/mock/existing", &ZellijOptions::default())
+            .unwrap();
 
-        // 3. Test connect via zoxide query
-        let result = service.connect("zoxide-match", &ZellijOptions::default());
-        assert!(result.is_ok());
-        let sessions = service.list_sessions().unwrap();
-        assert!(
-            sessions
-                .iter()
-                .any(|s| s.name == "zoxide-match" && s.is_current)
-        );
+        assert!(service.store.kills_on_detach("project"));
+        assert!(!service.store.kills_on_detach("existing"));
     }
 
     #[test]
-    fn test_connect_fallback_behavior() {
-        // Setup with no existing sessions but a valid directory
+    fn test_connect_to_directory_expands_tilde() {
         let service = create_service(
             None,
             None,
             Some(vec![(
-                PathBuf::from("/mock/valid-dir"),
-                "valid-dir".to_string(),
+                PathBuf::from("/mock/home/projects/foo"),
+                "foo".to_string(),
             )]),
         );
 
-        // Test with a name that's not a session, should fallback to directory path
-        let result = service.connect("/mock/valid-dir", &ZellijOptions::default());
+        let result = service.connect_to_directory("~/projects/foo", &ZellijOptions::default());
         assert!(result.is_ok());
 
         let sessions = service.list_sessions().unwrap();
         assert_eq!(sessions.len(), 1);
-        assert_eq!(sessions[0].name, "valid-dir");
-
-        // Setup with no sessions but a zoxide match
-        let mut path_scores = HashMap::new();
-        path_scores.insert(PathBuf::from("/mock/zoxide-path"), 10.0);
+        assert_eq!(sessions[0].name, "foo");
+        assert_eq!(
+            service.fs.current_dir().unwrap(),
+            PathBuf::from("/mock/home/projects/foo")
+        );
+    }
 
+    #[test]
+    fn test_connect_recent_picks_top_scored_path() {
         let service = create_service(
             None,
-            Some(path_scores),
-            Some(vec![(
-                PathBuf::from("/mock/zoxide-path"),
-                "zoxide-path".to_string(),
-            )]),
+            Some(HashMap::from([
+                (PathBuf::from("/mock/low"), 1.0),
+                (PathBuf::from("/mock/top"), 42.0),
+                (PathBuf::from("/mock/mid"), 7.5),
+            ])),
+            Some(vec![
+                (PathBuf::from("/mock/low"), "low".to_string()),
+                (PathBuf::from("/mock/top"), "top".to_string()),
+                (PathBuf::from("/mock/mid"), "mid".to_string()),
+            ]),
         );
 
-        // Test with a name that should match zoxide query
-        let result = service.connect("zoxide", &ZellijOptions::default());
-        assert!(result.is_ok());
+        service.connect_recent(&ZellijOptions::default()).unwrap();
 
         let sessions = service.list_sessions().unwrap();
         assert_eq!(sessions.len(), 1);
-        assert_eq!(sessions[0].name, "zoxide-path");
+        assert_eq!(sessions[0].name, "top");
+        assert!(sessions[0].is_current);
+        assert_eq!(
+            service.fs.current_dir().unwrap(),
+            PathBuf::from("/mock/top")
+        );
     }
 
     #[test]
-    fn test_connect_failure_cases() {
-        // Setup with nothing valid
+    fn test_connect_recent_empty_zoxide() {
         let service = create_service(None, None, None);
 
-        // Test with non-existent name
-        let result = service.connect("non-existent", &ZellijOptions::default());
-        assert!(result.is_err());
-        if let Err(ConnectError::NoMatch(name)) = result {
-            assert_eq!(name, "non-existent");
-        } else {
-            panic!("Expected ConnectError::NoMatch");
-        }
+        let result = service.connect_recent(&ZellijOptions::default());
+        assert!(matches!(result, Err(ConnectError::NoMatch(_))));
+    }
 
-        // Test with failing dependencies
-        let zellij = FailingZellijClient;
-        let zoxide = MockZoxideClient::new();
-        let fs = MockFs::new();
-        let service = ConnectService::new(zellij, zoxide, fs, TestGit::new(false, "./"));
+    #[test]
+    fn test_next_available_name() {
+        let service = create_service(
+            Some(HashMap::from([
+                ("project".to_string(), false),
+                ("project-2".to_string(), false),
+            ])),
+            None,
+            None,
+        );
 
-        let result = service.connect("anything", &ZellijOptions::default());
-        assert!(result.is_err());
-        if let Err(ConnectError::Zellij(_)) = result {
-            // Expected error
-        } else {
-            panic!("Expected ConnectError::Zellij");
-        }
+        assert_eq!(service.next_available_name("other").unwrap(), "other");
+        assert_eq!(service.next_available_name("project").unwrap(), "project-3");
     }
 
     #[test]
-    fn test_connect_case_sensitivity() {
-        // Setup with case-sensitive session names
-        let mut sessions = HashMap::new();
-        sessions.insert("Case-Sensitive".to_string(), false);
-
-        let service = create_service(Some(sessions), None, None);
+    fn test_connect_to_directory_force_new_suffixes_name() {
+        let service = create_service(
+            Some(HashMap::from([("project".to_string(), false)])),
+            None,
+            Some(vec![(
+                PathBuf::from("/mock/project"),
+                "project".to_string(),
+            )]),
+        )
+        .with_options(ConnectOptions {
+            force_new: true,
+            ..ConnectOptions::default()
+        });
+
+        service
+            .connect_to_directory("/mock/project", &ZellijOptions::default())
+            .unwrap();
+        let outcome = service
+            .connect_to_directory("/mock/project", &ZellijOptions::default())
+            .unwrap();
 
-        // Test with exact case match
-        let result = service.connect("Case-Sensitive", &ZellijOptions::default());
-        assert!(result.is_ok());
+        // The outcome has the name that was picked
+        assert_eq!(outcome, ConnectOutcome::created("project-3"));
+        let mut names: Vec<_> = service
+            .list_sessions()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["project", "project-2", "project-3"]);
+        assert_eq!(
+            service.store.lookup("project-3"),
+            Some(PathBuf::from("/mock/project"))
+        );
+    }
 
-        // Test with different case (should fail)
-        let result = service.connect("case-sensitive", &ZellijOptions::default());
-        assert!(result.is_err());
+    fn resolve_service() -> ConnectService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
+        create_service(
+            Some(HashMap::from([("work".to_string(), false)])),
+            Some(HashMap::from([(PathBuf::from("/mock/zoxide/api"), 3.0)])),
+            Some(vec![(PathBuf::from("/mock/dir"), "dir".to_string())]),
+        )
     }
 
     #[test]
-    fn test_list_sessions_error_handling() {
-        // Setup with failing zellij
-        let zellij = FailingZellijClient;
-        let zoxide = MockZoxideClient::new();
-        let fs = MockFs::new();
-        let service = ConnectService::new(zellij, zoxide, fs, TestGit::new(false, "./"));
+    fn test_resolve_existing_session() {
+        let service = resolve_service();
 
-        // Test listing sessions with failing dependency
-        let result = service.list_sessions();
-        assert!(result.is_err());
-        if let Err(ConnectError::Zellij(_)) = result {
-            // Expected error
-        } else {
-            panic!("Expected ConnectError::Zellij");
-        }
+        assert_eq!(
+            service.resolve("work").unwrap(),
+            ResolveKind::ExistingSession
+        );
+        // Nothing was attached to
+        assert_eq!(service.zellij.current_session().unwrap(), None);
     }
 
     #[test]
-    fn test_complex_workflow() {
-        // Setup for a complex workflow test
-        let mut sessions = HashMap::new();
-        sessions.insert("existing".to_string(), true);
+    fn test_resolve_directory() {
+        let service = resolve_service();
 
-        let mut path_scores = HashMap::new();
-        path_scores.insert(PathBuf::from("/mock/project1"), 10.0);
-        path_scores.insert(PathBuf::from("/mock/project2"), 5.0);
+        assert_eq!(
+            service.resolve("/mock/dir").unwrap(),
+            ResolveKind::Directory(PathBuf::from("/mock/dir"))
+        );
+        assert_eq!(service.list_sessions().unwrap().len(), 1);
+        assert!(
+            service
+                .zoxide
+                .list()
+                .unwrap()
+                .iter()
+                .all(|e| e.path != Path::new("/mock/dir"))
+        );
+    }
 
-        let service = create_service(
-            Some(sessions.clone()),
-            Some(path_scores.clone()),
-            Some(vec![
-                (PathBuf::from("/mock/project1"), "project1".to_string()),
-                (PathBuf::from("/mock/project2"), "project2".to_string()),
-                (PathBuf::from("/mock/project3"), "project3".to_string()),
-            ]),
+    #[test]
+    fn test_resolve_zoxide_match() {
+        let service = resolve_service();
+
+        assert_eq!(
+            service.resolve("api").unwrap(),
+            ResolveKind::ZoxideMatch(PathBuf::from("/mock/zoxide/api"))
+        );
+        assert_eq!(service.list_sessions().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_resolve_no_match() {
+        let service = resolve_service();
+
+        assert_eq!(service.resolve("nothing").unwrap(), ResolveKind::NoMatch);
+    }
+
+    #[test]
+    fn test_resolve_forced_interpretation() {
+        let service = resolve_service();
+        service
+            .fs
+            .with_directory(Path::new("/mock/current/work"), "work");
+
+        // Sessions win by default, but a directory can be asked for
+        assert_eq!(
+            service.resolve("work").unwrap(),
+            ResolveKind::ExistingSession
+        );
+        assert_eq!(
+            service.resolve_directory("work").unwrap(),
+            ResolveKind::Directory(PathBuf::from("/mock/current/work"))
+        );
+        assert_eq!(
+            service.resolve_session("/mock/dir").unwrap(),
+            ResolveKind::NoMatch
+        );
+    }
+
+    #[test]
+    fn test_query_prints_resolved_paths() {
+        let service = resolve_service();
+        service
+            .store
+            .record("work", Path::new("/mock/work"))
+            .unwrap();
+
+        // A session resolves to its root, directories and zoxide matches to
+        // themselves
+        assert_eq!(service.query("work").unwrap(), PathBuf::from("/mock/work"));
+        assert_eq!(
+            service.query("/mock/dir").unwrap(),
+            PathBuf::from("/mock/dir")
+        );
+        assert_eq!(
+            service.query("api").unwrap(),
+            PathBuf::from("/mock/zoxide/api")
+        );
+
+        // Nothing was connected to or recorded
+        assert_eq!(service.zellij.current_session().unwrap(), None);
+        assert_eq!(service.list_sessions().unwrap().len(), 1);
+        assert!(service.history.recent().is_empty());
+    }
+
+    #[test]
+    fn test_query_session_without_root_falls_back_to_directory() {
+        let service = resolve_service();
+        service
+            .fs
+            .with_directory(Path::new("/mock/current/work"), "work");
+
+        assert_eq!(
+            service.query("work").unwrap(),
+            PathBuf::from("/mock/current/work")
+        );
+    }
+
+    #[test]
+    fn test_query_no_match() {
+        let service = resolve_service();
+
+        assert!(matches!(
+            service.query("nothing"),
+            Err(ConnectError::NoMatch(name)) if name == "nothing"
+        ));
+        // A session zesh doesn't know the directory of has no path either
+        assert!(matches!(
+            service.query("work"),
+            Err(ConnectError::NoMatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_force_new_skips_sessions() {
+        let service = resolve_service().with_options(ConnectOptions {
+            force_new: true,
+            ..ConnectOptions::default()
+        });
+
+        assert_eq!(service.resolve("work").unwrap(), ResolveKind::NoMatch);
+    }
+
+    /// A `docs` directory in the current repository, and an unrelated
+    /// session named `docs`
+    fn same_named_service(
+        prefer: Prefer,
+    ) -> ConnectService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
+        create_service_with_git(
+            Some(HashMap::from([("docs".to_string(), false)])),
+            Some(HashMap::from([(PathBuf::from("/mock/src/docs-site"), 2.0)])),
+            Some(vec![
+                (PathBuf::from("/mock/current"), "current".to_string()),
+                (PathBuf::from("/mock/current/docs"), "docs".to_string()),
+                (
+                    PathBuf::from("/mock/src/docs-site"),
+                    "docs-site".to_string(),
+                ),
+            ]),
+            true,
+            "/mock/current",
+        )
+        .with_options(ConnectOptions {
+            prefer,
+            ..ConnectOptions::default()
+        })
+    }
+
+    #[test]
+    fn test_connect_prefers_session_by_default() {
+        let service = same_named_service(Prefer::default());
+
+        service.connect("docs", &ZellijOptions::default()).unwrap();
+
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("docs".to_string())
+        );
+        assert_eq!(service.list_sessions().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_connect_prefer_dir_picks_directory_over_session() {
+        let service = same_named_service(Prefer::Dir);
+
+        assert_eq!(
+            service.resolve("docs").unwrap(),
+            ResolveKind::Directory(PathBuf::from("/mock/current/docs"))
+        );
+        service.connect("docs", &ZellijOptions::default()).unwrap();
+
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("current_docs".to_string())
+        );
+    }
+
+    #[test]
+    fn test_connect_prefer_zoxide_picks_match_over_session() {
+        let service = same_named_service(Prefer::Zoxide);
+
+        assert_eq!(
+            service.resolve("docs").unwrap(),
+            ResolveKind::ZoxideMatch(PathBuf::from("/mock/src/docs-site"))
+        );
+        service.connect("docs", &ZellijOptions::default()).unwrap();
+
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("docs-site".to_string())
+        );
+    }
+
+    #[test]
+    fn test_connect_prefer_dir_falls_back_to_session() {
+        let service = same_named_service(Prefer::Dir);
+
+        // There's no such directory, so the session still wins over zoxide
+        service.fs.with_current_dir(Path::new("/mock/src"));
+        service.connect("docs", &ZellijOptions::default()).unwrap();
+
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("docs".to_string())
+        );
+        assert_eq!(
+            service.resolve("docs").unwrap(),
+            ResolveKind::ExistingSession
+        );
+    }
+
+    #[test]
+    fn test_connect_as_tab_opens_tab_in_current_session() {
+        let service = create_service(
+            Some(HashMap::from([
+                ("work".to_string(), true),
+                ("project".to_string(), false),
+            ])),
+            None,
+            Some(vec![(
+                PathBuf::from("/mock/project"),
+                "project".to_string(),
+            )]),
+        )
+        .with_options(ConnectOptions {
+            target: Target::Tab,
+            ..ConnectOptions::default()
+        });
+
+        // Even though a session matches the name, the directory gets a tab
+        let outcome = service
+            .connect("/mock/project", &ZellijOptions::default())
+            .unwrap();
+
+        assert_eq!(outcome, ConnectOutcome::created("work"));
+
+        let tabs = service.zellij.list_tabs().unwrap();
+        assert_eq!(tabs.len(), 1);
+        assert_eq!(tabs[0].name.as_deref(), Some("project"));
+        assert_eq!(
+            service.zellij.tab_cwd(0),
+            Some(PathBuf::from("/mock/project"))
+        );
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("work".to_string())
+        );
+        assert_eq!(service.list_sessions().unwrap().len(), 2);
+        assert_eq!(service.store.lookup("project"), None);
+    }
+
+    #[test]
+    fn test_connect_as_tab_outside_session() {
+        let service = create_service(
+            None,
+            None,
+            Some(vec![(
+                PathBuf::from("/mock/project"),
+                "project".to_string(),
+            )]),
+        )
+        .with_options(ConnectOptions {
+            target: Target::Tab,
+            ..ConnectOptions::default()
+        });
+
+        let result = service.connect("/mock/project", &ZellijOptions::default());
+
+        assert!(matches!(result, Err(ConnectError::NoSession)));
+        assert!(service.zellij.list_tabs().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_connect_force_new_skips_existing_session_name() {
+        let service = create_service(
+            Some(HashMap::from([("project".to_string(), false)])),
+            None,
+            Some(vec![(
+                PathBuf::from("/mock/project"),
+                "project".to_string(),
+            )]),
+        )
+        .with_options(ConnectOptions {
+            force_new: true,
+            ..ConnectOptions::default()
+        });
+
+        service
+            .connect("/mock/project", &ZellijOptions::default())
+            .unwrap();
+
+        let sessions = service.list_sessions().unwrap();
+        let original = sessions.iter().find(|s| s.name == "project").unwrap();
+        assert!(!original.is_current);
+        let created = sessions.iter().find(|s| s.name == "project-2").unwrap();
+        assert!(created.is_current);
+    }
+
+    #[test]
+    fn test_connect_to_directory_existing_session() {
+        // Setup test directory and existing session with same name
+        let dir_path = PathBuf::from("/mock/project");
+        let mut sessions = HashMap::new();
+        sessions.insert("project".to_string(), false);
+
+        let service = create_service(
+            Some(sessions),
+            None,
+            Some(vec![(dir_path.clone(), "project".to_string())]),
+        );
+
+        // Test connecting to directory that already has a session
+        let result = service.connect_to_directory("/mock/project", &ZellijOptions::default());
+        assert!(result.is_ok());
+
+        // After connection, should attach to existing session
+        let sessions = service.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "project");
+        assert!(sessions[0].is_current);
+    }
+
+    /// A service where `/mock/project` already has a session of the same
+    /// name, and zoxide knows the directory
+    fn colliding_service(
+        policy: CollisionPolicy,
+    ) -> ConnectService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
+        create_service(
+            Some(HashMap::from([("project".to_string(), false)])),
+            Some(HashMap::from([(PathBuf::from("/mock/project"), 10.0)])),
+            Some(vec![(
+                PathBuf::from("/mock/project"),
+                "project".to_string(),
+            )]),
+        )
+        .with_config(Config {
+            collision_policy: policy,
+            ..Config::default()
+        })
+    }
+
+    fn current_session_name(
+        service: &ConnectService<MockZellijClient, MockZoxideClient, MockFs, TestGit>,
+    ) -> Option<String> {
+        service.zellij.current_session().unwrap()
+    }
+
+    #[test]
+    fn test_collision_policy_attach() {
+        let service = colliding_service(CollisionPolicy::Attach);
+        service
+            .connect_to_directory("/mock/project", &ZellijOptions::default())
+            .unwrap();
+        assert_eq!(service.list_sessions().unwrap().len(), 1);
+        assert_eq!(current_session_name(&service).as_deref(), Some("project"));
+
+        let service = colliding_service(CollisionPolicy::Attach);
+        service
+            .connect_via_zoxide(&["project"], &ZellijOptions::default())
+            .unwrap();
+        assert_eq!(service.list_sessions().unwrap().len(), 1);
+        assert_eq!(current_session_name(&service).as_deref(), Some("project"));
+    }
+
+    #[test]
+    fn test_collision_policy_suffix() {
+        let service = colliding_service(CollisionPolicy::Suffix);
+        service
+            .connect_to_directory("/mock/project", &ZellijOptions::default())
+            .unwrap();
+        assert_eq!(service.list_sessions().unwrap().len(), 2);
+        assert_eq!(current_session_name(&service).as_deref(), Some("project-2"));
+
+        let service = colliding_service(CollisionPolicy::Suffix);
+        service
+            .connect_via_zoxide(&["project"], &ZellijOptions::default())
+            .unwrap();
+        assert_eq!(service.list_sessions().unwrap().len(), 2);
+        assert_eq!(current_session_name(&service).as_deref(), Some("project-2"));
+    }
+
+    #[test]
+    fn test_collision_policy_error() {
+        let service = colliding_service(CollisionPolicy::Error);
+        let result = service.connect_to_directory("/mock/project", &ZellijOptions::default());
+        assert!(matches!(result, Err(ConnectError::SessionExists(name)) if name == "project"));
+
+        let result = service.connect_via_zoxide(&["project"], &ZellijOptions::default());
+        assert!(matches!(result, Err(ConnectError::SessionExists(name)) if name == "project"));
+
+        // Nothing was attached or created
+        assert_eq!(service.list_sessions().unwrap().len(), 1);
+        assert_eq!(current_session_name(&service), None);
+    }
+
+    #[test]
+    fn test_collision_policy_error_is_not_masked_by_zoxide_fallback() {
+        let service = colliding_service(CollisionPolicy::Error);
+        let result = service.connect("/mock/project", &ZellijOptions::default());
+        assert!(matches!(result, Err(ConnectError::SessionExists(_))));
+    }
+
+    #[test]
+    fn test_force_new_overrides_collision_policy() {
+        let service = colliding_service(CollisionPolicy::Error).with_options(ConnectOptions {
+            force_new: true,
+            ..ConnectOptions::default()
+        });
+        service
+            .connect_to_directory("/mock/project", &ZellijOptions::default())
+            .unwrap();
+        assert_eq!(current_session_name(&service).as_deref(), Some("project-2"));
+    }
+
+    #[test]
+    fn test_connect_to_directory_invalid_path() {
+        let service = create_service(None, None, None);
+
+        // Test with non-existent path
+        let result = service.connect_to_directory("/mock/non-existent", &ZellijOptions::default());
+        assert!(result.is_err());
+        if let Err(ConnectError::Fs(_)) = result {
+            // Expected error
+        } else {
+            panic!("Expected ConnectError::Fs");
+        }
+
+        // Setup a file path (not a directory)
+        let fs = MockFs::new();
+        fs.with_file(&PathBuf::from("/mock/file.txt"));
+        let service = ConnectService::new(
+            MockZellijClient::new(),
+            MockZoxideClient::new(),
+            fs,
+            TestGit::new(false, "./"),
+        );
+
+        // Test with a file path instead of directory
+        let result = service.connect_to_directory("/mock/file.txt", &ZellijOptions::default());
+        assert!(result.is_err());
+        if let Err(ConnectError::Fs(_)) = result {
+            // Expected error
+        } else {
+            panic!("Expected ConnectError::Fs");
+        }
+    }
+
+    #[test]
+    fn test_connect_to_directory_error_handling() {
+        // Setup test with failing filesystem
+        let zellij = MockZellijClient::new();
+        let zoxide = MockZoxideClient::new();
+        let fs = FailingFs;
+        let service = ConnectService::new(zellij, zoxide, fs, TestGit::new(false, "./"));
+
+        let result = service.connect_to_directory("/any/path", &ZellijOptions::default());
+        assert!(result.is_err());
+        if let Err(ConnectError::Fs(_)) = result {
+            // Expected error
+        } else {
+            panic!("Expected ConnectError::Fs");
+        }
+
+        // Setup test with working filesystem but failing zellij
+        let zellij = FailingZellijClient;
+        let zoxide = MockZoxideClient::new();
+        let fs = MockFs::new();
+        fs.with_directory(&PathBuf::from("/mock/project"), "project");
+        let service = ConnectService::new(zellij, zoxide, fs, TestGit::new(false, "./"));
+
+        let result = service.connect_to_directory("/mock/project", &ZellijOptions::default());
+        assert!(result.is_err());
+        if let Err(ConnectError::Zellij(_)) = result {
+            // Expected error
+        } else {
+            panic!("Expected ConnectError::Zellij");
+        }
+
+        // Setup test with working filesystem and zellij but failing zoxide
+        let zellij = MockZellijClient::new();
+        let zoxide = FailingZoxideClient;
+        let fs = MockFs::new();
+        fs.with_directory(&PathBuf::from("/mock/project"), "project");
+        let service = ConnectService::new(zellij, zoxide, fs, TestGit::new(false, "./"));
+
+        let result = service.connect_to_directory("/mock/project", &ZellijOptions::default());
+        assert!(result.is_err());
+        if let Err(ConnectError::Zoxide(_)) = result {
+            // Expected error
+        } else {
+            panic!("Expected ConnectError::Zoxide");
+        }
+    }
+
+    #[test]
+    fn test_connect_to_directory_with_special_chars() {
+        // Test directory with spaces and special characters
+        let dir_path = PathBuf::from("/mock/special project-name!");
+        let service = create_service(
+            None,
+            None,
+            Some(vec![(
+                dir_path.clone(),
+                "special project-name!".to_string(),
+            )]),
+        );
+
+        let result =
+            service.connect_to_directory("/mock/special project-name!", &ZellijOptions::default());
+        assert!(result.is_ok());
+
+        // Verify session was created with special characters
+        let sessions = service.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "special project-name!");
+    }
+
+    #[test]
+    fn test_connect_via_zoxide_single_match() {
+        // Setup zoxide with a single matching path
+        let mut path_scores = HashMap::new();
+        path_scores.insert(PathBuf::from("/mock/zoxide-dir"), 10.0);
+
+        let service = create_service(
+            None,
+            Some(path_scores),
+            Some(vec![(
+                PathBuf::from("/mock/zoxide-dir"),
+                "zoxide-dir".to_string(),
+            )]),
+        );
+
+        // Test connecting via zoxide query that matches single entry
+        let result = service.connect_via_zoxide(&["zoxide"], &ZellijOptions::default());
+        assert!(result.is_ok());
+
+        // After connection, should have a new session with the directory name
+        let sessions = service.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "zoxide-dir");
+    }
+
+    #[test]
+    fn test_connect_via_frecency_store() {
+        let store = FrecencyStore::new();
+        store.add("/mock/zesh-dir").unwrap();
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/zesh-dir"), "zesh-dir");
+        let service = ConnectService::new(
+            MockZellijClient::new(),
+            Directories::<MockZoxideClient>::Zesh(store),
+            fs,
+            TestGit::new(false, "./"),
+        );
+
+        service
+            .connect_via_zoxide(&["zesh"], &ZellijOptions::default())
+            .unwrap();
+
+        assert_eq!(service.list_sessions().unwrap()[0].name, "zesh-dir");
+        // The visit is ranked in zesh's store
+        assert!(service.zoxide().list().unwrap()[0].score > 4.0);
+    }
+
+    #[test]
+    fn test_connect_via_zoxide_multiple_matches() {
+        // Setup zoxide with multiple matching paths
+        let mut path_scores = HashMap::new();
+        path_scores.insert(PathBuf::from("/mock/best-match"), 20.0);
+        path_scores.insert(PathBuf::from("/mock/second-match"), 10.0);
+
+        let service = create_service(
+            None,
+            Some(path_scores),
+            Some(vec![
+                (PathBuf::from("/mock/best-match"), "best-match".to_string()),
+                (
+                    PathBuf::from("/mock/second-match"),
+                    "second-match".to_string(),
+                ),
+            ]),
+        );
+
+        // Test connecting via zoxide query that matches multiple entries
+        let result = service.connect_via_zoxide(&["match"], &ZellijOptions::default());
+        assert!(result.is_ok());
+
+        // Should connect to highest scored match
+        let sessions = service.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "best-match");
+    }
+
+    #[test]
+    fn test_connect_keywords_matches_all() {
+        let mut path_scores = HashMap::new();
+        path_scores.insert(PathBuf::from("/mock/src/api"), 5.0);
+        path_scores.insert(PathBuf::from("/mock/work/api"), 10.0);
+        path_scores.insert(PathBuf::from("/mock/src/web"), 20.0);
+
+        let service = create_service(
+            None,
+            Some(path_scores),
+            Some(vec![(PathBuf::from("/mock/src/api"), "api".to_string())]),
+        );
+
+        // Only one path contains both keywords, even though others score higher
+        let result = service.connect_keywords(&["src", "api"], &ZellijOptions::default());
+        assert!(result.is_ok());
+
+        let sessions = service.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "api");
+    }
+
+    #[test]
+    fn test_connect_keywords_no_match() {
+        let mut path_scores = HashMap::new();
+        path_scores.insert(PathBuf::from("/mock/src/api"), 5.0);
+        let service = create_service(None, Some(path_scores), None);
+
+        let result = service.connect_keywords(&["src", "web"], &ZellijOptions::default());
+        if let Err(ConnectError::NoMatch(query)) = result {
+            assert_eq!(query, "src web");
+        } else {
+            panic!("Expected ConnectError::NoMatch");
+        }
+    }
+
+    #[test]
+    fn test_connect_keywords_single_session_name() {
+        let mut sessions = HashMap::new();
+        sessions.insert("api".to_string(), false);
+        let service = create_service(Some(sessions), None, None);
+
+        // A single keyword still attaches to a session by exact name
+        let result = service.connect_keywords(&["api"], &ZellijOptions::default());
+        assert!(result.is_ok());
+        assert!(service.list_sessions().unwrap()[0].is_current);
+    }
+
+    #[test]
+    fn test_connect_via_zoxide_existing_session() {
+        // Setup zoxide path and existing session with same name
+        let mut path_scores = HashMap::new();
+        path_scores.insert(PathBuf::from("/mock/existing"), 10.0);
+
+        let mut sessions = HashMap::new();
+        sessions.insert("existing".to_string(), false);
+
+        let service = create_service(
+            Some(sessions),
+            Some(path_scores),
+            Some(vec![(
+                PathBuf::from("/mock/existing"),
+                "existing".to_string(),
+            )]),
+        );
+
+        // Test connecting via zoxide when session already exists
+        let result = service.connect_via_zoxide(&["existing"], &ZellijOptions::default());
+        assert!(result.is_ok());
+
+        // Should attach to existing session
+        let sessions = service.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "existing");
+        assert!(sessions[0].is_current);
+    }
+
+    #[test]
+    fn test_connect_via_zoxide_no_matches() {
+        // Setup empty zoxide database
+        let service = create_service(None, None, None);
+
+        // Test connecting via zoxide with no matches
+        let result = service.connect_via_zoxide(&["non-existent"], &ZellijOptions::default());
+        assert!(result.is_err());
+        if let Err(ConnectError::NoMatch(query)) = result {
+            assert_eq!(query, "non-existent");
+        } else {
+            panic!("Expected ConnectError::NoMatch");
+        }
+    }
+
+    #[test]
+    fn test_connect_via_zoxide_error_handling() {
+        // Setup failing zoxide client
+        let zellij = MockZellijClient::new();
+        let zoxide = FailingZoxideClient;
+        let fs = MockFs::new();
+        let service = ConnectService::new(zellij, zoxide, fs, TestGit::new(false, "./"));
+
+        let result = service.connect_via_zoxide(&["query"], &ZellijOptions::default());
+        assert!(result.is_err());
+        if let Err(ConnectError::Zoxide(_)) = result {
+            // Expected error
+        } else {
+            panic!("Expected ConnectError::Zoxide");
+        }
+    }
+
+    #[test]
+    fn test_connect_success_scenarios() {
+        // Setup for various connection types
+        let mut sessions = HashMap::new();
+        sessions.insert("existing-session".to_string(), false);
+
+        let mut path_scores = HashMap::new();
+        path_scores.insert(PathBuf::from("/mock/zoxide-match"), 10.0);
+
+        let service = create_service(
+            Some(sessions),
+            Some(path_scores),
+            Some(vec![
+                (PathBuf::from("/mock/dir-path"), "dir-path".to_string()),
+                (
+                    PathBuf::from("/mock/zoxide-match"),
+                    "zoxide-match".to_string(),
+                ),
+            ]),
+        );
+
+        // 1. Test connect to existing session
+        let result = service.connect("existing-session", &ZellijOptions::default());
+        assert!(result.is_ok());
+        let sessions = service.list_sessions().unwrap();
+        assert!(
+            sessions
+                .iter()
+                .any(|s| s.name == "existing-session" && s.is_current)
+        );
+
+        // 2. Test connect to directory path
+        let result = service.connect("/mock/dir-path", &ZellijOptions::default());
+        assert!(result.is_ok());
+        let sessions = service.list_sessions().unwrap();
+        assert!(
+            sessions
+                .iter()
+                .any(|s| s.name == "dir-path" && s.is_current)
+        );
+
+        // 3. Test connect via zoxide query
+        let result = service.connect("zoxide-match", &ZellijOptions::default());
+        assert!(result.is_ok());
+        let sessions = service.list_sessions().unwrap();
+        assert!(
+            sessions
+                .iter()
+                .any(|s| s.name == "zoxide-match" && s.is_current)
+        );
+    }
+
+    #[test]
+    fn test_connect_fallback_behavior() {
+        // Setup with no existing sessions but a valid directory
+        let service = create_service(
+            None,
+            None,
+            Some(vec![(
+                PathBuf::from("/mock/valid-dir"),
+                "valid-dir".to_string(),
+            )]),
+        );
+
+        // Test with a name that's not a session, should fallback to directory path
+        let result = service.connect("/mock/valid-dir", &ZellijOptions::default());
+        assert!(result.is_ok());
+
+        let sessions = service.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "valid-dir");
+
+        // Setup with no sessions but a zoxide match
+        let mut path_scores = HashMap::new();
+        path_scores.insert(PathBuf::from("/mock/zoxide-path"), 10.0);
+
+        let service = create_service(
+            None,
+            Some(path_scores),
+            Some(vec![(
+                PathBuf::from("/mock/zoxide-path"),
+                "zoxide-path".to_string(),
+            )]),
+        );
+
+        // Test with a name that should match zoxide query
+        let result = service.connect("zoxide", &ZellijOptions::default());
+        assert!(result.is_ok());
+
+        let sessions = service.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "zoxide-path");
+    }
+
+    #[test]
+    fn test_connect_failure_cases() {
+        // Setup with nothing valid
+        let service = create_service(None, None, None);
+
+        // Test with non-existent name
+        let result = service.connect("non-existent", &ZellijOptions::default());
+        assert!(result.is_err());
+        if let Err(ConnectError::NoMatch(name)) = result {
+            assert_eq!(name, "non-existent");
+        } else {
+            panic!("Expected ConnectError::NoMatch");
+        }
+
+        // Test with failing dependencies
+        let zellij = FailingZellijClient;
+        let zoxide = MockZoxideClient::new();
+        let fs = MockFs::new();
+        let service = ConnectService::new(zellij, zoxide, fs, TestGit::new(false, "./"));
+
+        let result = service.connect("anything", &ZellijOptions::default());
+        assert!(result.is_err());
+        if let Err(ConnectError::Zellij(_)) = result {
+            // Expected error
+        } else {
+            panic!("Expected ConnectError::Zellij");
+        }
+    }
+
+    #[test]
+    fn test_connect_case_sensitivity() {
+        // Setup with case-sensitive session names
+        let mut sessions = HashMap::new();
+        sessions.insert("Case-Sensitive".to_string(), false);
+
+        let service = create_service(Some(sessions), None, None);
+
+        // Test with exact case match
+        let result = service.connect("Case-Sensitive", &ZellijOptions::default());
+        assert!(result.is_ok());
+
+        // Test with different case (should fail)
+        let result = service.connect("case-sensitive", &ZellijOptions::default());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_list_sessions_error_handling() {
+        // Setup with failing zellij
+        let zellij = FailingZellijClient;
+        let zoxide = MockZoxideClient::new();
+        let fs = MockFs::new();
+        let service = ConnectService::new(zellij, zoxide, fs, TestGit::new(false, "./"));
+
+        // Test listing sessions with failing dependency
+        let result = service.list_sessions();
+        assert!(result.is_err());
+        if let Err(ConnectError::Zellij(_)) = result {
+            // Expected error
+        } else {
+            panic!("Expected ConnectError::Zellij");
+        }
+    }
+
+    #[test]
+    fn test_complex_workflow() {
+        // Setup for a complex workflow test
+        let mut sessions = HashMap::new();
+        sessions.insert("existing".to_string(), true);
+
+        let mut path_scores = HashMap::new();
+        path_scores.insert(PathBuf::from("/mock/project1"), 10.0);
+        path_scores.insert(PathBuf::from("/mock/project2"), 5.0);
+
+        let service = create_service(
+            Some(sessions.clone()),
+            Some(path_scores.clone()),
+            Some(vec![
+                (PathBuf::from("/mock/project1"), "project1".to_string()),
+                (PathBuf::from("/mock/project2"), "project2".to_string()),
+                (PathBuf::from("/mock/project3"), "project3".to_string()),
+            ]),
         );
 
         // 1. List initial sessions
@@ -819,182 +2775,1247 @@ mod tests {
         assert_eq!(initial_sessions.len(), 1);
         assert_eq!(initial_sessions[0].name, "existing");
 
-        // 2. Connect to directory directly
-        let result = service.connect_to_directory("/mock/project3", &ZellijOptions::default());
-        assert!(result.is_ok());
+        // 2. Connect to directory directly
+        let result = service.connect_to_directory("/mock/project3", &ZellijOptions::default());
+        assert!(result.is_ok());
+
+        // Verify new session created
+        let sessions_after_dir = service.list_sessions().unwrap();
+        assert_eq!(sessions_after_dir.len(), 2);
+        assert!(
+            sessions_after_dir
+                .iter()
+                .any(|s| s.name == "project3" && s.is_current)
+        );
+        assert!(
+            sessions_after_dir
+                .iter()
+                .any(|s| s.name == "existing" && !s.is_current)
+        );
+
+        // 3. Connect via zoxide
+        let result = service.connect_via_zoxide(&["project1"], &ZellijOptions::default());
+        assert!(result.is_ok());
+
+        // Verify another session created
+        let sessions_after_zoxide = service.list_sessions().unwrap();
+        assert_eq!(sessions_after_zoxide.len(), 3);
+        assert!(
+            sessions_after_zoxide
+                .iter()
+                .any(|s| s.name == "project1" && s.is_current)
+        );
+
+        // 4. Connect back to first session
+        let result = service.connect_to_session("existing");
+        assert!(result.is_ok());
+
+        // Verify attached to existing session
+        let final_sessions = service.list_sessions().unwrap();
+        assert_eq!(final_sessions.len(), 3);
+        assert!(
+            final_sessions
+                .iter()
+                .any(|s| s.name == "existing" && s.is_current)
+        );
+        assert!(
+            final_sessions
+                .iter()
+                .any(|s| s.name == "project1" && !s.is_current)
+        );
+        assert!(
+            final_sessions
+                .iter()
+                .any(|s| s.name == "project3" && !s.is_current)
+        );
+    }
+    // Custom Git mock for testing
+    struct TestGit {
+        is_git_repo: bool,
+        git_root: String,
+        top_level_calls: std::cell::Cell<usize>,
+    }
+
+    impl TestGit {
+        fn new(is_git_repo: bool, git_root: &str) -> Self {
+            Self {
+                is_git_repo,
+                git_root: git_root.to_string(),
+                top_level_calls: std::cell::Cell::new(0),
+            }
+        }
+    }
+
+    impl Git for TestGit {
+        fn show_top_level(&self, _name: &str) -> Result<(bool, String), GitError> {
+            self.top_level_calls.set(self.top_level_calls.get() + 1);
+            Ok((self.is_git_repo, self.git_root.clone()))
+        }
+
+        fn is_inside_work_tree(&self, _name: &str) -> Result<bool, GitError> {
+            Ok(self.is_git_repo)
+        }
+
+        fn git_common_dir(&self, _name: &str) -> Result<(bool, String), GitError> {
+            Ok((self.is_git_repo, "/mock/repo/common-dir".to_string()))
+        }
+
+        fn clone(&self, _url: &str, _cmd_dir: &str, _dir: &str) -> Result<String, GitError> {
+            Ok("Mock clone successful".to_string())
+        }
+
+        fn clone_bare(&self, _url: &str, _cmd_dir: &str, _dir: &str) -> Result<String, GitError> {
+            Ok("Mock clone successful".to_string())
+        }
+
+        fn worktree_add(&self, _repo: &str, _path: &str, _branch: &str) -> Result<(), GitError> {
+            Ok(())
+        }
+
+        fn version(&self) -> Result<String, GitError> {
+            Ok("git version 2.43.0".to_string())
+        }
+
+        fn default_branch(&self, _url_or_dir: &str) -> Result<String, GitError> {
+            Ok("main".to_string())
+        }
+    }
+
+    // Helper function to create a ConnectService with the TestGit
+    fn create_service_with_git(
+        zellij_sessions: Option<HashMap<String, bool>>,
+        zoxide_paths: Option<HashMap<PathBuf, f64>>,
+        fs_dirs: Option<Vec<(PathBuf, String)>>,
+        is_git_repo: bool,
+        git_root: &str,
+    ) -> ConnectService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
+        let zellij = if let Some(sessions) = zellij_sessions {
+            MockZellijClient::with_sessions(sessions)
+        } else {
+            MockZellijClient::new()
+        };
+
+        let zoxide = if let Some(paths) = zoxide_paths {
+            MockZoxideClient::with_paths(paths)
+        } else {
+            MockZoxideClient::new()
+        };
+
+        let fs = MockFs::new();
+        if let Some(dirs) = fs_dirs {
+            for (path, name) in dirs {
+                fs.with_directory(&path, &name);
+            }
+        }
+
+        let git = TestGit::new(is_git_repo, git_root);
+
+        ConnectService::new(zellij, zoxide, fs, git)
+    }
+
+    #[test]
+    fn test_get_session_name_for_git_repo() {
+        // Set up mock file system with git repo structure
+        let git_root = PathBuf::from("/mock/foo");
+        let subdir = PathBuf::from("/mock/foo/bar");
+
+        let fs_dirs = vec![
+            (git_root.clone(), "foo".to_string()),
+            (subdir.clone(), "bar".to_string()),
+        ];
+
+        // Create service with git repo configuration
+        let service = create_service_with_git(None, None, Some(fs_dirs), true, "/mock/foo");
+
+        // Test getting session name for git root
+        let name = service.get_session_name_for_path(&git_root).unwrap();
+        assert_eq!(name, "foo");
+
+        // Test getting session name for subdirectory
+        let name = service.get_session_name_for_path(&subdir).unwrap();
+        assert_eq!(name, "foo_bar");
+    }
+
+    #[test]
+    fn test_get_session_name_for_non_git_path() {
+        // Set up mock file system with directory not in git repo
+        let path = PathBuf::from("/mock/not-git");
+
+        let fs_dirs = vec![(path.clone(), "not-git".to_string())];
+
+        // Create service with non-git configuration
+        let service = create_service_with_git(None, None, Some(fs_dirs), false, "");
+
+        // Test getting session name for non-git path
+        let name = service.get_session_name_for_path(&path).unwrap();
+        assert_eq!(name, "not-git");
+
+        // The toplevel lookup is skipped outside of a work tree
+        assert_eq!(service.git.top_level_calls.get(), 0);
+    }
+
+    #[test]
+    fn test_session_name_for_path() {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/foo"), "foo");
+        fs.with_directory(Path::new("/mock/foo/bar"), "bar");
+        fs.with_directory(Path::new("/mock/foo-feature"), "foo-feature");
+
+        let repo = TestGit::new(true, "/mock/foo");
+        let name = |git: &TestGit, path: &str| session_name_for_path(&fs, git, Path::new(path));
+        assert_eq!(name(&repo, "/mock/foo").unwrap(), "foo");
+        assert_eq!(name(&repo, "/mock/foo/bar").unwrap(), "foo_bar");
+
+        // A worktree is a repository root of its own, so it's named after
+        // its own directory rather than the main checkout
+        let worktree = TestGit::new(true, "/mock/foo-feature");
+        assert_eq!(name(&worktree, "/mock/foo-feature").unwrap(), "foo-feature");
+
+        let not_repo = TestGit::new(false, "");
+        assert_eq!(name(&not_repo, "/mock/foo/bar").unwrap(), "bar");
+    }
+
+    fn symlinked_service(
+        follow_symlinks: bool,
+    ) -> ConnectService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
+        let service = create_service(
+            None,
+            None,
+            Some(vec![
+                (
+                    PathBuf::from("/mock/src/api-server"),
+                    "api-server".to_string(),
+                ),
+                (PathBuf::from("/mock/links/api"), "api".to_string()),
+            ]),
+        )
+        .with_config(Config {
+            follow_symlinks,
+            ..Config::default()
+        });
+        service.fs.with_symlink(
+            Path::new("/mock/links/api"),
+            Path::new("/mock/src/api-server"),
+        );
+        service
+    }
+
+    #[test]
+    fn test_connect_through_symlink_names_after_target() {
+        let service = symlinked_service(true);
+
+        service
+            .connect_to_directory("/mock/links/api", &ZellijOptions::default())
+            .unwrap();
+
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("api-server".to_string())
+        );
+        assert_eq!(
+            service.store.lookup("api-server"),
+            Some(PathBuf::from("/mock/src/api-server"))
+        );
+    }
+
+    #[test]
+    fn test_connect_through_symlink_keeps_logical_path() {
+        let service = symlinked_service(false);
+
+        service
+            .connect_to_directory("/mock/links/./api", &ZellijOptions::default())
+            .unwrap();
+
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("api".to_string())
+        );
+        assert_eq!(
+            service.store.lookup("api"),
+            Some(PathBuf::from("/mock/links/api"))
+        );
+        assert_eq!(
+            service.fs.current_dir().unwrap(),
+            PathBuf::from("/mock/links/api")
+        );
+    }
+
+    #[test]
+    fn test_connect_without_following_symlinks_still_validates() {
+        let service = symlinked_service(false);
+        // A dangling link
+        service
+            .fs
+            .with_symlink(Path::new("/mock/links/gone"), Path::new("/mock/src/gone"));
+
+        let result = service.connect_to_directory("/mock/links/gone", &ZellijOptions::default());
+
+        assert!(matches!(
+            result,
+            Err(ConnectError::Fs(FsError::PathNotFound(_)))
+        ));
+    }
+
+    #[test]
+    fn test_sanitize_session_name() {
+        assert_eq!(sanitize_session_name("project"), "project");
+        assert_eq!(sanitize_session_name("repo_docs/api"), "repo_docs_api");
+        assert_eq!(sanitize_session_name("repo_docs\\api"), "repo_docs_api");
+        assert_eq!(sanitize_session_name("C:\\"), "C");
+        assert_eq!(sanitize_session_name("d:/"), "d");
+        assert_eq!(sanitize_session_name("C:"), "C");
+        assert_eq!(sanitize_session_name("\\\\server\\share\\"), "server_share");
+        // Only a drive letter loses its colon
+        assert_eq!(sanitize_session_name("notes:2024"), "notes:2024");
+    }
+
+    #[test]
+    fn test_session_name_for_windows_style_paths() {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/foo"), "foo");
+        fs.with_directory(Path::new("/mock/foo/docs/api"), "api");
+        fs.with_directory(Path::new("C:\\"), "C:\\");
+
+        let not_repo = TestGit::new(false, "");
+        let name = |git: &TestGit, path: &str| session_name_for_path(&fs, git, Path::new(path));
+        assert_eq!(name(&not_repo, "C:\\").unwrap(), "C");
+
+        // Nested subdirectories don't leak separators into the name
+        let repo = TestGit::new(true, "/mock/foo");
+        assert_eq!(name(&repo, "/mock/foo/docs/api").unwrap(), "foo_docs_api");
+    }
+
+    #[test]
+    fn test_get_session_name_inside_work_tree_uses_top_level() {
+        let git_root = PathBuf::from("/mock/foo");
+        let service = create_service_with_git(
+            None,
+            None,
+            Some(vec![(git_root.clone(), "foo".to_string())]),
+            true,
+            "/mock/foo",
+        );
+
+        let name = service.get_session_name_for_path(&git_root).unwrap();
+        assert_eq!(name, "foo");
+        assert_eq!(service.git.top_level_calls.get(), 1);
+    }
+
+    #[test]
+    fn test_connect_to_git_directory() {
+        // Set up mock file system with git repo structure
+        let git_root = PathBuf::from("/mock/project");
+        let subdir = PathBuf::from("/mock/project/feature");
+
+        let fs_dirs = vec![
+            (git_root.clone(), "project".to_string()),
+            (subdir.clone(), "feature".to_string()),
+        ];
+
+        // Create service with git repo configuration
+        let service = create_service_with_git(None, None, Some(fs_dirs), true, "/mock/project");
+
+        // Connect to subdirectory
+        let result =
+            service.connect_to_directory("/mock/project/feature", &ZellijOptions::default());
+        assert!(result.is_ok());
+
+        // Verify the session name is git-aware
+        let sessions = service.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "project_feature");
+        assert!(sessions[0].is_current);
+    }
+
+    #[test]
+    fn test_read_target_single_line() {
+        let target = read_target(Cursor::new("/mock/project\n")).unwrap();
+        assert_eq!(target, "/mock/project");
+
+        // Surrounding whitespace and blank lines are ignored
+        let target = read_target(Cursor::new("\n  project  \n\n")).unwrap();
+        assert_eq!(target, "project");
+    }
+
+    #[test]
+    fn test_read_target_empty() {
+        for input in ["", "\n", "   \n\n"] {
+            let result = read_target(Cursor::new(input));
+            assert!(matches!(result, Err(ConnectError::InvalidInput(_))));
+        }
+    }
+
+    #[test]
+    fn test_read_target_multiple_lines() {
+        let result = read_target(Cursor::new("one\ntwo\n"));
+        assert!(matches!(result, Err(ConnectError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_connect_with_read_target() {
+        let service = create_service(
+            None,
+            None,
+            Some(vec![(PathBuf::from("/mock/piped"), "piped".to_string())]),
+        );
+
+        let target = read_target(Cursor::new("/mock/piped\n")).unwrap();
+        let result = service.connect(&target, &ZellijOptions::default());
+        assert!(result.is_ok());
+
+        let sessions = service.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "piped");
+    }
+
+    #[test]
+    fn test_connect_relative_path_uses_session_root() {
+        let mut sessions = HashMap::new();
+        sessions.insert("project".to_string(), true);
+
+        let service = create_service(
+            Some(sessions),
+            None,
+            Some(vec![
+                (PathBuf::from("/mock/src/project"), "project".to_string()),
+                (PathBuf::from("/mock/src/sibling"), "sibling".to_string()),
+            ]),
+        );
+        service
+            .store
+            .record("project", Path::new("/mock/src/project"))
+            .unwrap();
+        // The shell is somewhere else entirely
+        service
+            .fs
+            .set_current_dir(Path::new("/mock/elsewhere"))
+            .unwrap();
+
+        let result = service.connect_to_directory("../sibling", &ZellijOptions::default());
+        assert!(result.is_ok());
+
+        let sessions = service.list_sessions().unwrap();
+        assert!(sessions.iter().any(|s| s.name == "sibling" && s.is_current));
+        assert_eq!(
+            service.store.lookup("sibling"),
+            Some(PathBuf::from("/mock/src/sibling"))
+        );
+    }
+
+    /// A service inside session `host`, rooted at /mock/src/host, whose
+    /// focused pane runs in /mock/work
+    fn pane_cwd_service(
+        pane_cwd: bool,
+    ) -> ConnectService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
+        let zellij = MockZellijClient::with_tabs(vec![Tab {
+            position: 0,
+            name: None,
+            is_active: true,
+            panes: vec![Pane {
+                id: 0,
+                name: None,
+                is_focused: true,
+                is_plugin: false,
+                cwd: Some(PathBuf::from("/mock/work")),
+            }],
+        }]);
+        zellij
+            .new_session("host", &ZellijOptions::default())
+            .unwrap();
+
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/src/host"), "host");
+        fs.with_directory(Path::new("/mock/work"), "work");
+        fs.with_directory(Path::new("/mock/work/api"), "api");
+
+        let service = ConnectService::new(
+            zellij,
+            MockZoxideClient::new(),
+            fs,
+            TestGit::new(false, "./"),
+        )
+        .with_options(ConnectOptions {
+            pane_cwd,
+            ..ConnectOptions::default()
+        });
+        service
+            .store
+            .record("host", Path::new("/mock/src/host"))
+            .unwrap();
+        service
+    }
+
+    #[test]
+    fn test_connect_pane_cwd_uses_focused_pane_directory() {
+        let service = pane_cwd_service(true);
+
+        service
+            .connect_to_directory("api", &ZellijOptions::default())
+            .unwrap();
+
+        assert_eq!(
+            service.store.lookup("api"),
+            Some(PathBuf::from("/mock/work/api"))
+        );
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("api".to_string())
+        );
+    }
+
+    #[test]
+    fn test_connect_dot_without_pane_cwd_uses_session_root() {
+        let service = pane_cwd_service(false);
+
+        let result = service.connect_to_directory(".", &ZellijOptions::default());
+
+        // `.` is the root of the session we're already in
+        assert!(matches!(result, Err(ConnectError::AlreadyAttached(name)) if name == "host"));
+    }
+
+    #[test]
+    fn test_connect_dot_with_pane_cwd() {
+        let service = pane_cwd_service(true);
+
+        service
+            .connect_to_directory(".", &ZellijOptions::default())
+            .unwrap();
+
+        assert_eq!(
+            service.store.lookup("work"),
+            Some(PathBuf::from("/mock/work"))
+        );
+    }
+
+    #[test]
+    fn test_connect_relative_path_without_session_root_uses_cwd() {
+        let mut sessions = HashMap::new();
+        sessions.insert("unknown".to_string(), true);
+
+        let service = create_service(
+            Some(sessions),
+            None,
+            Some(vec![(PathBuf::from("/mock/cwd/sub"), "sub".to_string())]),
+        );
+        service.fs.set_current_dir(Path::new("/mock/cwd")).unwrap();
+
+        let result = service.connect_to_directory("./sub", &ZellijOptions::default());
+        assert!(result.is_ok());
+
+        let sessions = service.list_sessions().unwrap();
+        assert!(sessions.iter().any(|s| s.name == "sub" && s.is_current));
+    }
+
+    #[test]
+    fn test_connect_records_session_root() {
+        let service = create_service(
+            None,
+            None,
+            Some(vec![(
+                PathBuf::from("/mock/project"),
+                "project".to_string(),
+            )]),
+        );
+
+        service
+            .connect_to_directory("/mock/project", &ZellijOptions::default())
+            .unwrap();
+
+        assert_eq!(
+            service.store.lookup("project"),
+            Some(PathBuf::from("/mock/project"))
+        );
+    }
+
+    /// Zellij client whose first attaches fail with the given error
+    struct FlakyZellijClient {
+        inner: MockZellijClient,
+        failures: std::cell::Cell<u32>,
+        error: String,
+        attach_calls: std::cell::Cell<u32>,
+        switch_calls: std::cell::Cell<u32>,
+    }
+
+    impl FlakyZellijClient {
+        fn new(sessions: HashMap<String, bool>, failures: u32, error: &str) -> Self {
+            Self {
+                inner: MockZellijClient::with_sessions(sessions),
+                failures: std::cell::Cell::new(failures),
+                error: error.to_string(),
+                attach_calls: std::cell::Cell::new(0),
+                switch_calls: std::cell::Cell::new(0),
+            }
+        }
+
+        /// Act as if zesh runs inside a zellij session
+        fn attached(mut self) -> Self {
+            self.inner = self.inner.with_attached(true);
+            self
+        }
+    }
+
+    impl ZellijOperations for FlakyZellijClient {
+        fn list_sessions(&self) -> zellij_rs::ZellijResult<Vec<Session>> {
+            self.inner.list_sessions()
+        }
+
+        fn is_attached(&self) -> bool {
+            self.inner.is_attached()
+        }
+
+        fn attach_session(&self, name: &str) -> zellij_rs::ZellijResult<()> {
+            self.attach_calls.set(self.attach_calls.get() + 1);
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err(ZellijError::CommandExecution(self.error.clone()));
+            }
+            self.inner.attach_session(name)
+        }
+
+        fn attach_session_with(
+            &self,
+            name: &str,
+            create: bool,
+            options: &ZellijOptions,
+        ) -> zellij_rs::ZellijResult<()> {
+            self.attach_calls.set(self.attach_calls.get() + 1);
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err(ZellijError::CommandExecution(self.error.clone()));
+            }
+            self.inner.attach_session_with(name, create, options)
+        }
+
+        fn switch_session(&self, name: &str) -> zellij_rs::ZellijResult<()> {
+            self.switch_calls.set(self.switch_calls.get() + 1);
+            self.inner.switch_session(name)
+        }
+
+        fn new_session(&self, name: &str, options: &ZellijOptions) -> zellij_rs::ZellijResult<()> {
+            self.inner.new_session(name, options)
+        }
+
+        fn new_session_detached(
+            &self,
+            name: &str,
+            options: &ZellijOptions,
+        ) -> zellij_rs::ZellijResult<()> {
+            self.inner.new_session_detached(name, options)
+        }
+
+        fn kill_session(&self, name: &str) -> zellij_rs::ZellijResult<()> {
+            self.inner.kill_session(name)
+        }
+
+        fn rename_session(&self, name: &str) -> zellij_rs::ZellijResult<()> {
+            self.inner.rename_session(name)
+        }
+
+        fn detach(&self) -> zellij_rs::ZellijResult<()> {
+            self.inner.detach()
+        }
+
+        fn list_tabs(&self) -> zellij_rs::ZellijResult<Vec<zellij_rs::Tab>> {
+            self.inner.list_tabs()
+        }
+
+        fn pane_cwds(&self) -> zellij_rs::ZellijResult<Vec<(u32, PathBuf)>> {
+            self.inner.pane_cwds()
+        }
+
+        fn new_tab(&self, name: Option<&str>, cwd: Option<&Path>) -> zellij_rs::ZellijResult<()> {
+            self.inner.new_tab(name, cwd)
+        }
+
+        fn new_tab_in(
+            &self,
+            session_name: &str,
+            name: Option<&str>,
+            cwd: Option<&Path>,
+        ) -> zellij_rs::ZellijResult<()> {
+            self.inner.new_tab_in(session_name, name, cwd)
+        }
+
+        fn rename_tab(&self, name: &str) -> zellij_rs::ZellijResult<()> {
+            self.inner.rename_tab(name)
+        }
+
+        fn go_to_tab(&self, session_name: &str, tab: &str) -> zellij_rs::ZellijResult<()> {
+            self.inner.go_to_tab(session_name, tab)
+        }
+
+        fn close_tab(&self) -> zellij_rs::ZellijResult<()> {
+            self.inner.close_tab()
+        }
+
+        fn move_pane_to_tab(&self, tab_position: u32) -> zellij_rs::ZellijResult<()> {
+            self.inner.move_pane_to_tab(tab_position)
+        }
+
+        fn toggle_floating_panes(&self) -> zellij_rs::ZellijResult<()> {
+            self.inner.toggle_floating_panes()
+        }
+
+        fn toggle_pane_embed_or_floating(&self) -> zellij_rs::ZellijResult<()> {
+            self.inner.toggle_pane_embed_or_floating()
+        }
+
+        fn run_command(&self, command: &str, args: &[&str]) -> zellij_rs::ZellijResult<()> {
+            self.inner.run_command(command, args)
+        }
+
+        fn run_command_in(
+            &self,
+            session_name: &str,
+            command: &str,
+            args: &[&str],
+        ) -> zellij_rs::ZellijResult<()> {
+            self.inner.run_command_in(session_name, command, args)
+        }
+
+        fn version(&self) -> zellij_rs::ZellijResult<String> {
+            self.inner.version()
+        }
+    }
+
+    fn no_backoff() -> Config {
+        Config {
+            attach_backoff_ms: 0,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_attach_retries_transient_failure() {
+        let zellij = FlakyZellijClient::new(
+            HashMap::from([("starting".to_string(), false)]),
+            1,
+            "Connection refused (os error 111)",
+        );
+        let service = ConnectService::new(
+            zellij,
+            MockZoxideClient::new(),
+            MockFs::new(),
+            TestGit::new(false, "./"),
+        )
+        .with_config(no_backoff());
+
+        let result = service.connect_to_session("starting");
+        assert!(result.is_ok());
+        assert_eq!(service.zellij.attach_calls.get(), 2);
+
+        let sessions = service.list_sessions().unwrap();
+        assert!(sessions[0].is_current);
+    }
+
+    #[test]
+    fn test_connect_creates_session_through_attach() {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/project"), "project");
+        let service = ConnectService::new(
+            FlakyZellijClient::new(HashMap::new(), 0, ""),
+            MockZoxideClient::new(),
+            fs,
+            TestGit::new(false, "./"),
+        );
+
+        service
+            .connect_to_directory("/mock/project", &ZellijOptions::default())
+            .unwrap();
+
+        // A single attach --create call made the session
+        assert_eq!(service.zellij.attach_calls.get(), 1);
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("project".to_string())
+        );
+    }
+
+    #[test]
+    fn test_connect_to_current_session_short_circuits() {
+        let zellij = FlakyZellijClient::new(
+            HashMap::from([("main".to_string(), true), ("other".to_string(), false)]),
+            0,
+            "",
+        );
+        let service = ConnectService::new(
+            zellij,
+            MockZoxideClient::new(),
+            MockFs::new(),
+            TestGit::new(false, "./"),
+        );
+
+        let result = service.connect_to_session("main");
+        assert!(matches!(result, Err(ConnectError::AlreadyAttached(name)) if name == "main"));
+
+        let result = service.connect("main", &ZellijOptions::default());
+        assert!(matches!(result, Err(ConnectError::AlreadyAttached(_))));
+        assert_eq!(service.zellij.attach_calls.get(), 0);
+        assert!(service.history.recent().is_empty());
+
+        // Other sessions still attach normally
+        service.connect_to_session("other").unwrap();
+        assert_eq!(service.zellij.attach_calls.get(), 1);
+    }
+
+    fn attach_service(
+        zellij: FlakyZellijClient,
+    ) -> ConnectService<FlakyZellijClient, MockZoxideClient, MockFs, TestGit> {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/project"), "project");
+        ConnectService::new(
+            zellij,
+            MockZoxideClient::new(),
+            fs,
+            TestGit::new(false, "./"),
+        )
+    }
+
+    #[test]
+    fn test_connect_detached_attaches() {
+        let service = attach_service(FlakyZellijClient::new(
+            HashMap::from([("work".to_string(), false)]),
+            0,
+            "",
+        ));
+
+        service.connect_to_session("work").unwrap();
+
+        assert_eq!(service.zellij.attach_calls.get(), 1);
+        assert_eq!(service.zellij.switch_calls.get(), 0);
+    }
+
+    #[test]
+    fn test_connect_attached_switches() {
+        let service = attach_service(
+            FlakyZellijClient::new(
+                HashMap::from([("main".to_string(), true), ("work".to_string(), false)]),
+                0,
+                "",
+            )
+            .attached(),
+        );
+
+        service.connect_to_session("work").unwrap();
 
-        // Verify new session created
-        let sessions_after_dir = service.list_sessions().unwrap();
-        assert_eq!(sessions_after_dir.len(), 2);
-        assert!(
-            sessions_after_dir
-                .iter()
-                .any(|s| s.name == "project3" && s.is_current)
+        assert_eq!(service.zellij.attach_calls.get(), 0);
+        assert_eq!(service.zellij.switch_calls.get(), 1);
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("work".to_string())
         );
-        assert!(
-            sessions_after_dir
-                .iter()
-                .any(|s| s.name == "existing" && !s.is_current)
+    }
+
+    #[test]
+    fn test_connect_attached_creates_then_switches() {
+        let service = attach_service(
+            FlakyZellijClient::new(HashMap::from([("main".to_string(), true)]), 0, "").attached(),
         );
 
-        // 3. Connect via zoxide
-        let result = service.connect_via_zoxide("project1", &ZellijOptions::default());
-        assert!(result.is_ok());
+        service
+            .connect_to_directory("/mock/project", &ZellijOptions::default())
+            .unwrap();
 
-        // Verify another session created
-        let sessions_after_zoxide = service.list_sessions().unwrap();
-        assert_eq!(sessions_after_zoxide.len(), 3);
-        assert!(
-            sessions_after_zoxide
-                .iter()
-                .any(|s| s.name == "project1" && s.is_current)
+        assert_eq!(service.zellij.attach_calls.get(), 0);
+        assert_eq!(service.zellij.switch_calls.get(), 1);
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("project".to_string())
         );
+    }
 
-        // 4. Connect back to first session
-        let result = service.connect_to_session("existing");
-        assert!(result.is_ok());
+    #[test]
+    fn test_connect_prints_created_session_root() {
+        let service = attach_service(FlakyZellijClient::new(HashMap::new(), 0, ""))
+            .with_printer(Printer::capturing(false));
 
-        // Verify attached to existing session
-        let final_sessions = service.list_sessions().unwrap();
-        assert_eq!(final_sessions.len(), 3);
-        assert!(
-            final_sessions
-                .iter()
-                .any(|s| s.name == "existing" && s.is_current)
+        service
+            .connect("/mock/project", &ZellijOptions::default())
+            .unwrap();
+
+        assert_eq!(
+            service.printer.captured(),
+            vec!["Connected to project (/mock/project)"]
         );
-        assert!(
-            final_sessions
-                .iter()
-                .any(|s| s.name == "project1" && !s.is_current)
+    }
+
+    #[test]
+    fn test_connect_reports_before_attaching() {
+        // The attach standing in for the user's whole stay in the session
+        let service = attach_service(FlakyZellijClient::new(HashMap::new(), 1, "detached"))
+            .with_printer(Printer::capturing(false));
+
+        let result = service.connect("/mock/project", &ZellijOptions::default());
+
+        assert!(result.is_err());
+        assert_eq!(
+            service.printer.captured(),
+            vec!["Connected to project (/mock/project)"]
         );
-        assert!(
-            final_sessions
-                .iter()
-                .any(|s| s.name == "project3" && !s.is_current)
+        let recent = service.history.recent();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].name, "project");
+    }
+
+    #[test]
+    fn test_connect_prints_attached_session_root() {
+        let store = SessionStore::new();
+        store.record("work", Path::new("/src/work")).unwrap();
+        let service = attach_service(FlakyZellijClient::new(
+            HashMap::from([("work".to_string(), false), ("other".to_string(), false)]),
+            0,
+            "",
+        ))
+        .with_store(store)
+        .with_printer(Printer::capturing(false));
+
+        service.connect_to_session("work").unwrap();
+        // zesh doesn't know where this one lives
+        service.connect_to_session("other").unwrap();
+
+        assert_eq!(
+            service.printer.captured(),
+            vec!["Connected to work (/src/work)", "Connected to other"]
         );
     }
-    // Custom Git mock for testing
-    struct TestGit {
-        is_git_repo: bool,
-        git_root: String,
+
+    #[test]
+    fn test_connect_quiet_prints_nothing() {
+        let service = attach_service(FlakyZellijClient::new(HashMap::new(), 0, ""))
+            .with_printer(Printer::capturing(true));
+
+        service
+            .connect("/mock/project", &ZellijOptions::default())
+            .unwrap();
+
+        assert!(service.printer.captured().is_empty());
     }
 
-    impl TestGit {
-        fn new(is_git_repo: bool, git_root: &str) -> Self {
-            Self {
-                is_git_repo,
-                git_root: git_root.to_string(),
-            }
-        }
+    #[test]
+    fn test_split_session_tab() {
+        assert_eq!(split_session_tab("api:2"), Some(("api", "2")));
+        assert_eq!(split_session_tab("api:logs"), Some(("api", "logs")));
+        assert_eq!(split_session_tab("a:b:c"), Some(("a:b", "c")));
+        assert_eq!(split_session_tab("api"), None);
+        assert_eq!(split_session_tab("api:"), None);
+        assert_eq!(split_session_tab(":2"), None);
     }
 
-    impl Git for TestGit {
-        fn show_top_level(&self, _name: &str) -> Result<(bool, String), GitError> {
-            Ok((self.is_git_repo, self.git_root.clone()))
-        }
+    fn tabbed_service() -> ConnectService<FlakyZellijClient, MockZoxideClient, MockFs, TestGit> {
+        let zellij = FlakyZellijClient::new(HashMap::from([("api".to_string(), false)]), 0, "");
+        zellij.new_tab(Some("editor"), None).unwrap();
+        zellij.new_tab(Some("logs"), None).unwrap();
+        ConnectService::new(
+            zellij,
+            MockZoxideClient::new(),
+            MockFs::new(),
+            TestGit::new(false, "./"),
+        )
+    }
 
-        fn git_common_dir(&self, _name: &str) -> Result<(bool, String), GitError> {
-            Ok((self.is_git_repo, "/mock/repo/common-dir".to_string()))
-        }
+    #[test]
+    fn test_connect_to_session_tab() {
+        let service = tabbed_service();
 
-        fn clone(&self, _url: &str, _cmd_dir: &str, _dir: &str) -> Result<String, GitError> {
-            Ok("Mock clone successful".to_string())
-        }
+        service
+            .connect_keywords(&["api:1"], &ZellijOptions::default())
+            .unwrap();
+
+        let tabs = service.zellij.list_tabs().unwrap();
+        assert!(tabs[0].is_active);
+        assert!(!tabs[1].is_active);
+        assert_eq!(service.zellij.attach_calls.get(), 1);
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("api".to_string())
+        );
     }
 
-    // Helper function to create a ConnectService with the TestGit
-    fn create_service_with_git(
-        zellij_sessions: Option<HashMap<String, bool>>,
-        zoxide_paths: Option<HashMap<PathBuf, f64>>,
-        fs_dirs: Option<Vec<(PathBuf, String)>>,
-        is_git_repo: bool,
-        git_root: &str,
-    ) -> ConnectService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
-        let zellij = if let Some(sessions) = zellij_sessions {
-            MockZellijClient::with_sessions(sessions)
-        } else {
-            MockZellijClient::new()
-        };
+    #[test]
+    fn test_connect_to_missing_tab_still_attaches() {
+        let service = tabbed_service();
 
-        let zoxide = if let Some(paths) = zoxide_paths {
-            MockZoxideClient::with_paths(paths)
-        } else {
-            MockZoxideClient::new()
-        };
+        service
+            .connect_keywords(&["api:missing"], &ZellijOptions::default())
+            .unwrap();
+
+        // The last tab created stays focused
+        assert!(service.zellij.list_tabs().unwrap()[1].is_active);
+        assert_eq!(service.zellij.attach_calls.get(), 1);
+    }
+
+    #[test]
+    fn test_missing_tab_warning_respects_quiet() {
+        let service = tabbed_service().with_printer(Printer::capturing(false));
+        service
+            .connect_keywords(&["api:missing"], &ZellijOptions::default())
+            .unwrap();
+        let warnings: Vec<String> = service
+            .printer
+            .captured()
+            .into_iter()
+            .filter(|m| m.starts_with("Warning:"))
+            .collect();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("Warning: could not focus tab 'missing'"));
+
+        let service = tabbed_service().with_printer(Printer::capturing(true));
+        service
+            .connect_keywords(&["api:missing"], &ZellijOptions::default())
+            .unwrap();
+        assert!(service.printer.captured().is_empty());
+    }
 
+    fn keep_current_service(
+        sessions: HashMap<String, bool>,
+    ) -> ConnectService<FlakyZellijClient, MockZoxideClient, MockFs, TestGit> {
         let fs = MockFs::new();
-        if let Some(dirs) = fs_dirs {
-            for (path, name) in dirs {
-                fs.with_directory(&path, &name);
-            }
-        }
+        fs.with_directory(Path::new("/mock/project"), "project");
+        ConnectService::new(
+            FlakyZellijClient::new(sessions, 0, ""),
+            MockZoxideClient::new(),
+            fs,
+            TestGit::new(false, "./"),
+        )
+        .with_options(ConnectOptions {
+            keep_current: true,
+            ..ConnectOptions::default()
+        })
+    }
 
-        let git = TestGit::new(is_git_repo, git_root);
+    #[test]
+    fn test_connect_keep_current_creates_detached_session() {
+        let service = keep_current_service(HashMap::from([("work".to_string(), true)]));
 
-        ConnectService::new(zellij, zoxide, fs, git)
+        service
+            .connect("/mock/project", &ZellijOptions::default())
+            .unwrap();
+
+        let sessions = service.list_sessions().unwrap();
+        let project = sessions.iter().find(|s| s.name == "project").unwrap();
+        assert!(!project.is_current);
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("work".to_string())
+        );
+        assert_eq!(service.zellij.attach_calls.get(), 0);
+        assert_eq!(service.zellij.switch_calls.get(), 0);
+        assert!(
+            service
+                .zoxide
+                .query(&["project"])
+                .unwrap()
+                .iter()
+                .any(|e| e.path == Path::new("/mock/project"))
+        );
     }
 
     #[test]
-    fn test_get_session_name_for_git_repo() {
-        // Set up mock file system with git repo structure
-        let git_root = PathBuf::from("/mock/foo");
-        let subdir = PathBuf::from("/mock/foo/bar");
+    fn test_connect_keep_current_existing_session_is_noop() {
+        let service = keep_current_service(HashMap::from([
+            ("work".to_string(), true),
+            ("project".to_string(), false),
+        ]));
+
+        service
+            .connect("project", &ZellijOptions::default())
+            .unwrap();
+        service
+            .connect("/mock/project", &ZellijOptions::default())
+            .unwrap();
 
-        let fs_dirs = vec![
-            (git_root.clone(), "foo".to_string()),
-            (subdir.clone(), "bar".to_string()),
-        ];
+        assert_eq!(service.list_sessions().unwrap().len(), 2);
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("work".to_string())
+        );
+        assert_eq!(service.zellij.attach_calls.get(), 0);
+        assert_eq!(service.zellij.switch_calls.get(), 0);
+    }
 
-        // Create service with git repo configuration
-        let service = create_service_with_git(None, None, Some(fs_dirs), true, "/mock/foo");
+    #[test]
+    fn test_connect_records_history() {
+        let service = create_service(
+            Some(HashMap::from([("work".to_string(), false)])),
+            None,
+            Some(vec![(
+                PathBuf::from("/mock/project"),
+                "project".to_string(),
+            )]),
+        );
 
-        // Test getting session name for git root
-        let name = service.get_session_name_for_path(&git_root).unwrap();
-        assert_eq!(name, "foo");
+        service
+            .connect("/mock/project", &ZellijOptions::default())
+            .unwrap();
+        service.connect("work", &ZellijOptions::default()).unwrap();
+
+        let recent = service.history.recent();
+        let names: Vec<_> = recent.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["work", "project"]);
+        assert_eq!(recent[1].path, Some(PathBuf::from("/mock/project")));
+        // zesh didn't create work, so its root is unknown
+        assert_eq!(recent[0].path, None);
+    }
 
-        // Test getting session name for subdirectory
-        let name = service.get_session_name_for_path(&subdir).unwrap();
-        assert_eq!(name, "foo_bar");
+    #[cfg(unix)]
+    #[test]
+    fn test_connect_reports_history_failures() {
+        // A history under a file can't be written
+        let history = History::open(Path::new("/dev/null/history.json")).unwrap();
+        let service = create_service(
+            None,
+            None,
+            Some(vec![(
+                PathBuf::from("/mock/project"),
+                "project".to_string(),
+            )]),
+        )
+        .with_history(history)
+        .with_printer(Printer::capturing(false));
+
+        service
+            .connect("/mock/project", &ZellijOptions::default())
+            .unwrap();
+
+        let captured = service.printer.captured();
+        assert_eq!(captured[0], "Connected to project (/mock/project)");
+        assert!(captured[1].starts_with("Warning: failed to record connection history: "));
     }
 
     #[test]
-    fn test_get_session_name_for_non_git_path() {
-        // Set up mock file system with directory not in git repo
-        let path = PathBuf::from("/mock/not-git");
+    fn test_connect_keep_current_skips_history() {
+        let service = keep_current_service(HashMap::from([("work".to_string(), true)]));
 
-        let fs_dirs = vec![(path.clone(), "not-git".to_string())];
+        service
+            .connect("/mock/project", &ZellijOptions::default())
+            .unwrap();
 
-        // Create service with non-git configuration
-        let service = create_service_with_git(None, None, Some(fs_dirs), false, "");
+        assert!(service.history.recent().is_empty());
+    }
 
-        // Test getting session name for non-git path
-        let name = service.get_session_name_for_path(&path).unwrap();
-        assert_eq!(name, "not-git");
+    fn history_service(
+        sessions: HashMap<String, bool>,
+    ) -> ConnectService<MockZellijClient, MockZoxideClient, MockFs, TestGit> {
+        let history = History::new();
+        history
+            .record("project", Some(Path::new("/mock/project")))
+            .unwrap();
+        history.record("work", None).unwrap();
+
+        create_service(
+            Some(sessions),
+            None,
+            Some(vec![(
+                PathBuf::from("/mock/project"),
+                "project".to_string(),
+            )]),
+        )
+        .with_history(history)
     }
 
     #[test]
-    fn test_connect_to_git_directory() {
-        // Set up mock file system with git repo structure
-        let git_root = PathBuf::from("/mock/project");
-        let subdir = PathBuf::from("/mock/project/feature");
+    fn test_connect_from_history_attaches_existing_session() {
+        let service = history_service(HashMap::from([
+            ("work".to_string(), false),
+            ("project".to_string(), false),
+        ]));
+
+        service
+            .connect_from_history(2, &ZellijOptions::default())
+            .unwrap();
 
-        let fs_dirs = vec![
-            (git_root.clone(), "project".to_string()),
-            (subdir.clone(), "feature".to_string()),
-        ];
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("project".to_string())
+        );
+        assert_eq!(service.zellij.list_sessions().unwrap().len(), 2);
+    }
 
-        // Create service with git repo configuration
-        let service = create_service_with_git(None, None, Some(fs_dirs), true, "/mock/project");
+    #[test]
+    fn test_connect_from_history_recreates_session_in_its_directory() {
+        let service = history_service(HashMap::from([("work".to_string(), true)]));
 
-        // Connect to subdirectory
-        let result =
-            service.connect_to_directory("/mock/project/feature", &ZellijOptions::default());
-        assert!(result.is_ok());
+        service
+            .connect_from_history(2, &ZellijOptions::default())
+            .unwrap();
 
-        // Verify the session name is git-aware
-        let sessions = service.list_sessions().unwrap();
-        assert_eq!(sessions.len(), 1);
-        assert_eq!(sessions[0].name, "project_feature");
-        assert!(sessions[0].is_current);
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("project".to_string())
+        );
+        assert_eq!(
+            service.fs.current_dir().unwrap(),
+            PathBuf::from("/mock/project")
+        );
+    }
+
+    #[test]
+    fn test_connect_from_history_out_of_range() {
+        let service = history_service(HashMap::new());
+
+        let result = service.connect_from_history(3, &ZellijOptions::default());
+        assert!(matches!(result, Err(ConnectError::NoMatch(_))));
+
+        // A session without a known root can't be recreated
+        let result = service.connect_from_history(1, &ZellijOptions::default());
+        assert!(matches!(result, Err(ConnectError::NoMatch(name)) if name == "work"));
+    }
+
+    #[test]
+    fn test_connect_to_directory_of_current_session_short_circuits() {
+        let service = create_service(
+            Some(HashMap::from([("project".to_string(), true)])),
+            None,
+            Some(vec![(
+                PathBuf::from("/mock/project"),
+                "project".to_string(),
+            )]),
+        );
+
+        let result = service.connect_to_directory("/mock/project", &ZellijOptions::default());
+        assert!(matches!(result, Err(ConnectError::AlreadyAttached(name)) if name == "project"));
+
+        let result = service.connect("/mock/project", &ZellijOptions::default());
+        assert!(matches!(result, Err(ConnectError::AlreadyAttached(_))));
+    }
+
+    #[test]
+    fn test_attach_retry_is_bounded() {
+        let zellij = FlakyZellijClient::new(
+            HashMap::from([("starting".to_string(), false)]),
+            10,
+            "Connection refused (os error 111)",
+        );
+        let service = ConnectService::new(
+            zellij,
+            MockZoxideClient::new(),
+            MockFs::new(),
+            TestGit::new(false, "./"),
+        )
+        .with_config(no_backoff());
+
+        let result = service.connect_to_session("starting");
+        assert!(matches!(result, Err(ConnectError::Zellij(_))));
+        assert_eq!(service.zellij.attach_calls.get(), 3);
+    }
+
+    #[test]
+    fn test_attach_does_not_retry_hard_failure() {
+        let zellij = FlakyZellijClient::new(
+            HashMap::from([("broken".to_string(), false)]),
+            1,
+            "Session 'broken' is corrupted",
+        );
+        let service = ConnectService::new(
+            zellij,
+            MockZoxideClient::new(),
+            MockFs::new(),
+            TestGit::new(false, "./"),
+        )
+        .with_config(no_backoff());
+
+        let result = service.connect_to_session("broken");
+        assert!(matches!(result, Err(ConnectError::Zellij(_))));
+        assert_eq!(service.zellij.attach_calls.get(), 1);
     }
 }
@@ -0,0 +1,142 @@
+use thiserror::Error;
+use zellij_rs::{Session, ZellijError, ZellijOperations};
+
+#[derive(Debug, Error)]
+pub enum CycleError {
+    #[error("Zellij error: {0}")]
+    Zellij(#[from] ZellijError),
+
+    #[error("Not inside a zellij session")]
+    NoSession,
+}
+
+/// Which way to move through the sessions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Next,
+    Previous,
+}
+
+/// The session after (or before) `current` in alphabetical order, wrapping
+/// around at either end. Exited sessions are skipped. Returns `None` when
+/// there's no other session to go to.
+pub fn adjacent_session(
+    sessions: &[Session],
+    current: &str,
+    direction: Direction,
+) -> Option<String> {
+    let mut names: Vec<&str> = sessions
+        .iter()
+        .filter(|s| !s.is_exited || s.name == current)
+        .map(|s| s.name.as_str())
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+
+    let index = names.iter().position(|&name| name == current)?;
+    if names.len() < 2 {
+        return None;
+    }
+
+    let target = match direction {
+        Direction::Next => (index + 1) % names.len(),
+        Direction::Previous => (index + names.len() - 1) % names.len(),
+    };
+    Some(names[target].to_string())
+}
+
+/// Switch the session we're running inside of to the next or previous one.
+/// Returns the session switched to, or `None` if it's the only session.
+pub fn switch_adjacent<Z: ZellijOperations>(
+    zellij: &Z,
+    direction: Direction,
+) -> Result<Option<String>, CycleError> {
+    let current = zellij.current_session()?.ok_or(CycleError::NoSession)?;
+    let sessions = zellij.list_sessions()?;
+
+    let Some(target) = adjacent_session(&sessions, &current, direction) else {
+        return Ok(None);
+    };
+    zellij.switch_session(&target)?;
+
+    Ok(Some(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use zellij_rs::MockZellijClient;
+
+    fn sessions(current: &str) -> MockZellijClient {
+        MockZellijClient::with_sessions(HashMap::from([
+            ("beta".to_string(), current == "beta"),
+            ("alpha".to_string(), current == "alpha"),
+            ("gamma".to_string(), current == "gamma"),
+        ]))
+    }
+
+    #[test]
+    fn test_next_wraps_to_first() {
+        let zellij = sessions("gamma");
+
+        let target = switch_adjacent(&zellij, Direction::Next).unwrap();
+
+        assert_eq!(target.as_deref(), Some("alpha"));
+        assert_eq!(zellij.current_session().unwrap(), Some("alpha".to_string()));
+    }
+
+    #[test]
+    fn test_previous_wraps_to_last() {
+        let zellij = sessions("alpha");
+
+        let target = switch_adjacent(&zellij, Direction::Previous).unwrap();
+
+        assert_eq!(target.as_deref(), Some("gamma"));
+        assert_eq!(zellij.current_session().unwrap(), Some("gamma".to_string()));
+    }
+
+    #[test]
+    fn test_next_and_previous_in_order() {
+        let zellij = sessions("beta");
+        let sessions = zellij.list_sessions().unwrap();
+
+        assert_eq!(
+            adjacent_session(&sessions, "beta", Direction::Next).as_deref(),
+            Some("gamma")
+        );
+        assert_eq!(
+            adjacent_session(&sessions, "beta", Direction::Previous).as_deref(),
+            Some("alpha")
+        );
+    }
+
+    #[test]
+    fn test_skips_exited_sessions() {
+        let zellij = sessions("alpha").with_exited(&["beta"]);
+        let sessions = zellij.list_sessions().unwrap();
+
+        assert_eq!(
+            adjacent_session(&sessions, "alpha", Direction::Next).as_deref(),
+            Some("gamma")
+        );
+    }
+
+    #[test]
+    fn test_single_session_is_noop() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([("only".to_string(), true)]));
+
+        let target = switch_adjacent(&zellij, Direction::Next).unwrap();
+
+        assert_eq!(target, None);
+        assert_eq!(zellij.current_session().unwrap(), Some("only".to_string()));
+    }
+
+    #[test]
+    fn test_outside_session() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([("work".to_string(), false)]));
+
+        let result = switch_adjacent(&zellij, Direction::Next);
+        assert!(matches!(result, Err(CycleError::NoSession)));
+    }
+}
@@ -0,0 +1,125 @@
+use thiserror::Error;
+use zellij_rs::{ZellijError, ZellijOperations};
+
+use crate::store::{SessionStore, StoreError};
+
+#[derive(Debug, Error)]
+pub enum DetachError {
+    #[error("Zellij error: {0}")]
+    Zellij(#[from] ZellijError),
+
+    #[error("Session store error: {0}")]
+    Store(#[from] StoreError),
+
+    #[error("Not inside a zellij session")]
+    NotInSession,
+}
+
+/// What detaching did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Detached {
+    /// The session we detached from
+    pub session: String,
+    /// Whether it was killed after detaching
+    pub killed: bool,
+}
+
+/// Detach from the current session. With `kill`, sessions created with
+/// `--kill-on-detach` are killed afterwards, anything else is only detached.
+pub fn detach_current_session<Z: ZellijOperations>(
+    zellij: &Z,
+    store: &SessionStore,
+    kill: bool,
+) -> Result<Detached, DetachError> {
+    let session = zellij.current_session()?.ok_or(DetachError::NotInSession)?;
+    let killed = kill && store.kills_on_detach(&session);
+
+    zellij.detach()?;
+    if killed {
+        // We run inside the session, so forget it before killing it takes
+        // us down with it
+        store.remove(&session)?;
+        zellij.kill_session(&session)?;
+    }
+
+    Ok(Detached { session, killed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::path::Path;
+    use zellij_rs::MockZellijClient;
+
+    fn session_names(zellij: &MockZellijClient) -> Vec<String> {
+        let mut names: Vec<String> = zellij
+            .list_sessions()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_detach_kill_kills_marked_session() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([
+            ("scratch".to_string(), true),
+            ("other".to_string(), false),
+        ]));
+        let store = SessionStore::new();
+        store.record("scratch", Path::new("/tmp/scratch")).unwrap();
+        store.mark_kill_on_detach("scratch").unwrap();
+
+        let detached = detach_current_session(&zellij, &store, true).unwrap();
+
+        assert_eq!(
+            detached,
+            Detached {
+                session: "scratch".to_string(),
+                killed: true
+            }
+        );
+        assert_eq!(session_names(&zellij), vec!["other"]);
+        assert_eq!(store.lookup("scratch"), None);
+    }
+
+    #[test]
+    fn test_detach_kill_only_detaches_unmarked_session() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([("api".to_string(), true)]));
+        let store = SessionStore::new();
+        store.record("api", Path::new("/src/api")).unwrap();
+
+        let detached = detach_current_session(&zellij, &store, true).unwrap();
+
+        assert!(!detached.killed);
+        assert_eq!(session_names(&zellij), vec!["api"]);
+        assert_eq!(zellij.current_session().unwrap(), None);
+        assert!(store.lookup("api").is_some());
+    }
+
+    #[test]
+    fn test_detach_without_kill_keeps_marked_session() {
+        let zellij =
+            MockZellijClient::with_sessions(HashMap::from([("scratch".to_string(), true)]));
+        let store = SessionStore::new();
+        store.mark_kill_on_detach("scratch").unwrap();
+
+        let detached = detach_current_session(&zellij, &store, false).unwrap();
+
+        assert!(!detached.killed);
+        assert_eq!(session_names(&zellij), vec!["scratch"]);
+        assert!(store.kills_on_detach("scratch"));
+    }
+
+    #[test]
+    fn test_detach_outside_session() {
+        let zellij = MockZellijClient::new();
+        let store = SessionStore::new();
+
+        let result = detach_current_session(&zellij, &store, true);
+        assert!(matches!(result, Err(DetachError::NotInSession)));
+    }
+}
@@ -0,0 +1,493 @@
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
+
+use zellij_rs::ZellijOperations;
+use zesh_git::Git;
+use zox_rs::ZoxideOperations;
+
+use crate::config::Config;
+use crate::fs::FsOperations;
+use crate::store::SessionStore;
+
+/// Oldest zellij release with everything zesh uses, like
+/// `attach --create-background` and `action rename-session`
+pub const MIN_ZELLIJ_VERSION: (u64, u64, u64) = (0, 40, 0);
+
+/// Outcome of a single `zesh doctor` check
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub passed: bool,
+    /// What was found, e.g. a version or the error
+    pub detail: String,
+    /// How to fix a failed check
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            passed: true,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            passed: false,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mark = if self.passed { "ok" } else { "FAIL" };
+        write!(f, "[{:>4}] {}: {}", mark, self.name, self.detail)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n       hint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Versions of zesh and the tools it runs, for `zesh version --json`. A
+/// tool that can't be found or run is `None`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Versions {
+    pub zesh: String,
+    pub zellij: Option<String>,
+    pub zoxide: Option<String>,
+    pub git: Option<String>,
+}
+
+impl fmt::Display for Versions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "zesh {}", self.zesh)?;
+        for (tool, version) in [
+            ("zellij", &self.zellij),
+            ("zoxide", &self.zoxide),
+            ("git", &self.git),
+        ] {
+            writeln!(f, "{}: {}", tool, version.as_deref().unwrap_or("not found"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that the tools and files zesh depends on are usable
+pub struct DoctorService<Z, X, G, F>
+where
+    Z: ZellijOperations,
+    X: ZoxideOperations,
+    G: Git,
+    F: FsOperations,
+{
+    zellij: Z,
+    zoxide: X,
+    git: G,
+    fs: F,
+    config_path: Option<PathBuf>,
+    state_dir: Option<PathBuf>,
+}
+
+impl<Z, X, G, F> DoctorService<Z, X, G, F>
+where
+    Z: ZellijOperations,
+    X: ZoxideOperations,
+    G: Git,
+    F: FsOperations,
+{
+    /// Create a doctor checking the default config file and state dir
+    pub fn new(zellij: Z, zoxide: X, git: G, fs: F) -> Self {
+        Self {
+            zellij,
+            zoxide,
+            git,
+            fs,
+            config_path: Config::path(),
+            state_dir: SessionStore::default_path()
+                .and_then(|path| path.parent().map(PathBuf::from)),
+        }
+    }
+
+    /// Check a different config file
+    pub fn with_config_path(mut self, path: Option<PathBuf>) -> Self {
+        self.config_path = path;
+        self
+    }
+
+    /// Check a different state dir
+    pub fn with_state_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.state_dir = dir;
+        self
+    }
+
+    /// Run every check, in the order they should be shown
+    pub fn run(&self) -> Vec<Check> {
+        vec![
+            self.check_zellij(),
+            self.check_zoxide(),
+            self.check_git(),
+            self.check_config(),
+            self.check_state_dir(),
+        ]
+    }
+
+    /// The versions of zesh and the tools it runs, as their `--version`
+    /// reports them
+    pub fn versions(&self) -> Versions {
+        Versions {
+            zesh: env!("CARGO_PKG_VERSION").to_string(),
+            zellij: self.zellij.version().ok(),
+            zoxide: self.zoxide.version().ok(),
+            git: self.git.version().ok(),
+        }
+    }
+
+    fn check_zellij(&self) -> Check {
+        let version = match self.zellij.version() {
+            Ok(version) => version,
+            Err(e) => {
+                return Check::fail(
+                    "zellij",
+                    e.to_string(),
+                    "install zellij: https://zellij.dev/documentation/installation",
+                );
+            }
+        };
+
+        match parse_version(&version) {
+            Some(found) if found < MIN_ZELLIJ_VERSION => {
+                let (major, minor, patch) = MIN_ZELLIJ_VERSION;
+                Check::fail(
+                    "zellij",
+                    format!("{} is too old", version),
+                    format!("upgrade zellij to {}.{}.{} or newer", major, minor, patch),
+                )
+            }
+            Some(_) => Check::pass("zellij", version),
+            // Don't fail a working zellij over a version format we don't know
+            None => Check::pass("zellij", format!("{} (unrecognized version)", version)),
+        }
+    }
+
+    fn check_zoxide(&self) -> Check {
+        match self.zoxide.version() {
+            Ok(version) => Check::pass("zoxide", version),
+            Err(e) => Check::fail(
+                "zoxide",
+                e.to_string(),
+                "install zoxide: https://github.com/ajeetdsouza/zoxide#installation",
+            ),
+        }
+    }
+
+    fn check_git(&self) -> Check {
+        match self.git.version() {
+            Ok(version) => Check::pass("git", version),
+            Err(e) => Check::fail(
+                "git",
+                e.to_string(),
+                "install git, which `zesh clone` and session naming need",
+            ),
+        }
+    }
+
+    fn check_config(&self) -> Check {
+        let Some(path) = &self.config_path else {
+            return Check::pass("config", "no config dir, using defaults");
+        };
+
+        if !self.fs.exists(path) {
+            return Check::pass(
+                "config",
+                format!("{} not found, using defaults", path.display()),
+            );
+        }
+
+        match Config::load_from(path) {
+            Ok(_) => Check::pass("config", path.display().to_string()),
+            Err(e) => Check::fail(
+                "config",
+                e.to_string(),
+                format!("fix or remove {}", path.display()),
+            ),
+        }
+    }
+
+    fn check_state_dir(&self) -> Check {
+        let Some(dir) = &self.state_dir else {
+            return Check::fail(
+                "state dir",
+                "no state or data dir found",
+                "set XDG_STATE_HOME so zesh can remember session roots",
+            );
+        };
+
+        match self.fs.ensure_writable_dir(dir) {
+            Ok(()) => Check::pass("state dir", dir.display().to_string()),
+            Err(e) => Check::fail(
+                "state dir",
+                e.to_string(),
+                format!("make {} writable", dir.display()),
+            ),
+        }
+    }
+}
+
+/// Find the first `major.minor.patch` version in a `--version` line like
+/// `zellij 0.41.2`. A missing patch component counts as 0.
+fn parse_version(output: &str) -> Option<(u64, u64, u64)> {
+    output.split_whitespace().find_map(|word| {
+        let mut parts = word.trim_start_matches('v').split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        // Ignore pre-release suffixes like `0-rc1`
+        let patch = parts
+            .next()
+            .map(|p| p.split(|c: char| !c.is_ascii_digit()).next().unwrap_or(""))
+            .map_or(Some(0), |p| p.parse().ok())?;
+        Some((major, minor, patch))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::tests::MockFs;
+    use std::path::Path;
+    use zellij_rs::MockZellijClient;
+    use zesh_git::{GitError, MockGit};
+    use zox_rs::MockZoxideClient;
+
+    struct MissingGit;
+
+    impl Git for MissingGit {
+        fn show_top_level(&self, _: &str) -> Result<(bool, String), GitError> {
+            unimplemented!()
+        }
+
+        fn is_inside_work_tree(&self, _: &str) -> Result<bool, GitError> {
+            unimplemented!()
+        }
+
+        fn git_common_dir(&self, _: &str) -> Result<(bool, String), GitError> {
+            unimplemented!()
+        }
+
+        fn clone(&self, _: &str, _: &str, _: &str) -> Result<String, GitError> {
+            unimplemented!()
+        }
+
+        fn clone_bare(&self, _: &str, _: &str, _: &str) -> Result<String, GitError> {
+            unimplemented!()
+        }
+
+        fn worktree_add(&self, _: &str, _: &str, _: &str) -> Result<(), GitError> {
+            unimplemented!()
+        }
+
+        fn version(&self) -> Result<String, GitError> {
+            Err(GitError::IoError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "git not found",
+            )))
+        }
+
+        fn default_branch(&self, _: &str) -> Result<String, GitError> {
+            unimplemented!()
+        }
+    }
+
+    fn doctor<Z: ZellijOperations, X: ZoxideOperations, G: Git>(
+        zellij: Z,
+        zoxide: X,
+        git: G,
+        fs: MockFs,
+    ) -> DoctorService<Z, X, G, MockFs> {
+        DoctorService::new(zellij, zoxide, git, fs)
+            .with_config_path(Some(PathBuf::from("/mock/config/zesh/config.toml")))
+            .with_state_dir(Some(PathBuf::from("/mock/state/zesh")))
+    }
+
+    fn failures(checks: &[Check]) -> Vec<&'static str> {
+        checks
+            .iter()
+            .filter(|c| !c.passed)
+            .map(|c| c.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_all_checks_pass() {
+        let checks = doctor(
+            MockZellijClient::new(),
+            MockZoxideClient::new(),
+            MockGit::new(),
+            MockFs::new(),
+        )
+        .run();
+
+        assert_eq!(checks.len(), 5);
+        assert!(failures(&checks).is_empty());
+        assert_eq!(checks[0].detail, zellij_rs::MOCK_ZELLIJ_VERSION);
+    }
+
+    #[test]
+    fn test_missing_zoxide() {
+        let checks = doctor(
+            MockZellijClient::new(),
+            MockZoxideClient::new().with_version(None),
+            MockGit::new(),
+            MockFs::new(),
+        )
+        .run();
+
+        assert_eq!(failures(&checks), vec!["zoxide"]);
+        let zoxide = checks.iter().find(|c| c.name == "zoxide").unwrap();
+        assert!(zoxide.hint.as_deref().unwrap().contains("install zoxide"));
+    }
+
+    #[test]
+    fn test_missing_zellij() {
+        let checks = doctor(
+            MockZellijClient::new().with_version(None),
+            MockZoxideClient::new(),
+            MockGit::new(),
+            MockFs::new(),
+        )
+        .run();
+
+        assert_eq!(failures(&checks), vec!["zellij"]);
+    }
+
+    #[test]
+    fn test_missing_git() {
+        let checks = doctor(
+            MockZellijClient::new(),
+            MockZoxideClient::new(),
+            MissingGit,
+            MockFs::new(),
+        )
+        .run();
+
+        assert_eq!(failures(&checks), vec!["git"]);
+    }
+
+    #[test]
+    fn test_old_zellij() {
+        let checks = doctor(
+            MockZellijClient::new().with_version(Some("zellij 0.39.2")),
+            MockZoxideClient::new(),
+            MockGit::new(),
+            MockFs::new(),
+        )
+        .run();
+
+        assert_eq!(failures(&checks), vec!["zellij"]);
+        assert_eq!(
+            checks[0].hint.as_deref(),
+            Some("upgrade zellij to 0.40.0 or newer")
+        );
+    }
+
+    #[test]
+    fn test_read_only_state_dir() {
+        let fs = MockFs::new();
+        fs.with_read_only(Path::new("/mock/state/zesh"));
+
+        let checks = doctor(
+            MockZellijClient::new(),
+            MockZoxideClient::new(),
+            MockGit::new(),
+            fs,
+        )
+        .run();
+
+        assert_eq!(failures(&checks), vec!["state dir"]);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let path = std::env::temp_dir().join(format!("zesh-doctor-{}.toml", std::process::id()));
+        std::fs::write(&path, "timeout = \"soon\"").unwrap();
+        let fs = MockFs::new();
+        fs.with_file(&path);
+
+        let checks = doctor(
+            MockZellijClient::new(),
+            MockZoxideClient::new(),
+            MockGit::new(),
+            fs,
+        )
+        .with_config_path(Some(path.clone()))
+        .run();
+
+        assert_eq!(failures(&checks), vec!["config"]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_versions_json() {
+        let versions = doctor(
+            MockZellijClient::new().with_version(Some("zellij 0.41.2")),
+            MockZoxideClient::new().with_version(Some("zoxide v0.9.4")),
+            MockGit::new(),
+            MockFs::new(),
+        )
+        .versions();
+
+        let json = serde_json::to_value(&versions).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "zesh": env!("CARGO_PKG_VERSION"),
+                "zellij": "zellij 0.41.2",
+                "zoxide": "zoxide v0.9.4",
+                "git": MockGit::new().version().unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_versions_of_missing_tools() {
+        let versions = doctor(
+            MockZellijClient::new().with_version(None),
+            MockZoxideClient::new(),
+            MissingGit,
+            MockFs::new(),
+        )
+        .versions();
+
+        let json = serde_json::to_value(&versions).unwrap();
+        assert!(json["zellij"].is_null());
+        assert!(json["git"].is_null());
+        assert!(json["zoxide"].is_string());
+        assert!(versions.to_string().contains("zellij: not found\n"));
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("zellij 0.41.2"), Some((0, 41, 2)));
+        assert_eq!(parse_version("git version 2.43.0"), Some((2, 43, 0)));
+        assert_eq!(parse_version("zoxide v0.9.4"), Some((0, 9, 4)));
+        assert_eq!(parse_version("zellij 0.42.0-rc1"), Some((0, 42, 0)));
+        assert_eq!(parse_version("zellij 1.0"), Some((1, 0, 0)));
+        assert_eq!(parse_version("zellij"), None);
+    }
+
+    #[test]
+    fn test_display_failed_check() {
+        let check = Check::fail("git", "not found", "install git");
+        assert_eq!(
+            check.to_string(),
+            "[FAIL] git: not found\n       hint: install git"
+        );
+    }
+}
@@ -0,0 +1,359 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use zox_rs::{ZoxideEntry, ZoxideError, ZoxideOperations, ZoxideResult};
+
+/// Once the ranks add up to more than this, they are all scaled down so
+/// old directories fade and the store stays small
+pub const MAX_TOTAL_RANK: f64 = 10_000.0;
+
+/// How much every rank is scaled by when aging
+const AGING_FACTOR: f64 = 0.9;
+
+/// Directories whose rank drops below this while aging are forgotten
+const MIN_RANK: f64 = 1.0;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+#[derive(Debug, Error)]
+pub enum FrecencyError {
+    #[error("Failed to access frecency store {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Failed to parse frecency store: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl From<FrecencyError> for ZoxideError {
+    fn from(err: FrecencyError) -> Self {
+        ZoxideError::CommandExecution(err.to_string())
+    }
+}
+
+/// What the store knows about a directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FrecencyRecord {
+    /// How often the directory was visited, decayed by aging
+    rank: f64,
+    /// Last visit, in seconds since the Unix epoch
+    last_access: u64,
+}
+
+/// Frecency of a directory with `rank` last visited `age` ago, ranking
+/// recent visits higher the same way zoxide does
+pub fn frecency(rank: f64, age: Duration) -> f64 {
+    let age = age.as_secs();
+    let factor = if age < HOUR {
+        4.0
+    } else if age < DAY {
+        2.0
+    } else if age < WEEK {
+        0.5
+    } else {
+        0.25
+    };
+    rank * factor
+}
+
+/// zesh's own ranking of directories, kept apart from zoxide's database so
+/// a project can have rankings that don't leak into the global ones.
+///
+/// Stores created with [`FrecencyStore::new`] only live in memory, while
+/// [`FrecencyStore::open`] persists every change to a JSON file.
+#[derive(Debug, Default)]
+pub struct FrecencyStore {
+    path: Option<PathBuf>,
+    entries: RefCell<BTreeMap<PathBuf, FrecencyRecord>>,
+}
+
+impl FrecencyStore {
+    /// Create an empty in-memory store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Default location of the store file
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| dir.join("zesh").join("frecency.json"))
+    }
+
+    /// Open the store backed by the given file, which is created on the first
+    /// write if it doesn't exist yet
+    pub fn open(path: &Path) -> Result<Self, FrecencyError> {
+        let entries = if path.exists() {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| FrecencyError::Io(path.display().to_string(), e))?;
+            serde_json::from_str(&contents)?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            entries: RefCell::new(entries),
+        })
+    }
+
+    /// Add `score` to the rank of a directory visited at `now`, in seconds
+    /// since the Unix epoch
+    fn add_at(&self, path: &Path, score: f64, now: u64) -> Result<(), FrecencyError> {
+        {
+            let mut entries = self.entries.borrow_mut();
+            let record = entries.entry(path.to_path_buf()).or_insert(FrecencyRecord {
+                rank: 0.0,
+                last_access: now,
+            });
+            record.rank += score;
+            record.last_access = now;
+
+            if entries.values().map(|r| r.rank).sum::<f64>() > MAX_TOTAL_RANK {
+                for record in entries.values_mut() {
+                    record.rank *= AGING_FACTOR;
+                }
+                entries.retain(|_, r| r.rank >= MIN_RANK);
+            }
+        }
+        self.save()
+    }
+
+    /// Every directory matching `keywords`, highest frecency at `now` first
+    fn entries_at(&self, keywords: &[&str], now: u64) -> Vec<ZoxideEntry> {
+        let mut entries: Vec<ZoxideEntry> = self
+            .entries
+            .borrow()
+            .iter()
+            .filter(|(path, _)| matches_keywords(path, keywords))
+            .map(|(path, record)| {
+                let age = Duration::from_secs(now.saturating_sub(record.last_access));
+                ZoxideEntry {
+                    path: path.clone(),
+                    score: frecency(record.rank, age),
+                    last_access: Some(UNIX_EPOCH + Duration::from_secs(record.last_access)),
+                }
+            })
+            .collect();
+
+        entries.sort_by(|a, b| b.score.total_cmp(&a.score));
+        entries
+    }
+
+    /// Every directory in the store, sorted
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.entries.borrow().keys().cloned().collect()
+    }
+
+    /// Forget a directory
+    pub fn remove(&self, path: &Path) -> Result<(), FrecencyError> {
+        self.entries.borrow_mut().remove(path);
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), FrecencyError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let io_err = |e| FrecencyError::Io(path.display().to_string(), e);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_err)?;
+        }
+        let contents = serde_json::to_string_pretty(&*self.entries.borrow())?;
+        std::fs::write(path, contents).map_err(io_err)
+    }
+}
+
+/// Whether the keywords appear in the path in order, ignoring case, like
+/// zoxide matches them
+pub fn matches_keywords(path: &Path, keywords: &[&str]) -> bool {
+    let path = path.to_string_lossy().to_lowercase();
+    let mut rest = path.as_str();
+    keywords.iter().all(|keyword| {
+        let keyword = keyword.to_lowercase();
+        match rest.find(&keyword) {
+            Some(index) => {
+                rest = &rest[index + keyword.len()..];
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+impl ZoxideOperations for FrecencyStore {
+    fn add<P: AsRef<Path>>(&self, path: P) -> ZoxideResult<()> {
+        self.add_with_score(path, 1.0)
+    }
+
+    fn add_with_score<P: AsRef<Path>>(&self, path: P, score: f64) -> ZoxideResult<()> {
+        Ok(self.add_at(path.as_ref(), score, now())?)
+    }
+
+    fn list(&self) -> ZoxideResult<Vec<ZoxideEntry>> {
+        Ok(self.entries_at(&[], now()))
+    }
+
+    fn query(&self, keywords: &[&str]) -> ZoxideResult<Vec<ZoxideEntry>> {
+        Ok(self.entries_at(keywords, now()))
+    }
+
+    fn version(&self) -> ZoxideResult<String> {
+        Ok(format!("zesh {} frecency store", env!("CARGO_PKG_VERSION")))
+    }
+
+    fn score_for<P: AsRef<Path>>(&self, path: P) -> ZoxideResult<Option<f64>> {
+        let path = path.as_ref();
+        Ok(self
+            .list()?
+            .into_iter()
+            .find(|entry| entry.path == path)
+            .map(|entry| entry.score))
+    }
+}
+
+/// The directory rankings zesh uses, zoxide's or its own, as picked by
+/// [`Config::directory_backend`](crate::config::Config::directory_backend)
+pub enum Directories<X: ZoxideOperations> {
+    Zoxide(X),
+    Zesh(FrecencyStore),
+}
+
+impl<X: ZoxideOperations> ZoxideOperations for Directories<X> {
+    fn add<P: AsRef<Path>>(&self, path: P) -> ZoxideResult<()> {
+        match self {
+            Directories::Zoxide(zoxide) => zoxide.add(path),
+            Directories::Zesh(store) => store.add(path),
+        }
+    }
+
+    fn add_with_score<P: AsRef<Path>>(&self, path: P, score: f64) -> ZoxideResult<()> {
+        match self {
+            Directories::Zoxide(zoxide) => zoxide.add_with_score(path, score),
+            Directories::Zesh(store) => store.add_with_score(path, score),
+        }
+    }
+
+    fn list(&self) -> ZoxideResult<Vec<ZoxideEntry>> {
+        match self {
+            Directories::Zoxide(zoxide) => zoxide.list(),
+            Directories::Zesh(store) => store.list(),
+        }
+    }
+
+    fn query(&self, keywords: &[&str]) -> ZoxideResult<Vec<ZoxideEntry>> {
+        match self {
+            Directories::Zoxide(zoxide) => zoxide.query(keywords),
+            Directories::Zesh(store) => store.query(keywords),
+        }
+    }
+
+    fn version(&self) -> ZoxideResult<String> {
+        match self {
+            Directories::Zoxide(zoxide) => zoxide.version(),
+            Directories::Zesh(store) => store.version(),
+        }
+    }
+
+    fn score_for<P: AsRef<Path>>(&self, path: P) -> ZoxideResult<Option<f64>> {
+        match self {
+            Directories::Zoxide(zoxide) => zoxide.score_for(path),
+            Directories::Zesh(store) => store.score_for(path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn paths(entries: Vec<ZoxideEntry>) -> Vec<PathBuf> {
+        entries.into_iter().map(|e| e.path).collect()
+    }
+
+    #[test]
+    fn test_frecency_favours_recent_visits() {
+        assert_eq!(frecency(10.0, Duration::from_secs(60)), 40.0);
+        assert_eq!(frecency(10.0, Duration::from_secs(2 * HOUR)), 20.0);
+        assert_eq!(frecency(10.0, Duration::from_secs(2 * DAY)), 5.0);
+        assert_eq!(frecency(10.0, Duration::from_secs(2 * WEEK)), 2.5);
+    }
+
+    #[test]
+    fn test_recent_directory_outranks_frequent_one() {
+        let store = FrecencyStore::new();
+        for _ in 0..5 {
+            store
+                .add_at(Path::new("/src/old"), 1.0, NOW - 2 * WEEK)
+                .unwrap();
+        }
+        store.add_at(Path::new("/src/new"), 1.0, NOW).unwrap();
+
+        let entries = store.entries_at(&[], NOW);
+        assert_eq!(
+            paths(entries.clone()),
+            vec![PathBuf::from("/src/new"), PathBuf::from("/src/old")]
+        );
+        assert_eq!(entries[0].score, 4.0);
+        assert_eq!(entries[1].score, 1.25);
+    }
+
+    #[test]
+    fn test_query_matches_keywords_in_order() {
+        let store = FrecencyStore::new();
+        store.add_at(Path::new("/src/work/api"), 1.0, NOW).unwrap();
+        store.add_at(Path::new("/src/api/work"), 1.0, NOW).unwrap();
+
+        assert_eq!(
+            paths(store.entries_at(&["WORK", "api"], NOW)),
+            vec![PathBuf::from("/src/work/api")]
+        );
+        assert_eq!(store.entries_at(&[], NOW).len(), 2);
+    }
+
+    #[test]
+    fn test_aging_forgets_rarely_used_directories() {
+        let store = FrecencyStore::new();
+        store.add_at(Path::new("/src/rare"), 1.0, NOW).unwrap();
+        store
+            .add_at(Path::new("/src/busy"), MAX_TOTAL_RANK, NOW)
+            .unwrap();
+
+        // The busy directory's rank is scaled down, the rare one drops out
+        let entries = store.entries_at(&[], NOW);
+        assert_eq!(paths(entries.clone()), vec![PathBuf::from("/src/busy")]);
+        assert_eq!(
+            entries[0].score,
+            frecency(MAX_TOTAL_RANK * AGING_FACTOR, Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_persists_to_file() {
+        let dir = std::env::temp_dir().join(format!("zesh-frecency-test-{}", std::process::id()));
+        let path = dir.join("frecency.json");
+
+        let store = FrecencyStore::open(&path).unwrap();
+        store.add("/src/api").unwrap();
+        store.add_with_score("/src/web", 3.0).unwrap();
+
+        let reopened = FrecencyStore::open(&path).unwrap();
+        assert_eq!(reopened.list().unwrap(), store.list().unwrap());
+        assert_eq!(reopened.score_for("/src/web").unwrap(), Some(12.0));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
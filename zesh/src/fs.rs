@@ -1,4 +1,4 @@
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use thiserror::Error;
 
 /// Error type for filesystem operations
@@ -16,6 +16,9 @@ pub enum FsError {
     #[error("Failed to canonicalize path: {0}")]
     Canonicalize(#[from] std::io::Error),
 
+    #[error("Couldn't find the home directory")]
+    NoHomeDir,
+
     #[error("Other filesystem error: {0}")]
     Other(String),
 }
@@ -40,6 +43,51 @@ pub trait FsOperations {
     /// Get the current directory
     fn current_dir(&self) -> Result<PathBuf, FsError>;
 
+    /// The current directory, or the home directory if the current one can't
+    /// be read, usually because it was deleted from under us, e.g. by `git
+    /// worktree remove`. Falling back also returns why, for callers to warn
+    /// about.
+    fn current_dir_or_home(&self) -> Result<(PathBuf, Option<FsError>), FsError> {
+        match self.current_dir() {
+            Ok(dir) => Ok((dir, None)),
+            Err(e) => Ok((self.home_dir()?, Some(e))),
+        }
+    }
+
+    /// Get the user's home directory
+    fn home_dir(&self) -> Result<PathBuf, FsError>;
+
+    /// Create a directory if it doesn't exist and check that files can be
+    /// written to it
+    fn ensure_writable_dir(&self, path: &Path) -> Result<(), FsError>;
+
+    /// Read a whole file as UTF-8
+    fn read_to_string(&self, path: &Path) -> Result<String, FsError>;
+
+    /// Write a file, replacing it if it exists
+    fn write(&self, path: &Path, contents: &str) -> Result<(), FsError>;
+
+    /// List the entries of a directory, sorted by path
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, FsError>;
+
+    /// Remove a directory and everything in it. A directory that doesn't
+    /// exist isn't an error.
+    fn remove_dir_all(&self, path: &Path) -> Result<(), FsError>;
+
+    /// Expand a leading `~` or `~/` to the home directory. Other paths,
+    /// including `~user`, are returned unchanged.
+    fn expand_path(&self, path: &Path) -> PathBuf {
+        let (Ok(rest), Ok(home)) = (path.strip_prefix("~"), self.home_dir()) else {
+            return path.to_path_buf();
+        };
+
+        if rest.as_os_str().is_empty() {
+            home
+        } else {
+            home.join(rest)
+        }
+    }
+
     /// Extract the directory name from a path and confirm it's a valid directory
     fn validate_dir_path(&self, path: &Path) -> Result<(PathBuf, String), FsError> {
         let canon_path = self.canonicalize(path)?;
@@ -56,6 +104,117 @@ pub trait FsOperations {
 
         Ok((canon_path, name))
     }
+
+    /// Make a path absolute against the current directory and resolve `.`
+    /// and `..` without following symlinks, like a shell's logical `pwd`
+    fn logical_path(&self, path: &Path) -> Result<PathBuf, FsError> {
+        let base = if path.is_relative() {
+            self.current_dir()?
+        } else {
+            PathBuf::new()
+        };
+        Ok(normalize_lexically(&base, path))
+    }
+
+    /// The directory name of a path as written, rather than of the directory
+    /// a symlink points to
+    fn logical_dir_name(&self, path: &Path) -> Result<String, FsError> {
+        let logical = self.logical_path(path)?;
+        logical
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(String::from)
+            .ok_or_else(|| FsError::NoDirectoryName(path.display().to_string()))
+    }
+
+    /// Like [`validate_dir_path`](Self::validate_dir_path), but return the
+    /// logical path and its name. The directory it resolves to still has to
+    /// exist.
+    fn validate_logical_dir_path(&self, path: &Path) -> Result<(PathBuf, String), FsError> {
+        self.validate_dir_path(path)?;
+        let logical = self.logical_path(path)?;
+        let name = self.logical_dir_name(&logical)?;
+        Ok((logical, name))
+    }
+
+    /// [`validate_dir_path`](Self::validate_dir_path) if `follow_symlinks`,
+    /// otherwise [`validate_logical_dir_path`](Self::validate_logical_dir_path)
+    fn validate_dir_path_with(
+        &self,
+        path: &Path,
+        follow_symlinks: bool,
+    ) -> Result<(PathBuf, String), FsError> {
+        if follow_symlinks {
+            self.validate_dir_path(path)
+        } else {
+            self.validate_logical_dir_path(path)
+        }
+    }
+}
+
+/// Join `path` onto `base` and resolve `.` and `..` in the result without
+/// touching the filesystem
+pub fn normalize_lexically(base: &Path, path: &Path) -> PathBuf {
+    let mut normalized = base.to_path_buf();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// `path` with symlinks resolved as far as it exists, so a directory that's
+/// yet to be created resolves to where it will be
+pub fn canonicalize_existing<F: FsOperations + ?Sized>(fs: &F, path: &Path) -> PathBuf {
+    let path = fs.logical_path(path).unwrap_or_else(|_| path.to_path_buf());
+    for ancestor in path.ancestors() {
+        if let Ok(canon) = fs.canonicalize(ancestor) {
+            return match path.strip_prefix(ancestor) {
+                Ok(rest) if !rest.as_os_str().is_empty() => canon.join(rest),
+                _ => canon,
+            };
+        }
+    }
+    path
+}
+
+/// Whether `path` is one of `roots` or inside one, comparing canonical paths
+/// so neither symlinks nor `..` lead out of them. No roots allow any path.
+pub fn is_under_allowed_roots<F: FsOperations + ?Sized>(
+    fs: &F,
+    path: &Path,
+    roots: &[PathBuf],
+) -> bool {
+    if roots.is_empty() {
+        return true;
+    }
+    let path = canonicalize_existing(fs, path);
+    roots
+        .iter()
+        .any(|root| path.starts_with(canonicalize_existing(fs, &fs.expand_path(root))))
+}
+
+/// Turn a Windows verbatim path like `\\?\C:\src` or `\\?\UNC\server\share`
+/// back into its usual form, `C:\src` or `\\server\share`. Other paths are
+/// returned unchanged.
+pub fn strip_verbatim_prefix(path: &Path) -> PathBuf {
+    let Some(path_str) = path.to_str() else {
+        return path.to_path_buf();
+    };
+
+    if let Some(rest) = path_str.strip_prefix(r"\\?\UNC\") {
+        return PathBuf::from(format!(r"\\{}", rest));
+    }
+    match path_str.strip_prefix(r"\\?\") {
+        // Only drive paths, others like `\\?\Volume{..}` need the prefix
+        Some(rest) if rest.as_bytes().get(1) == Some(&b':') => PathBuf::from(rest),
+        _ => path.to_path_buf(),
+    }
 }
 
 /// Default implementation that uses the standard filesystem
@@ -85,14 +244,25 @@ impl FsOperations for RealFs {
     }
 
     fn canonicalize(&self, path: &Path) -> Result<PathBuf, FsError> {
-        path.canonicalize().map_err(FsError::Canonicalize)
+        let canon = path.canonicalize().map_err(FsError::Canonicalize)?;
+        // Windows canonicalizes to `\\?\C:\...`, which other programs and
+        // users don't expect to see
+        #[cfg(windows)]
+        let canon = strip_verbatim_prefix(&canon);
+        Ok(canon)
     }
 
     fn get_dir_name(&self, path: &Path) -> Result<String, FsError> {
-        path.file_name()
-            .and_then(|n| n.to_str())
-            .map(String::from)
-            .ok_or_else(|| FsError::NoDirectoryName(path.display().to_string()))
+        let name = path.file_name().and_then(|n| n.to_str()).map(String::from);
+        // A drive root has no file name, so it's named after the drive
+        #[cfg(windows)]
+        let name = name.or_else(|| match path.components().next() {
+            Some(std::path::Component::Prefix(prefix)) => {
+                prefix.as_os_str().to_str().map(String::from)
+            }
+            _ => None,
+        });
+        name.ok_or_else(|| FsError::NoDirectoryName(path.display().to_string()))
     }
 
     fn set_current_dir(&self, path: &Path) -> Result<(), FsError> {
@@ -102,13 +272,57 @@ impl FsOperations for RealFs {
     fn current_dir(&self) -> Result<PathBuf, FsError> {
         std::env::current_dir().map_err(|e| FsError::Other(e.to_string()))
     }
+
+    fn home_dir(&self) -> Result<PathBuf, FsError> {
+        dirs::home_dir().ok_or(FsError::NoHomeDir)
+    }
+
+    fn ensure_writable_dir(&self, path: &Path) -> Result<(), FsError> {
+        let other = |e: std::io::Error| FsError::Other(format!("{}: {}", path.display(), e));
+
+        std::fs::create_dir_all(path).map_err(other)?;
+        let probe = path.join(format!(".zesh-write-test-{}", std::process::id()));
+        std::fs::write(&probe, b"").map_err(other)?;
+        std::fs::remove_file(&probe).map_err(other)
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String, FsError> {
+        std::fs::read_to_string(path)
+            .map_err(|e| FsError::Other(format!("{}: {}", path.display(), e)))
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> Result<(), FsError> {
+        std::fs::write(path, contents)
+            .map_err(|e| FsError::Other(format!("{}: {}", path.display(), e)))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, FsError> {
+        let other = |e: std::io::Error| FsError::Other(format!("{}: {}", path.display(), e));
+
+        let mut entries = std::fs::read_dir(path)
+            .map_err(other)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(other)?;
+        entries.sort();
+        Ok(entries)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<(), FsError> {
+        match std::fs::remove_dir_all(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(FsError::Other(format!("{}: {}", path.display(), e)))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
     use std::cell::RefCell;
-    use std::collections::HashMap;
+    use std::collections::{HashMap, HashSet};
 
     /// A mock implementation of filesystem operations for testing
     #[derive(Default)]
@@ -117,6 +331,12 @@ pub mod tests {
         is_dir_map: RefCell<HashMap<PathBuf, bool>>,
         dir_names: RefCell<HashMap<PathBuf, String>>,
         current_dir: RefCell<PathBuf>,
+        current_dir_deleted: RefCell<bool>,
+        home_dir: RefCell<Option<PathBuf>>,
+        read_only: RefCell<HashSet<PathBuf>>,
+        contents: RefCell<HashMap<PathBuf, String>>,
+        pub removed: RefCell<Vec<PathBuf>>,
+        symlinks: RefCell<Vec<(PathBuf, PathBuf)>>,
     }
 
     impl MockFs {
@@ -126,6 +346,12 @@ pub mod tests {
                 is_dir_map: RefCell::new(HashMap::new()),
                 dir_names: RefCell::new(HashMap::new()),
                 current_dir: RefCell::new(PathBuf::from("/mock/current")),
+                current_dir_deleted: RefCell::new(false),
+                home_dir: RefCell::new(Some(PathBuf::from("/mock/home"))),
+                read_only: RefCell::new(HashSet::new()),
+                contents: RefCell::new(HashMap::new()),
+                removed: RefCell::new(Vec::new()),
+                symlinks: RefCell::new(Vec::new()),
             }
         }
 
@@ -146,10 +372,43 @@ pub mod tests {
             self
         }
 
+        pub fn with_file_contents(&self, path: &Path, contents: &str) -> &Self {
+            self.with_file(path);
+            self.contents
+                .borrow_mut()
+                .insert(path.to_path_buf(), contents.to_string());
+            self
+        }
+
         pub fn with_current_dir(&self, path: &Path) -> &Self {
             *self.current_dir.borrow_mut() = path.to_path_buf();
             self
         }
+
+        /// Make reading the current directory fail, as if it was deleted,
+        /// until another one is set
+        pub fn with_deleted_current_dir(&self) -> &Self {
+            *self.current_dir_deleted.borrow_mut() = true;
+            self
+        }
+
+        pub fn with_home_dir(&self, path: Option<&Path>) -> &Self {
+            *self.home_dir.borrow_mut() = path.map(Path::to_path_buf);
+            self
+        }
+
+        pub fn with_read_only(&self, path: &Path) -> &Self {
+            self.read_only.borrow_mut().insert(path.to_path_buf());
+            self
+        }
+
+        /// Make `link` a symlink to `target`, which canonicalizing resolves
+        pub fn with_symlink(&self, link: &Path, target: &Path) -> &Self {
+            self.symlinks
+                .borrow_mut()
+                .push((link.to_path_buf(), target.to_path_buf()));
+            self
+        }
     }
 
     impl FsOperations for MockFs {
@@ -170,8 +429,19 @@ pub mod tests {
         }
 
         fn canonicalize(&self, path: &Path) -> Result<PathBuf, FsError> {
-            // For mock, we just return the path as is
-            Ok(path.to_path_buf())
+            // For mock, resolve against the current dir and normalize `.` and
+            // `..` without touching the real filesystem, then follow symlinks
+            let canon = self.logical_path(path)?;
+            for (link, target) in self.symlinks.borrow().iter() {
+                if let Ok(rest) = canon.strip_prefix(link) {
+                    return Ok(if rest.as_os_str().is_empty() {
+                        target.clone()
+                    } else {
+                        target.join(rest)
+                    });
+                }
+            }
+            Ok(canon)
         }
 
         fn get_dir_name(&self, path: &Path) -> Result<String, FsError> {
@@ -185,12 +455,80 @@ pub mod tests {
 
         fn set_current_dir(&self, path: &Path) -> Result<(), FsError> {
             *self.current_dir.borrow_mut() = path.to_path_buf();
+            *self.current_dir_deleted.borrow_mut() = false;
             Ok(())
         }
 
         fn current_dir(&self) -> Result<PathBuf, FsError> {
+            if *self.current_dir_deleted.borrow() {
+                return Err(FsError::Other(
+                    "No such file or directory (os error 2)".to_string(),
+                ));
+            }
             Ok(self.current_dir.borrow().clone())
         }
+
+        fn home_dir(&self) -> Result<PathBuf, FsError> {
+            self.home_dir.borrow().clone().ok_or(FsError::NoHomeDir)
+        }
+
+        fn ensure_writable_dir(&self, path: &Path) -> Result<(), FsError> {
+            if self.read_only.borrow().contains(path) {
+                return Err(FsError::Other(format!("{} is read-only", path.display())));
+            }
+            Ok(())
+        }
+
+        fn read_to_string(&self, path: &Path) -> Result<String, FsError> {
+            self.contents
+                .borrow()
+                .get(path)
+                .cloned()
+                .ok_or_else(|| FsError::PathNotFound(path.display().to_string()))
+        }
+
+        fn write(&self, path: &Path, contents: &str) -> Result<(), FsError> {
+            if path
+                .parent()
+                .is_some_and(|dir| self.read_only.borrow().contains(dir))
+            {
+                return Err(FsError::Other(format!("{} is read-only", path.display())));
+            }
+            self.with_file_contents(path, contents);
+            Ok(())
+        }
+
+        fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, FsError> {
+            if !self.is_dir(path) {
+                return Err(FsError::NotADirectory(path.display().to_string()));
+            }
+            let mut entries: Vec<PathBuf> = self
+                .exists_map
+                .borrow()
+                .keys()
+                .filter(|entry| entry.parent() == Some(path))
+                .cloned()
+                .collect();
+            entries.sort();
+            Ok(entries)
+        }
+
+        fn remove_dir_all(&self, path: &Path) -> Result<(), FsError> {
+            self.exists_map
+                .borrow_mut()
+                .retain(|p, _| !p.starts_with(path));
+            self.is_dir_map
+                .borrow_mut()
+                .retain(|p, _| !p.starts_with(path));
+            self.dir_names
+                .borrow_mut()
+                .retain(|p, _| !p.starts_with(path));
+            self.contents
+                .borrow_mut()
+                .retain(|p, _| !p.starts_with(path));
+            self.removed.borrow_mut().push(path.to_path_buf());
+            Ok(())
+        }
     }
 
     #[test]
@@ -216,4 +554,153 @@ pub mod tests {
         let result = mock_fs.validate_dir_path(&file_path);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_mock_read_dir() {
+        let mock_fs = MockFs::new();
+        mock_fs.with_directory(Path::new("/src"), "src");
+        mock_fs.with_directory(Path::new("/src/b"), "b");
+        mock_fs.with_file(Path::new("/src/a.txt"));
+        mock_fs.with_directory(Path::new("/src/b/nested"), "nested");
+
+        assert_eq!(
+            mock_fs.read_dir(Path::new("/src")).unwrap(),
+            vec![PathBuf::from("/src/a.txt"), PathBuf::from("/src/b")]
+        );
+        assert!(mock_fs.read_dir(Path::new("/src/a.txt")).is_err());
+    }
+
+    #[test]
+    fn test_expand_path() {
+        let mock_fs = MockFs::new();
+
+        assert_eq!(
+            mock_fs.expand_path(Path::new("~")),
+            PathBuf::from("/mock/home")
+        );
+        assert_eq!(
+            mock_fs.expand_path(Path::new("~/projects/foo")),
+            PathBuf::from("/mock/home/projects/foo")
+        );
+
+        // Only a leading `~` component is expanded
+        for path in ["/abs/path", "rel/path", "~user/foo", "foo/~", "~foo"] {
+            assert_eq!(mock_fs.expand_path(Path::new(path)), PathBuf::from(path));
+        }
+    }
+
+    #[test]
+    fn test_current_dir_or_home() {
+        let fs = MockFs::new();
+        let (dir, fallback) = fs.current_dir_or_home().unwrap();
+        assert_eq!(dir, PathBuf::from("/mock/current"));
+        assert!(fallback.is_none());
+
+        fs.with_deleted_current_dir();
+        assert!(fs.current_dir().is_err());
+        let (dir, fallback) = fs.current_dir_or_home().unwrap();
+        assert_eq!(dir, PathBuf::from("/mock/home"));
+        assert!(fallback.is_some());
+
+        fs.with_home_dir(None);
+        assert!(matches!(fs.current_dir_or_home(), Err(FsError::NoHomeDir)));
+    }
+
+    #[test]
+    fn test_expand_path_without_home() {
+        let mock_fs = MockFs::new();
+        mock_fs.with_home_dir(None);
+
+        assert!(matches!(mock_fs.home_dir(), Err(FsError::NoHomeDir)));
+        assert_eq!(
+            mock_fs.expand_path(Path::new("~/foo")),
+            PathBuf::from("~/foo")
+        );
+    }
+
+    #[test]
+    fn test_logical_path_keeps_symlinks() {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/src/api-server"), "api-server");
+        fs.with_symlink(
+            Path::new("/mock/links/api"),
+            Path::new("/mock/src/api-server"),
+        );
+        fs.with_current_dir(Path::new("/mock/links"));
+
+        assert_eq!(
+            fs.validate_dir_path(Path::new("api")).unwrap(),
+            (
+                PathBuf::from("/mock/src/api-server"),
+                "api-server".to_string()
+            )
+        );
+        assert_eq!(
+            fs.validate_logical_dir_path(Path::new("./api")).unwrap(),
+            (PathBuf::from("/mock/links/api"), "api".to_string())
+        );
+        assert_eq!(
+            fs.logical_dir_name(Path::new("../links/api")).unwrap(),
+            "api"
+        );
+        assert!(fs.validate_logical_dir_path(Path::new("missing")).is_err());
+    }
+
+    #[test]
+    fn test_is_under_allowed_roots() {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/src"), "src");
+        fs.with_directory(Path::new("/mock/src/api"), "api");
+        fs.with_directory(Path::new("/mock/src-other"), "src-other");
+        fs.with_directory(Path::new("/mock/secret"), "secret");
+        fs.with_symlink(Path::new("/mock/src/escape"), Path::new("/mock/secret"));
+        fs.with_home_dir(Some(Path::new("/mock")));
+        let roots = vec![PathBuf::from("~/src")];
+        let allowed = |path: &str| is_under_allowed_roots(&fs, Path::new(path), &roots);
+
+        assert!(allowed("/mock/src/api"));
+        // Directories that are yet to be created, like clones
+        assert!(allowed("/mock/src/new/repo"));
+        assert!(!allowed("/mock/secret"));
+        assert!(!allowed("/mock/src/../secret"));
+        assert!(!allowed("/mock/src/escape"));
+    }
+
+    #[test]
+    fn test_is_under_allowed_roots_boundaries() {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/src"), "src");
+        fs.with_directory(Path::new("/mock/src-other"), "src-other");
+        let roots = vec![PathBuf::from("/mock/src")];
+
+        // The root itself is allowed, a sibling sharing its prefix isn't
+        assert!(is_under_allowed_roots(&fs, Path::new("/mock/src"), &roots));
+        assert!(!is_under_allowed_roots(
+            &fs,
+            Path::new("/mock/src-other"),
+            &roots
+        ));
+        assert!(!is_under_allowed_roots(&fs, Path::new("/mock"), &roots));
+        // Without roots everything is allowed
+        assert!(is_under_allowed_roots(&fs, Path::new("/mock"), &[]));
+    }
+
+    #[test]
+    fn test_strip_verbatim_prefix() {
+        let strip = |path: &str| strip_verbatim_prefix(Path::new(path));
+        assert_eq!(
+            strip(r"\\?\C:\Users\me\src"),
+            PathBuf::from(r"C:\Users\me\src")
+        );
+        assert_eq!(
+            strip(r"\\?\UNC\server\share\src"),
+            PathBuf::from(r"\\server\share\src")
+        );
+        assert_eq!(
+            strip(r"\\?\Volume{1234}\src"),
+            PathBuf::from(r"\\?\Volume{1234}\src")
+        );
+        assert_eq!(strip(r"C:\Users\me"), PathBuf::from(r"C:\Users\me"));
+        assert_eq!(strip("/home/me/src"), PathBuf::from("/home/me/src"));
+    }
 }
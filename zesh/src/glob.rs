@@ -0,0 +1,99 @@
+use std::path::Path;
+
+use crate::fs::FsOperations;
+
+/// Whether `name` matches a shell-style `pattern`, where `*` matches any run
+/// of characters and `?` matches exactly one
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Backtrack to just after the last `*` when a match fails
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Whether `path` matches any of the `ignore` patterns from the config. A
+/// pattern without a `/`, like `node_modules` or `*.bak`, matches any
+/// component of the path. One with a `/` matches the whole path, after
+/// expanding a leading `~`, e.g. `~/src/archive/*`. On Windows `\` works
+/// as a separator too.
+pub fn is_ignored<F: FsOperations>(fs: &F, path: &Path, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| {
+        if pattern.contains(std::path::is_separator) {
+            let pattern = fs.expand_path(Path::new(pattern));
+            wildcard_match(&with_slashes(&pattern), &with_slashes(path))
+        } else {
+            path.components()
+                .any(|c| wildcard_match(pattern, &c.as_os_str().to_string_lossy()))
+        }
+    })
+}
+
+/// The path as a string, with Windows separators turned into `/` so that
+/// patterns can mix them with the ones `expand_path` adds
+fn with_slashes(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::tests::MockFs;
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*", "anything"));
+        assert!(wildcard_match("zesh-*", "zesh-git"));
+        assert!(wildcard_match("a*b*c", "axxbyyc"));
+        assert!(wildcard_match("?pi", "api"));
+        assert!(!wildcard_match("?pi", "pi"));
+        assert!(!wildcard_match("zesh-*", "zellij"));
+        assert!(!wildcard_match("a*c", "abcd"));
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        let fs = MockFs::new();
+        let patterns = vec!["node_modules".to_string(), "~/src/archive/*".to_string()];
+
+        assert!(is_ignored(
+            &fs,
+            Path::new("/mock/home/src/web/node_modules/react"),
+            &patterns
+        ));
+        assert!(is_ignored(
+            &fs,
+            Path::new("/mock/home/src/archive/old"),
+            &patterns
+        ));
+        assert!(!is_ignored(&fs, Path::new("/mock/home/src/web"), &patterns));
+        assert!(!is_ignored(&fs, Path::new("/mock/home/src/web"), &[]));
+    }
+}
@@ -0,0 +1,147 @@
+use std::ops::Range;
+
+/// Turns matched text bold red on terminals and in `fzf --ansi`
+const MATCH_START: &str = "\x1b[1;31m";
+const MATCH_END: &str = "\x1b[0m";
+
+/// A piece of a candidate, matched by the query or not
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span<'a> {
+    pub text: &'a str,
+    pub matched: bool,
+}
+
+/// Byte ranges of `text` matched by the whitespace separated keywords of
+/// `query`, in order and ignoring case, like zoxide matches them. Empty if
+/// any keyword doesn't match.
+pub fn match_ranges(query: &str, text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut from = 0;
+    for keyword in query.split_whitespace() {
+        let Some(range) = find_ignore_case(&text[from..], keyword) else {
+            return Vec::new();
+        };
+        let range = from + range.start..from + range.end;
+        from = range.end;
+        ranges.push(range);
+    }
+    ranges
+}
+
+/// Split `text` into the spans matched by `query` and the ones between them
+pub fn spans<'a>(query: &str, text: &'a str) -> Vec<Span<'a>> {
+    let mut spans = Vec::new();
+    let mut from = 0;
+    for range in match_ranges(query, text) {
+        if range.start > from {
+            spans.push(Span {
+                text: &text[from..range.start],
+                matched: false,
+            });
+        }
+        spans.push(Span {
+            text: &text[range.clone()],
+            matched: true,
+        });
+        from = range.end;
+    }
+    if from < text.len() {
+        spans.push(Span {
+            text: &text[from..],
+            matched: false,
+        });
+    }
+    spans
+}
+
+/// `text` with the parts matched by `query` styled with ANSI escapes
+pub fn highlight_ansi(query: &str, text: &str) -> String {
+    spans(query, text)
+        .into_iter()
+        .map(|span| match span.matched {
+            true => format!("{}{}{}", MATCH_START, span.text, MATCH_END),
+            false => span.text.to_string(),
+        })
+        .collect()
+}
+
+/// The first range of `haystack` equal to `needle` ignoring case
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<Range<usize>> {
+    haystack.char_indices().find_map(|(start, _)| {
+        let mut chars = haystack[start..].chars();
+        let mut end = start;
+        for n in needle.chars() {
+            let c = chars.next()?;
+            if c != n && !c.to_lowercase().eq(n.to_lowercase()) {
+                return None;
+            }
+            end += c.len_utf8();
+        }
+        Some(start..end)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_ranges() {
+        assert_eq!(match_ranges("api", "~/src/api"), vec![6..9]);
+        assert_eq!(match_ranges("API", "~/src/my-api"), vec![9..12]);
+        assert_eq!(match_ranges("Src", "/home/user/SRC/web"), vec![11..14]);
+        // Keywords match in order, each after the previous one
+        assert_eq!(match_ranges("src api", "/src/work/api"), vec![1..4, 10..13]);
+        assert_eq!(match_ranges("o o", "/foo"), vec![2..3, 3..4]);
+    }
+
+    #[test]
+    fn test_match_ranges_no_match() {
+        assert!(match_ranges("web", "~/src/api").is_empty());
+        // Out of order keywords don't match
+        assert!(match_ranges("api src", "/src/api").is_empty());
+        assert!(match_ranges("", "/src/api").is_empty());
+        assert!(match_ranges("api", "").is_empty());
+    }
+
+    #[test]
+    fn test_match_ranges_non_ascii() {
+        // Byte ranges stay on character boundaries
+        assert_eq!(match_ranges("caf", "/src/café-app"), vec![5..8]);
+        assert_eq!(match_ranges("ÉT", "/été"), vec![1..4]);
+    }
+
+    #[test]
+    fn test_spans() {
+        assert_eq!(
+            spans("api", "~/src/api-docs"),
+            vec![
+                Span {
+                    text: "~/src/",
+                    matched: false
+                },
+                Span {
+                    text: "api",
+                    matched: true
+                },
+                Span {
+                    text: "-docs",
+                    matched: false
+                },
+            ]
+        );
+        assert_eq!(
+            spans("web", "api"),
+            vec![Span {
+                text: "api",
+                matched: false
+            }]
+        );
+    }
+
+    #[test]
+    fn test_highlight_ansi() {
+        assert_eq!(highlight_ansi("API", "my-api"), "my-\x1b[1;31mapi\x1b[0m");
+        assert_eq!(highlight_ansi("web", "my-api"), "my-api");
+    }
+}
@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// How many connections are remembered by default
+pub const DEFAULT_HISTORY_SIZE: usize = 100;
+
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    #[error("Failed to access history {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Failed to parse history: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// A session zesh connected to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Session name
+    pub name: String,
+    /// Directory the session is for, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// When the connection was made, in seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+impl HistoryEntry {
+    /// How long ago the connection was made
+    pub fn age(&self) -> Duration {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        now.saturating_sub(Duration::from_secs(self.timestamp))
+    }
+}
+
+/// The most recent connections, oldest first, dropping the oldest once
+/// there are more than `capacity`.
+///
+/// Histories created with [`History::new`] only live in memory, while
+/// [`History::open`] persists every change to a JSON file.
+#[derive(Debug)]
+pub struct History {
+    path: Option<PathBuf>,
+    capacity: usize,
+    entries: RefCell<VecDeque<HistoryEntry>>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            path: None,
+            capacity: DEFAULT_HISTORY_SIZE,
+            entries: RefCell::new(VecDeque::new()),
+        }
+    }
+}
+
+impl History {
+    /// Create an empty in-memory history
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep at most `capacity` entries, dropping the oldest ones
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self.truncate();
+        self
+    }
+
+    /// Default location of the history file
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| dir.join("zesh").join("history.json"))
+    }
+
+    /// Open the history backed by the given file, which is created on the
+    /// first write if it doesn't exist yet
+    pub fn open(path: &Path) -> Result<Self, HistoryError> {
+        let entries = if path.exists() {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| HistoryError::Io(path.display().to_string(), e))?;
+            serde_json::from_str(&contents)?
+        } else {
+            VecDeque::new()
+        };
+
+        let history = Self {
+            path: Some(path.to_path_buf()),
+            entries: RefCell::new(entries),
+            ..Self::default()
+        };
+        history.truncate();
+        Ok(history)
+    }
+
+    /// Record a connection to a session, made now
+    pub fn record(&self, name: &str, path: Option<&Path>) -> Result<(), HistoryError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        self.push(HistoryEntry {
+            name: name.to_string(),
+            path: path.map(Path::to_path_buf),
+            timestamp,
+        })
+    }
+
+    /// Add an entry as the most recent one
+    pub fn push(&self, entry: HistoryEntry) -> Result<(), HistoryError> {
+        self.entries.borrow_mut().push_back(entry);
+        self.truncate();
+        self.save()
+    }
+
+    /// All entries, most recent first
+    pub fn recent(&self) -> Vec<HistoryEntry> {
+        self.entries.borrow().iter().rev().cloned().collect()
+    }
+
+    /// The `n`th most recent entry, counting from 1
+    pub fn get(&self, n: usize) -> Option<HistoryEntry> {
+        if n == 0 {
+            return None;
+        }
+        let entries = self.entries.borrow();
+        let index = entries.len().checked_sub(n)?;
+        entries.get(index).cloned()
+    }
+
+    /// Keep only the entries `keep` returns true for
+    pub fn retain(&self, mut keep: impl FnMut(&HistoryEntry) -> bool) -> Result<(), HistoryError> {
+        self.entries.borrow_mut().retain(|entry| keep(entry));
+        self.save()
+    }
+
+    fn truncate(&self) {
+        let mut entries = self.entries.borrow_mut();
+        while entries.len() > self.capacity {
+            entries.pop_front();
+        }
+    }
+
+    fn save(&self) -> Result<(), HistoryError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let io_err = |e| HistoryError::Io(path.display().to_string(), e);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_err)?;
+        }
+        let contents = serde_json::to_string_pretty(&*self.entries.borrow())?;
+        std::fs::write(path, contents).map_err(io_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(history: &History) -> Vec<String> {
+        history.recent().into_iter().map(|e| e.name).collect()
+    }
+
+    #[test]
+    fn test_record_most_recent_first() {
+        let history = History::new();
+        history.record("api", Some(Path::new("/src/api"))).unwrap();
+        history.record("web", None).unwrap();
+
+        assert_eq!(names(&history), vec!["web", "api"]);
+        assert_eq!(history.get(1).unwrap().name, "web");
+        assert_eq!(
+            history.get(2).unwrap().path,
+            Some(PathBuf::from("/src/api"))
+        );
+        assert_eq!(history.get(0), None);
+        assert_eq!(history.get(3), None);
+    }
+
+    #[test]
+    fn test_capacity_drops_oldest() {
+        let history = History::new().with_capacity(2);
+        for name in ["one", "two", "three"] {
+            history.record(name, None).unwrap();
+        }
+
+        assert_eq!(names(&history), vec!["three", "two"]);
+    }
+
+    #[test]
+    fn test_retain() {
+        let history = History::new();
+        history.record("api", Some(Path::new("/src/api"))).unwrap();
+        history.record("web", None).unwrap();
+        history.record("api", None).unwrap();
+
+        history.retain(|e| e.name != "api").unwrap();
+
+        assert_eq!(names(&history), vec!["web"]);
+    }
+
+    #[test]
+    fn test_persists_to_file() {
+        let dir = std::env::temp_dir().join(format!("zesh-history-test-{}", std::process::id()));
+        let path = dir.join("history.json");
+
+        let history = History::open(&path).unwrap();
+        history.record("api", Some(Path::new("/src/api"))).unwrap();
+        history.record("web", Some(Path::new("/src/web"))).unwrap();
+
+        let reopened = History::open(&path).unwrap();
+        assert_eq!(reopened.recent(), history.recent());
+
+        // A smaller capacity applies to what was already saved
+        let capped = History::open(&path).unwrap().with_capacity(1);
+        assert_eq!(names(&capped), vec!["web"]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
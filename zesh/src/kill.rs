@@ -0,0 +1,419 @@
+use std::path::Path;
+use thiserror::Error;
+use zellij_rs::{ZellijError, ZellijOperations};
+use zesh_git::{Git, RealGit};
+
+use crate::connection::session_name_for_path;
+use crate::fs::{FsError, FsOperations, RealFs};
+use crate::glob::wildcard_match;
+use crate::output::Printer;
+use crate::prompt::{Prompt, StdinPrompt};
+use crate::store::SessionStore;
+
+#[derive(Debug, Error)]
+pub enum KillError {
+    #[error("Zellij error: {0}")]
+    Zellij(#[from] ZellijError),
+
+    #[error("Filesystem error: {0}")]
+    Fs(#[from] FsError),
+
+    #[error("No session matches '{0}'")]
+    NoMatch(String),
+
+    #[error("'{0}' matches several sessions: {}", .1.join(", "))]
+    Ambiguous(String, Vec<String>),
+}
+
+/// Service for killing zellij sessions
+pub struct KillService<Z, P = StdinPrompt, F = RealFs, G = RealGit>
+where
+    Z: ZellijOperations,
+    P: Prompt,
+    F: FsOperations,
+    G: Git,
+{
+    zellij: Z,
+    prompt: P,
+    fs: F,
+    git: G,
+    store: SessionStore,
+    printer: Printer,
+}
+
+impl<Z: ZellijOperations> KillService<Z> {
+    /// Create a new kill service
+    pub fn new(zellij: Z) -> Self {
+        Self {
+            zellij,
+            prompt: StdinPrompt::default(),
+            fs: RealFs::new(),
+            git: RealGit::new(),
+            store: SessionStore::new(),
+            printer: Printer::default(),
+        }
+    }
+}
+
+impl<Z, P, F, G> KillService<Z, P, F, G>
+where
+    Z: ZellijOperations,
+    P: Prompt,
+    F: FsOperations,
+    G: Git,
+{
+    /// Forget the roots of killed sessions in the given store
+    pub fn with_store(mut self, store: SessionStore) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Print warnings with the given printer
+    pub fn with_printer(mut self, printer: Printer) -> Self {
+        self.printer = printer;
+        self
+    }
+
+    /// Use the given prompt to confirm kills
+    pub fn with_prompt<P2: Prompt>(self, prompt: P2) -> KillService<Z, P2, F, G> {
+        KillService {
+            zellij: self.zellij,
+            prompt,
+            fs: self.fs,
+            git: self.git,
+            store: self.store,
+            printer: self.printer,
+        }
+    }
+
+    /// Use the given filesystem to resolve directory paths
+    pub fn with_fs<F2: FsOperations>(self, fs: F2) -> KillService<Z, P, F2, G> {
+        KillService {
+            zellij: self.zellij,
+            prompt: self.prompt,
+            fs,
+            git: self.git,
+            store: self.store,
+            printer: self.printer,
+        }
+    }
+
+    /// Use the given git to name sessions for directory paths
+    pub fn with_git<G2: Git>(self, git: G2) -> KillService<Z, P, F, G2> {
+        KillService {
+            zellij: self.zellij,
+            prompt: self.prompt,
+            fs: self.fs,
+            git,
+            store: self.store,
+            printer: self.printer,
+        }
+    }
+
+    /// Find the session to kill: an exact name match, the session zesh would
+    /// create for a directory path, or the only session whose name contains
+    /// `pattern`
+    pub fn resolve(&self, pattern: &str) -> Result<String, KillError> {
+        let sessions = self.zellij.list_sessions_matching(pattern)?;
+
+        if sessions.iter().any(|s| s.name == pattern) {
+            return Ok(pattern.to_string());
+        }
+
+        if let Some(name) = self.session_name_for_dir(pattern)? {
+            let sessions = self.zellij.list_sessions()?;
+            return match sessions.into_iter().find(|s| s.name == name) {
+                Some(session) => Ok(session.name),
+                None => Err(KillError::NoMatch(format!(
+                    "{} (session '{}')",
+                    pattern, name
+                ))),
+            };
+        }
+
+        let mut names: Vec<String> = sessions.into_iter().map(|s| s.name).collect();
+        match names.len() {
+            0 => Err(KillError::NoMatch(pattern.to_string())),
+            1 => Ok(names.remove(0)),
+            _ => {
+                names.sort();
+                Err(KillError::Ambiguous(pattern.to_string(), names))
+            }
+        }
+    }
+
+    /// The session name for `pattern` if it's a directory. Session names
+    /// can't contain path separators, so only patterns that look like paths
+    /// are checked, which keeps `zesh kill api` from picking up an `api`
+    /// directory.
+    fn session_name_for_dir(&self, pattern: &str) -> Result<Option<String>, KillError> {
+        let looks_like_path = pattern.contains(std::path::is_separator)
+            || pattern.starts_with('~')
+            || matches!(pattern, "." | "..");
+        if !looks_like_path {
+            return Ok(None);
+        }
+
+        let path = self.fs.expand_path(Path::new(pattern));
+        let Ok((canon_path, _)) = self.fs.validate_dir_path(&path) else {
+            return Ok(None);
+        };
+        Ok(Some(session_name_for_path(
+            &self.fs,
+            &self.git,
+            &canon_path,
+        )?))
+    }
+
+    /// Kill the session matching `pattern` once the user confirms, returning
+    /// its name. Returns `None` if the user backed out.
+    pub fn kill(&self, pattern: &str) -> Result<Option<String>, KillError> {
+        let name = self.resolve(pattern)?;
+        if !self.prompt.confirm(&format!("Kill session '{}'?", name)) {
+            return Ok(None);
+        }
+
+        self.kill_session(&name)?;
+        Ok(Some(name))
+    }
+
+    /// Kill every session whose name matches the glob `pattern`, like
+    /// `tmp-*`, once the user confirms the list, returning their names.
+    /// The current session is left out unless `force` is set. Returns `None`
+    /// if the user backed out.
+    pub fn kill_matching(
+        &self,
+        pattern: &str,
+        force: bool,
+    ) -> Result<Option<Vec<String>>, KillError> {
+        let mut names: Vec<String> = self
+            .zellij
+            .list_sessions()?
+            .into_iter()
+            .filter(|s| force || !s.is_current)
+            .map(|s| s.name)
+            .filter(|name| wildcard_match(pattern, name))
+            .collect();
+        if names.is_empty() {
+            return Err(KillError::NoMatch(pattern.to_string()));
+        }
+        names.sort();
+
+        let question = format!("Kill {} sessions: {}?", names.len(), names.join(", "));
+        if !self.prompt.confirm(&question) {
+            return Ok(None);
+        }
+
+        for name in &names {
+            self.kill_session(name)?;
+        }
+        Ok(Some(names))
+    }
+
+    fn kill_session(&self, name: &str) -> Result<(), KillError> {
+        self.zellij.kill_session(name)?;
+
+        if let Err(e) = self.store.remove(name) {
+            self.printer
+                .warn(format_args!("failed to forget session root: {}", e));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::tests::MockFs;
+    use crate::prompt::tests::MockPrompt;
+    use std::collections::HashMap;
+    use zellij_rs::MockZellijClient;
+    use zesh_git::MockGit;
+
+    fn create_service(names: &[&str]) -> KillService<MockZellijClient, MockPrompt> {
+        let sessions = names.iter().map(|n| (n.to_string(), false)).collect();
+        KillService::new(MockZellijClient::with_sessions(sessions)).with_prompt(MockPrompt::yes())
+    }
+
+    #[test]
+    fn test_kill_unique_match() {
+        let service = create_service(&["api-server", "web"]);
+        service
+            .store
+            .record("api-server", Path::new("/src/api"))
+            .unwrap();
+
+        assert_eq!(service.kill("API").unwrap(), Some("api-server".to_string()));
+        assert_eq!(
+            *service.prompt.questions.borrow(),
+            vec!["Kill session 'api-server'?".to_string()]
+        );
+
+        let sessions = service.zellij.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "web");
+        assert_eq!(service.store.lookup("api-server"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_kill_reports_store_failures() {
+        // A store under a file can't be written
+        let store = SessionStore::open(Path::new("/dev/null/sessions.json")).unwrap();
+        let service = create_service(&["web"])
+            .with_store(store)
+            .with_printer(Printer::capturing(false));
+
+        assert_eq!(service.kill("web").unwrap(), Some("web".to_string()));
+
+        // The session is still killed
+        assert!(service.zellij.list_sessions().unwrap().is_empty());
+        let captured = service.printer.captured();
+        assert_eq!(captured.len(), 1);
+        assert!(captured[0].starts_with("Warning: failed to forget session root: "));
+    }
+
+    #[test]
+    fn test_kill_exact_name_beats_substring() {
+        let service = create_service(&["api", "api-2"]);
+
+        assert_eq!(service.kill("api").unwrap(), Some("api".to_string()));
+        assert_eq!(service.zellij.list_sessions().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_kill_declined() {
+        let sessions = HashMap::from([("api".to_string(), false)]);
+        let service = KillService::new(MockZellijClient::with_sessions(sessions))
+            .with_prompt(MockPrompt::no());
+        service.store.record("api", Path::new("/src/api")).unwrap();
+
+        assert_eq!(service.kill("api").unwrap(), None);
+
+        // Nothing was killed or forgotten
+        assert_eq!(service.zellij.list_sessions().unwrap().len(), 1);
+        assert_eq!(
+            service.store.lookup("api"),
+            Some(Path::new("/src/api").into())
+        );
+    }
+
+    #[test]
+    fn test_kill_ambiguous() {
+        let service = create_service(&["api-server", "api-client"]);
+
+        let result = service.kill("api");
+        match result {
+            Err(KillError::Ambiguous(pattern, names)) => {
+                assert_eq!(pattern, "api");
+                assert_eq!(names, vec!["api-client", "api-server"]);
+            }
+            other => panic!("Expected KillError::Ambiguous, got {:?}", other),
+        }
+        assert_eq!(service.zellij.list_sessions().unwrap().len(), 2);
+    }
+
+    /// MockGit puts every path in the repository at /mock/repo/top-level
+    fn repo_service(names: &[&str]) -> KillService<MockZellijClient, MockPrompt, MockFs, MockGit> {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/repo/top-level"), "top-level");
+        fs.with_directory(Path::new("/mock/repo/top-level/api"), "api");
+        fs.with_current_dir(Path::new("/mock/repo/top-level/api"));
+        create_service(names).with_fs(fs).with_git(MockGit::new())
+    }
+
+    #[test]
+    fn test_kill_by_directory_path() {
+        let service = repo_service(&["top-level", "top-level_api", "api"]);
+
+        assert_eq!(
+            service.kill("/mock/repo/top-level/api").unwrap(),
+            Some("top-level_api".to_string())
+        );
+        assert_eq!(service.kill("..").unwrap(), Some("top-level".to_string()));
+
+        let sessions = service.zellij.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "api");
+    }
+
+    #[test]
+    fn test_kill_by_directory_path_without_session() {
+        let service = repo_service(&["api"]);
+
+        assert!(matches!(service.kill("."), Err(KillError::NoMatch(_))));
+        // A plain word is still a name, not the api directory
+        assert_eq!(service.kill("api").unwrap(), Some("api".to_string()));
+    }
+
+    fn session_names(service: &KillService<MockZellijClient, MockPrompt>) -> Vec<String> {
+        let mut names: Vec<String> = service
+            .zellij
+            .list_sessions()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_kill_matching() {
+        let service = create_service(&["tmp-1", "tmp-2", "api", "my-tmp-3"]);
+        service.store.record("tmp-1", Path::new("/tmp/1")).unwrap();
+
+        assert_eq!(
+            service.kill_matching("tmp-*", false).unwrap(),
+            Some(vec!["tmp-1".to_string(), "tmp-2".to_string()])
+        );
+        assert_eq!(
+            *service.prompt.questions.borrow(),
+            vec!["Kill 2 sessions: tmp-1, tmp-2?".to_string()]
+        );
+        assert_eq!(session_names(&service), vec!["api", "my-tmp-3"]);
+        assert_eq!(service.store.lookup("tmp-1"), None);
+    }
+
+    #[test]
+    fn test_kill_matching_skips_current_session() {
+        let sessions = HashMap::from([("tmp-1".to_string(), true), ("tmp-2".to_string(), false)]);
+        let service = KillService::new(MockZellijClient::with_sessions(sessions))
+            .with_prompt(MockPrompt::yes());
+
+        assert_eq!(
+            service.kill_matching("tmp-?", false).unwrap(),
+            Some(vec!["tmp-2".to_string()])
+        );
+        assert_eq!(session_names(&service), vec!["tmp-1"]);
+
+        // Only the current session is left, which needs --force
+        assert!(matches!(
+            service.kill_matching("tmp-?", false),
+            Err(KillError::NoMatch(_))
+        ));
+        assert_eq!(
+            service.kill_matching("tmp-?", true).unwrap(),
+            Some(vec!["tmp-1".to_string()])
+        );
+        assert!(session_names(&service).is_empty());
+    }
+
+    #[test]
+    fn test_kill_matching_declined() {
+        let sessions = HashMap::from([("tmp-1".to_string(), false), ("tmp-2".to_string(), false)]);
+        let service = KillService::new(MockZellijClient::with_sessions(sessions))
+            .with_prompt(MockPrompt::no());
+
+        assert_eq!(service.kill_matching("tmp-*", false).unwrap(), None);
+        assert_eq!(session_names(&service), vec!["tmp-1", "tmp-2"]);
+    }
+
+    #[test]
+    fn test_kill_no_match() {
+        let service = KillService::new(MockZellijClient::with_sessions(HashMap::new()))
+            .with_prompt(MockPrompt::yes());
+
+        assert!(matches!(service.kill("api"), Err(KillError::NoMatch(_))));
+    }
+}
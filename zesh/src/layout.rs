@@ -0,0 +1,226 @@
+use std::path::Path;
+use thiserror::Error;
+
+use crate::fs::FsOperations;
+
+#[derive(Debug, Error)]
+pub enum LayoutError {
+    #[error("Layout file {0} does not exist")]
+    NotFound(String),
+
+    #[error("Layout file {0} is a directory")]
+    NotAFile(String),
+
+    #[error("Failed to read layout file {0}: {1}")]
+    Unreadable(String, String),
+
+    #[error("Layout file {0} is not a valid layout: {1}")]
+    Malformed(String, String),
+}
+
+/// Whether a `--layout` value is a path to a layout file, rather than the
+/// name of a layout like `compact`. Bare file names are left out, zellij
+/// also looks for those in its layout directory.
+pub fn is_layout_path(layout: &str) -> bool {
+    layout.contains(std::path::MAIN_SEPARATOR) || layout.contains('/') || layout.starts_with('~')
+}
+
+/// Check that a `--layout` value zellij will be handed points at a readable,
+/// plausibly valid layout, so mistakes fail before a session is created and
+/// with a better error than zellij's. Layout names are left to zellij.
+pub fn validate_layout<F: FsOperations>(fs: &F, layout: &str) -> Result<(), LayoutError> {
+    if !is_layout_path(layout) {
+        return Ok(());
+    }
+    validate_layout_file(fs, &fs.expand_path(Path::new(layout)))
+}
+
+/// Check that `path` is a readable KDL file with a `layout` node
+pub fn validate_layout_file<F: FsOperations>(fs: &F, path: &Path) -> Result<(), LayoutError> {
+    let display = path.display().to_string();
+    if !fs.exists(path) {
+        return Err(LayoutError::NotFound(display));
+    }
+    if fs.is_dir(path) {
+        return Err(LayoutError::NotAFile(display));
+    }
+    let kdl = fs
+        .read_to_string(path)
+        .map_err(|e| LayoutError::Unreadable(display.clone(), e.to_string()))?;
+    check_kdl(&kdl).map_err(|reason| LayoutError::Malformed(display, reason))
+}
+
+/// A lightweight well-formedness check of a KDL layout: strings and block
+/// comments are terminated, braces are balanced, and there's a top level
+/// `layout` node. Not a full parser, zellij has the final say.
+pub fn check_kdl(kdl: &str) -> Result<(), String> {
+    // Lines braces were opened on, innermost last
+    let mut open = Vec::new();
+    let mut has_layout = false;
+    let mut at_node_start = true;
+    let mut line = 1;
+    let mut chars = kdl.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => {
+                line += 1;
+                at_node_start = true;
+            }
+            '"' => {
+                let start = line;
+                loop {
+                    match chars.next() {
+                        Some('\\') => {
+                            if chars.next() == Some('\n') {
+                                line += 1;
+                            }
+                        }
+                        Some('"') => break,
+                        Some('\n') => line += 1,
+                        Some(_) => {}
+                        None => return Err(format!("unterminated string on line {}", start)),
+                    }
+                }
+                at_node_start = false;
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                let start = line;
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some('*') if chars.peek() == Some(&'/') => {
+                            chars.next();
+                            break;
+                        }
+                        Some('\n') => line += 1,
+                        Some(_) => {}
+                        None => return Err(format!("unterminated comment on line {}", start)),
+                    }
+                }
+            }
+            '{' => {
+                open.push(line);
+                at_node_start = true;
+            }
+            '}' => {
+                if open.pop().is_none() {
+                    return Err(format!("unmatched '}}' on line {}", line));
+                }
+                at_node_start = true;
+            }
+            ';' => at_node_start = true,
+            c if c.is_whitespace() => {}
+            c => {
+                if at_node_start && open.is_empty() {
+                    let mut name = String::from(c);
+                    while let Some(&c) = chars.peek() {
+                        if c.is_whitespace() || "{}();=\"".contains(c) {
+                            break;
+                        }
+                        name.push(c);
+                        chars.next();
+                    }
+                    has_layout |= name == "layout";
+                }
+                at_node_start = false;
+            }
+        }
+    }
+
+    if let Some(line) = open.pop() {
+        return Err(format!("unclosed '{{' on line {}", line));
+    }
+    if !has_layout {
+        return Err("no top level `layout` node".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::tests::MockFs;
+
+    const VALID: &str = r#"// A layout with a comment { and a brace
+layout {
+    cwd "/src/api"
+    tab name="code {}" focus=true {
+        pane command="nvim" {
+            args "-c" "echo \"hi\""
+        }
+        /* pane name="disabled" { */
+        pane
+    }
+}
+"#;
+
+    #[test]
+    fn test_valid_layout_file() {
+        let fs = MockFs::new();
+        fs.with_file_contents(Path::new("/layouts/dev.kdl"), VALID);
+
+        assert!(validate_layout_file(&fs, Path::new("/layouts/dev.kdl")).is_ok());
+        assert!(validate_layout(&fs, "/layouts/dev.kdl").is_ok());
+    }
+
+    #[test]
+    fn test_missing_layout_file() {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/layouts"), "layouts");
+
+        let result = validate_layout(&fs, "/layouts/missing.kdl");
+        assert!(
+            matches!(result, Err(LayoutError::NotFound(path)) if path == "/layouts/missing.kdl")
+        );
+        let result = validate_layout(&fs, "/layouts");
+        assert!(matches!(result, Err(LayoutError::NotAFile(_))));
+    }
+
+    #[test]
+    fn test_malformed_layout_file() {
+        let fs = MockFs::new();
+        fs.with_file_contents(Path::new("/layouts/bad.kdl"), "layout {\n    tab {\n}\n");
+
+        let result = validate_layout(&fs, "/layouts/bad.kdl");
+        assert!(
+            matches!(result, Err(LayoutError::Malformed(_, reason)) if reason == "unclosed '{' on line 1")
+        );
+    }
+
+    #[test]
+    fn test_check_kdl_errors() {
+        assert_eq!(
+            check_kdl("layout {\n}\n}\n"),
+            Err("unmatched '}' on line 3".to_string())
+        );
+        assert_eq!(
+            check_kdl("layout {\n    pane name=\"editor\n}\n"),
+            Err("unterminated string on line 2".to_string())
+        );
+        assert_eq!(
+            check_kdl("layout {\n/* pane\n"),
+            Err("unterminated comment on line 2".to_string())
+        );
+        assert_eq!(
+            check_kdl("tab {\n    pane\n}\n"),
+            Err("no top level `layout` node".to_string())
+        );
+        // A layout node nested somewhere else doesn't count
+        assert!(check_kdl("keybinds {\n    layout\n}\n").is_err());
+    }
+
+    #[test]
+    fn test_layout_names_are_not_checked() {
+        let fs = MockFs::new();
+
+        assert!(validate_layout(&fs, "compact").is_ok());
+        assert!(validate_layout(&fs, "dev.kdl").is_ok());
+        assert!(validate_layout(&fs, "./dev.kdl").is_err());
+    }
+}
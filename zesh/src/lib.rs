@@ -1,3 +1,28 @@
+pub mod adopt;
+pub mod backend;
+pub mod cache;
+pub mod clean;
+pub mod clipboard;
 pub mod clone;
+pub mod config;
 pub mod connection;
+pub mod cycle;
+pub mod detach;
+pub mod doctor;
+pub mod frecency;
 pub mod fs;
+pub mod glob;
+pub mod highlight;
+pub mod history;
+pub mod kill;
+pub mod layout;
+pub mod list;
+pub mod output;
+pub mod preview;
+pub mod prompt;
+pub mod rename;
+pub mod shell;
+pub mod store;
+pub mod tag;
+pub mod template;
+pub mod up;
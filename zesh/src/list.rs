@@ -0,0 +1,1268 @@
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+use zellij_rs::{ZellijError, ZellijOperations};
+use zox_rs::{ZoxideError, ZoxideOperations};
+
+use crate::fs::FsOperations;
+use crate::glob::is_ignored;
+use crate::store::SessionStore;
+
+/// Default number of zoxide entries to list
+pub const DEFAULT_LIST_LIMIT: usize = 20;
+
+#[derive(Debug, Error)]
+pub enum ListError {
+    #[error("Zellij error: {0}")]
+    Zellij(#[from] ZellijError),
+
+    #[error("Zoxide error: {0}")]
+    Zoxide(#[from] ZoxideError),
+
+    #[error("Invalid format: {0}")]
+    InvalidFormat(String),
+}
+
+/// A list entry for output (used for both display and JSON serialization)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ListEntry {
+    /// The source of this entry: "zellij" or "zoxide"
+    pub src: String,
+    /// Display name (session name or shortened path)
+    pub name: String,
+    /// Absolute path (only for zoxide entries)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Zoxide score (only for zoxide entries)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// Whether this is the currently attached session
+    #[serde(skip)]
+    pub current: bool,
+    /// Whether this is an exited session
+    #[serde(skip)]
+    pub exited: bool,
+    /// How long ago the session was created, if zellij said
+    #[serde(skip)]
+    pub created: Option<Duration>,
+    /// Number of tabs (only for the current session with `detailed`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tabs: Option<usize>,
+    /// Number of terminal panes, not counting plugins like the tab bar (only
+    /// for the current session with `detailed`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub panes: Option<usize>,
+}
+
+impl ListEntry {
+    /// The name followed by tab and pane counts when they're known, e.g.
+    /// `work (2 tabs, 5 panes)`
+    pub fn detailed_line(&self) -> String {
+        match (self.tabs, self.panes) {
+            (Some(tabs), Some(panes)) => format!(
+                "{} ({} {}, {} {})",
+                self.name,
+                tabs,
+                if tabs == 1 { "tab" } else { "tabs" },
+                panes,
+                if panes == 1 { "pane" } else { "panes" }
+            ),
+            _ => self.name.clone(),
+        }
+    }
+}
+
+/// How sessions are ordered in a listing. Zoxide entries always stay in
+/// score order after the sessions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortKey {
+    /// Alphabetically by name
+    #[default]
+    Name,
+    /// Newest first, sessions of unknown age last
+    Age,
+    /// The current session first, then by name
+    Current,
+}
+
+impl SortKey {
+    /// Compare two session entries, falling back to the name for ties
+    fn compare(self, a: &ListEntry, b: &ListEntry) -> Ordering {
+        let by_key = match self {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Age => match (a.created, b.created) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            },
+            SortKey::Current => b.current.cmp(&a.current),
+        };
+        by_key.then_with(|| a.name.cmp(&b.name))
+    }
+}
+
+/// Which entries to include in a listing
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    /// Show only zellij sessions
+    pub zellij_only: bool,
+    /// Show only zoxide results
+    pub zoxide_only: bool,
+    /// Hide the currently attached zellij session
+    pub hide_attached: bool,
+    /// Hide duplicate entries (by name)
+    pub hide_duplicates: bool,
+    /// Maximum number of zoxide entries, highest score first. `0` is
+    /// unlimited. Sessions are never truncated.
+    pub limit: usize,
+    /// Only show sessions and zoxide paths containing this, ignoring case
+    pub filter: Option<String>,
+    /// Also show zoxide entries for directories that no longer exist
+    pub include_missing: bool,
+    /// How to order sessions
+    pub sort: SortKey,
+    /// Count the tabs and panes of the current session. zellij can only
+    /// query the session it's running in, so other sessions get no counts.
+    pub detailed: bool,
+    /// Show only exited sessions that zellij can resurrect, and no zoxide
+    /// results
+    pub dead: bool,
+    /// Leave out zoxide directories matching these patterns, see
+    /// [`is_ignored`]
+    pub ignore: Vec<String>,
+    /// Show only the sessions with these names, and no zoxide results. Set
+    /// from [`SessionStore::tagged`](crate::store::SessionStore::tagged) by
+    /// `--tag`.
+    pub tagged: Option<BTreeSet<String>>,
+    /// Show only zoxide entries accessed this recently. zoxide doesn't
+    /// report access times, so this only keeps entries from zesh's own
+    /// [`FrecencyStore`](crate::frecency::FrecencyStore).
+    pub since: Option<Duration>,
+}
+
+/// Collect the entries to list, sessions first and zoxide results after
+pub fn build_entries<Z, X, F>(
+    zellij: &Z,
+    zoxide: &X,
+    fs: &F,
+    options: &ListOptions,
+) -> Result<Vec<ListEntry>, ListError>
+where
+    Z: ZellijOperations,
+    X: ZoxideOperations,
+    F: FsOperations,
+{
+    // If no source flags, show all sources. If any source flag is set,
+    // show only the requested sources.
+    let show_all = !options.zellij_only && !options.zoxide_only;
+    let show_zellij = show_all || options.zellij_only || options.dead;
+    let show_zoxide =
+        (show_all || options.zoxide_only) && !options.dead && options.tagged.is_none();
+
+    let mut entries: Vec<ListEntry> = Vec::new();
+
+    // Zellij sessions first (matching sesh's default order: sessions before zoxide)
+    if show_zellij {
+        let sessions = if options.dead {
+            let mut sessions = zellij.list_resurrectable()?;
+            if let Some(pattern) = &options.filter {
+                let pattern = pattern.to_lowercase();
+                sessions.retain(|s| s.name.to_lowercase().contains(&pattern));
+            }
+            sessions
+        } else {
+            match &options.filter {
+                Some(pattern) => zellij.list_sessions_matching(pattern)?,
+                None => zellij.list_sessions()?,
+            }
+        };
+        for session in &sessions {
+            if options.hide_attached && session.is_current {
+                continue;
+            }
+            if let Some(tagged) = &options.tagged
+                && !tagged.contains(&session.name)
+            {
+                continue;
+            }
+            let (tabs, panes) = if options.detailed && session.is_current {
+                let tabs = zellij.list_tabs()?;
+                let panes = tabs
+                    .iter()
+                    .flat_map(|tab| &tab.panes)
+                    .filter(|pane| !pane.is_plugin)
+                    .count();
+                (Some(tabs.len()), Some(panes))
+            } else {
+                (None, None)
+            };
+            entries.push(ListEntry {
+                src: "zellij".to_string(),
+                name: session.name.clone(),
+                path: None,
+                score: None,
+                current: session.is_current,
+                exited: session.is_exited,
+                created: session.created,
+                tabs,
+                panes,
+            });
+        }
+        entries.sort_by(|a, b| options.sort.compare(a, b));
+    }
+
+    // Zoxide entries
+    if show_zoxide {
+        let mut zoxide_entries = zoxide.list()?;
+        // zoxide keeps directories around after they're deleted
+        if !options.include_missing {
+            zoxide_entries.retain(|e| fs.exists(&e.path));
+        }
+        zoxide_entries.retain(|e| !is_ignored(fs, &e.path, &options.ignore));
+        if let Some(since) = options.since {
+            let now = SystemTime::now();
+            zoxide_entries.retain(|e| accessed_within(e.last_access, since, now));
+        }
+        if let Some(pattern) = &options.filter {
+            let pattern = pattern.to_lowercase();
+            zoxide_entries.retain(|e| e.path.to_string_lossy().to_lowercase().contains(&pattern));
+        }
+        zoxide_entries.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        if options.limit > 0 {
+            zoxide_entries.truncate(options.limit);
+        }
+        let home = fs.home_dir().ok();
+        for entry in &zoxide_entries {
+            entries.push(ListEntry {
+                src: "zoxide".to_string(),
+                name: shorten_home(&entry.path, home.as_deref()),
+                path: Some(entry.path.display().to_string()),
+                score: Some(entry.score),
+                current: false,
+                exited: false,
+                created: None,
+                tabs: None,
+                panes: None,
+            });
+        }
+    }
+
+    // remove entries with duplicate names
+    if options.hide_duplicates {
+        let mut seen = HashSet::new();
+        entries.retain(|e| seen.insert(e.name.clone()));
+    }
+
+    Ok(entries)
+}
+
+/// Sessions that share a repository prefix
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionGroup {
+    /// The shared prefix, or `None` for sessions that don't share one
+    pub name: Option<String>,
+    pub entries: Vec<ListEntry>,
+}
+
+/// Cluster session entries by repository, using the `<repo>_<subdir>`
+/// naming zesh gives sessions inside git repositories. A prefix only forms a
+/// group when at least two sessions share it; the rest go in a trailing
+/// ungrouped bucket. Non-session entries are ignored.
+pub fn group_sessions(entries: &[ListEntry]) -> Vec<SessionGroup> {
+    let mut by_prefix: BTreeMap<&str, Vec<ListEntry>> = BTreeMap::new();
+    for entry in entries.iter().filter(|e| e.src == "zellij") {
+        let prefix = entry.name.split('_').next().unwrap_or(&entry.name);
+        by_prefix.entry(prefix).or_default().push(entry.clone());
+    }
+
+    let mut groups = Vec::new();
+    let mut ungrouped = Vec::new();
+    for (prefix, mut members) in by_prefix {
+        if members.len() > 1 {
+            members.sort_by(|a, b| a.name.cmp(&b.name));
+            groups.push(SessionGroup {
+                name: Some(prefix.to_string()),
+                entries: members,
+            });
+        } else {
+            ungrouped.extend(members);
+        }
+    }
+
+    if !ungrouped.is_empty() {
+        ungrouped.sort_by(|a, b| a.name.cmp(&b.name));
+        groups.push(SessionGroup {
+            name: None,
+            entries: ungrouped,
+        });
+    }
+
+    groups
+}
+
+/// Shorten a path by replacing the home directory prefix with ~
+pub fn shorten_home(path: &Path, home: Option<&Path>) -> String {
+    if let Some(home) = home
+        && let Ok(suffix) = path.strip_prefix(home)
+    {
+        return format!("~/{}", suffix.display());
+    }
+    path.display().to_string()
+}
+
+/// Whether something last accessed at `last_access` was accessed within
+/// `since` before `now`. An unknown access time never is.
+pub fn accessed_within(last_access: Option<SystemTime>, since: Duration, now: SystemTime) -> bool {
+    last_access.is_some_and(|at| now.duration_since(at).unwrap_or_default() <= since)
+}
+
+/// Parse a human duration like `7d`, `12h` or `1w2d`, in seconds (`s`),
+/// minutes (`m`), hours (`h`), days (`d`) or weeks (`w`)
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration '{}', expected e.g. 7d or 12h", input);
+    let mut secs: u64 = 0;
+    let mut count = String::new();
+    for c in input.trim().chars() {
+        if c.is_ascii_digit() {
+            count.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3_600,
+            'd' => 86_400,
+            'w' => 7 * 86_400,
+            _ => return Err(invalid()),
+        };
+        let count: u64 = std::mem::take(&mut count).parse().map_err(|_| invalid())?;
+        secs = count
+            .checked_mul(unit)
+            .and_then(|s| secs.checked_add(s))
+            .ok_or_else(invalid)?;
+    }
+    // A trailing number without a unit, or no number at all
+    if !count.is_empty() || input.trim().is_empty() {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+/// Describe an age by its largest unit, e.g. `3d ago`
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    let (count, unit) = match secs {
+        0..60 => (secs, "s"),
+        60..3_600 => (secs / 60, "m"),
+        3_600..86_400 => (secs / 3_600, "h"),
+        _ => (secs / 86_400, "d"),
+    };
+    format!("{}{} ago", count, unit)
+}
+
+/// The columns of `zesh list --porcelain`, in order.
+///
+/// This is a contract for scripts: one line per entry, columns separated by
+/// a tab, and columns are only ever added at the end. `current` and
+/// `exited` are `true` or `false`, and `root` is the directory the session
+/// was created in, empty if zesh doesn't know it. Zoxide entries are never
+/// current or exited and have their absolute path as the root. Tabs and
+/// newlines inside a value are replaced by spaces.
+pub const PORCELAIN_COLUMNS: [&str; 4] = ["name", "current", "exited", "root"];
+
+/// An entry as a line of [`PORCELAIN_COLUMNS`], with session roots looked up
+/// in `store`
+pub fn porcelain_line(entry: &ListEntry, store: &SessionStore) -> String {
+    let root = match &entry.path {
+        Some(path) => path.clone(),
+        None => store
+            .lookup(&entry.name)
+            .map(|root| root.display().to_string())
+            .unwrap_or_default(),
+    };
+    [
+        entry.name.clone(),
+        entry.current.to_string(),
+        entry.exited.to_string(),
+        root,
+    ]
+    .iter()
+    .map(|value| value.replace(['\t', '\n'], " "))
+    .collect::<Vec<_>>()
+    .join("\t")
+}
+
+/// A placeholder that can be used in a list format string
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Name,
+    Current,
+    Exited,
+    Score,
+    Path,
+    Created,
+    Tabs,
+    Panes,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Field(Field),
+}
+
+/// A parsed list format string, such as `{name}\t{current}`.
+///
+/// Supported placeholders are `{name}`, `{current}`, `{exited}`,
+/// `{created}` (like `2h ago`, for sessions), `{tabs}` and `{panes}` (for the
+/// current session with `detailed`), and `{score}` and `{path}` for zoxide
+/// entries. Placeholders that don't apply to an entry are empty. Use
+/// `{{` and `}}` for literal braces, and `\t` or `\n` for tabs and newlines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parse a format string, rejecting unknown placeholders
+    pub fn parse(format: &str) -> Result<Self, ListError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = format.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '\\' if chars.peek() == Some(&'t') => {
+                    chars.next();
+                    literal.push('\t');
+                }
+                '\\' if chars.peek() == Some(&'n') => {
+                    chars.next();
+                    literal.push('\n');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => {
+                                return Err(ListError::InvalidFormat(format!(
+                                    "unclosed placeholder '{{{}'",
+                                    placeholder
+                                )));
+                            }
+                        }
+                    }
+                    let field = match placeholder.as_str() {
+                        "name" => Field::Name,
+                        "current" => Field::Current,
+                        "exited" => Field::Exited,
+                        "score" => Field::Score,
+                        "path" => Field::Path,
+                        "created" => Field::Created,
+                        "tabs" => Field::Tabs,
+                        "panes" => Field::Panes,
+                        _ => {
+                            return Err(ListError::InvalidFormat(format!(
+                                "unknown placeholder '{{{}}}'",
+                                placeholder
+                            )));
+                        }
+                    };
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Field(field));
+                }
+                '}' => {
+                    return Err(ListError::InvalidFormat(
+                        "unmatched '}', use '}}' for a literal brace".to_string(),
+                    ));
+                }
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Self { parts })
+    }
+
+    /// Render an entry with this template
+    pub fn render(&self, entry: &ListEntry) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(s) => out.push_str(s),
+                Part::Field(Field::Name) => out.push_str(&entry.name),
+                Part::Field(Field::Current) => out.push_str(&entry.current.to_string()),
+                Part::Field(Field::Exited) => out.push_str(&entry.exited.to_string()),
+                Part::Field(Field::Score) => {
+                    if let Some(score) = entry.score {
+                        out.push_str(&score.to_string());
+                    }
+                }
+                Part::Field(Field::Path) => {
+                    if let Some(path) = &entry.path {
+                        out.push_str(path);
+                    }
+                }
+                Part::Field(Field::Created) => {
+                    if let Some(created) = entry.created {
+                        out.push_str(&format_age(created));
+                    }
+                }
+                Part::Field(Field::Tabs) => {
+                    if let Some(tabs) = entry.tabs {
+                        out.push_str(&tabs.to_string());
+                    }
+                }
+                Part::Field(Field::Panes) => {
+                    if let Some(panes) = entry.panes {
+                        out.push_str(&panes.to_string());
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frecency::FrecencyStore;
+    use crate::fs::tests::MockFs;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use zellij_rs::options::ZellijOptions;
+    use zellij_rs::{MockZellijClient, Pane, Tab};
+    use zox_rs::MockZoxideClient;
+
+    /// A filesystem where every path zoxide knows about exists
+    fn existing(zoxide: &MockZoxideClient) -> MockFs {
+        let fs = MockFs::new();
+        for entry in zoxide.list().unwrap() {
+            fs.with_directory(&entry.path, "dir");
+        }
+        fs
+    }
+
+    fn session(name: &str, current: bool, exited: bool) -> ListEntry {
+        ListEntry {
+            src: "zellij".to_string(),
+            name: name.to_string(),
+            path: None,
+            score: None,
+            current,
+            exited,
+            created: None,
+            tabs: None,
+            panes: None,
+        }
+    }
+
+    fn zoxide_entry(path: &str, score: f64) -> ListEntry {
+        ListEntry {
+            src: "zoxide".to_string(),
+            name: path.to_string(),
+            path: Some(path.to_string()),
+            score: Some(score),
+            current: false,
+            exited: false,
+            created: None,
+            tabs: None,
+            panes: None,
+        }
+    }
+
+    #[test]
+    fn test_build_entries_sessions_before_zoxide() {
+        let zellij =
+            MockZellijClient::with_sessions(HashMap::from([("project".to_string(), true)]));
+        let zoxide =
+            MockZoxideClient::with_paths(HashMap::from([(PathBuf::from("/mock/dir"), 4.0)]));
+
+        let entries = build_entries(
+            &zellij,
+            &zoxide,
+            &existing(&zoxide),
+            &ListOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                session("project", true, false),
+                zoxide_entry("/mock/dir", 4.0)
+            ]
+        );
+
+        let options = ListOptions {
+            hide_attached: true,
+            ..ListOptions::default()
+        };
+        let entries = build_entries(&zellij, &zoxide, &existing(&zoxide), &options).unwrap();
+        assert_eq!(entries, vec![zoxide_entry("/mock/dir", 4.0)]);
+    }
+
+    #[test]
+    fn test_entries_json() {
+        let zellij =
+            MockZellijClient::with_sessions(HashMap::from([("project".to_string(), true)]));
+        let zoxide =
+            MockZoxideClient::with_paths(HashMap::from([(PathBuf::from("/mock/dir"), 4.0)]));
+
+        let entries = build_entries(
+            &zellij,
+            &zoxide,
+            &existing(&zoxide),
+            &ListOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&entries).unwrap(),
+            serde_json::json!([
+                { "src": "zellij", "name": "project" },
+                { "src": "zoxide", "name": "/mock/dir", "path": "/mock/dir", "score": 4.0 },
+            ])
+        );
+    }
+
+    /// beta is current, alpha is the oldest, gamma the newest and delta's
+    /// age is unknown
+    fn sort_zellij() -> MockZellijClient {
+        MockZellijClient::with_sessions(HashMap::from([
+            ("gamma".to_string(), false),
+            ("alpha".to_string(), false),
+            ("delta".to_string(), false),
+            ("beta".to_string(), true),
+        ]))
+        .with_created("alpha", Duration::from_secs(86_400))
+        .with_created("beta", Duration::from_secs(3_600))
+        .with_created("gamma", Duration::from_secs(60))
+    }
+
+    fn sorted_names(sort: SortKey) -> Vec<String> {
+        let zoxide =
+            MockZoxideClient::with_paths(HashMap::from([(PathBuf::from("/mock/dir"), 4.0)]));
+        let options = ListOptions {
+            sort,
+            ..ListOptions::default()
+        };
+        build_entries(&sort_zellij(), &zoxide, &existing(&zoxide), &options)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_sort_by_name() {
+        assert_eq!(
+            sorted_names(SortKey::Name),
+            vec!["alpha", "beta", "delta", "gamma", "/mock/dir"]
+        );
+        assert_eq!(SortKey::default(), SortKey::Name);
+    }
+
+    #[test]
+    fn test_sort_by_age() {
+        assert_eq!(
+            sorted_names(SortKey::Age),
+            vec!["gamma", "beta", "alpha", "delta", "/mock/dir"]
+        );
+    }
+
+    #[test]
+    fn test_sort_by_current() {
+        assert_eq!(
+            sorted_names(SortKey::Current),
+            vec!["beta", "alpha", "delta", "gamma", "/mock/dir"]
+        );
+    }
+
+    #[test]
+    fn test_build_entries_limits_zoxide_by_score() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([
+            ("one".to_string(), false),
+            ("two".to_string(), false),
+            ("three".to_string(), false),
+        ]));
+        let zoxide = MockZoxideClient::with_paths(HashMap::from([
+            (PathBuf::from("/mock/low"), 1.0),
+            (PathBuf::from("/mock/high"), 30.0),
+            (PathBuf::from("/mock/mid"), 10.0),
+        ]));
+
+        let options = ListOptions {
+            limit: 2,
+            ..ListOptions::default()
+        };
+        let entries = build_entries(&zellij, &zoxide, &existing(&zoxide), &options).unwrap();
+
+        // All sessions are kept, only zoxide entries are truncated
+        let sessions = entries.iter().filter(|e| e.src == "zellij").count();
+        assert_eq!(sessions, 3);
+        let zoxide_entries: Vec<&ListEntry> =
+            entries.iter().filter(|e| e.src == "zoxide").collect();
+        assert_eq!(
+            zoxide_entries,
+            vec![
+                &zoxide_entry("/mock/high", 30.0),
+                &zoxide_entry("/mock/mid", 10.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_entries_limit_zero_is_unlimited() {
+        let paths = (0..30)
+            .map(|i| (PathBuf::from(format!("/mock/{}", i)), i as f64))
+            .collect();
+        let zoxide = MockZoxideClient::with_paths(paths);
+
+        let options = ListOptions {
+            zoxide_only: true,
+            limit: 0,
+            ..ListOptions::default()
+        };
+        let entries = build_entries(
+            &MockZellijClient::new(),
+            &zoxide,
+            &existing(&zoxide),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 30);
+
+        let options = ListOptions {
+            zoxide_only: true,
+            limit: DEFAULT_LIST_LIMIT,
+            ..ListOptions::default()
+        };
+        let entries = build_entries(
+            &MockZellijClient::new(),
+            &zoxide,
+            &existing(&zoxide),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(entries.len(), DEFAULT_LIST_LIMIT);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("7d"), Ok(Duration::from_secs(7 * 86_400)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 3_600)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("45s"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("1w2d"), Ok(Duration::from_secs(9 * 86_400)));
+        assert_eq!(parse_duration(" 1d "), Ok(Duration::from_secs(86_400)));
+    }
+
+    #[test]
+    fn test_parse_duration_invalid() {
+        for input in ["", "7", "d", "7x", "7 d", "-1d", "99999999999999999999d"] {
+            assert!(
+                parse_duration(input).is_err(),
+                "expected '{}' to be rejected",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_accessed_within() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let day = Duration::from_secs(86_400);
+
+        assert!(accessed_within(Some(now - day), 2 * day, now));
+        assert!(accessed_within(Some(now - day), day, now));
+        assert!(!accessed_within(Some(now - 3 * day), 2 * day, now));
+        // Clock skew puts accesses in the future, they're recent
+        assert!(accessed_within(Some(now + day), day, now));
+        assert!(!accessed_within(None, 2 * day, now));
+    }
+
+    #[test]
+    fn test_build_entries_since_with_frecency_store() {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let dir = std::env::temp_dir().join(format!("zesh-list-since-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("frecency.json");
+        let records = serde_json::json!({
+            "/mock/recent": { "rank": 1.0, "last_access": now - 3_600 },
+            "/mock/old": { "rank": 5.0, "last_access": now - 10 * 86_400 },
+        });
+        std::fs::write(&path, records.to_string()).unwrap();
+        let frecency = FrecencyStore::open(&path).unwrap();
+
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/recent"), "recent");
+        fs.with_directory(Path::new("/mock/old"), "old");
+        let zellij = MockZellijClient::with_sessions(HashMap::from([("api".to_string(), false)]));
+
+        let options = ListOptions {
+            since: Some(parse_duration("7d").unwrap()),
+            ..ListOptions::default()
+        };
+        let entries = build_entries(&zellij, &frecency, &fs, &options).unwrap();
+
+        // Sessions aren't filtered
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["api", "/mock/recent"]);
+
+        let options = ListOptions {
+            since: Some(parse_duration("2w").unwrap()),
+            ..ListOptions::default()
+        };
+        let entries = build_entries(&zellij, &frecency, &fs, &options).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_entries_since_drops_unknown_access_times() {
+        let zoxide =
+            MockZoxideClient::with_paths(HashMap::from([(PathBuf::from("/mock/api"), 1.0)]));
+
+        let options = ListOptions {
+            zoxide_only: true,
+            since: Some(Duration::from_secs(86_400)),
+            ..ListOptions::default()
+        };
+        let entries = build_entries(
+            &MockZellijClient::new(),
+            &zoxide,
+            &existing(&zoxide),
+            &options,
+        )
+        .unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn test_build_entries_filter() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([
+            ("api".to_string(), false),
+            ("web".to_string(), false),
+        ]));
+        let zoxide = MockZoxideClient::with_paths(HashMap::from([
+            (PathBuf::from("/mock/API-docs"), 2.0),
+            (PathBuf::from("/mock/web"), 1.0),
+        ]));
+
+        let options = ListOptions {
+            filter: Some("api".to_string()),
+            ..ListOptions::default()
+        };
+        let entries = build_entries(&zellij, &zoxide, &existing(&zoxide), &options).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                session("api", false, false),
+                zoxide_entry("/mock/API-docs", 2.0)
+            ]
+        );
+
+        let options = ListOptions {
+            filter: Some("missing".to_string()),
+            ..ListOptions::default()
+        };
+        assert!(
+            build_entries(&zellij, &zoxide, &existing(&zoxide), &options)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_build_entries_hides_missing_directories() {
+        let zoxide = MockZoxideClient::with_paths(HashMap::from([
+            (PathBuf::from("/mock/kept"), 3.0),
+            (PathBuf::from("/mock/deleted"), 9.0),
+            (PathBuf::from("/mock/also-kept"), 1.0),
+        ]));
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/kept"), "kept");
+        fs.with_directory(Path::new("/mock/also-kept"), "also-kept");
+
+        let options = ListOptions {
+            zoxide_only: true,
+            limit: 1,
+            ..ListOptions::default()
+        };
+        let entries = build_entries(&MockZellijClient::new(), &zoxide, &fs, &options).unwrap();
+        // The missing directory doesn't use up the limit
+        assert_eq!(entries, vec![zoxide_entry("/mock/kept", 3.0)]);
+
+        let options = ListOptions {
+            zoxide_only: true,
+            include_missing: true,
+            ..ListOptions::default()
+        };
+        let entries = build_entries(&MockZellijClient::new(), &zoxide, &fs, &options).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                zoxide_entry("/mock/deleted", 9.0),
+                zoxide_entry("/mock/kept", 3.0),
+                zoxide_entry("/mock/also-kept", 1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_entries_skips_ignored_directories() {
+        let zoxide = MockZoxideClient::with_paths(HashMap::from([
+            (PathBuf::from("/mock/web"), 3.0),
+            (PathBuf::from("/mock/web/node_modules"), 9.0),
+            (PathBuf::from("/mock/archive/old"), 1.0),
+        ]));
+        let options = ListOptions {
+            zoxide_only: true,
+            ignore: vec!["node_modules".to_string(), "/mock/archive/*".to_string()],
+            ..ListOptions::default()
+        };
+
+        let entries = build_entries(
+            &MockZellijClient::new(),
+            &zoxide,
+            &existing(&zoxide),
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(entries, vec![zoxide_entry("/mock/web", 3.0)]);
+    }
+
+    #[test]
+    fn test_build_entries_shortens_home() {
+        let zoxide = MockZoxideClient::with_paths(HashMap::from([
+            (PathBuf::from("/mock/home/src/zesh"), 2.0),
+            (PathBuf::from("/srv/app"), 1.0),
+        ]));
+        let fs = existing(&zoxide);
+        let options = ListOptions {
+            zoxide_only: true,
+            ..ListOptions::default()
+        };
+
+        let names = |fs: &MockFs| -> Vec<String> {
+            build_entries(&MockZellijClient::new(), &zoxide, fs, &options)
+                .unwrap()
+                .into_iter()
+                .map(|e| e.name)
+                .collect()
+        };
+        assert_eq!(names(&fs), vec!["~/src/zesh", "/srv/app"]);
+
+        fs.with_home_dir(None);
+        assert_eq!(names(&fs), vec!["/mock/home/src/zesh", "/srv/app"]);
+    }
+
+    #[test]
+    fn test_group_sessions() {
+        let entries = vec![
+            session("foo_bar", false, false),
+            session("baz", false, false),
+            session("foo", true, false),
+            session("qux_feature", false, false),
+            zoxide_entry("/mock/foo", 1.0),
+        ];
+
+        let groups = group_sessions(&entries);
+        assert_eq!(
+            groups,
+            vec![
+                SessionGroup {
+                    name: Some("foo".to_string()),
+                    entries: vec![
+                        session("foo", true, false),
+                        session("foo_bar", false, false)
+                    ],
+                },
+                SessionGroup {
+                    name: None,
+                    entries: vec![
+                        session("baz", false, false),
+                        session("qux_feature", false, false)
+                    ],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_sessions_empty() {
+        assert!(group_sessions(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_template_session_fields() {
+        let template = Template::parse("{name}\\t{current}\\t{exited}").unwrap();
+
+        assert_eq!(
+            template.render(&session("work", true, false)),
+            "work\ttrue\tfalse"
+        );
+        assert_eq!(
+            template.render(&session("old", false, true)),
+            "old\tfalse\ttrue"
+        );
+    }
+
+    #[test]
+    fn test_template_created() {
+        let template = Template::parse("{name} {created}").unwrap();
+
+        let entry = ListEntry {
+            created: Some(Duration::from_secs(2 * 3_600 + 5 * 60)),
+            ..session("work", false, false)
+        };
+        assert_eq!(template.render(&entry), "work 2h ago");
+        // Unknown ages, like zoxide entries have, render empty
+        assert_eq!(template.render(&session("old", false, false)), "old ");
+    }
+
+    #[test]
+    fn test_build_entries_session_created() {
+        let zellij =
+            MockZellijClient::with_sessions(HashMap::from([("project".to_string(), false)]))
+                .with_created("project", Duration::from_secs(90));
+        let zoxide = MockZoxideClient::new();
+
+        let entries = build_entries(
+            &zellij,
+            &zoxide,
+            &existing(&zoxide),
+            &ListOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(entries[0].created, Some(Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn test_build_entries_dead() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([
+            ("work".to_string(), true),
+            ("old-api".to_string(), false),
+            ("old-web".to_string(), false),
+        ]))
+        .with_exited(&["old-api", "old-web"]);
+        let zoxide = MockZoxideClient::new();
+        zoxide.add("/mock/old-project").unwrap();
+
+        let options = ListOptions {
+            dead: true,
+            filter: Some("API".to_string()),
+            ..ListOptions::default()
+        };
+        let entries = build_entries(&zellij, &zoxide, &existing(&zoxide), &options).unwrap();
+
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["old-api"]);
+        assert!(entries[0].exited);
+    }
+
+    #[test]
+    fn test_build_entries_tagged() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([
+            ("api".to_string(), true),
+            ("web".to_string(), false),
+            ("blog".to_string(), false),
+        ]));
+        let zoxide = MockZoxideClient::new();
+        zoxide.add("/mock/api").unwrap();
+        let store = SessionStore::new();
+        store.tag("api", "work").unwrap();
+        store.tag("web", "work").unwrap();
+        store.tag("blog", "oss").unwrap();
+
+        let options = ListOptions {
+            tagged: Some(store.tagged("work")),
+            ..ListOptions::default()
+        };
+        let entries = build_entries(&zellij, &zoxide, &existing(&zoxide), &options).unwrap();
+
+        // Tags are for sessions, so zoxide results are left out
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["api", "web"]);
+    }
+
+    fn detailed_zellij() -> MockZellijClient {
+        let pane = |id, is_plugin| Pane {
+            id,
+            name: None,
+            is_focused: false,
+            is_plugin,
+            cwd: None,
+        };
+        let zellij = MockZellijClient::with_tabs(vec![
+            Tab {
+                position: 0,
+                name: Some("editor".to_string()),
+                is_active: true,
+                panes: vec![pane(0, true), pane(1, false), pane(2, false)],
+            },
+            Tab {
+                position: 1,
+                name: Some("shell".to_string()),
+                is_active: false,
+                panes: vec![pane(0, true), pane(1, false)],
+            },
+        ]);
+        zellij
+            .new_session_detached("other", &ZellijOptions::default())
+            .unwrap();
+        zellij
+            .new_session("work", &ZellijOptions::default())
+            .unwrap();
+        zellij
+    }
+
+    #[test]
+    fn test_build_entries_detailed() {
+        let zoxide = MockZoxideClient::new();
+        let options = ListOptions {
+            detailed: true,
+            ..ListOptions::default()
+        };
+        let entries =
+            build_entries(&detailed_zellij(), &zoxide, &existing(&zoxide), &options).unwrap();
+
+        let lines: Vec<String> = entries.iter().map(|e| e.detailed_line()).collect();
+        // Only the current session can be queried, and plugins don't count
+        assert_eq!(lines, vec!["other", "work (2 tabs, 3 panes)"]);
+
+        let template = Template::parse("{name} {tabs}/{panes}").unwrap();
+        assert_eq!(template.render(&entries[1]), "work 2/3");
+        assert_eq!(template.render(&entries[0]), "other /");
+    }
+
+    #[test]
+    fn test_build_entries_not_detailed() {
+        let zoxide = MockZoxideClient::new();
+        let entries = build_entries(
+            &detailed_zellij(),
+            &zoxide,
+            &existing(&zoxide),
+            &ListOptions::default(),
+        )
+        .unwrap();
+
+        assert!(
+            entries
+                .iter()
+                .all(|e| e.tabs.is_none() && e.panes.is_none())
+        );
+        assert_eq!(entries[1].detailed_line(), "work");
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(0)), "0s ago");
+        assert_eq!(format_age(Duration::from_secs(59)), "59s ago");
+        assert_eq!(format_age(Duration::from_secs(60)), "1m ago");
+        assert_eq!(format_age(Duration::from_secs(7_199)), "1h ago");
+        assert_eq!(format_age(Duration::from_secs(3 * 86_400)), "3d ago");
+    }
+
+    #[test]
+    fn test_template_zoxide_fields() {
+        let template = Template::parse("{score} {path}").unwrap();
+
+        assert_eq!(
+            template.render(&zoxide_entry("/src/zesh", 12.5)),
+            "12.5 /src/zesh"
+        );
+        // Zoxide-only fields are blank for sessions
+        assert_eq!(template.render(&session("work", false, false)), " ");
+    }
+
+    #[test]
+    fn test_template_literal_braces() {
+        let template = Template::parse("{{{name}}}").unwrap();
+        assert_eq!(template.render(&session("work", false, false)), "{work}");
+    }
+
+    #[test]
+    fn test_porcelain_lines() {
+        let store = SessionStore::new();
+        store.record("work", Path::new("/src/work")).unwrap();
+        store.record("old", Path::new("/src/old")).unwrap();
+
+        let lines: Vec<String> = [
+            session("work", true, false),
+            session("old", false, true),
+            session("remote", false, false),
+            zoxide_entry("/src/zesh", 12.5),
+        ]
+        .iter()
+        .map(|entry| porcelain_line(entry, &store))
+        .collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "work\ttrue\tfalse\t/src/work",
+                "old\tfalse\ttrue\t/src/old",
+                "remote\tfalse\tfalse\t",
+                "/src/zesh\tfalse\tfalse\t/src/zesh",
+            ]
+        );
+        assert!(
+            lines
+                .iter()
+                .all(|line| line.split('\t').count() == PORCELAIN_COLUMNS.len())
+        );
+    }
+
+    #[test]
+    fn test_porcelain_line_escapes_separators() {
+        let store = SessionStore::new();
+        store.record("odd", Path::new("/src/a\tb\nc")).unwrap();
+
+        assert_eq!(
+            porcelain_line(&session("odd", false, false), &store),
+            "odd\tfalse\tfalse\t/src/a b c"
+        );
+    }
+
+    #[test]
+    fn test_template_invalid() {
+        for format in ["{nope}", "{name", "name}", "{}"] {
+            assert!(
+                matches!(Template::parse(format), Err(ListError::InvalidFormat(_))),
+                "expected '{}' to be rejected",
+                format
+            );
+        }
+    }
+}
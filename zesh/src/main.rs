@@ -1,14 +1,42 @@
 use clap::{Parser, Subcommand};
-use serde::Serialize;
-use std::collections::HashSet;
 use std::env;
-use std::fs;
+use std::fmt::Display;
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
 use zellij_rs::options::ZellijOptions;
-use zesh::clone::CloneService;
-use zesh::connection::ConnectService;
-use zesh::fs::RealFs;
+use zesh::adopt::adopt_session;
+use zesh::clean::CleanService;
+use zesh::clipboard::SystemClipboard;
+use zesh::clone::{CloneOptions, CloneService, parse_clone_path, repo_url_from_clipboard};
+use zesh::config::{Config, DEFAULT_TIMEOUT_SECS, DirectoryBackend, PROFILE_ENV};
+use zesh::connection::{
+    ConnectError, ConnectOptions, ConnectService, Prefer, ResolveKind, Target, read_target,
+    session_name_for_path,
+};
+use zesh::cycle::{Direction, switch_adjacent};
+use zesh::detach::detach_current_session;
+use zesh::doctor::DoctorService;
+use zesh::frecency::{Directories, FrecencyStore};
+use zesh::fs::{FsOperations, RealFs};
+use zesh::highlight::highlight_ansi;
+use zesh::history::History;
+use zesh::kill::KillService;
+use zesh::list::{
+    DEFAULT_LIST_LIMIT, ListEntry, ListOptions, SortKey, Template, build_entries, format_age,
+    group_sessions, parse_duration, porcelain_line,
+};
+use zesh::output::{OutputFormat, Printer, error_json};
+use zesh::preview::{DEFAULT_MAX_ENTRIES, TreeOptions, render_tree};
+use zesh::prompt::StdinPrompt;
+use zesh::rename::rename_current_session;
+use zesh::shell::cd_command;
+use zesh::store::SessionStore;
+use zesh::tag::tag_session;
+use zesh::template::LayoutTemplate;
+use zesh::up::{FreezeService, Manifest, UpService};
 use zesh_git::RealGit;
+use zesh_proc::{Exec, SystemRunner};
 
 use zellij_rs::{ZellijClient, ZellijOperations};
 use zox_rs::{ZoxideClient, ZoxideOperations};
@@ -18,6 +46,31 @@ use zox_rs::{ZoxideClient, ZoxideOperations};
 #[clap(version, about, long_about = None)]
 #[clap(propagate_version = true)]
 struct Cli {
+    /// Seconds to wait for zellij, zoxide and git commands (0 disables)
+    #[clap(long, global = true)]
+    timeout: Option<u64>,
+
+    /// Only print command results and errors
+    #[clap(short, long, global = true)]
+    quiet: bool,
+
+    /// Answer yes to confirmation prompts
+    #[clap(short, long, global = true)]
+    yes: bool,
+
+    /// Print every zellij, zoxide, git and hook command to stderr before running it
+    #[clap(long, global = true)]
+    print_cmd: bool,
+
+    /// Write results and errors as text or JSON, for editor integrations
+    #[clap(long, global = true, value_enum, default_value_t)]
+    output: OutputFormat,
+
+    /// Merge this `[profiles.<name>]` table of the config over the rest of
+    /// it. Defaults to $ZESH_PROFILE.
+    #[clap(long, global = true)]
+    profile: Option<String>,
+
     #[clap(subcommand)]
     command: Commands,
 }
@@ -27,6 +80,9 @@ enum Commands {
     /// List sessions
     #[clap(visible_alias = "l")]
     List {
+        /// Only show sessions and zoxide paths containing this (ignoring case)
+        filter: Option<String>,
+
         /// Show only zellij sessions
         #[clap(short = 'Z', long)]
         zesh: bool,
@@ -46,14 +102,119 @@ enum Commands {
         /// Hide duplicate entries (by name)
         #[clap(short = 'd', long)]
         hide_duplicates: bool,
+
+        /// Custom output format, e.g. '{name}\t{current}'. Placeholders:
+        /// {name}, {current}, {exited}, {created} for sessions, {tabs}/{panes}
+        /// with --detailed, and {score}/{path} for zoxide entries
+        #[clap(long, conflicts_with = "json")]
+        format: Option<String>,
+
+        /// Maximum number of zoxide results to show, highest score first
+        /// (0 for unlimited). Sessions are never truncated.
+        #[clap(long, default_value_t = DEFAULT_LIST_LIMIT)]
+        limit: usize,
+
+        /// Include zoxide directories that no longer exist
+        #[clap(long)]
+        include_missing: bool,
+
+        /// How to order sessions
+        #[clap(long, value_enum, default_value_t)]
+        sort: SortKey,
+
+        /// Group sessions from the same repository under a header
+        #[clap(short, long, conflicts_with = "json")]
+        group: bool,
+
+        /// Show tab and pane counts. zellij can only query the session it's
+        /// running in, so only the current session gets counts.
+        #[clap(long)]
+        detailed: bool,
+
+        /// Show only exited sessions that zellij can resurrect
+        #[clap(long, conflicts_with = "zoxide")]
+        dead: bool,
+
+        /// Show only sessions tagged with this (see `zesh tag`)
+        #[clap(long, conflicts_with = "zoxide")]
+        tag: Option<String>,
+
+        /// Highlight what the filter matched with ANSI colors, for
+        /// `fzf --ansi`
+        #[clap(long, requires = "filter", conflicts_with = "json")]
+        highlight: bool,
+
+        /// Stable tab separated `name current exited root` lines for
+        /// scripts, which won't change with the human readable output
+        #[clap(long, conflicts_with_all = ["json", "format", "group", "highlight"])]
+        porcelain: bool,
+
+        /// Show only directories accessed within this long, like 7d or 12h.
+        /// Needs `directory_backend = "zesh"`, zoxide doesn't report when
+        /// directories were accessed.
+        #[clap(long, value_name = "DURATION", value_parser = parse_duration, conflicts_with = "zesh")]
+        since: Option<Duration>,
     },
 
     /// Connect to the given session. Zellij arguments are only passed if
     /// creating a new session
     #[clap(visible_alias = "cn")]
     Connect {
-        /// Session name or part of path
-        name: String,
+        /// Session name, path, or one or more zoxide keywords
+        #[clap(required_unless_present_any = ["stdin", "recent", "from_history"])]
+        name: Vec<String>,
+
+        /// Read the session name or path from stdin instead
+        #[clap(long, conflicts_with = "name")]
+        stdin: bool,
+
+        /// Connect to the highest scored zoxide directory instead
+        #[clap(long, conflicts_with_all = ["name", "stdin"])]
+        recent: bool,
+
+        /// Reconnect to the nth most recent connection from `zesh history`
+        #[clap(long, value_name = "N", conflicts_with_all = ["name", "stdin", "recent"])]
+        from_history: Option<usize>,
+
+        /// Create a new session with a suffixed name (e.g. `project-2`)
+        /// instead of attaching if the name is already taken
+        #[clap(long)]
+        force_new: bool,
+
+        /// Open the directory in a new tab of the current session instead
+        /// of a session of its own
+        #[clap(long, conflicts_with = "force_new")]
+        as_tab: bool,
+
+        /// Create the session in the background if it doesn't exist, but
+        /// stay in the current one
+        #[clap(long, conflicts_with = "as_tab")]
+        keep_current: bool,
+
+        /// Lay out a new session with a built-in template
+        #[clap(long, value_enum, conflicts_with = "new_session_with_layout")]
+        template: Option<LayoutTemplate>,
+
+        /// Try the name as this first, e.g. `dir` to reach a directory
+        /// named like an unrelated session
+        #[clap(long, value_enum, default_value_t)]
+        prefer: Prefer,
+
+        /// Kill a session this creates when detaching from it with
+        /// `zesh detach --kill`
+        #[clap(long, conflicts_with = "as_tab")]
+        kill_on_detach: bool,
+
+        /// Resolve relative paths like `.` against the focused pane's
+        /// directory instead of the session root
+        #[clap(long)]
+        pane_cwd: bool,
+
+        /// Create a new session in this directory instead of the one the
+        /// name resolves to, keeping the name as the session name
+        #[clap(long, value_name = "PATH", conflicts_with_all = ["recent", "from_history"])]
+        cwd: Option<PathBuf>,
+
         #[clap(flatten)]
         zellij_options: ZellijOptions,
     },
@@ -61,219 +222,1030 @@ enum Commands {
     /// Clone a git repo and connect to it as a session
     #[clap(visible_alias = "cl")]
     Clone {
-        /// Repository URL to clone
-        repo_url: String,
+        /// Repository URL to clone, or shorthand like `user/repo` or `gh:user/repo`
+        #[clap(required_unless_present = "clipboard")]
+        repo_url: Option<String>,
+
+        /// Clone the repository URL on the clipboard
+        #[clap(long, conflicts_with = "repo_url")]
+        clipboard: bool,
 
         /// Optional custom session name (defaults to repo name)
         #[clap(long)]
         name: Option<String>,
 
-        /// Optional path to clone into (defaults to current directory)
-        #[clap(long)]
+        /// Optional path to clone into (defaults to clone_root from the config,
+        /// or the current directory)
+        #[clap(long, value_parser = parse_clone_path)]
         path: Option<PathBuf>,
+
+        /// Clone even if the target is inside an existing git repository
+        #[clap(long)]
+        force: bool,
+
+        /// Don't use the repository's .zesh-layout.kdl as the session layout
+        #[clap(long)]
+        no_repo_layout: bool,
+
+        /// Create the session in the background instead of attaching to it
+        #[clap(long)]
+        no_attach: bool,
+
+        /// Retry this many times if the clone fails on a network error,
+        /// waiting longer after each attempt
+        #[clap(long, default_value_t = 0)]
+        retries: u32,
+
+        /// Open the clone in a new tab of the current session instead of a
+        /// session of its own
+        #[clap(long, conflicts_with = "no_attach")]
+        as_tab: bool,
+
+        /// Make a bare clone in <repo>.git and open a worktree of the default
+        /// branch in <repo>/<branch>
+        #[clap(long)]
+        bare: bool,
+
+        /// Lay out the new session with a built-in template instead of the
+        /// repository's layout
+        #[clap(long, value_enum, conflicts_with = "new_session_with_layout")]
+        template: Option<LayoutTemplate>,
+
+        /// Open the clone in your editor (`editor` from the config, or
+        /// $EDITOR) in a new pane of the new session
+        #[clap(long)]
+        edit: bool,
+
         /// Zellij options
         #[clap(flatten)]
         zellij_options: ZellijOptions,
     },
 
+    /// Kill a session by name, by part of its name if only one matches, or
+    /// by the directory it was created for
+    #[clap(visible_alias = "k")]
+    Kill {
+        /// Session name, part of it, or a directory path like `.`
+        #[clap(required_unless_present = "pattern")]
+        name: Option<String>,
+
+        /// Kill every session whose name matches a glob like 'tmp-*'
+        #[clap(long, conflicts_with = "name")]
+        pattern: Option<String>,
+
+        /// Include the current session in the sessions --pattern kills
+        #[clap(long, requires = "pattern")]
+        force: bool,
+    },
+
+    /// Rename the current session
+    Rename {
+        /// New session name
+        name: String,
+    },
+
+    /// Tag a session, e.g. `work` or `oss`, to list it with `zesh list --tag`
+    Tag {
+        /// Session name
+        session: String,
+
+        /// Tag to add
+        tag: String,
+    },
+
+    /// List stale state, like recorded sessions that no longer exist and
+    /// history entries for deleted directories, and offer to remove it
+    Clean,
+
+    /// Record the root of a session created outside zesh, so `zesh root`
+    /// and reconnecting from history work for it
+    Adopt {
+        /// Session name
+        session: String,
+
+        /// Directory the session is for
+        #[clap(default_value = ".")]
+        path: PathBuf,
+    },
+
+    /// Detach from the current session
+    Detach {
+        /// Kill the session after detaching if it was created with
+        /// `--kill-on-detach`
+        #[clap(long)]
+        kill: bool,
+    },
+
+    /// Switch to the next session in alphabetical order, wrapping around
+    Next,
+
+    /// Switch to the previous session in alphabetical order, wrapping around
+    Prev,
+
+    /// Create the sessions listed in a manifest in the background
+    Up {
+        /// Path to the manifest file
+        #[clap(default_value = "zesh.toml")]
+        manifest: PathBuf,
+
+        /// Create a session for every directory matching a glob instead,
+        /// e.g. `~/src/*`. Only the last component can have wildcards.
+        #[clap(long, conflicts_with = "manifest")]
+        glob: Option<PathBuf>,
+    },
+
+    /// Write the running sessions to a manifest that `zesh up` can replay
+    Freeze {
+        /// Path to write the manifest to
+        #[clap(default_value = "zesh.toml")]
+        manifest: PathBuf,
+    },
+
+    /// Print the session name zesh would give a directory, without
+    /// creating anything
+    Name {
+        /// Directory path
+        #[clap(default_value = ".")]
+        path: String,
+    },
+
     /// Show the root directory from the active session
     #[clap(visible_alias = "r")]
-    Root,
+    Root {
+        /// Print a `cd` command to the root for the shell to eval, e.g.
+        /// `eval "$(zesh root --cd)"`
+        #[clap(long)]
+        cd: bool,
+    },
+
+    /// Show the most recent connections, newest first
+    History {
+        /// Maximum number of connections to show (0 for unlimited)
+        #[clap(long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Manage panes in the current session
+    Pane {
+        #[clap(subcommand)]
+        command: PaneCommands,
+    },
+
+    /// Check that zellij, zoxide, git, the config file and the state dir
+    /// are usable
+    Doctor,
 
     /// Preview a session or directory
     #[clap(visible_alias = "p")]
     Preview {
         /// Session name or directory path
         target: String,
+
+        /// Only preview a session with this name
+        #[clap(long, conflicts_with = "dir")]
+        session: bool,
+
+        /// Only preview a directory, even if a session has the same name
+        #[clap(long)]
+        dir: bool,
+
+        /// Levels of a directory's tree to show
+        #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+        depth: u64,
+
+        /// Stop after this many entries (0 shows all of them)
+        #[clap(long, default_value_t = DEFAULT_MAX_ENTRIES)]
+        max_entries: usize,
+    },
+
+    /// Tell whether a directory has a session and whether we're in it, i.e.
+    /// whether `zesh cn .` would create or attach
+    Status {
+        /// Directory to check
+        #[clap(default_value = ".")]
+        dir: String,
+    },
+
+    /// Print the versions of zesh, zellij, zoxide and git
+    Version {
+        /// Print JSON, with `null` for tools that weren't found
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Print the directory connecting to a name would open, without
+    /// connecting, e.g. `cd "$(zesh query api)"`
+    #[clap(visible_alias = "q")]
+    Query {
+        /// Session name, path, or zoxide query
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum PaneCommands {
+    /// Move the focused pane to another tab
+    Move {
+        /// Tab number, starting at 1 like `zellij action go-to-tab`
+        #[clap(value_parser = clap::value_parser!(u32).range(1..))]
+        tab: u32,
+    },
+
+    /// Float the focused pane, or embed it again if it's floating
+    Float {
+        /// Show or hide all floating panes instead
+        #[clap(long)]
+        all: bool,
     },
 }
 
-/// A list entry for output (used for both display and JSON serialization)
-#[derive(Debug, Serialize)]
-struct ListEntry {
-    /// The source of this entry: "zellij" or "zoxide"
-    src: String,
-    /// Display name (session name or shortened path)
-    name: String,
-    /// Absolute path (only for zoxide entries)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    path: Option<String>,
-    /// Zoxide score (only for zoxide entries)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    score: Option<f64>,
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            match cli.output {
+                OutputFormat::Text => eprintln!("Error: {}", e),
+                OutputFormat::Json => eprintln!("{}", error_json(e.as_ref())),
+            }
+            ExitCode::FAILURE
+        }
+    }
 }
 
-/// Shorten a path by replacing the home directory prefix with ~
-fn shorten_home(path: &Path) -> String {
-    if let Some(home) = dirs::home_dir()
-        && let Ok(suffix) = path.strip_prefix(&home)
-    {
-        return format!("~/{}", suffix.display());
+/// Print context for a failure. In JSON mode only the final error is
+/// written, so stderr stays parseable.
+fn report(output: OutputFormat, message: impl Display) {
+    if output == OutputFormat::Text {
+        eprintln!("{}", message);
     }
-    path.display().to_string()
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
-    let zellij = ZellijClient::new();
-    let zoxide = ZoxideClient::new();
-    let fs = RealFs::new();
-    let git = RealGit;
+fn run(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    // Runs before loading the config so a broken config gets reported
+    // instead of failing here
+    match cli.command {
+        Commands::Doctor => return doctor(cli),
+        Commands::Version { json } => return version(cli, json),
+        _ => {}
+    }
+
+    // A .zesh.toml in the current project is merged over the user's config
+    let profile = cli
+        .profile
+        .clone()
+        .or_else(|| env::var(PROFILE_ENV).ok())
+        .filter(|profile| !profile.is_empty());
+    let printer = Printer::new(cli.quiet).with_format(cli.output);
+    // The current directory can be gone, e.g. after `git worktree remove`.
+    // Moving to the fallback keeps later lookups of it from failing too.
+    let (cwd, fallback) = RealFs::new().current_dir_or_home()?;
+    if let Some(e) = fallback {
+        printer.warn(format_args!(
+            "can't read the current directory ({}), using {} instead",
+            e,
+            cwd.display()
+        ));
+    }
+    let _ = env::set_current_dir(&cwd);
+    let mut config = Config::load_for_path(
+        &cwd,
+        profile.as_deref(),
+        &RealFs::new(),
+        &RealGit::new().with_print_cmd(cli.print_cmd),
+    )?;
+    if let Some(timeout) = cli.timeout {
+        config.timeout = timeout;
+    }
+    if config.editor.is_none() {
+        config.editor = env::var("EDITOR").ok();
+    }
 
-    let connect_service = ConnectService::new(zellij, zoxide, fs, git);
+    let zellij = ZellijClient::new()
+        .with_timeout(config.timeout())
+        .with_print_cmd(cli.print_cmd);
+    let fs = RealFs::new();
+    let zoxide = match config.directory_backend {
+        DirectoryBackend::Zoxide => Directories::Zoxide(
+            ZoxideClient::new()
+                .with_timeout(config.timeout())
+                .with_print_cmd(cli.print_cmd),
+        ),
+        DirectoryBackend::Zesh => Directories::Zesh(
+            match config
+                .frecency_file
+                .clone()
+                .or_else(FrecencyStore::default_path)
+            {
+                Some(path) => FrecencyStore::open(&fs.expand_path(&path))?,
+                None => FrecencyStore::new(),
+            },
+        ),
+    };
+    let git = RealGit::new()
+        .with_timeout(config.timeout())
+        .with_print_cmd(cli.print_cmd);
+    let store = match SessionStore::default_path() {
+        Some(path) => SessionStore::open(&path)?,
+        None => SessionStore::new(),
+    };
+    let history = match History::default_path() {
+        Some(path) => History::open(&path)?,
+        None => History::new(),
+    };
 
     match &cli.command {
         Commands::List {
+            filter,
             zesh,
             zoxide: zoxide_only,
             json,
             hide_attached,
             hide_duplicates,
+            format,
+            limit,
+            include_missing,
+            sort,
+            group,
+            detailed,
+            dead,
+            tag,
+            highlight,
+            porcelain,
+            since,
         } => {
-            // If no source flags, show all sources. If any source flag is set,
-            // show only the requested sources.
-            let show_all = !zesh && !zoxide_only;
-            let show_zellij = show_all || *zesh;
-            let show_zoxide = show_all || *zoxide_only;
-
-            let mut entries: Vec<ListEntry> = Vec::new();
-
-            // Zellij sessions first (matching sesh's default order: sessions before zoxide)
-            if show_zellij {
-                let sessions = zellij.list_sessions()?;
-                for session in &sessions {
-                    if *hide_attached && session.is_current {
-                        continue;
-                    }
-                    entries.push(ListEntry {
-                        src: "zellij".to_string(),
-                        name: session.name.clone(),
-                        path: None,
-                        score: None,
-                    });
-                }
-            }
-
-            // Zoxide entries
-            if show_zoxide {
-                let zoxide_entries = zoxide.list()?;
-                for entry in &zoxide_entries {
-                    entries.push(ListEntry {
-                        src: "zoxide".to_string(),
-                        name: shorten_home(&entry.path),
-                        path: Some(entry.path.display().to_string()),
-                        score: Some(entry.score),
-                    });
-                }
+            // Parse the format first so a bad template fails before any work
+            let template = format.as_deref().map(Template::parse).transpose()?;
+            if since.is_some() && matches!(zoxide, Directories::Zoxide(_)) {
+                printer.warn(
+                    "zoxide doesn't report access times, so --since lists no directories. Set directory_backend = \"zesh\" to use it.",
+                );
             }
 
-            // remove entries with duplicate names
-            if *hide_duplicates {
-                let mut seen = HashSet::new();
-                entries.retain(|e| seen.insert(e.name.clone()));
-            }
+            let options = ListOptions {
+                zellij_only: *zesh,
+                zoxide_only: *zoxide_only,
+                hide_attached: *hide_attached,
+                hide_duplicates: *hide_duplicates,
+                limit: *limit,
+                filter: filter.clone(),
+                include_missing: *include_missing,
+                sort: *sort,
+                detailed: *detailed,
+                dead: *dead,
+                ignore: config.ignore.clone(),
+                tagged: tag.as_deref().map(|tag| store.tagged(tag)),
+                since: *since,
+            };
+            let entries = build_entries(&zellij, &zoxide, &fs, &options)?;
 
             // Output
-            if *json {
+            if *json || cli.output == OutputFormat::Json {
                 let json_str = serde_json::to_string(&entries)?;
                 println!("{}", json_str);
-            } else {
+            } else if *porcelain {
                 for entry in &entries {
-                    println!("{}", entry.name);
+                    println!("{}", porcelain_line(entry, &store));
+                }
+            } else {
+                let line = |entry: &ListEntry| {
+                    let line = match &template {
+                        Some(template) => template.render(entry),
+                        // Counts are only filled in with --detailed
+                        None => entry.detailed_line(),
+                    };
+                    match filter {
+                        Some(filter) if *highlight => highlight_ansi(filter, &line),
+                        _ => line,
+                    }
+                };
+
+                if *group {
+                    for group in group_sessions(&entries) {
+                        println!("{}:", group.name.as_deref().unwrap_or("ungrouped"));
+                        for entry in &group.entries {
+                            println!("  {}", line(entry));
+                        }
+                    }
+                    for entry in entries.iter().filter(|e| e.src != "zellij") {
+                        println!("{}", line(entry));
+                    }
+                } else {
+                    for entry in &entries {
+                        println!("{}", line(entry));
+                    }
                 }
             }
         }
         Commands::Connect {
             name,
+            stdin: _,
+            recent,
+            from_history,
+            force_new,
+            as_tab,
+            keep_current,
+            template,
+            prefer,
+            kill_on_detach,
+            pane_cwd,
+            cwd,
             zellij_options,
         } => {
-            if let Err(e) = connect_service.connect(name, zellij_options) {
-                eprintln!("Error connecting to '{}': {}", name, e);
-                return Err(e.into());
+            let connect_service = ConnectService::new(zellij, zoxide, fs, git)
+                .with_config(config)
+                .with_store(store)
+                .with_history(history)
+                .with_printer(printer)
+                .with_options(ConnectOptions {
+                    force_new: *force_new,
+                    target: if *as_tab {
+                        Target::Tab
+                    } else {
+                        Target::Session
+                    },
+                    keep_current: *keep_current,
+                    template: *template,
+                    prefer: *prefer,
+                    kill_on_detach: *kill_on_detach,
+                    pane_cwd: *pane_cwd,
+                });
+
+            if *recent {
+                match connect_service.connect_recent(zellij_options) {
+                    Ok(_) => {}
+                    Err(e @ ConnectError::AlreadyAttached(_)) => {
+                        report(cli.output, format_args!("{}", e))
+                    }
+                    Err(e) => {
+                        report(
+                            cli.output,
+                            format_args!("Error connecting to a recent directory: {}", e),
+                        );
+                        return Err(e.into());
+                    }
+                }
+                return Ok(());
+            }
+
+            if let Some(n) = from_history {
+                match connect_service.connect_from_history(*n, zellij_options) {
+                    Ok(_) => {}
+                    Err(e @ ConnectError::AlreadyAttached(_)) => {
+                        report(cli.output, format_args!("{}", e))
+                    }
+                    Err(e) => {
+                        report(
+                            cli.output,
+                            format_args!("Error reconnecting to history entry {}: {}", n, e),
+                        );
+                        return Err(e.into());
+                    }
+                }
+                return Ok(());
+            }
+
+            // clap only allows a missing name when --stdin is set
+            let keywords = if name.is_empty() {
+                match read_target(std::io::stdin().lock()) {
+                    Ok(name) => vec![name],
+                    Err(e) => {
+                        report(cli.output, format_args!("Error reading from stdin: {}", e));
+                        return Err(e.into());
+                    }
+                }
+            } else {
+                name.clone()
+            };
+            let keywords: Vec<&str> = keywords.iter().map(String::as_str).collect();
+            let name = keywords.join(" ");
+            let result = match (cwd, keywords.as_slice()) {
+                (Some(cwd), [name]) => connect_service.connect_with_cwd(name, cwd, zellij_options),
+                (Some(_), _) => Err(ConnectError::InvalidInput(
+                    "--cwd needs a single session name".to_string(),
+                )),
+                (None, _) => connect_service.connect_keywords(&keywords, zellij_options),
+            };
+            match result {
+                Ok(_) => {}
+                // Nothing to do, so this isn't a failure
+                Err(e @ ConnectError::AlreadyAttached(_)) => {
+                    report(cli.output, format_args!("{}", e))
+                }
+                Err(e) => {
+                    report(
+                        cli.output,
+                        format_args!("Error connecting to '{}': {}", name, e),
+                    );
+                    return Err(e.into());
+                }
             }
         }
 
         Commands::Clone {
             repo_url,
+            clipboard: _,
             name,
             path,
+            force,
+            no_repo_layout,
+            no_attach,
+            retries,
+            as_tab,
+            bare,
+            template,
+            edit,
             zellij_options,
         } => {
-            let clone_service = CloneService::new(zellij, zoxide, fs, git);
-            if let Err(e) =
-                clone_service.clone_repo(repo_url, name.as_deref(), path.as_ref(), zellij_options)
-            {
-                eprintln!("Clone failed: {}", e);
+            // clap only allows a missing URL when --clipboard is set
+            let repo_url = match repo_url {
+                Some(repo_url) => repo_url.clone(),
+                None => match repo_url_from_clipboard(&SystemClipboard, &config.default_host) {
+                    Ok(repo_url) => repo_url,
+                    Err(e) => {
+                        report(cli.output, format_args!("Clone failed: {}", e));
+                        return Err(e.into());
+                    }
+                },
+            };
+            // Like clones, hooks such as `npm install` take as long as they
+            // take, so they aren't timed out
+            let runner = SystemRunner::new(Exec {
+                timeout: None,
+                print_cmd: cli.print_cmd,
+            });
+            let clone_service = CloneService::new(zellij, zoxide, fs, git)
+                .with_runner(runner)
+                .with_config(config)
+                .with_store(store)
+                .with_printer(printer);
+            let options = CloneOptions {
+                name: name.clone(),
+                path: path.clone(),
+                force: *force,
+                no_repo_layout: *no_repo_layout,
+                no_attach: *no_attach,
+                retries: *retries,
+                bare: *bare,
+                template: *template,
+                edit: *edit,
+                target: if *as_tab {
+                    Target::Tab
+                } else {
+                    Target::Session
+                },
+            };
+            if let Err(e) = clone_service.clone_repo(&repo_url, &options, zellij_options) {
+                report(cli.output, format_args!("Clone failed: {}", e));
                 return Err(e.into());
             }
         }
 
-        Commands::Root => {
-            // Get current session
-            let sessions = zellij.list_sessions()?;
-            let current = sessions.iter().find(|s| s.is_current);
+        Commands::Kill {
+            name,
+            pattern,
+            force,
+        } => {
+            let kill_service = KillService::new(zellij)
+                .with_store(store)
+                .with_fs(fs)
+                .with_git(git)
+                .with_printer(Printer::new(cli.quiet).with_format(cli.output))
+                .with_prompt(StdinPrompt::new(cli.yes));
 
-            if let Some(_session) = current {
-                // Assume session name is the directory name
-                println!("{}", env::current_dir()?.display());
-            } else {
-                println!("No active zellij session");
+            if let Some(pattern) = pattern {
+                match kill_service.kill_matching(pattern, *force) {
+                    Ok(Some(killed)) => {
+                        for name in killed {
+                            printer.info(format_args!("Killed session '{}'", name));
+                        }
+                    }
+                    Ok(None) => report(cli.output, format_args!("Not killing '{}'", pattern)),
+                    Err(e) => {
+                        report(
+                            cli.output,
+                            format_args!("Failed to kill '{}': {}", pattern, e),
+                        );
+                        return Err(e.into());
+                    }
+                }
+                return Ok(());
+            }
+
+            // clap requires a name without --pattern
+            let name = name.as_deref().unwrap_or_default();
+            match kill_service.kill(name) {
+                Ok(Some(killed)) => printer.info(format_args!("Killed session '{}'", killed)),
+                Ok(None) => report(cli.output, format_args!("Not killing '{}'", name)),
+                Err(e) => {
+                    report(cli.output, format_args!("Failed to kill '{}': {}", name, e));
+                    return Err(e.into());
+                }
             }
         }
 
-        Commands::Preview { target } => {
-            // Check if it's a session
-            let sessions = zellij.list_sessions()?;
-            let session_match = sessions.iter().find(|s| s.name == *target);
+        Commands::Tag { session, tag } => match tag_session(&zellij, &store, session, tag) {
+            Ok(true) => printer.info(format_args!("Tagged '{}' with '{}'", session, tag)),
+            Ok(false) => printer.info(format_args!("'{}' is already tagged '{}'", session, tag)),
+            Err(e) => {
+                report(
+                    cli.output,
+                    format_args!("Failed to tag '{}': {}", session, e),
+                );
+                return Err(e.into());
+            }
+        },
 
-            if let Some(session) = session_match {
-                println!("Session: {}", session.name);
-                return Ok(());
+        Commands::Clean => {
+            let mut clean_service = CleanService::new(zellij)
+                .with_store(store)
+                .with_history(history)
+                .with_fs(fs)
+                .with_prompt(StdinPrompt::new(cli.yes));
+            if let Directories::Zesh(frecency) = zoxide {
+                clean_service = clean_service.with_frecency(frecency);
             }
 
-            // If not a session, check if it's a directory
-            let path = PathBuf::from(target);
-            if path.is_dir() {
-                println!("Directory: {}", path.display());
-                preview_directory(&path)?;
+            let stale = clean_service.find_stale()?;
+            if stale.is_empty() {
+                printer.info("Nothing to clean");
                 return Ok(());
             }
+            for line in stale.lines() {
+                printer.info(line);
+            }
+            match clean_service.clean(&stale) {
+                Ok(true) => printer.info(format_args!("Removed {} stale entries", stale.len())),
+                Ok(false) => report(cli.output, "Not cleaning"),
+                Err(e) => {
+                    report(cli.output, format_args!("Failed to clean: {}", e));
+                    return Err(e.into());
+                }
+            }
+        }
+
+        Commands::Adopt { session, path } => {
+            match adopt_session(&zellij, &fs, &store, session, path) {
+                Ok(root) => printer.info(format_args!(
+                    "Adopted '{}' with root {}",
+                    session,
+                    root.display()
+                )),
+                Err(e) => {
+                    report(
+                        cli.output,
+                        format_args!("Failed to adopt '{}': {}", session, e),
+                    );
+                    return Err(e.into());
+                }
+            }
+        }
+
+        Commands::Detach { kill } => match detach_current_session(&zellij, &store, *kill) {
+            Ok(detached) if detached.killed => printer.info(format_args!(
+                "Detached from and killed '{}'",
+                detached.session
+            )),
+            Ok(detached) => printer.info(format_args!("Detached from '{}'", detached.session)),
+            Err(e) => {
+                report(cli.output, format_args!("Failed to detach: {}", e));
+                return Err(e.into());
+            }
+        },
+
+        Commands::Rename { name } => match rename_current_session(&zellij, &store, name) {
+            Ok(old_name) => {
+                printer.info(format_args!("Renamed session '{}' to '{}'", old_name, name))
+            }
+            Err(e) => {
+                report(cli.output, format_args!("Failed to rename session: {}", e));
+                return Err(e.into());
+            }
+        },
+
+        Commands::Next | Commands::Prev => {
+            let direction = match &cli.command {
+                Commands::Next => Direction::Next,
+                _ => Direction::Previous,
+            };
+            match switch_adjacent(&zellij, direction) {
+                Ok(Some(name)) => printer.info(format_args!("Switched to session '{}'", name)),
+                Ok(None) => printer.info(format_args!("No other session to switch to")),
+                Err(e) => {
+                    report(cli.output, format_args!("Failed to switch session: {}", e));
+                    return Err(e.into());
+                }
+            }
+        }
+
+        Commands::Up {
+            manifest: path,
+            glob,
+        } => {
+            let (manifest, base_dir) = match glob {
+                Some(pattern) => (
+                    Manifest::from_glob(&fs, pattern, &config.ignore)?,
+                    env::current_dir()?,
+                ),
+                None => (
+                    Manifest::load(path)?,
+                    // Relative roots and layouts are relative to the manifest
+                    // itself. Keep it absolute since up changes directory for
+                    // every session.
+                    env::current_dir()?.join(path.parent().unwrap_or(Path::new(""))),
+                ),
+            };
+            let up_service = UpService::new(zellij, fs)
+                .with_store(store)
+                .with_printer(printer);
+            if let Err(e) = up_service.up(&manifest, &base_dir) {
+                report(
+                    cli.output,
+                    format_args!("Failed to bring up sessions: {}", e),
+                );
+                return Err(e.into());
+            }
+        }
 
-            // If not a directory, try zoxide query
-            let entries = zoxide.query(&[target])?;
+        Commands::Freeze { manifest: path } => {
+            let freeze_service = FreezeService::new(zellij, fs, git)
+                .with_store(store)
+                .with_printer(Printer::new(cli.quiet).with_format(cli.output));
+            let manifest = freeze_service.freeze()?;
+            manifest.save(path)?;
+            printer.info(format_args!(
+                "Wrote {} sessions to {}",
+                manifest.sessions.len(),
+                path.display()
+            ));
+        }
 
-            if entries.is_empty() {
-                println!("No matching sessions or directories found for '{}'", target);
+        Commands::Name { path } => {
+            let (dir, _) = fs
+                .validate_dir_path_with(&fs.expand_path(Path::new(path)), config.follow_symlinks)?;
+            let name = session_name_for_path(&fs, &git, &dir)?;
+            if cli.output == OutputFormat::Json {
+                println!(
+                    "{}",
+                    serde_json::json!({ "name": name, "path": dir.display().to_string() })
+                );
+            } else {
+                println!("{}", name);
+            }
+        }
+
+        Commands::Root { cd } => {
+            let Some(session) = zellij.current_session()? else {
+                if *cd {
+                    // Nothing on stdout, so an eval of the output is a no-op
+                    report(cli.output, format_args!("No active zellij session"));
+                    return Err("not inside a zellij session".into());
+                }
+                match cli.output {
+                    OutputFormat::Text => println!("No active zellij session"),
+                    OutputFormat::Json => println!("{}", serde_json::json!({ "root": null })),
+                }
                 return Ok(());
+            };
+
+            // Sessions zesh didn't create have no recorded root, so fall
+            // back to the current directory
+            let root = match store.lookup(&session) {
+                Some(root) => root,
+                None => env::current_dir()?,
+            };
+
+            if *cd {
+                println!("{}", cd_command(&root));
+            } else if cli.output == OutputFormat::Json {
+                println!(
+                    "{}",
+                    serde_json::json!({ "session": session, "root": root.display().to_string() })
+                );
+            } else {
+                println!("{}", root.display());
+            }
+        }
+
+        Commands::History { limit } => {
+            let mut entries = history.recent();
+            if *limit > 0 {
+                entries.truncate(*limit);
+            }
+
+            if cli.output == OutputFormat::Json {
+                println!("{}", serde_json::to_string(&entries)?);
+            } else {
+                for (i, entry) in entries.iter().enumerate() {
+                    let path = entry
+                        .path
+                        .as_ref()
+                        .map_or("-".to_string(), |p| p.display().to_string());
+                    println!(
+                        "{}\t{}\t{}\t{}",
+                        i + 1,
+                        entry.name,
+                        path,
+                        format_age(entry.age())
+                    );
+                }
             }
+        }
+
+        Commands::Pane {
+            command: PaneCommands::Move { tab },
+        } => {
+            if let Err(e) = zellij.move_pane_to_tab(tab - 1) {
+                report(
+                    cli.output,
+                    format_args!("Failed to move pane to tab {}: {}", tab, e),
+                );
+                return Err(e.into());
+            }
+        }
 
-            // Use the highest scored match
-            let best_match = &entries[0];
-            println!("Directory (via zoxide): {}", best_match.path.display());
-            preview_directory(&best_match.path)?;
+        Commands::Pane {
+            command: PaneCommands::Float { all },
+        } => {
+            let result = if *all {
+                zellij.toggle_floating_panes()
+            } else {
+                zellij.toggle_pane_embed_or_floating()
+            };
+            if let Err(e) = result {
+                report(
+                    cli.output,
+                    format_args!("Failed to toggle floating panes: {}", e),
+                );
+                return Err(e.into());
+            }
+        }
+
+        // Handled before the config is loaded
+        Commands::Doctor | Commands::Version { .. } => unreachable!(),
+
+        Commands::Preview {
+            target,
+            session,
+            dir,
+            depth,
+            max_entries,
+        } => {
+            let tree = TreeOptions {
+                depth: *depth as usize,
+                max_entries: *max_entries,
+                ignore: config.ignore.clone(),
+            };
+            // Preview what connecting would open
+            let connect_service = ConnectService::new(zellij, zoxide, fs, git)
+                .with_config(config)
+                .with_store(store);
+
+            let kind = if *session {
+                connect_service.resolve_session(target)?
+            } else if *dir {
+                connect_service.resolve_directory(target)?
+            } else {
+                connect_service.resolve(target)?
+            };
+            match kind {
+                ResolveKind::ExistingSession => println!("Session: {}", target),
+                ResolveKind::Directory(path) => {
+                    match connect_service.zoxide().score_for(&path)? {
+                        Some(score) => {
+                            println!("Directory: {} (zoxide score {})", path.display(), score)
+                        }
+                        None => println!("Directory: {}", path.display()),
+                    }
+                    preview_directory(&path, &tree)?;
+                }
+                ResolveKind::ZoxideMatch(path) => {
+                    match connect_service.zoxide().score_for(&path)? {
+                        Some(score) => println!(
+                            "Directory (via zoxide): {} (zoxide score {})",
+                            path.display(),
+                            score
+                        ),
+                        None => println!("Directory (via zoxide): {}", path.display()),
+                    }
+                    preview_directory(&path, &tree)?;
+                }
+                ResolveKind::NoMatch => {
+                    println!("No matching sessions or directories found for '{}'", target)
+                }
+            }
+        }
+
+        Commands::Query { name } => {
+            let connect_service = ConnectService::new(zellij, zoxide, fs, git)
+                .with_config(config)
+                .with_store(store);
+
+            match connect_service.query(name) {
+                Ok(path) => println!("{}", path.display()),
+                Err(e) => {
+                    report(cli.output, format_args!("{}", e));
+                    return Err(e.into());
+                }
+            }
+        }
+
+        Commands::Status { dir } => {
+            let connect_service = ConnectService::new(zellij, zoxide, fs, git).with_config(config);
+
+            let status = match connect_service.status(dir) {
+                Ok(status) => status,
+                Err(e) => {
+                    report(cli.output, format_args!("Failed to check {}: {}", dir, e));
+                    return Err(e.into());
+                }
+            };
+            match cli.output {
+                OutputFormat::Json => println!(
+                    "{}",
+                    serde_json::json!({
+                        "session": status.session,
+                        "path": status.path.display().to_string(),
+                        "exists": status.exists,
+                        "exited": status.exited,
+                        "attached": status.attached,
+                    })
+                ),
+                OutputFormat::Text if status.attached => {
+                    println!(
+                        "In session '{}' for {}",
+                        status.session,
+                        status.path.display()
+                    )
+                }
+                OutputFormat::Text if status.exists => println!(
+                    "Session '{}' for {} exists{}, connecting attaches to it",
+                    status.session,
+                    status.path.display(),
+                    if status.exited { " but has exited" } else { "" }
+                ),
+                OutputFormat::Text => println!(
+                    "No session '{}' for {}, connecting creates it",
+                    status.session,
+                    status.path.display()
+                ),
+            }
         }
     }
 
     Ok(())
 }
 
-/// Preview directory contents
-fn preview_directory(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    let entries = fs::read_dir(path)?;
-
-    for entry in entries {
-        let entry = entry?;
-        let metadata = entry.metadata()?;
-        let file_type = if metadata.is_dir() {
-            "dir"
-        } else if metadata.is_file() {
-            "file"
-        } else {
-            "other"
-        };
-
-        println!("{:<6} {}", file_type, entry.file_name().to_string_lossy());
+/// A doctor for the real tools, configured from the command line only
+fn doctor_service(cli: &Cli) -> DoctorService<ZellijClient, ZoxideClient, RealGit, RealFs> {
+    let timeout = Config {
+        timeout: cli.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS),
+        ..Config::default()
+    }
+    .timeout();
+    DoctorService::new(
+        ZellijClient::new()
+            .with_timeout(timeout)
+            .with_print_cmd(cli.print_cmd),
+        ZoxideClient::new()
+            .with_timeout(timeout)
+            .with_print_cmd(cli.print_cmd),
+        RealGit::new()
+            .with_timeout(timeout)
+            .with_print_cmd(cli.print_cmd),
+        RealFs::new(),
+    )
+}
+
+/// Print a checklist of the environment, failing if any check failed
+fn doctor(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let checks = doctor_service(cli).run();
+    for check in &checks {
+        println!("{}", check);
     }
 
+    let failed = checks.iter().filter(|c| !c.passed).count();
+    if failed > 0 {
+        return Err(format!("{} check(s) failed", failed).into());
+    }
+    Ok(())
+}
+
+/// Print the versions of zesh and the tools it runs
+fn version(cli: &Cli, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let versions = doctor_service(cli).versions();
+    if json || cli.output == OutputFormat::Json {
+        println!("{}", serde_json::to_string(&versions)?);
+    } else {
+        print!("{}", versions);
+    }
+    Ok(())
+}
+
+/// Preview directory contents
+fn preview_directory(path: &Path, options: &TreeOptions) -> Result<(), Box<dyn std::error::Error>> {
+    for line in render_tree(&RealFs::new(), path, options)? {
+        println!("{}", line);
+    }
     Ok(())
 }
@@ -0,0 +1,180 @@
+use serde_json::{Value, json};
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt::Display;
+
+/// How results and errors are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable text
+    #[default]
+    Text,
+    /// JSON for editor and plugin integrations. Errors are written to stderr
+    /// as `{"error": "...", "kind": "..."}`, and informational messages are
+    /// dropped so stdout only holds results.
+    Json,
+}
+
+/// Printer for informational messages like "Cloning..." and warnings, which
+/// `--quiet` turns off. Command results and errors don't go through here and
+/// are always printed.
+#[derive(Debug, Default)]
+pub struct Printer {
+    quiet: bool,
+    format: OutputFormat,
+    captured: Option<RefCell<Vec<String>>>,
+}
+
+impl Printer {
+    /// Create a printer that writes to stdout unless `quiet` is set
+    pub fn new(quiet: bool) -> Self {
+        Self {
+            quiet,
+            format: OutputFormat::Text,
+            captured: None,
+        }
+    }
+
+    /// Create a printer that records messages instead of printing them
+    #[cfg(test)]
+    pub fn capturing(quiet: bool) -> Self {
+        Self {
+            quiet,
+            format: OutputFormat::Text,
+            captured: Some(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Drop informational messages when writing JSON
+    pub fn with_format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Messages recorded by a capturing printer
+    #[cfg(test)]
+    pub fn captured(&self) -> Vec<String> {
+        self.captured
+            .as_ref()
+            .map(|c| c.borrow().clone())
+            .unwrap_or_default()
+    }
+
+    /// Print an informational message
+    pub fn info(&self, message: impl Display) {
+        if self.quiet || self.format == OutputFormat::Json {
+            return;
+        }
+
+        match &self.captured {
+            Some(captured) => captured.borrow_mut().push(message.to_string()),
+            None => println!("{}", message),
+        }
+    }
+
+    /// Print a warning to stderr, keeping stdout for results. Dropped like
+    /// informational messages when quiet or writing JSON.
+    pub fn warn(&self, message: impl Display) {
+        if self.quiet || self.format == OutputFormat::Json {
+            return;
+        }
+
+        let message = format!("Warning: {}", message);
+        match &self.captured {
+            Some(captured) => captured.borrow_mut().push(message),
+            None => eprintln!("{}", message),
+        }
+    }
+}
+
+/// Describe an error for [`OutputFormat::Json`]. The kind is the name of the
+/// error's variant, like `NoMatch` for `ConnectError::NoMatch`.
+pub fn error_json(error: &dyn Error) -> Value {
+    // thiserror enums don't expose their variant names, but Debug starts
+    // with them
+    let debug = format!("{:?}", error);
+    let kind: String = debug
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+
+    json!({
+        "error": error.to_string(),
+        "kind": if kind.is_empty() { "Error" } else { &kind },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::ConnectError;
+
+    #[test]
+    fn test_info_is_recorded() {
+        let printer = Printer::capturing(false);
+        printer.info("Cloning...");
+        printer.info(format_args!("Created session '{}'", "api"));
+
+        assert_eq!(
+            printer.captured(),
+            vec![
+                "Cloning...".to_string(),
+                "Created session 'api'".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_drops_info() {
+        let printer = Printer::capturing(false).with_format(OutputFormat::Json);
+        printer.info("Cloning...");
+
+        assert!(printer.captured().is_empty());
+    }
+
+    #[test]
+    fn test_error_json() {
+        let error = ConnectError::NoMatch("api".to_string());
+
+        assert_eq!(
+            error_json(&error),
+            json!({
+                "error": "No matching sessions or directories found for 'api'",
+                "kind": "NoMatch",
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_json_without_variant() {
+        let error: Box<dyn Error> = "2 check(s) failed".into();
+
+        assert_eq!(error_json(error.as_ref())["kind"], "Error");
+    }
+
+    #[test]
+    fn test_quiet_drops_info() {
+        let printer = Printer::capturing(true);
+        printer.info("Cloning...");
+
+        assert!(printer.captured().is_empty());
+    }
+
+    #[test]
+    fn test_warn() {
+        let printer = Printer::capturing(false);
+        printer.warn(format_args!("could not focus tab '{}'", "logs"));
+        assert_eq!(
+            printer.captured(),
+            vec!["Warning: could not focus tab 'logs'"]
+        );
+
+        for printer in [
+            Printer::capturing(true),
+            Printer::capturing(false).with_format(OutputFormat::Json),
+        ] {
+            printer.warn("could not focus tab 'logs'");
+            assert!(printer.captured().is_empty());
+        }
+    }
+}
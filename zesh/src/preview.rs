@@ -0,0 +1,183 @@
+use std::path::{Path, PathBuf};
+
+use crate::fs::{FsError, FsOperations};
+use crate::glob::is_ignored;
+
+/// Default number of entries `zesh preview` shows before stopping
+pub const DEFAULT_MAX_ENTRIES: usize = 200;
+
+/// What [`render_tree`] shows of a directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeOptions {
+    /// Levels of the tree to show, 1 being the directory's own entries
+    pub depth: usize,
+    /// Stop after this many entries, so huge trees don't flood a preview
+    /// window. `0` shows all of them.
+    pub max_entries: usize,
+    /// Glob patterns of entries to leave out, see [`is_ignored`]
+    pub ignore: Vec<String>,
+}
+
+impl Default for TreeOptions {
+    fn default() -> Self {
+        Self {
+            depth: 1,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            ignore: Vec::new(),
+        }
+    }
+}
+
+/// The lines of a `zesh preview` of `root`: each entry as `dir` or `file`
+/// and its name, indented by how deep it is. Subdirectories that can't be
+/// read are shown without their entries. If the cap is hit, a last line
+/// says so.
+pub fn render_tree<F: FsOperations>(
+    fs: &F,
+    root: &Path,
+    options: &TreeOptions,
+) -> Result<Vec<String>, FsError> {
+    let mut lines = Vec::new();
+    let entries = fs.read_dir(root)?;
+    if !render_entries(fs, entries, 1, options, &mut lines) {
+        lines.push(format!("... stopped after {} entries", options.max_entries));
+    }
+    Ok(lines)
+}
+
+/// Render `entries` at `level` and what's below them, returning false if
+/// the entry cap was hit
+fn render_entries<F: FsOperations>(
+    fs: &F,
+    entries: Vec<PathBuf>,
+    level: usize,
+    options: &TreeOptions,
+    lines: &mut Vec<String>,
+) -> bool {
+    for entry in entries {
+        if is_ignored(fs, &entry, &options.ignore) {
+            continue;
+        }
+        if options.max_entries > 0 && lines.len() >= options.max_entries {
+            return false;
+        }
+
+        let is_dir = fs.is_dir(&entry);
+        let name = entry
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        lines.push(format!(
+            "{:<6} {}{}",
+            if is_dir { "dir" } else { "file" },
+            "  ".repeat(level - 1),
+            name
+        ));
+
+        if is_dir
+            && level < options.depth
+            && let Ok(children) = fs.read_dir(&entry)
+            && !render_entries(fs, children, level + 1, options, lines)
+        {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::tests::MockFs;
+
+    fn project() -> MockFs {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/src/app"), "app");
+        fs.with_directory(Path::new("/src/app/src"), "src");
+        fs.with_directory(Path::new("/src/app/src/bin"), "bin");
+        fs.with_directory(Path::new("/src/app/target"), "target");
+        fs.with_file(Path::new("/src/app/Cargo.toml"));
+        fs.with_file(Path::new("/src/app/src/main.rs"));
+        fs.with_file(Path::new("/src/app/src/bin/tool.rs"));
+        fs.with_file(Path::new("/src/app/target/app"));
+        fs
+    }
+
+    #[test]
+    fn test_render_tree_depth_1() {
+        let lines =
+            render_tree(&project(), Path::new("/src/app"), &TreeOptions::default()).unwrap();
+
+        assert_eq!(
+            lines,
+            vec!["file   Cargo.toml", "dir    src", "dir    target"]
+        );
+    }
+
+    #[test]
+    fn test_render_tree_depth_2() {
+        let options = TreeOptions {
+            depth: 2,
+            ignore: vec!["target".to_string()],
+            ..TreeOptions::default()
+        };
+
+        let lines = render_tree(&project(), Path::new("/src/app"), &options).unwrap();
+
+        // bin's entries are too deep, target is ignored
+        assert_eq!(
+            lines,
+            vec![
+                "file   Cargo.toml",
+                "dir    src",
+                "dir      bin",
+                "file     main.rs",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_tree_entry_cap() {
+        let options = TreeOptions {
+            depth: 3,
+            max_entries: 4,
+            ..TreeOptions::default()
+        };
+
+        let lines = render_tree(&project(), Path::new("/src/app"), &options).unwrap();
+
+        assert_eq!(
+            lines,
+            vec![
+                "file   Cargo.toml",
+                "dir    src",
+                "dir      bin",
+                "file       tool.rs",
+                "... stopped after 4 entries",
+            ]
+        );
+
+        let all = TreeOptions {
+            max_entries: 0,
+            ..options
+        };
+        assert_eq!(
+            render_tree(&project(), Path::new("/src/app"), &all)
+                .unwrap()
+                .len(),
+            7
+        );
+    }
+
+    #[test]
+    fn test_render_tree_of_missing_directory() {
+        assert!(
+            render_tree(
+                &MockFs::new(),
+                Path::new("/missing"),
+                &TreeOptions::default()
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use std::io::{self, BufRead, Write};
+
+/// Trait for asking the user to confirm destructive actions
+pub trait Prompt {
+    /// Ask a yes/no question, returning `true` if the user agreed
+    fn confirm(&self, message: &str) -> bool;
+}
+
+/// Default implementation that asks on the terminal. With `assume_yes` set,
+/// as by `--yes`, every question is answered yes without asking.
+#[derive(Copy, Clone, Default)]
+pub struct StdinPrompt {
+    assume_yes: bool,
+}
+
+impl StdinPrompt {
+    /// Create a new StdinPrompt
+    pub fn new(assume_yes: bool) -> Self {
+        StdinPrompt { assume_yes }
+    }
+}
+
+impl Prompt for StdinPrompt {
+    fn confirm(&self, message: &str) -> bool {
+        if self.assume_yes {
+            return true;
+        }
+
+        // Prompt on stderr so it doesn't end up in piped output
+        eprint!("{} [y/N] ", message);
+        let _ = io::stderr().flush();
+
+        let mut answer = String::new();
+        match io::stdin().lock().read_line(&mut answer) {
+            Ok(_) => is_yes(&answer),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Whether an answer to a prompt means yes. Anything else, including an
+/// empty answer, means no.
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// A mock prompt that gives a fixed answer and records what it was asked
+    #[derive(Default)]
+    pub struct MockPrompt {
+        pub questions: RefCell<Vec<String>>,
+        answer: bool,
+    }
+
+    impl MockPrompt {
+        pub fn yes() -> Self {
+            Self {
+                questions: RefCell::new(Vec::new()),
+                answer: true,
+            }
+        }
+
+        pub fn no() -> Self {
+            Self::default()
+        }
+    }
+
+    impl Prompt for MockPrompt {
+        fn confirm(&self, message: &str) -> bool {
+            self.questions.borrow_mut().push(message.to_string());
+            self.answer
+        }
+    }
+
+    #[test]
+    fn test_is_yes() {
+        for answer in ["y", "Y", "yes", "YES\n", " y \r\n"] {
+            assert!(is_yes(answer), "{:?} should be yes", answer);
+        }
+        for answer in ["", "\n", "n", "no", "yep", "maybe"] {
+            assert!(!is_yes(answer), "{:?} should be no", answer);
+        }
+    }
+
+    #[test]
+    fn test_assume_yes_does_not_ask() {
+        assert!(StdinPrompt::new(true).confirm("Kill session 'api'?"));
+    }
+}
@@ -0,0 +1,78 @@
+use thiserror::Error;
+use zellij_rs::{ZellijError, ZellijOperations};
+
+use crate::store::{SessionStore, StoreError};
+
+#[derive(Debug, Error)]
+pub enum RenameError {
+    #[error("Zellij error: {0}")]
+    Zellij(#[from] ZellijError),
+
+    #[error("Session store error: {0}")]
+    Store(#[from] StoreError),
+
+    #[error("Not inside a zellij session")]
+    NoSession,
+}
+
+/// Rename the session we're running inside of, moving its recorded root to
+/// the new name so `zesh root` keeps working. Returns the old name.
+pub fn rename_current_session<Z: ZellijOperations>(
+    zellij: &Z,
+    store: &SessionStore,
+    new_name: &str,
+) -> Result<String, RenameError> {
+    let old_name = zellij.current_session()?.ok_or(RenameError::NoSession)?;
+
+    zellij.rename_session(new_name)?;
+    store.rename(&old_name, new_name)?;
+
+    Ok(old_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use zellij_rs::MockZellijClient;
+
+    #[test]
+    fn test_rename_moves_stored_root() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([("old".to_string(), true)]));
+        let store = SessionStore::new();
+        store.record("old", Path::new("/src/project")).unwrap();
+
+        let old_name = rename_current_session(&zellij, &store, "new").unwrap();
+
+        assert_eq!(old_name, "old");
+        assert_eq!(zellij.current_session().unwrap(), Some("new".to_string()));
+        assert_eq!(store.lookup("new"), Some(PathBuf::from("/src/project")));
+        assert_eq!(store.lookup("old"), None);
+    }
+
+    #[test]
+    fn test_rename_keeps_tags() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([("old".to_string(), true)]));
+        let store = SessionStore::new();
+        store.tag("old", "work").unwrap();
+
+        rename_current_session(&zellij, &store, "new").unwrap();
+
+        assert_eq!(store.tags("new"), vec!["work"]);
+        assert!(store.tags("old").is_empty());
+        assert_eq!(
+            store.tagged("work").into_iter().collect::<Vec<_>>(),
+            vec!["new"]
+        );
+    }
+
+    #[test]
+    fn test_rename_outside_session() {
+        let zellij = MockZellijClient::new();
+        let store = SessionStore::new();
+
+        let result = rename_current_session(&zellij, &store, "new");
+        assert!(matches!(result, Err(RenameError::NoSession)));
+    }
+}
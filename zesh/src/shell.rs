@@ -0,0 +1,42 @@
+use std::path::Path;
+
+/// Quote a string for use inside double quotes in a POSIX shell
+pub fn double_quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// A `cd` command for the parent shell to `eval`, since zesh can't change
+/// its working directory itself
+pub fn cd_command(path: &Path) -> String {
+    format!("cd {}", double_quote(&path.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cd_command() {
+        assert_eq!(
+            cd_command(Path::new("/home/user/project")),
+            r#"cd "/home/user/project""#
+        );
+    }
+
+    #[test]
+    fn test_cd_command_escapes_special_chars() {
+        assert_eq!(
+            cd_command(Path::new(r#"/tmp/my "dir" $HOME `x` \y"#)),
+            r#"cd "/tmp/my \"dir\" \$HOME \`x\` \\y""#
+        );
+    }
+}
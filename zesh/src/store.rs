@@ -0,0 +1,277 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("Failed to access session store {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Failed to parse session store: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// What zesh remembers about a session it created or was told about
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    /// Directory the session was created in, unknown for sessions zesh
+    /// didn't create
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<PathBuf>,
+    /// Tags the user gave the session, like `work` or `oss`
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    /// Whether `zesh detach --kill` kills the session after detaching
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub kill_on_detach: bool,
+}
+
+/// Store of the sessions zesh has created, keyed by session name.
+///
+/// Stores created with [`SessionStore::new`] only live in memory, while
+/// [`SessionStore::open`] persists every change to a JSON file.
+#[derive(Debug, Default)]
+pub struct SessionStore {
+    path: Option<PathBuf>,
+    sessions: RefCell<BTreeMap<String, SessionRecord>>,
+}
+
+impl SessionStore {
+    /// Create an empty in-memory store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Default location of the store file
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| dir.join("zesh").join("sessions.json"))
+    }
+
+    /// Open the store backed by the given file, which is created on the first
+    /// write if it doesn't exist yet
+    pub fn open(path: &Path) -> Result<Self, StoreError> {
+        let sessions = if path.exists() {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| StoreError::Io(path.display().to_string(), e))?;
+            serde_json::from_str(&contents)?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            sessions: RefCell::new(sessions),
+        })
+    }
+
+    /// Record the root directory of a session, keeping its tags
+    pub fn record(&self, name: &str, root: &Path) -> Result<(), StoreError> {
+        self.sessions
+            .borrow_mut()
+            .entry(name.to_string())
+            .or_default()
+            .root = Some(root.to_path_buf());
+        self.save()
+    }
+
+    /// Look up the root directory of a session
+    pub fn lookup(&self, name: &str) -> Option<PathBuf> {
+        self.sessions
+            .borrow()
+            .get(name)
+            .and_then(|r| r.root.clone())
+    }
+
+    /// Tag a session. Returns false if it already had the tag.
+    pub fn tag(&self, name: &str, tag: &str) -> Result<bool, StoreError> {
+        let added = self
+            .sessions
+            .borrow_mut()
+            .entry(name.to_string())
+            .or_default()
+            .tags
+            .insert(tag.to_string());
+        if added {
+            self.save()?;
+        }
+        Ok(added)
+    }
+
+    /// The tags of a session, sorted
+    pub fn tags(&self, name: &str) -> Vec<String> {
+        self.sessions
+            .borrow()
+            .get(name)
+            .map(|r| r.tags.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Names of the sessions with the given tag
+    pub fn tagged(&self, tag: &str) -> BTreeSet<String> {
+        self.sessions
+            .borrow()
+            .iter()
+            .filter(|(_, r)| r.tags.contains(tag))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Mark a session to be killed by `zesh detach --kill`
+    pub fn mark_kill_on_detach(&self, name: &str) -> Result<(), StoreError> {
+        self.sessions
+            .borrow_mut()
+            .entry(name.to_string())
+            .or_default()
+            .kill_on_detach = true;
+        self.save()
+    }
+
+    /// Whether a session is marked to be killed by `zesh detach --kill`
+    pub fn kills_on_detach(&self, name: &str) -> bool {
+        self.sessions
+            .borrow()
+            .get(name)
+            .is_some_and(|r| r.kill_on_detach)
+    }
+
+    /// Names of all recorded sessions, sorted
+    pub fn names(&self) -> Vec<String> {
+        self.sessions.borrow().keys().cloned().collect()
+    }
+
+    /// Forget a session
+    pub fn remove(&self, name: &str) -> Result<(), StoreError> {
+        self.sessions.borrow_mut().remove(name);
+        self.save()
+    }
+
+    /// Move a session's record to a new name, after the session itself was
+    /// renamed. Does nothing if the old name has no record.
+    pub fn rename(&self, old: &str, new: &str) -> Result<(), StoreError> {
+        {
+            let mut sessions = self.sessions.borrow_mut();
+            let Some(record) = sessions.remove(old) else {
+                return Ok(());
+            };
+            sessions.insert(new.to_string(), record);
+        }
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), StoreError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let io_err = |e| StoreError::Io(path.display().to_string(), e);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_err)?;
+        }
+        let contents = serde_json::to_string_pretty(&*self.sessions.borrow())?;
+        std::fs::write(path, contents).map_err(io_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_lookup() {
+        let store = SessionStore::new();
+        store.record("project", Path::new("/src/project")).unwrap();
+
+        assert_eq!(store.lookup("project"), Some(PathBuf::from("/src/project")));
+        assert_eq!(store.lookup("missing"), None);
+
+        store.remove("project").unwrap();
+        assert_eq!(store.lookup("project"), None);
+    }
+
+    #[test]
+    fn test_rename() {
+        let store = SessionStore::new();
+        store.record("old", Path::new("/src/project")).unwrap();
+
+        store.rename("old", "new").unwrap();
+
+        assert_eq!(store.lookup("new"), Some(PathBuf::from("/src/project")));
+        assert_eq!(store.lookup("old"), None);
+    }
+
+    #[test]
+    fn test_rename_unknown_session() {
+        let store = SessionStore::new();
+        store.record("other", Path::new("/src/other")).unwrap();
+
+        store.rename("missing", "new").unwrap();
+
+        assert_eq!(store.lookup("new"), None);
+        assert_eq!(store.lookup("other"), Some(PathBuf::from("/src/other")));
+    }
+
+    #[test]
+    fn test_tags() {
+        let store = SessionStore::new();
+        store.record("api", Path::new("/src/api")).unwrap();
+
+        assert!(store.tag("api", "work").unwrap());
+        assert!(!store.tag("api", "work").unwrap());
+        // Sessions zesh didn't create can be tagged too
+        assert!(store.tag("web", "work").unwrap());
+        store.tag("web", "oss").unwrap();
+
+        assert_eq!(store.tags("web"), vec!["oss", "work"]);
+        assert_eq!(store.tags("missing"), Vec::<String>::new());
+        assert_eq!(
+            store.tagged("work"),
+            BTreeSet::from(["api".to_string(), "web".to_string()])
+        );
+        assert_eq!(store.lookup("web"), None);
+
+        // Recording the root again keeps the tags
+        store.record("api", Path::new("/src/api-v2")).unwrap();
+        assert_eq!(store.tags("api"), vec!["work"]);
+    }
+
+    #[test]
+    fn test_kill_on_detach() {
+        let store = SessionStore::new();
+        store.record("scratch", Path::new("/tmp/scratch")).unwrap();
+        assert!(!store.kills_on_detach("scratch"));
+
+        store.mark_kill_on_detach("scratch").unwrap();
+        store.record("scratch", Path::new("/tmp/scratch")).unwrap();
+        assert!(store.kills_on_detach("scratch"));
+        assert!(!store.kills_on_detach("missing"));
+
+        store.rename("scratch", "renamed").unwrap();
+        assert!(store.kills_on_detach("renamed"));
+    }
+
+    #[test]
+    fn test_persists_to_file() {
+        let dir = std::env::temp_dir().join(format!("zesh-store-test-{}", std::process::id()));
+        let path = dir.join("sessions.json");
+
+        let store = SessionStore::open(&path).unwrap();
+        store.record("project", Path::new("/src/project")).unwrap();
+        store.tag("project", "work").unwrap();
+        store.tag("other", "oss").unwrap();
+
+        let reopened = SessionStore::open(&path).unwrap();
+        assert_eq!(
+            reopened.lookup("project"),
+            Some(PathBuf::from("/src/project"))
+        );
+        assert_eq!(reopened.tags("project"), vec!["work"]);
+        assert_eq!(reopened.tags("other"), vec!["oss"]);
+        assert_eq!(reopened.lookup("other"), None);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+use thiserror::Error;
+use zellij_rs::{ZellijError, ZellijOperations};
+
+use crate::store::{SessionStore, StoreError};
+
+#[derive(Debug, Error)]
+pub enum TagError {
+    #[error("Zellij error: {0}")]
+    Zellij(#[from] ZellijError),
+
+    #[error("Session store error: {0}")]
+    Store(#[from] StoreError),
+
+    #[error("No session named '{0}'")]
+    NoSuchSession(String),
+
+    #[error("Invalid tag '{0}': tags can't be empty or contain whitespace")]
+    InvalidTag(String),
+}
+
+/// Tag a running or exited session so it can be listed with
+/// `zesh list --tag`. Returns false if it already had the tag.
+pub fn tag_session<Z: ZellijOperations>(
+    zellij: &Z,
+    store: &SessionStore,
+    session: &str,
+    tag: &str,
+) -> Result<bool, TagError> {
+    if tag.is_empty() || tag.chars().any(char::is_whitespace) {
+        return Err(TagError::InvalidTag(tag.to_string()));
+    }
+
+    let exists = zellij.list_sessions()?.iter().any(|s| s.name == session)
+        || zellij
+            .list_resurrectable()?
+            .iter()
+            .any(|s| s.name == session);
+    if !exists {
+        return Err(TagError::NoSuchSession(session.to_string()));
+    }
+
+    Ok(store.tag(session, tag)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use zellij_rs::MockZellijClient;
+
+    #[test]
+    fn test_tag_session() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([("api".to_string(), false)]));
+        let store = SessionStore::new();
+
+        assert!(tag_session(&zellij, &store, "api", "work").unwrap());
+        assert!(!tag_session(&zellij, &store, "api", "work").unwrap());
+        assert_eq!(store.tags("api"), vec!["work"]);
+    }
+
+    #[test]
+    fn test_tag_unknown_session() {
+        let zellij = MockZellijClient::new();
+        let store = SessionStore::new();
+
+        let result = tag_session(&zellij, &store, "missing", "work");
+        assert!(matches!(result, Err(TagError::NoSuchSession(name)) if name == "missing"));
+        assert!(store.tagged("work").is_empty());
+    }
+
+    #[test]
+    fn test_tag_rejects_invalid_tags() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([("api".to_string(), false)]));
+        let store = SessionStore::new();
+
+        for tag in ["", "two words"] {
+            let result = tag_session(&zellij, &store, "api", tag);
+            assert!(matches!(result, Err(TagError::InvalidTag(_))));
+        }
+    }
+}
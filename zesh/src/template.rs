@@ -0,0 +1,173 @@
+use std::path::PathBuf;
+use zellij_rs::options::ZellijOptions;
+
+use crate::fs::{FsError, FsOperations};
+
+/// The tab and status bars zellij's default layout has, kept by every
+/// template
+const TAB_TEMPLATE: &str = r#"    default_tab_template {
+        pane size=1 borderless=true {
+            plugin location="zellij:tab-bar"
+        }
+        children
+        pane size=2 borderless=true {
+            plugin location="zellij:status-bar"
+        }
+    }
+"#;
+
+const EDITOR_TERMINAL: &str = r#"    tab name="code" focus=true {
+        pane split_direction="horizontal" {
+            pane name="editor" size="70%" focus=true
+            pane name="terminal"
+        }
+    }
+"#;
+
+const THREE_PANE: &str = r#"    tab name="code" focus=true {
+        pane split_direction="vertical" {
+            pane name="editor" size="60%" focus=true
+            pane split_direction="horizontal" {
+                pane name="terminal"
+                pane name="shell"
+            }
+        }
+    }
+"#;
+
+const TABS: &str = r#"    tab name="editor" focus=true {
+        pane name="editor"
+    }
+    tab name="terminal" {
+        pane name="terminal"
+    }
+"#;
+
+/// A starter layout for new sessions, bundled with zesh for projects that
+/// don't have a layout of their own yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LayoutTemplate {
+    /// An editor pane with a terminal below it
+    EditorTerminal,
+    /// An editor on the left and two terminals stacked on the right
+    ThreePane,
+    /// An editor and a terminal in tabs of their own
+    Tabs,
+}
+
+impl LayoutTemplate {
+    /// Name of the template, as passed to `--template`
+    pub fn name(self) -> &'static str {
+        match self {
+            LayoutTemplate::EditorTerminal => "editor-terminal",
+            LayoutTemplate::ThreePane => "three-pane",
+            LayoutTemplate::Tabs => "tabs",
+        }
+    }
+
+    /// The template as a zellij KDL layout
+    pub fn kdl(self) -> String {
+        let tabs = match self {
+            LayoutTemplate::EditorTerminal => EDITOR_TERMINAL,
+            LayoutTemplate::ThreePane => THREE_PANE,
+            LayoutTemplate::Tabs => TABS,
+        };
+        format!("layout {{\n{}{}}}\n", TAB_TEMPLATE, tabs)
+    }
+
+    /// Write the layout to a temporary file for `session_name` and return
+    /// `options` with it as the new session's layout, replacing any layout
+    /// they had
+    pub fn apply<F: FsOperations>(
+        self,
+        fs: &F,
+        session_name: &str,
+        options: &ZellijOptions,
+    ) -> Result<ZellijOptions, FsError> {
+        let path = layout_path(self, session_name);
+        fs.write(&path, &self.kdl())?;
+
+        let mut options = options.clone();
+        options.new_session_with_layout = Some(path.display().to_string());
+        Ok(options)
+    }
+}
+
+/// Where the layout for a session is written
+fn layout_path(template: LayoutTemplate, session_name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "zesh-{}-{}.kdl",
+        session_name.replace(['/', '\\'], "-"),
+        template.name()
+    ))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::fs::tests::MockFs;
+    use clap::ValueEnum;
+    use std::path::Path;
+
+    /// Check the layout is well-formed KDL as far as zellij layouts go:
+    /// a single `layout` node, balanced braces outside of quoted strings,
+    /// and no unterminated strings
+    pub fn assert_valid_kdl(kdl: &str) {
+        assert!(kdl.starts_with("layout {"), "not a layout: {}", kdl);
+
+        let mut depth = 0i32;
+        let mut in_string = false;
+        for c in kdl.chars() {
+            match c {
+                '"' => in_string = !in_string,
+                '{' if !in_string => depth += 1,
+                '}' if !in_string => {
+                    depth -= 1;
+                    assert!(depth >= 0, "unbalanced braces: {}", kdl);
+                }
+                '\n' => assert!(!in_string, "unterminated string: {}", kdl),
+                _ => {}
+            }
+        }
+        assert_eq!(depth, 0, "unbalanced braces: {}", kdl);
+        assert!(kdl.trim_end().ends_with('}'), "trailing content: {}", kdl);
+    }
+
+    #[test]
+    fn test_templates_are_valid_kdl() {
+        for template in LayoutTemplate::value_variants() {
+            let kdl = template.kdl();
+            assert_valid_kdl(&kdl);
+            assert!(kdl.contains("zellij:tab-bar"));
+            assert!(kdl.contains("name=\"editor\""));
+        }
+    }
+
+    #[test]
+    fn test_names_match_cli_values() {
+        for template in LayoutTemplate::value_variants() {
+            let value = template.to_possible_value().unwrap();
+            assert_eq!(value.get_name(), template.name());
+        }
+    }
+
+    #[test]
+    fn test_apply_writes_layout() {
+        let fs = MockFs::new();
+        let options = ZellijOptions {
+            new_session_with_layout: Some("compact".to_string()),
+            ..ZellijOptions::default()
+        };
+
+        let options = LayoutTemplate::ThreePane
+            .apply(&fs, "my/project", &options)
+            .unwrap();
+
+        let path = options.new_session_with_layout.unwrap();
+        assert!(path.ends_with("zesh-my-project-three-pane.kdl"));
+        assert_eq!(
+            fs.read_to_string(Path::new(&path)).unwrap(),
+            LayoutTemplate::ThreePane.kdl()
+        );
+    }
+}
@@ -0,0 +1,679 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use zellij_rs::{ZellijError, ZellijOperations, options::ZellijOptions};
+use zesh_git::{Git, GitError};
+
+use crate::fs::{FsError, FsOperations};
+use crate::glob::{is_ignored, wildcard_match};
+use crate::output::Printer;
+use crate::store::SessionStore;
+
+#[derive(Debug, Error)]
+pub enum UpError {
+    #[error("Zellij error: {0}")]
+    Zellij(#[from] ZellijError),
+
+    #[error("Filesystem error: {0}")]
+    Fs(#[from] FsError),
+
+    #[error("Failed to read manifest {0}: {1}")]
+    Read(String, std::io::Error),
+
+    #[error("Failed to write manifest {0}: {1}")]
+    Write(String, std::io::Error),
+
+    #[error("Invalid manifest: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("Failed to serialize manifest: {0}")]
+    Serialize(#[from] toml::ser::Error),
+
+    #[error("Git error: {0}")]
+    Git(#[from] GitError),
+
+    #[error("Failed to write layout for session '{0}': {1}")]
+    Layout(String, std::io::Error),
+
+    #[error("Unsupported glob {0}: only the last path component can have wildcards")]
+    Glob(String),
+}
+
+/// A set of sessions to bring up together, read from a TOML file:
+///
+/// ```toml
+/// [[sessions]]
+/// name = "api"
+/// root = "/home/me/src/api"
+/// layout = "compact"
+///
+/// [[sessions]]
+/// name = "web"
+/// root = "../web"
+/// layout = { path = "layouts/web.kdl" }
+///
+/// [[sessions]]
+/// name = "notes"
+/// root = "/home/me/notes"
+/// layout = { inline = "layout { pane; pane; }" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub sessions: Vec<ManifestEntry>,
+}
+
+/// A single session in a [`Manifest`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Session name
+    pub name: String,
+
+    /// Directory the session starts in, relative to the manifest
+    pub root: PathBuf,
+
+    /// Layout the session is created with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<LayoutSpec>,
+}
+
+/// Layout of a manifest entry: the name of a layout zellij knows about, a
+/// layout file relative to the manifest, or a KDL layout written inline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LayoutSpec {
+    Name(String),
+    Path { path: PathBuf },
+    Inline { inline: String },
+}
+
+/// A layout ready to be passed to zellij. Inline layouts are backed by a temp
+/// file that is removed when this is dropped.
+#[derive(Debug)]
+pub struct ResolvedLayout {
+    arg: String,
+    temp_file: Option<PathBuf>,
+}
+
+impl ResolvedLayout {
+    /// Value for `--new-session-with-layout`
+    pub fn arg(&self) -> &str {
+        &self.arg
+    }
+}
+
+impl Drop for ResolvedLayout {
+    fn drop(&mut self) {
+        if let Some(path) = &self.temp_file {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl LayoutSpec {
+    /// Turn the layout into something zellij accepts, writing inline layouts
+    /// to a temp file
+    pub fn resolve(&self, base_dir: &Path, session: &str) -> Result<ResolvedLayout, UpError> {
+        match self {
+            LayoutSpec::Name(name) => Ok(ResolvedLayout {
+                arg: name.clone(),
+                temp_file: None,
+            }),
+            LayoutSpec::Path { path } => Ok(ResolvedLayout {
+                arg: base_dir.join(path).display().to_string(),
+                temp_file: None,
+            }),
+            LayoutSpec::Inline { inline } => {
+                let path = std::env::temp_dir().join(temp_layout_name(session));
+                std::fs::write(&path, inline)
+                    .map_err(|e| UpError::Layout(session.to_string(), e))?;
+                Ok(ResolvedLayout {
+                    arg: path.display().to_string(),
+                    temp_file: Some(path),
+                })
+            }
+        }
+    }
+}
+
+/// Temp file name for an inline layout, unique per process and session
+fn temp_layout_name(session: &str) -> String {
+    let session: String = session
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("zesh-layout-{}-{}.kdl", std::process::id(), session)
+}
+
+impl Manifest {
+    /// A manifest with a session for every directory matching `pattern`,
+    /// named after the directory. Only the last component of the pattern
+    /// can have wildcards (`*` and `?`), e.g. `~/src/*`. Directories
+    /// matching an `ignore` pattern are left out, see [`is_ignored`].
+    pub fn from_glob<F: FsOperations>(
+        fs: &F,
+        pattern: &Path,
+        ignore: &[String],
+    ) -> Result<Self, UpError> {
+        let pattern = fs.expand_path(pattern);
+        let glob = || UpError::Glob(pattern.display().to_string());
+        let name_pattern = pattern
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(glob)?;
+        let parent = match pattern.parent() {
+            Some(parent) if parent.as_os_str().is_empty() => fs.current_dir()?,
+            Some(parent) => parent.to_path_buf(),
+            None => return Err(glob()),
+        };
+        if parent.to_string_lossy().contains(['*', '?']) {
+            return Err(glob());
+        }
+
+        let mut sessions = Vec::new();
+        for entry in fs.read_dir(&parent)? {
+            let Some(name) = entry.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            // Like a shell, only match hidden directories when asked to
+            if name.starts_with('.') && !name_pattern.starts_with('.') {
+                continue;
+            }
+            if !wildcard_match(name_pattern, name)
+                || !fs.is_dir(&entry)
+                || is_ignored(fs, &entry, ignore)
+            {
+                continue;
+            }
+            sessions.push(ManifestEntry {
+                name: name.to_string(),
+                root: entry.clone(),
+                layout: None,
+            });
+        }
+
+        Ok(Self { sessions })
+    }
+
+    /// Read a manifest from a file
+    pub fn load(path: &Path) -> Result<Self, UpError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| UpError::Read(path.display().to_string(), e))?;
+        Self::parse(&contents)
+    }
+
+    /// Parse a manifest from a string
+    pub fn parse(contents: &str) -> Result<Self, UpError> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Serialize the manifest to TOML
+    pub fn to_toml(&self) -> Result<String, UpError> {
+        Ok(toml::to_string(self)?)
+    }
+
+    /// Write the manifest to a file
+    pub fn save(&self, path: &Path) -> Result<(), UpError> {
+        std::fs::write(path, self.to_toml()?)
+            .map_err(|e| UpError::Write(path.display().to_string(), e))
+    }
+}
+
+/// Service for bringing up the sessions in a manifest
+pub struct UpService<Z: ZellijOperations, F: FsOperations> {
+    zellij: Z,
+    fs: F,
+    store: SessionStore,
+    printer: Printer,
+}
+
+impl<Z: ZellijOperations, F: FsOperations> UpService<Z, F> {
+    /// Create a new up service
+    pub fn new(zellij: Z, fs: F) -> Self {
+        Self {
+            zellij,
+            fs,
+            store: SessionStore::new(),
+            printer: Printer::default(),
+        }
+    }
+
+    /// Record session roots in the given store
+    pub fn with_store(mut self, store: SessionStore) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Print informational messages with the given printer
+    pub fn with_printer(mut self, printer: Printer) -> Self {
+        self.printer = printer;
+        self
+    }
+
+    /// Create every session in the manifest that doesn't exist yet, in the
+    /// background. Relative roots and layout paths resolve against
+    /// `base_dir`. Returns the names of the sessions that were created.
+    pub fn up(&self, manifest: &Manifest, base_dir: &Path) -> Result<Vec<String>, UpError> {
+        let existing = self.zellij.list_sessions()?;
+        let mut created = Vec::new();
+
+        for entry in &manifest.sessions {
+            if existing.iter().any(|s| s.name == entry.name) {
+                self.printer.info(format_args!(
+                    "Session '{}' already exists, skipping",
+                    entry.name
+                ));
+                continue;
+            }
+
+            let root = base_dir.join(self.fs.expand_path(&entry.root));
+            let (root, _) = self.fs.validate_dir_path(&root)?;
+
+            // Keep the layout alive until zellij has read it
+            let layout = entry
+                .layout
+                .as_ref()
+                .map(|layout| layout.resolve(base_dir, &entry.name))
+                .transpose()?;
+            let options = ZellijOptions {
+                new_session_with_layout: layout.as_ref().map(|l| l.arg().to_string()),
+                ..Default::default()
+            };
+
+            self.fs.set_current_dir(&root)?;
+            self.zellij.new_session_detached(&entry.name, &options)?;
+            drop(layout);
+
+            if let Err(e) = self.store.record(&entry.name, &root) {
+                self.printer
+                    .warn(format_args!("failed to record session root: {}", e));
+            }
+
+            self.printer
+                .info(format_args!("Created session '{}'", entry.name));
+            created.push(entry.name.clone());
+        }
+
+        Ok(created)
+    }
+}
+
+/// Service for exporting the running sessions as a manifest
+pub struct FreezeService<Z: ZellijOperations, F: FsOperations, G: Git> {
+    zellij: Z,
+    fs: F,
+    git: G,
+    store: SessionStore,
+    printer: Printer,
+}
+
+impl<Z: ZellijOperations, F: FsOperations, G: Git> FreezeService<Z, F, G> {
+    /// Create a new freeze service
+    pub fn new(zellij: Z, fs: F, git: G) -> Self {
+        Self {
+            zellij,
+            fs,
+            git,
+            store: SessionStore::new(),
+            printer: Printer::default(),
+        }
+    }
+
+    /// Look up session roots in the given store
+    pub fn with_store(mut self, store: SessionStore) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Print warnings with the given printer
+    pub fn with_printer(mut self, printer: Printer) -> Self {
+        self.printer = printer;
+        self
+    }
+
+    /// Build a manifest from the running sessions. Sessions whose root can't
+    /// be determined are skipped with a warning.
+    pub fn freeze(&self) -> Result<Manifest, UpError> {
+        let mut sessions = Vec::new();
+
+        for session in self.zellij.list_sessions()? {
+            if session.is_exited {
+                continue;
+            }
+
+            match self.resolve_root(&session.name, session.is_current)? {
+                Some(root) => sessions.push(ManifestEntry {
+                    name: session.name,
+                    root,
+                    layout: None,
+                }),
+                None => self.printer.warn(format_args!(
+                    "skipping session '{}', its root directory is unknown",
+                    session.name
+                )),
+            }
+        }
+
+        Ok(Manifest { sessions })
+    }
+
+    /// Root of a session from the store. The current session can also fall
+    /// back to the git repository we're running in.
+    fn resolve_root(&self, name: &str, is_current: bool) -> Result<Option<PathBuf>, UpError> {
+        if let Some(root) = self.store.lookup(name) {
+            return Ok(Some(root));
+        }
+
+        if !is_current {
+            return Ok(None);
+        }
+
+        let cwd = self.fs.current_dir()?;
+        let (success, top_level) = self.git.show_top_level(&cwd.to_string_lossy())?;
+        if success {
+            Ok(Some(PathBuf::from(top_level.trim())))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::tests::MockFs;
+    use std::collections::HashMap;
+    use zellij_rs::MockZellijClient;
+
+    #[test]
+    fn test_parse_all_layout_forms() {
+        let manifest = Manifest::parse(
+            r#"
+            [[sessions]]
+            name = "plain"
+            root = "/src/plain"
+
+            [[sessions]]
+            name = "api"
+            root = "/src/api"
+            layout = "compact"
+
+            [[sessions]]
+            name = "web"
+            root = "../web"
+            layout = { path = "layouts/web.kdl" }
+
+            [[sessions]]
+            name = "notes"
+            root = "/notes"
+            layout = { inline = "layout { pane; }" }
+            "#,
+        )
+        .unwrap();
+
+        let layouts: Vec<_> = manifest.sessions.iter().map(|s| s.layout.clone()).collect();
+        assert_eq!(
+            layouts,
+            vec![
+                None,
+                Some(LayoutSpec::Name("compact".to_string())),
+                Some(LayoutSpec::Path {
+                    path: PathBuf::from("layouts/web.kdl")
+                }),
+                Some(LayoutSpec::Inline {
+                    inline: "layout { pane; }".to_string()
+                }),
+            ]
+        );
+        assert_eq!(manifest.sessions[2].root, PathBuf::from("../web"));
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_layout_table() {
+        let result = Manifest::parse(
+            r#"
+            [[sessions]]
+            name = "api"
+            root = "/src/api"
+            layout = { file = "web.kdl" }
+            "#,
+        );
+
+        assert!(matches!(result, Err(UpError::Parse(_))));
+    }
+
+    #[test]
+    fn test_resolve_name_and_path() {
+        let base = Path::new("/manifests");
+
+        let named = LayoutSpec::Name("compact".to_string())
+            .resolve(base, "api")
+            .unwrap();
+        assert_eq!(named.arg(), "compact");
+
+        let relative = LayoutSpec::Path {
+            path: PathBuf::from("layouts/web.kdl"),
+        }
+        .resolve(base, "web")
+        .unwrap();
+        assert_eq!(relative.arg(), "/manifests/layouts/web.kdl");
+
+        let absolute = LayoutSpec::Path {
+            path: PathBuf::from("/etc/web.kdl"),
+        }
+        .resolve(base, "web")
+        .unwrap();
+        assert_eq!(absolute.arg(), "/etc/web.kdl");
+    }
+
+    #[test]
+    fn test_resolve_inline_writes_and_cleans_up_temp_file() {
+        let kdl = "layout { pane; }";
+        let layout = LayoutSpec::Inline {
+            inline: kdl.to_string(),
+        }
+        .resolve(Path::new("/manifests"), "inline test/1")
+        .unwrap();
+
+        let path = PathBuf::from(layout.arg());
+        assert!(path.starts_with(std::env::temp_dir()));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), kdl);
+
+        drop(layout);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_up_creates_missing_sessions() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([("api".to_string(), true)]));
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/src/api"), "api");
+        fs.with_directory(Path::new("/manifests/web"), "web");
+        let manifest = Manifest::parse(
+            r#"
+            [[sessions]]
+            name = "api"
+            root = "/src/api"
+
+            [[sessions]]
+            name = "web"
+            root = "web"
+            layout = { inline = "layout { pane; }" }
+            "#,
+        )
+        .unwrap();
+
+        let service = UpService::new(zellij, fs);
+        let created = service.up(&manifest, Path::new("/manifests")).unwrap();
+
+        assert_eq!(created, vec!["web".to_string()]);
+        assert_eq!(service.zellij.list_sessions().unwrap().len(), 2);
+        assert_eq!(
+            service.fs.current_dir().unwrap(),
+            PathBuf::from("/manifests/web")
+        );
+        assert_eq!(
+            service.store.lookup("web"),
+            Some(PathBuf::from("/manifests/web"))
+        );
+        // The session wasn't attached to, so the original stays current
+        assert_eq!(
+            service.zellij.current_session().unwrap(),
+            Some("api".to_string())
+        );
+    }
+
+    #[test]
+    fn test_freeze_round_trips_through_up_loader() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([
+            ("api".to_string(), false),
+            ("web".to_string(), false),
+            ("current".to_string(), true),
+            ("unknown".to_string(), false),
+            ("dead".to_string(), false),
+        ]))
+        .with_exited(&["dead"]);
+        let store = SessionStore::new();
+        store.record("api", Path::new("/src/api")).unwrap();
+        store.record("web", Path::new("/src/web")).unwrap();
+        store.record("dead", Path::new("/src/dead")).unwrap();
+
+        let service = FreezeService::new(zellij, MockFs::new(), zesh_git::MockGit::new())
+            .with_store(store)
+            .with_printer(Printer::capturing(false));
+        let mut manifest = service.freeze().unwrap();
+        assert_eq!(
+            service.printer.captured(),
+            vec!["Warning: skipping session 'unknown', its root directory is unknown"]
+        );
+        manifest.sessions.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let names: Vec<_> = manifest.sessions.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["api", "current", "web"]);
+        // The current session has no record, so its root comes from git
+        assert_eq!(
+            manifest.sessions[1].root,
+            PathBuf::from("/mock/repo/top-level")
+        );
+
+        let loaded = Manifest::parse(&manifest.to_toml().unwrap()).unwrap();
+        assert_eq!(loaded, manifest);
+    }
+
+    #[test]
+    fn test_freeze_with_layout_round_trips() {
+        let manifest = Manifest {
+            sessions: vec![
+                ManifestEntry {
+                    name: "api".to_string(),
+                    root: PathBuf::from("/src/api"),
+                    layout: Some(LayoutSpec::Name("compact".to_string())),
+                },
+                ManifestEntry {
+                    name: "notes".to_string(),
+                    root: PathBuf::from("/notes"),
+                    layout: Some(LayoutSpec::Inline {
+                        inline: "layout {\n    pane\n}".to_string(),
+                    }),
+                },
+            ],
+        };
+
+        let loaded = Manifest::parse(&manifest.to_toml().unwrap()).unwrap();
+        assert_eq!(loaded, manifest);
+    }
+
+    fn projects_fs() -> MockFs {
+        let fs = MockFs::new();
+        fs.with_directory(Path::new("/mock/home/src"), "src");
+        fs.with_directory(Path::new("/mock/home/src/api"), "api");
+        fs.with_directory(Path::new("/mock/home/src/web"), "web");
+        fs.with_directory(Path::new("/mock/home/src/notes"), "notes");
+        fs.with_directory(Path::new("/mock/home/src/.cache"), ".cache");
+        fs.with_file(Path::new("/mock/home/src/README.md"));
+        fs
+    }
+
+    #[test]
+    fn test_up_glob_creates_session_per_directory() {
+        let zellij = MockZellijClient::with_sessions(HashMap::from([("web".to_string(), true)]));
+        let fs = projects_fs();
+        let manifest = Manifest::from_glob(&fs, Path::new("~/src/*"), &[]).unwrap();
+
+        let service = UpService::new(zellij, fs);
+        let created = service.up(&manifest, Path::new("/mock/current")).unwrap();
+
+        // The file, the hidden directory and the existing session are skipped
+        assert_eq!(created, vec!["api".to_string(), "notes".to_string()]);
+        let mut sessions: Vec<_> = service
+            .zellij
+            .list_sessions()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        sessions.sort();
+        assert_eq!(sessions, vec!["api", "notes", "web"]);
+        assert_eq!(
+            service.store.lookup("api"),
+            Some(PathBuf::from("/mock/home/src/api"))
+        );
+    }
+
+    #[test]
+    fn test_glob_matches_last_component() {
+        let fs = projects_fs();
+
+        let manifest = Manifest::from_glob(&fs, Path::new("/mock/home/src/?e*"), &[]).unwrap();
+        let names: Vec<_> = manifest.sessions.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["web"]);
+
+        let manifest = Manifest::from_glob(&fs, Path::new("/mock/home/src/.*"), &[]).unwrap();
+        assert_eq!(manifest.sessions[0].name, ".cache");
+    }
+
+    #[test]
+    fn test_glob_skips_ignored_directories() {
+        let fs = projects_fs();
+        let ignore = vec!["notes".to_string(), "~/src/w*".to_string()];
+
+        let manifest = Manifest::from_glob(&fs, Path::new("~/src/*"), &ignore).unwrap();
+
+        let names: Vec<_> = manifest.sessions.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["api"]);
+    }
+
+    #[test]
+    fn test_glob_rejects_wildcard_parent() {
+        let fs = projects_fs();
+
+        let result = Manifest::from_glob(&fs, Path::new("/mock/*/src/*"), &[]);
+        assert!(matches!(result, Err(UpError::Glob(_))));
+    }
+
+    #[test]
+    fn test_up_missing_root() {
+        let zellij = MockZellijClient::new();
+        let fs = MockFs::new();
+        let manifest = Manifest::parse(
+            r#"
+            [[sessions]]
+            name = "api"
+            root = "/missing"
+            "#,
+        )
+        .unwrap();
+
+        let service = UpService::new(zellij, fs);
+        let result = service.up(&manifest, Path::new("/manifests"));
+
+        assert!(matches!(result, Err(UpError::Fs(FsError::PathNotFound(_)))));
+        assert!(service.zellij.list_sessions().unwrap().is_empty());
+    }
+}
@@ -1,5 +1,9 @@
+use std::cell::RefCell;
+use std::path::Path;
 use std::process::Command;
+use std::time::Duration;
 use thiserror::Error;
+use zesh_proc::{Exec, ProcError, ProcessRunner, SystemRunner};
 
 #[derive(Debug, Error)]
 pub enum GitError {
@@ -10,6 +14,60 @@ pub enum GitError {
     CommandError(String),
 }
 
+/// stderr fragments (lowercased) git prints when the connection failed
+/// rather than the request, so trying again may help
+const TRANSIENT_ERRORS: &[&str] = &[
+    "could not resolve host",
+    "connection timed out",
+    "operation timed out",
+    "connection refused",
+    "connection reset",
+    "failed to connect",
+    "the remote end hung up unexpectedly",
+    "unexpected disconnect",
+    "early eof",
+    "rpc failed",
+    "gnutls_handshake",
+    "ssl_error",
+];
+
+/// stderr fragments (lowercased) that mean the credentials or permissions
+/// are wrong, which no amount of retrying will fix
+const AUTH_ERRORS: &[&str] = &[
+    "authentication failed",
+    "permission denied",
+    "could not read username",
+    "could not read password",
+    "terminal prompts disabled",
+    "repository not found",
+    "access denied",
+    "returned error: 403",
+];
+
+impl GitError {
+    /// Whether the error looks like a network hiccup worth retrying, as
+    /// opposed to an authentication problem or any other failure
+    pub fn is_transient(&self) -> bool {
+        let GitError::CommandError(stderr) = self else {
+            return false;
+        };
+        let stderr = stderr.to_lowercase();
+        let contains_any = |fragments: &[&str]| fragments.iter().any(|f| stderr.contains(f));
+
+        // An auth failure can also hang up the connection, so it wins
+        !contains_any(AUTH_ERRORS) && contains_any(TRANSIENT_ERRORS)
+    }
+}
+
+impl From<ProcError> for GitError {
+    fn from(err: ProcError) -> Self {
+        match err {
+            ProcError::Io(e) => GitError::IoError(e),
+            ProcError::Timeout { .. } => GitError::CommandError(err.to_string()),
+        }
+    }
+}
+
 /// A trait representing Git operations.
 pub trait Git {
     /// Runs `git rev-parse --show-toplevel` in the given directory.
@@ -17,26 +75,124 @@ pub trait Git {
     /// and the second element is either the top-level directory path or the error output.
     fn show_top_level(&self, name: &str) -> Result<(bool, String), GitError>;
 
+    /// Runs `git rev-parse --is-inside-work-tree` in the given directory.
+    /// Returns `true` if the directory is inside a git work tree. Unlike
+    /// `show_top_level`, a directory outside any repository is not an error.
+    fn is_inside_work_tree(&self, name: &str) -> Result<bool, GitError>;
+
     /// Runs `git rev-parse --git-common-dir` in the given directory.
     /// Returns a tuple where the first element is `true` if the command succeeded,
     /// and the second element is either the common directory path or the error output.
     fn git_common_dir(&self, name: &str) -> Result<(bool, String), GitError>;
 
-    /// Runs `git clone <url> <dir>` in the given command directory.
-    /// Returns the output string on success.
+    /// Runs `git clone <url> <dir>` in the given command directory, with
+    /// git's progress going straight to the terminal. Returns any output that
+    /// wasn't shown to the user on success.
     fn clone(&self, url: &str, cmd_dir: &str, dir: &str) -> Result<String, GitError>;
+
+    /// Like [`clone`](Self::clone), but runs `git clone --bare`, so `dir`
+    /// gets the repository itself and no checkout
+    fn clone_bare(&self, url: &str, cmd_dir: &str, dir: &str) -> Result<String, GitError>;
+
+    /// Runs `git -C <repo> worktree add <path> <branch>`, checking out an
+    /// existing branch of the repository at `path`
+    fn worktree_add(&self, repo: &str, path: &str, branch: &str) -> Result<(), GitError>;
+
+    /// Runs `git --version` and returns its output, e.g. `git version 2.43.0`
+    fn version(&self) -> Result<String, GitError>;
+
+    /// Finds the default branch, e.g. `main`, of a local repository
+    /// (from `git symbolic-ref`) or a remote URL (from `git ls-remote`)
+    fn default_branch(&self, url_or_dir: &str) -> Result<String, GitError>;
 }
 
 /// A real implementation of the Git trait that calls the actual git commands.
-#[derive(Copy, Clone)]
-pub struct RealGit;
+#[derive(Copy, Clone, Default)]
+pub struct RealGit<R: ProcessRunner = SystemRunner> {
+    // Clones run on the terminal, everything else through the runner
+    exec: Exec,
+    runner: R,
+}
 
-impl Git for RealGit {
+impl RealGit {
+    /// Create a new RealGit
+    pub fn new() -> Self {
+        RealGit {
+            exec: Exec::default(),
+            runner: SystemRunner::default(),
+        }
+    }
+
+    /// Kill non-interactive git commands that run longer than `timeout`.
+    /// Clones are never timed out, since large ones take a while and git
+    /// may be waiting on the user for credentials or a host key.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.runner.exec.timeout = timeout;
+        self
+    }
+
+    /// Print every git command to stderr before running it
+    pub fn with_print_cmd(mut self, print_cmd: bool) -> Self {
+        self.exec.print_cmd = print_cmd;
+        self.runner.exec.print_cmd = print_cmd;
+        self
+    }
+}
+
+impl<R: ProcessRunner> RealGit<R> {
+    /// Run git commands that don't need the terminal through `runner`
+    /// instead
+    pub fn with_runner<R2: ProcessRunner>(self, runner: R2) -> RealGit<R2> {
+        RealGit {
+            exec: self.exec,
+            runner,
+        }
+    }
+
+    /// The runner git commands go through
+    pub fn runner(&self) -> &R {
+        &self.runner
+    }
+
+    /// Run `git clone` with `flags` before the URL
+    fn run_clone(
+        &self,
+        flags: &[&str],
+        url: &str,
+        cmd_dir: &str,
+        dir: &str,
+    ) -> Result<String, GitError> {
+        // Large clones take a while, so let git show its progress (and ask
+        // for credentials) on the terminal. stderr is still copied so the
+        // error can say why it failed; --progress keeps git reporting
+        // progress even though stderr is a pipe.
+        let (status, stderr) = self.exec.status_tee_stderr(
+            Command::new("git")
+                .args(["clone", "--progress"])
+                .args(flags)
+                .args([url, dir])
+                .current_dir(cmd_dir),
+        )?;
+        if status.success() {
+            Ok(String::new())
+        } else {
+            Err(GitError::CommandError(clone_failure(
+                status,
+                &String::from_utf8_lossy(&stderr),
+            )))
+        }
+    }
+}
+
+impl<R: ProcessRunner> Git for RealGit<R> {
     fn show_top_level(&self, name: &str) -> Result<(bool, String), GitError> {
-        let output = Command::new("git")
-            .args(["-C", name, "rev-parse", "--show-toplevel"])
-            .output()?;
-        if output.status.success() {
+        let output = self.runner.run_command(Command::new("git").args([
+            "-C",
+            name,
+            "rev-parse",
+            "--show-toplevel",
+        ]))?;
+        if output.success() {
             let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
             Ok((true, stdout))
         } else {
@@ -45,11 +201,25 @@ impl Git for RealGit {
         }
     }
 
+    fn is_inside_work_tree(&self, name: &str) -> Result<bool, GitError> {
+        let output = self.runner.run_command(Command::new("git").args([
+            "-C",
+            name,
+            "rev-parse",
+            "--is-inside-work-tree",
+        ]))?;
+        // git exits non-zero outside of a repository
+        Ok(output.success() && String::from_utf8_lossy(&output.stdout).trim() == "true")
+    }
+
     fn git_common_dir(&self, name: &str) -> Result<(bool, String), GitError> {
-        let output = Command::new("git")
-            .args(["-C", name, "rev-parse", "--git-common-dir"])
-            .output()?;
-        if output.status.success() {
+        let output = self.runner.run_command(Command::new("git").args([
+            "-C",
+            name,
+            "rev-parse",
+            "--git-common-dir",
+        ]))?;
+        if output.success() {
             let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
             Ok((true, stdout))
         } else {
@@ -59,33 +229,383 @@ impl Git for RealGit {
     }
 
     fn clone(&self, url: &str, cmd_dir: &str, dir: &str) -> Result<String, GitError> {
-        let output = Command::new("git")
-            .args(["clone", url, dir])
-            .current_dir(cmd_dir)
-            .output()?;
-        if output.status.success() {
-            let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            Ok(stdout)
+        self.run_clone(&[], url, cmd_dir, dir)
+    }
+
+    fn clone_bare(&self, url: &str, cmd_dir: &str, dir: &str) -> Result<String, GitError> {
+        self.run_clone(&["--bare"], url, cmd_dir, dir)
+    }
+
+    fn worktree_add(&self, repo: &str, path: &str, branch: &str) -> Result<(), GitError> {
+        let output = self
+            .runner
+            .run_command(Command::new("git").args(["-C", repo, "worktree", "add", path, branch]))?;
+        if output.success() {
+            Ok(())
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            Err(GitError::CommandError(stderr))
+        }
+    }
+
+    fn version(&self) -> Result<String, GitError> {
+        let output = self
+            .runner
+            .run_command(Command::new("git").arg("--version"))?;
+        if output.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
         } else {
             let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
             Err(GitError::CommandError(stderr))
         }
     }
+
+    fn default_branch(&self, url_or_dir: &str) -> Result<String, GitError> {
+        if !Path::new(url_or_dir).is_dir() {
+            let output = self.runner.run_command(Command::new("git").args([
+                "ls-remote",
+                "--symref",
+                url_or_dir,
+                "HEAD",
+            ]))?;
+            if !output.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+                return Err(GitError::CommandError(stderr));
+            }
+            return parse_symref_head(&String::from_utf8_lossy(&output.stdout)).ok_or_else(|| {
+                GitError::CommandError(format!("{} has no default branch", url_or_dir))
+            });
+        }
+
+        // origin's HEAD is the default branch of a clone; a repository
+        // without a remote only has the branch it's on
+        let mut stderr = String::new();
+        for reference in ["refs/remotes/origin/HEAD", "HEAD"] {
+            let output = self.runner.run_command(Command::new("git").args([
+                "-C",
+                url_or_dir,
+                "symbolic-ref",
+                "--short",
+                reference,
+            ]))?;
+            if output.success() {
+                let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                return Ok(branch
+                    .strip_prefix("origin/")
+                    .map(String::from)
+                    .unwrap_or(branch));
+            }
+            stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        }
+        Err(GitError::CommandError(stderr))
+    }
 }
 
-/// A mocked implementation of the Git trait for testing purposes.
-pub struct MockGit;
+/// Find the branch in `git ls-remote --symref` output, from a line like
+/// `ref: refs/heads/main\tHEAD`
+fn parse_symref_head(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (target, name) = line.strip_prefix("ref: ")?.split_once('\t')?;
+        if name.trim() != "HEAD" {
+            return None;
+        }
+        target.strip_prefix("refs/heads/").map(String::from)
+    })
+}
+
+/// Describe a failed clone by its exit status and git's error lines,
+/// leaving out the progress output around them
+fn clone_failure(status: std::process::ExitStatus, stderr: &str) -> String {
+    let reasons: Vec<&str> = stderr
+        .lines()
+        .flat_map(|line| line.split('\r'))
+        .map(str::trim)
+        .filter(|line| line.starts_with("fatal:") || line.starts_with("error:"))
+        .collect();
+
+    if reasons.is_empty() {
+        format!("git clone exited with {}", status)
+    } else {
+        format!("git clone exited with {}: {}", status, reasons.join("; "))
+    }
+}
+
+/// A mocked implementation of the Git trait for testing purposes. Clones
+/// and worktrees are only recorded, see [`MockGit::calls`].
+#[derive(Debug, Default)]
+pub struct MockGit {
+    calls: RefCell<Vec<String>>,
+}
+
+impl MockGit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The clones and worktrees asked for so far, as git arguments, e.g.
+    /// `clone --bare <url> <dir>`
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.borrow().clone()
+    }
+
+    fn record(&self, call: String) {
+        self.calls.borrow_mut().push(call);
+    }
+}
 
 impl Git for MockGit {
     fn show_top_level(&self, _name: &str) -> Result<(bool, String), GitError> {
         Ok((true, String::from("/mock/repo/top-level")))
     }
 
+    fn is_inside_work_tree(&self, _name: &str) -> Result<bool, GitError> {
+        Ok(true)
+    }
+
     fn git_common_dir(&self, _name: &str) -> Result<(bool, String), GitError> {
         Ok((true, String::from("/mock/repo/common-dir")))
     }
 
-    fn clone(&self, _url: &str, _cmd_dir: &str, _dir: &str) -> Result<String, GitError> {
+    fn clone(&self, url: &str, _cmd_dir: &str, dir: &str) -> Result<String, GitError> {
+        self.record(format!("clone {} {}", url, dir));
+        Ok(String::from("Mock clone successful"))
+    }
+
+    fn clone_bare(&self, url: &str, _cmd_dir: &str, dir: &str) -> Result<String, GitError> {
+        self.record(format!("clone --bare {} {}", url, dir));
         Ok(String::from("Mock clone successful"))
     }
+
+    fn worktree_add(&self, repo: &str, path: &str, branch: &str) -> Result<(), GitError> {
+        self.record(format!("-C {} worktree add {} {}", repo, path, branch));
+        Ok(())
+    }
+
+    fn version(&self) -> Result<String, GitError> {
+        Ok(String::from("git version 2.43.0"))
+    }
+
+    fn default_branch(&self, _url_or_dir: &str) -> Result<String, GitError> {
+        Ok(String::from("main"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use zesh_proc::{MockRunner, RunOutput};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zesh-git-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_is_inside_work_tree() {
+        let repo = temp_dir("inside");
+        let status = Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(&repo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let git = RealGit::new();
+        assert!(git.is_inside_work_tree(repo.to_str().unwrap()).unwrap());
+
+        std::fs::remove_dir_all(repo).unwrap();
+    }
+
+    #[test]
+    fn test_is_outside_work_tree() {
+        let dir = temp_dir("outside");
+
+        let git = RealGit::new();
+        assert!(!git.is_inside_work_tree(dir.to_str().unwrap()).unwrap());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_default_branch_of_local_repo() {
+        let repo = temp_dir("default-branch");
+        let status = Command::new("git")
+            .args(["init", "-q", "-b", "trunk"])
+            .current_dir(&repo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let git = RealGit::new();
+        assert_eq!(git.default_branch(repo.to_str().unwrap()).unwrap(), "trunk");
+
+        std::fs::remove_dir_all(repo).unwrap();
+    }
+
+    #[test]
+    fn test_parse_symref_head() {
+        let output = "ref: refs/heads/master\tHEAD\n0123456789abcdef\tHEAD\n";
+        assert_eq!(parse_symref_head(output).as_deref(), Some("master"));
+        assert_eq!(parse_symref_head("0123456789abcdef\tHEAD\n"), None);
+    }
+
+    #[test]
+    fn test_real_git_argv() {
+        let runner = MockRunner::new()
+            .with_output(RunOutput::success_with("/src/api\n"))
+            .with_output(RunOutput::success_with("ref: refs/heads/trunk\tHEAD\n"));
+        let git = RealGit::new().with_runner(runner);
+
+        assert_eq!(
+            git.show_top_level("/src/api/lib").unwrap(),
+            (true, "/src/api".to_string())
+        );
+        assert_eq!(
+            git.default_branch("https://example.com/repo.git").unwrap(),
+            "trunk"
+        );
+        assert_eq!(
+            git.runner().argvs(),
+            vec![
+                vec!["git", "-C", "/src/api/lib", "rev-parse", "--show-toplevel"],
+                vec![
+                    "git",
+                    "ls-remote",
+                    "--symref",
+                    "https://example.com/repo.git",
+                    "HEAD"
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_timeout_skips_clones() {
+        let git = RealGit::new().with_timeout(Some(Duration::from_secs(30)));
+
+        assert_eq!(git.runner().exec.timeout, Some(Duration::from_secs(30)));
+        assert_eq!(git.exec.timeout, None);
+    }
+
+    #[test]
+    fn test_mock_default_branch_is_main() {
+        assert_eq!(MockGit::new().default_branch("/mock/repo").unwrap(), "main");
+    }
+
+    #[test]
+    fn test_mock_clone_returns_success_string() {
+        let result = MockGit::new().clone("https://github.com/user/repo", "/tmp", "repo");
+        assert_eq!(result.unwrap(), "Mock clone successful");
+    }
+
+    #[test]
+    fn test_worktree_add_argv() {
+        let git = RealGit::new().with_runner(MockRunner::new());
+
+        git.worktree_add("/src/api.git", "/src/api/main", "main")
+            .unwrap();
+
+        assert_eq!(
+            git.runner().argvs(),
+            vec![vec![
+                "git",
+                "-C",
+                "/src/api.git",
+                "worktree",
+                "add",
+                "/src/api/main",
+                "main"
+            ]]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_clone_bare_and_add_worktree() {
+        let dir = temp_dir("bare");
+        let source = dir.join("source");
+        std::fs::create_dir_all(&source).unwrap();
+        let git_in = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(args)
+                .current_dir(&source)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git_in(&["init", "-q", "-b", "trunk"]);
+        git_in(&[
+            "-c",
+            "user.name=zesh",
+            "-c",
+            "user.email=zesh@example.com",
+            "commit",
+            "-q",
+            "--allow-empty",
+            "-m",
+            "init",
+        ]);
+
+        let git = RealGit::new();
+        let dir_str = dir.to_str().unwrap();
+        git.clone_bare(source.to_str().unwrap(), dir_str, "repo.git")
+            .unwrap();
+        let bare = dir.join("repo.git");
+        let bare_str = bare.to_str().unwrap();
+        assert_eq!(git.default_branch(bare_str).unwrap(), "trunk");
+
+        let worktree = dir.join("repo").join("trunk");
+        git.worktree_add(bare_str, worktree.to_str().unwrap(), "trunk")
+            .unwrap();
+        assert!(git.is_inside_work_tree(worktree.to_str().unwrap()).unwrap());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_clone_reports_failure_from_exit_status() {
+        let dir = temp_dir("clone-fail");
+        let missing = dir.join("missing-source");
+
+        let git = RealGit::new().with_timeout(Some(Duration::from_secs(30)));
+        let result = Git::clone(
+            &git,
+            missing.to_str().unwrap(),
+            dir.to_str().unwrap(),
+            "dest",
+        );
+
+        match result {
+            Err(GitError::CommandError(message)) => {
+                assert!(message.starts_with("git clone exited with"), "{}", message);
+                // git's reason is kept, e.g. "fatal: repository ... does not exist"
+                assert!(message.contains("fatal:"), "{}", message);
+            }
+            other => panic!("Expected GitError::CommandError, got {:?}", other),
+        }
+        assert!(!dir.join("dest").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_is_transient() {
+        let err = |stderr: &str| GitError::CommandError(stderr.to_string());
+
+        assert!(err("fatal: unable to access 'https://github.com/u/r/': Could not resolve host: github.com").is_transient());
+        assert!(
+            err("error: RPC failed; curl 56 GnuTLS recv error\nfatal: early EOF").is_transient()
+        );
+        assert!(err("fatal: the remote end hung up unexpectedly").is_transient());
+
+        assert!(!err("fatal: Authentication failed for 'https://github.com/u/r/'").is_transient());
+        assert!(!err("git@github.com: Permission denied (publickey).\nfatal: the remote end hung up unexpectedly").is_transient());
+        assert!(
+            !err("fatal: destination path 'r' already exists and is not an empty directory.")
+                .is_transient()
+        );
+        assert!(!GitError::IoError(std::io::Error::other("not found")).is_transient());
+    }
 }
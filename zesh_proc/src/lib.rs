@@ -0,0 +1,763 @@
+use std::cell::RefCell;
+use std::ffi::OsStr;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// How often a running child is polled while waiting for it to exit
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Set when Ctrl-C is hit while an [`InterruptGuard`] is alive
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Error)]
+pub enum ProcError {
+    #[error("failed to execute command: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("`{program}` timed out after {}s", timeout.as_secs_f64())]
+    Timeout { program: String, timeout: Duration },
+}
+
+/// How external commands are run: with an optional timeout, and optionally
+/// echoing each command to stderr first (`--print-cmd`). Clients route every
+/// command through this so those settings apply everywhere.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Exec {
+    pub timeout: Option<Duration>,
+    pub print_cmd: bool,
+}
+
+impl Exec {
+    /// Run a command and collect its output, see [`output_with_timeout`]
+    pub fn output(&self, cmd: &mut Command) -> Result<Output, ProcError> {
+        self.trace(cmd);
+        output_with_timeout(cmd, self.timeout)
+    }
+
+    /// Run a command attached to the terminal, see [`status_with_timeout`]
+    pub fn status(&self, cmd: &mut Command) -> Result<ExitStatus, ProcError> {
+        self.trace(cmd);
+        status_with_timeout(cmd, self.timeout)
+    }
+
+    /// Run a user-provided shell command, like a hook, in `cwd` attached to
+    /// the terminal, see [`shell_command`]
+    pub fn shell(&self, command: &str, cwd: &Path) -> Result<ExitStatus, ProcError> {
+        self.status(shell_command(command).current_dir(cwd))
+    }
+
+    /// Run a command attached to the terminal but keep a copy of its stderr,
+    /// see [`status_tee_stderr`]
+    pub fn status_tee_stderr(&self, cmd: &mut Command) -> Result<(ExitStatus, Vec<u8>), ProcError> {
+        self.trace(cmd);
+        status_tee_stderr(cmd, self.timeout)
+    }
+
+    /// Spawn a command without a timeout, for interactive commands that run
+    /// for as long as the user wants
+    pub fn spawn(&self, cmd: &mut Command) -> io::Result<Child> {
+        self.trace(cmd);
+        cmd.spawn()
+    }
+
+    fn trace(&self, cmd: &Command) {
+        if self.print_cmd {
+            eprintln!("+ {}", format_command(cmd));
+        }
+    }
+}
+
+/// What a finished command returned, as collected by a [`ProcessRunner`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunOutput {
+    /// Exit code, or `None` if the command was killed by a signal
+    pub code: Option<i32>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl RunOutput {
+    /// Output of a command that exited with `code`
+    pub fn with_code(code: i32, stdout: &str, stderr: &str) -> Self {
+        Self {
+            code: Some(code),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    /// Output of a command that exited successfully, printing `stdout`
+    pub fn success_with(stdout: &str) -> Self {
+        Self::with_code(0, stdout, "")
+    }
+
+    /// Whether the command exited with code 0
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+impl From<Output> for RunOutput {
+    fn from(output: Output) -> Self {
+        Self {
+            code: output.status.code(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        }
+    }
+}
+
+/// Runs non-interactive commands to completion and collects their output.
+/// Clients take one so the exact command lines they build can be tested
+/// with a [`MockRunner`]. Interactive commands that need the terminal, like
+/// attaching to a session, still go through [`Exec`] directly.
+pub trait ProcessRunner {
+    /// Run `program` with `args`, in `cwd` if given, feeding it `stdin` if
+    /// given
+    fn run(
+        &self,
+        program: &OsStr,
+        args: &[&OsStr],
+        cwd: Option<&Path>,
+        stdin: Option<&[u8]>,
+    ) -> Result<RunOutput, ProcError>;
+
+    /// Run a command built with [`Command`], taking its program, arguments
+    /// and working directory. Anything else set on it, like environment
+    /// variables, is ignored.
+    fn run_command(&self, cmd: &Command) -> Result<RunOutput, ProcError> {
+        let args: Vec<&OsStr> = cmd.get_args().collect();
+        self.run(cmd.get_program(), &args, cmd.get_current_dir(), None)
+    }
+
+    /// Run a user-provided shell command, like a hook, in `cwd`. Unlike
+    /// other commands it's attached to the terminal, so only the exit code
+    /// is collected.
+    fn run_shell(&self, command: &str, cwd: &Path) -> Result<RunOutput, ProcError>;
+
+    /// Whether the user hit Ctrl-C since the current [`InterruptGuard`] was
+    /// created
+    fn interrupted(&self) -> bool {
+        INTERRUPTED.load(Ordering::SeqCst)
+    }
+}
+
+/// Catches Ctrl-C until dropped, so only the child processes we're waiting
+/// on die and we get the chance to clean up after them. Whether it was hit
+/// is reported by [`ProcessRunner::interrupted`].
+pub struct InterruptGuard(());
+
+impl InterruptGuard {
+    /// Start catching Ctrl-C
+    pub fn new() -> Self {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        #[cfg(unix)]
+        // SAFETY: the handler only stores to an atomic, which is
+        // async-signal-safe
+        unsafe {
+            libc::signal(
+                libc::SIGINT,
+                on_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            );
+        }
+        InterruptGuard(())
+    }
+}
+
+impl Default for InterruptGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        // SAFETY: restores the default disposition
+        unsafe {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+        }
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_sigint(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Runs commands for real with [`Command`], honouring the timeout and
+/// `--print-cmd` settings of its [`Exec`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRunner {
+    pub exec: Exec,
+}
+
+impl SystemRunner {
+    pub fn new(exec: Exec) -> Self {
+        Self { exec }
+    }
+}
+
+impl ProcessRunner for SystemRunner {
+    fn run(
+        &self,
+        program: &OsStr,
+        args: &[&OsStr],
+        cwd: Option<&Path>,
+        stdin: Option<&[u8]>,
+    ) -> Result<RunOutput, ProcError> {
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+        if let Some(cwd) = cwd {
+            cmd.current_dir(cwd);
+        }
+
+        let output = match stdin {
+            Some(input) => {
+                self.exec.trace(&cmd);
+                output_with_input(&mut cmd, input, self.exec.timeout)?
+            }
+            None => self.exec.output(&mut cmd)?,
+        };
+        Ok(output.into())
+    }
+
+    fn run_shell(&self, command: &str, cwd: &Path) -> Result<RunOutput, ProcError> {
+        let status = self.exec.shell(command, cwd)?;
+        Ok(RunOutput {
+            code: status.code(),
+            ..RunOutput::default()
+        })
+    }
+}
+
+/// A command line a [`MockRunner`] was asked to run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    /// Program followed by its arguments
+    pub argv: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub stdin: Option<Vec<u8>>,
+}
+
+/// Records every command instead of running it, answering each with the
+/// next queued output, or a successful empty one once the queue runs out
+#[derive(Debug, Default)]
+pub struct MockRunner {
+    invocations: RefCell<Vec<Invocation>>,
+    outputs: RefCell<Vec<RunOutput>>,
+    interrupted: bool,
+}
+
+impl MockRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue the output for the next command that isn't answered yet
+    pub fn with_output(self, output: RunOutput) -> Self {
+        self.outputs.borrow_mut().push(output);
+        self
+    }
+
+    /// Report that the user hit Ctrl-C
+    pub fn with_interrupted(mut self) -> Self {
+        self.interrupted = true;
+        self
+    }
+
+    /// Every command run so far, oldest first
+    pub fn invocations(&self) -> Vec<Invocation> {
+        self.invocations.borrow().clone()
+    }
+
+    /// The program and arguments of every command run so far
+    pub fn argvs(&self) -> Vec<Vec<String>> {
+        self.invocations
+            .borrow()
+            .iter()
+            .map(|i| i.argv.clone())
+            .collect()
+    }
+
+    /// The shell commands given to [`ProcessRunner::run_shell`] so far, with
+    /// the directory each ran in
+    pub fn shell_commands(&self) -> Vec<(String, PathBuf)> {
+        let shell = shell_command("");
+        let shell = shell.get_program().to_string_lossy();
+        self.invocations
+            .borrow()
+            .iter()
+            .filter(|i| i.argv.first().is_some_and(|program| *program == shell))
+            .filter_map(|i| Some((i.argv.last()?.clone(), i.cwd.clone()?)))
+            .collect()
+    }
+
+    fn record(&self, invocation: Invocation) -> RunOutput {
+        self.invocations.borrow_mut().push(invocation);
+
+        let mut outputs = self.outputs.borrow_mut();
+        if outputs.is_empty() {
+            RunOutput::success_with("")
+        } else {
+            outputs.remove(0)
+        }
+    }
+}
+
+impl ProcessRunner for MockRunner {
+    fn run(
+        &self,
+        program: &OsStr,
+        args: &[&OsStr],
+        cwd: Option<&Path>,
+        stdin: Option<&[u8]>,
+    ) -> Result<RunOutput, ProcError> {
+        let argv = std::iter::once(program)
+            .chain(args.iter().copied())
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        Ok(self.record(Invocation {
+            argv,
+            cwd: cwd.map(Path::to_path_buf),
+            stdin: stdin.map(<[u8]>::to_vec),
+        }))
+    }
+
+    fn run_shell(&self, command: &str, cwd: &Path) -> Result<RunOutput, ProcError> {
+        let cmd = shell_command(command);
+        let argv = std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        Ok(self.record(Invocation {
+            argv,
+            cwd: Some(cwd.to_path_buf()),
+            stdin: None,
+        }))
+    }
+
+    fn interrupted(&self) -> bool {
+        self.interrupted
+    }
+}
+
+/// A command running `command` through the system shell, `sh -c` or `cmd /C`
+/// on Windows
+pub fn shell_command(command: &str) -> Command {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    cmd.arg(command);
+    cmd
+}
+
+/// Render a command as a copy-pastable shell line, quoting arguments that
+/// need it
+pub fn format_command(cmd: &Command) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| shell_quote(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Single-quote an argument unless it only has characters the shell leaves
+/// alone
+fn shell_quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Run a command to completion and collect its output, like
+/// `Command::output`, but kill the child if it runs longer than `timeout`.
+/// A `timeout` of `None` waits forever.
+pub fn output_with_timeout(
+    cmd: &mut Command,
+    timeout: Option<Duration>,
+) -> Result<Output, ProcError> {
+    let Some(timeout) = timeout else {
+        return Ok(cmd.output()?);
+    };
+
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Drain the pipes on separate threads so a chatty child can't block
+    // on a full pipe while we wait for it
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let status = wait_or_kill(cmd, &mut child, timeout)?;
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Like [`output_with_timeout`], but `input` is written to the child's stdin
+pub fn output_with_input(
+    cmd: &mut Command,
+    input: &[u8],
+    timeout: Option<Duration>,
+) -> Result<Output, ProcError> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Write from a thread too, since the child may not read all of its
+    // input before filling up its output pipes
+    let input = input.to_vec();
+    let writer = child.stdin.take().map(|mut pipe| {
+        thread::spawn(move || {
+            let _ = pipe.write_all(&input);
+        })
+    });
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let status = match timeout {
+        Some(timeout) => wait_or_kill(cmd, &mut child, timeout)?,
+        None => child.wait()?,
+    };
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Run a command to completion with the parent's stdin, stdout and stderr,
+/// like `Command::status`, so the user sees its output live. The child is
+/// killed if it runs longer than `timeout`. A `timeout` of `None` waits
+/// forever.
+pub fn status_with_timeout(
+    cmd: &mut Command,
+    timeout: Option<Duration>,
+) -> Result<ExitStatus, ProcError> {
+    let Some(timeout) = timeout else {
+        return Ok(cmd.status()?);
+    };
+
+    let mut child = cmd.spawn()?;
+    wait_or_kill(cmd, &mut child, timeout)
+}
+
+/// Like [`status_with_timeout`], but the child's stderr is copied through to
+/// ours as it arrives and also returned, so callers can both show progress
+/// and inspect why a command failed.
+pub fn status_tee_stderr(
+    cmd: &mut Command,
+    timeout: Option<Duration>,
+) -> Result<(ExitStatus, Vec<u8>), ProcError> {
+    let mut child = cmd.stderr(Stdio::piped()).spawn()?;
+    let stderr = tee_to_stderr(child.stderr.take());
+
+    let status = match timeout {
+        Some(timeout) => wait_or_kill(cmd, &mut child, timeout)?,
+        None => child.wait()?,
+    };
+
+    Ok((status, stderr.join().unwrap_or_default()))
+}
+
+/// Wait for the child to exit, killing it once `timeout` has passed
+fn wait_or_kill(
+    cmd: &Command,
+    child: &mut Child,
+    timeout: Duration,
+) -> Result<ExitStatus, ProcError> {
+    match wait_until(child, Instant::now() + timeout)? {
+        Some(status) => Ok(status),
+        None => {
+            // The child may exit between the last poll and the kill, so a
+            // failure here is not interesting
+            let _ = child.kill();
+            let _ = child.wait();
+            Err(ProcError::Timeout {
+                program: cmd.get_program().to_string_lossy().to_string(),
+                timeout,
+            })
+        }
+    }
+}
+
+/// Poll the child until it exits or the deadline passes
+fn wait_until(child: &mut Child, deadline: Instant) -> io::Result<Option<ExitStatus>> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+fn tee_to_stderr<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let Some(mut pipe) = pipe else {
+            return buf;
+        };
+
+        // Forward chunks as they come, since progress output is often a
+        // single line rewritten with carriage returns
+        let mut chunk = [0; 4096];
+        while let Ok(n) = pipe.read(&mut chunk) {
+            if n == 0 {
+                break;
+            }
+            let _ = io::stderr().write_all(&chunk[..n]);
+            buf.extend_from_slice(&chunk[..n]);
+        }
+        buf
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_fires_for_slow_command() {
+        let mut cmd = Command::new("sleep");
+        cmd.arg("5");
+
+        let start = Instant::now();
+        let result = output_with_timeout(&mut cmd, Some(Duration::from_millis(100)));
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        match result {
+            Err(ProcError::Timeout { program, timeout }) => {
+                assert_eq!(program, "sleep");
+                assert_eq!(timeout, Duration::from_millis(100));
+            }
+            other => panic!("Expected ProcError::Timeout, got {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fast_command_returns_output() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo out; echo err >&2"]);
+
+        let output = output_with_timeout(&mut cmd, Some(Duration::from_secs(5))).unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "out\n");
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "err\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_no_timeout_waits_for_completion() {
+        let mut cmd = Command::new("sleep");
+        cmd.arg("0.1");
+
+        let output = output_with_timeout(&mut cmd, None).unwrap();
+        assert!(output.status.success());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_status_with_timeout() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "exit 3"]);
+        let status = status_with_timeout(&mut cmd, Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(status.code(), Some(3));
+
+        let mut cmd = Command::new("sleep");
+        cmd.arg("5");
+        let result = status_with_timeout(&mut cmd, Some(Duration::from_millis(100)));
+        assert!(matches!(result, Err(ProcError::Timeout { .. })));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_status_tee_stderr_keeps_stderr() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo 'fatal: nope' >&2; exit 128"]);
+
+        let (status, stderr) = status_tee_stderr(&mut cmd, Some(Duration::from_secs(5))).unwrap();
+
+        assert_eq!(status.code(), Some(128));
+        assert_eq!(String::from_utf8_lossy(&stderr), "fatal: nope\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_system_runner_feeds_stdin() {
+        let output = SystemRunner::default()
+            .run(
+                OsStr::new("tr"),
+                &[OsStr::new("a-z"), OsStr::new("A-Z")],
+                None,
+                Some(b"zesh"),
+            )
+            .unwrap();
+
+        assert!(output.success());
+        assert_eq!(output.stdout, b"ZESH");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_system_runner_uses_cwd() {
+        let dir = std::env::temp_dir().canonicalize().unwrap();
+        let output = SystemRunner::default()
+            .run(OsStr::new("pwd"), &[], Some(&dir), None)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            dir.to_string_lossy()
+        );
+    }
+
+    #[test]
+    fn test_mock_runner_records_invocations() {
+        let runner = MockRunner::new().with_output(RunOutput::with_code(1, "", "nope"));
+        let mut cmd = Command::new("git");
+        cmd.args(["status", "--short"]).current_dir("/repo");
+
+        let first = runner.run_command(&cmd).unwrap();
+        let second = runner
+            .run(OsStr::new("cat"), &[], None, Some(b"hi"))
+            .unwrap();
+
+        assert!(!first.success());
+        assert_eq!(first.stderr, b"nope");
+        assert!(second.success());
+        assert_eq!(
+            runner.invocations(),
+            vec![
+                Invocation {
+                    argv: vec!["git".into(), "status".into(), "--short".into()],
+                    cwd: Some(PathBuf::from("/repo")),
+                    stdin: None,
+                },
+                Invocation {
+                    argv: vec!["cat".into()],
+                    cwd: None,
+                    stdin: Some(b"hi".to_vec()),
+                },
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_system_runner_run_shell() {
+        let runner = SystemRunner::default();
+
+        assert!(runner.run_shell("true", Path::new("/")).unwrap().success());
+        assert_eq!(
+            runner.run_shell("exit 3", Path::new("/")).unwrap().code,
+            Some(3)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_shell_times_out() {
+        let runner = SystemRunner::new(Exec {
+            timeout: Some(Duration::from_millis(50)),
+            print_cmd: false,
+        });
+
+        let result = runner.run_shell("sleep 5", Path::new("/"));
+        assert!(matches!(result, Err(ProcError::Timeout { .. })));
+    }
+
+    #[test]
+    fn test_mock_runner_records_shell_commands() {
+        let runner = MockRunner::new()
+            .with_output(RunOutput::with_code(1, "", ""))
+            .with_interrupted();
+        let output = runner
+            .run_shell("npm install", Path::new("/src/app"))
+            .unwrap();
+        runner.run_command(&Command::new("git")).unwrap();
+
+        assert!(!output.success());
+        assert!(runner.interrupted());
+        assert_eq!(
+            runner.shell_commands(),
+            vec![("npm install".to_string(), PathBuf::from("/src/app"))]
+        );
+        assert!(!MockRunner::new().interrupted());
+    }
+
+    #[test]
+    fn test_timeout_error_message() {
+        let err = ProcError::Timeout {
+            program: "git".to_string(),
+            timeout: Duration::from_secs(30),
+        };
+        assert_eq!(err.to_string(), "`git` timed out after 30s");
+    }
+
+    #[test]
+    fn test_format_command() {
+        let mut cmd = Command::new("zellij");
+        cmd.args([
+            "--session",
+            "my-project",
+            "--new-session-with-layout",
+            "compact",
+        ]);
+
+        assert_eq!(
+            format_command(&cmd),
+            "zellij --session my-project --new-session-with-layout compact"
+        );
+    }
+
+    #[test]
+    fn test_format_command_quotes_special_args() {
+        let mut cmd = Command::new("git");
+        cmd.args(["clone", "", "my dir", "it's"]);
+
+        assert_eq!(format_command(&cmd), r#"git clone '' 'my dir' 'it'\''s'"#);
+    }
+}
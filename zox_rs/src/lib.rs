@@ -3,6 +3,8 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::str;
+use std::time::{Duration, SystemTime};
+use zesh_proc::{ProcError, ProcessRunner, RunOutput, SystemRunner};
 
 /// Result type for zoxide operations
 pub type ZoxideResult<T> = Result<T, ZoxideError>;
@@ -23,11 +25,23 @@ pub enum ZoxideError {
     Utf8(#[from] std::str::Utf8Error),
 }
 
+impl From<ProcError> for ZoxideError {
+    fn from(err: ProcError) -> Self {
+        match err {
+            ProcError::Io(e) => ZoxideError::Io(e),
+            ProcError::Timeout { .. } => ZoxideError::CommandExecution(err.to_string()),
+        }
+    }
+}
+
 /// Entry with path and score from zoxide
 #[derive(Debug, Clone, PartialEq)]
 pub struct ZoxideEntry {
     pub path: PathBuf,
     pub score: f64,
+    /// Last time the path was accessed, if known. `zoxide query` does not
+    /// expose this, so it is always `None` for entries from the real client.
+    pub last_access: Option<SystemTime>,
 }
 
 /// Trait defining zoxide operations
@@ -35,61 +49,125 @@ pub trait ZoxideOperations {
     /// Add a path to zoxide database
     fn add<P: AsRef<Path>>(&self, path: P) -> ZoxideResult<()>;
 
+    /// Add a path to zoxide database, increasing its score by `score`
+    /// instead of zoxide's default increment
+    fn add_with_score<P: AsRef<Path>>(&self, path: P, score: f64) -> ZoxideResult<()>;
+
     /// List all paths in zoxide database with their scores
     fn list(&self) -> ZoxideResult<Vec<ZoxideEntry>>;
 
     /// Query zoxide for matching paths
     fn query(&self, keywords: &[&str]) -> ZoxideResult<Vec<ZoxideEntry>>;
+
+    /// The installed zoxide version, as printed by `zoxide --version`
+    fn version(&self) -> ZoxideResult<String>;
+
+    /// The score of exactly this path, or `None` if zoxide doesn't know it.
+    /// Unlike [`query`](Self::query) this doesn't match other paths.
+    fn score_for<P: AsRef<Path>>(&self, path: P) -> ZoxideResult<Option<f64>>;
+
+    /// Query zoxide for the highest scored matching path, if any
+    fn query_best(&self, keywords: &[&str]) -> ZoxideResult<Option<ZoxideEntry>> {
+        Ok(self.query(keywords)?.into_iter().next())
+    }
+
+    /// List all paths sorted by last access time, most recent first.
+    /// Entries without an access time keep their score order at the end.
+    fn list_by_recency(&self) -> ZoxideResult<Vec<ZoxideEntry>> {
+        let mut entries = self.list()?;
+        // sort_by_key is stable, so entries with equal (or unknown) access
+        // times stay in score order
+        entries.sort_by_key(|e| std::cmp::Reverse(e.last_access));
+        Ok(entries)
+    }
 }
 
 /// Default implementation that calls the real zoxide command
 #[derive(Copy, Clone)]
-pub struct ZoxideClient;
+pub struct ZoxideClient<R: ProcessRunner = SystemRunner> {
+    runner: R,
+}
 
 impl ZoxideClient {
     /// Create a new ZoxideClient
     pub fn new() -> Self {
-        ZoxideClient
+        ZoxideClient {
+            runner: SystemRunner::default(),
+        }
     }
-}
 
-impl Default for ZoxideClient {
-    fn default() -> Self {
-        Self::new()
+    /// Kill zoxide commands that run longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.runner.exec.timeout = timeout;
+        self
+    }
+
+    /// Print every zoxide command to stderr before running it
+    pub fn with_print_cmd(mut self, print_cmd: bool) -> Self {
+        self.runner.exec.print_cmd = print_cmd;
+        self
     }
 }
 
-impl ZoxideOperations for ZoxideClient {
-    fn add<P: AsRef<Path>>(&self, path: P) -> ZoxideResult<()> {
+impl<R: ProcessRunner> ZoxideClient<R> {
+    /// Run zoxide commands through `runner` instead
+    pub fn with_runner<R2: ProcessRunner>(self, runner: R2) -> ZoxideClient<R2> {
+        ZoxideClient { runner }
+    }
+
+    /// The runner zoxide commands go through
+    pub fn runner(&self) -> &R {
+        &self.runner
+    }
+
+    /// Run `zoxide add`, with `--score` if a custom increment is given
+    fn run_add(&self, path: &Path, score: Option<f64>) -> ZoxideResult<()> {
         let path_str = path
-            .as_ref()
             .to_str()
             .ok_or_else(|| ZoxideError::CommandExecution("Invalid path".to_string()))?;
 
-        let output = Command::new("zoxide").arg("add").arg(path_str).output()?;
+        let mut cmd = Command::new("zoxide");
+        cmd.arg("add");
+        if let Some(score) = score {
+            cmd.arg("--score").arg(score.to_string());
+        }
+        cmd.arg(path_str);
+
+        let output = self.runner.run_command(&cmd)?;
 
-        if !output.status.success() {
+        if !output.success() {
             let error = String::from_utf8_lossy(&output.stderr);
             return Err(ZoxideError::CommandExecution(error.to_string()));
         }
 
         Ok(())
     }
+}
+
+impl Default for ZoxideClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-    fn list(&self) -> ZoxideResult<Vec<ZoxideEntry>> {
-        let output = Command::new("zoxide")
-            .arg("query")
-            .arg("--list")
-            .arg("--score")
-            .output()?;
+impl<R: ProcessRunner> ZoxideOperations for ZoxideClient<R> {
+    fn add<P: AsRef<Path>>(&self, path: P) -> ZoxideResult<()> {
+        self.run_add(path.as_ref(), None)
+    }
 
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(ZoxideError::CommandExecution(error.to_string()));
-        }
+    fn add_with_score<P: AsRef<Path>>(&self, path: P, score: f64) -> ZoxideResult<()> {
+        self.run_add(path.as_ref(), Some(score))
+    }
 
-        let stdout = str::from_utf8(&output.stdout)?;
-        parse_zoxide_list_output(stdout)
+    fn list(&self) -> ZoxideResult<Vec<ZoxideEntry>> {
+        let output = self.runner.run_command(
+            Command::new("zoxide")
+                .arg("query")
+                .arg("--list")
+                .arg("--score"),
+        )?;
+
+        entries_from_output(&output)
     }
 
     fn query(&self, keywords: &[&str]) -> ZoxideResult<Vec<ZoxideEntry>> {
@@ -102,43 +180,89 @@ impl ZoxideOperations for ZoxideClient {
             cmd.arg(keyword);
         }
 
-        let output = cmd.output()?;
+        let output = self.runner.run_command(&cmd)?;
 
-        if !output.status.success() {
+        entries_from_output(&output)
+    }
+
+    fn version(&self) -> ZoxideResult<String> {
+        let output = self
+            .runner
+            .run_command(Command::new("zoxide").arg("--version"))?;
+
+        if !output.success() {
             let error = String::from_utf8_lossy(&output.stderr);
             return Err(ZoxideError::CommandExecution(error.to_string()));
         }
 
-        let stdout = str::from_utf8(&output.stdout)?;
-        parse_zoxide_query_output(stdout)
+        Ok(str::from_utf8(&output.stdout)?.trim().to_string())
+    }
+
+    fn score_for<P: AsRef<Path>>(&self, path: P) -> ZoxideResult<Option<f64>> {
+        // Keywords match any path containing them, so look the path up in
+        // the full listing instead
+        let path = path.as_ref();
+        Ok(self
+            .list()?
+            .into_iter()
+            .find(|entry| entry.path == path)
+            .map(|entry| entry.score))
+    }
+}
+
+/// Entries from a finished `zoxide query --score`, with or without
+/// `--list`. Finding nothing is an empty result rather than an error.
+fn entries_from_output(output: &RunOutput) -> ZoxideResult<Vec<ZoxideEntry>> {
+    if !output.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        if is_no_match(output.code, &error) {
+            return Ok(Vec::new());
+        }
+        return Err(ZoxideError::CommandExecution(error.to_string()));
     }
+
+    let stdout = str::from_utf8(&output.stdout)?;
+    parse_zoxide_query_output(stdout)
+}
+
+/// Whether a failed `zoxide query` just found nothing. zoxide exits with 1
+/// and says so when no path matches, which is also what it does when its
+/// database is empty or was never created.
+fn is_no_match(code: Option<i32>, stderr: &str) -> bool {
+    code == Some(1) && stderr.contains("no match found")
 }
 
 /// Parse output from zoxide query --list or zoxide query --score
 fn parse_zoxide_list_output(output: &str) -> ZoxideResult<Vec<ZoxideEntry>> {
     let mut entries = Vec::new();
 
-    for line in output.lines() {
+    // Lines can end in `\r\n`, or even a lone `\r`, depending on the shell
+    // and OS, so a CR never ends up in a path
+    for line in output.split(['\n', '\r']) {
         let line = line.trim();
         if line.is_empty() {
             continue;
         }
 
-        let parts: Vec<&str> = line.splitn(2, ' ').collect();
-        if parts.len() != 2 {
+        let Some((score, path)) = line.split_once(char::is_whitespace) else {
             return Err(ZoxideError::OutputParsing(format!(
                 "Invalid output format: {}",
                 line
             )));
-        }
+        };
 
-        let score = parts[0].parse::<f64>().map_err(|_| {
-            ZoxideError::OutputParsing(format!("Failed to parse score: {}", parts[0]))
-        })?;
+        let score = score
+            .parse::<f64>()
+            .map_err(|_| ZoxideError::OutputParsing(format!("Failed to parse score: {}", score)))?;
 
-        let path = PathBuf::from(parts[1]);
+        // Paths are absolute, so whitespace around them is never part of them
+        let path = PathBuf::from(path.trim());
 
-        entries.push(ZoxideEntry { path, score });
+        entries.push(ZoxideEntry {
+            path,
+            score,
+            last_access: None,
+        });
     }
 
     Ok(entries)
@@ -155,29 +279,64 @@ fn parse_zoxide_query_output(output: &str) -> ZoxideResult<Vec<ZoxideEntry>> {
 pub struct MockZoxideClient {
     // Store paths and their scores
     paths: RefCell<HashMap<PathBuf, f64>>,
+    // Store last access times for paths
+    access_times: RefCell<HashMap<PathBuf, SystemTime>>,
+    // None simulates zoxide not being installed
+    version: RefCell<Option<String>>,
 }
 
+/// Version reported by a default [`MockZoxideClient`]
+pub const MOCK_ZOXIDE_VERSION: &str = "zoxide 0.9.4";
+
 impl MockZoxideClient {
     pub fn new() -> Self {
-        Self {
-            paths: RefCell::new(HashMap::new()),
-        }
+        Self::with_paths(HashMap::new())
     }
 
     /// Preset paths and scores for testing
     pub fn with_paths(paths: HashMap<PathBuf, f64>) -> Self {
         Self {
             paths: RefCell::new(paths),
+            access_times: RefCell::new(HashMap::new()),
+            version: RefCell::new(Some(MOCK_ZOXIDE_VERSION.to_string())),
+        }
+    }
+
+    /// Preset the reported version for testing, or `None` to act as if
+    /// zoxide isn't installed
+    pub fn with_version(self, version: Option<&str>) -> Self {
+        *self.version.borrow_mut() = version.map(String::from);
+        self
+    }
+
+    /// Preset last access times for testing
+    pub fn with_access_times(self, access_times: HashMap<PathBuf, SystemTime>) -> Self {
+        *self.access_times.borrow_mut() = access_times;
+        self
+    }
+
+    fn entry_for(&self, path: &Path, score: f64) -> ZoxideEntry {
+        ZoxideEntry {
+            path: path.to_path_buf(),
+            score,
+            last_access: self.access_times.borrow().get(path).copied(),
         }
     }
 }
 
 impl ZoxideOperations for MockZoxideClient {
     fn add<P: AsRef<Path>>(&self, path: P) -> ZoxideResult<()> {
+        self.add_with_score(path, 1.0)
+    }
+
+    fn add_with_score<P: AsRef<Path>>(&self, path: P, score: f64) -> ZoxideResult<()> {
         let path_buf = path.as_ref().to_path_buf();
         let mut paths = self.paths.borrow_mut();
 
-        *paths.entry(path_buf).or_insert(0.0) += 1.0;
+        *paths.entry(path_buf.clone()).or_insert(0.0) += score;
+        self.access_times
+            .borrow_mut()
+            .insert(path_buf, SystemTime::now());
 
         Ok(())
     }
@@ -187,10 +346,7 @@ impl ZoxideOperations for MockZoxideClient {
 
         let mut entries: Vec<ZoxideEntry> = paths
             .iter()
-            .map(|(path, &score)| ZoxideEntry {
-                path: path.clone(),
-                score,
-            })
+            .map(|(path, &score)| self.entry_for(path, score))
             .collect();
 
         // Sort by score descending
@@ -206,7 +362,8 @@ impl ZoxideOperations for MockZoxideClient {
     fn query(&self, keywords: &[&str]) -> ZoxideResult<Vec<ZoxideEntry>> {
         let paths = self.paths.borrow();
 
-        // Simple filtering: check if any keyword is a substring of the path
+        // Simple filtering: like zoxide, every keyword must be a substring
+        // of the path
         let filtered: Vec<ZoxideEntry> = paths
             .iter()
             .filter(|(path, _)| {
@@ -217,12 +374,9 @@ impl ZoxideOperations for MockZoxideClient {
                 let path_str = path.to_string_lossy().to_lowercase();
                 keywords
                     .iter()
-                    .any(|&keyword| path_str.contains(&keyword.to_lowercase()))
-            })
-            .map(|(path, &score)| ZoxideEntry {
-                path: path.clone(),
-                score,
+                    .all(|&keyword| path_str.contains(&keyword.to_lowercase()))
             })
+            .map(|(path, &score)| self.entry_for(path, score))
             .collect();
 
         // Sort by score descending
@@ -235,4 +389,251 @@ impl ZoxideOperations for MockZoxideClient {
 
         Ok(result)
     }
+
+    fn version(&self) -> ZoxideResult<String> {
+        self.version.borrow().clone().ok_or_else(|| {
+            ZoxideError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "zoxide not found",
+            ))
+        })
+    }
+
+    fn score_for<P: AsRef<Path>>(&self, path: P) -> ZoxideResult<Option<f64>> {
+        Ok(self.paths.borrow().get(path.as_ref()).copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use zesh_proc::MockRunner;
+
+    #[test]
+    fn test_parse_list_output_has_no_access_time() {
+        let entries = parse_zoxide_list_output("  12.5 /home/user/project\n").unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, PathBuf::from("/home/user/project"));
+        assert_eq!(entries[0].score, 12.5);
+        assert_eq!(entries[0].last_access, None);
+    }
+
+    #[test]
+    fn test_parse_list_output_with_crlf() {
+        let entries =
+            parse_zoxide_list_output("  12.5 /home/user/project\r\n   3.0 /src/api \r\n\r\n")
+                .unwrap();
+        let paths: Vec<PathBuf> = entries.into_iter().map(|e| e.path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/home/user/project"),
+                PathBuf::from("/src/api")
+            ]
+        );
+
+        // Lone CRs separate lines too
+        let entries = parse_zoxide_list_output("1.0 /src/a\r2.0\t/src/b\r").unwrap();
+        assert_eq!(entries[0].path, PathBuf::from("/src/a"));
+        assert_eq!(entries[1].path, PathBuf::from("/src/b"));
+        assert_eq!(entries[1].score, 2.0);
+    }
+
+    #[test]
+    fn test_parse_list_output_invalid() {
+        assert!(matches!(
+            parse_zoxide_list_output("/src/api\r\n"),
+            Err(ZoxideError::OutputParsing(_))
+        ));
+        assert!(matches!(
+            parse_zoxide_list_output("high /src/api\r\n"),
+            Err(ZoxideError::OutputParsing(_))
+        ));
+    }
+
+    #[test]
+    fn test_no_match_is_empty_result() {
+        assert!(is_no_match(Some(1), "zoxide: no match found\n"));
+
+        // Other failures are still errors
+        assert!(!is_no_match(Some(1), "zoxide: could not read database\n"));
+        assert!(!is_no_match(Some(2), "zoxide: no match found\n"));
+        assert!(!is_no_match(None, ""));
+    }
+
+    fn client_with(outputs: Vec<RunOutput>) -> ZoxideClient<MockRunner> {
+        let runner = outputs
+            .into_iter()
+            .fold(MockRunner::new(), MockRunner::with_output);
+        ZoxideClient::new().with_runner(runner)
+    }
+
+    #[test]
+    fn test_empty_database_is_empty_list() {
+        let zoxide = client_with(vec![
+            RunOutput::with_code(1, "", "zoxide: no match found\n"),
+            RunOutput::success_with("  2.0 /src/api\n"),
+            RunOutput::with_code(1, "", "zoxide: permission denied\n"),
+        ]);
+
+        assert!(zoxide.list().unwrap().is_empty());
+        assert_eq!(zoxide.list().unwrap().len(), 1);
+        assert!(matches!(
+            zoxide.list(),
+            Err(ZoxideError::CommandExecution(_))
+        ));
+    }
+
+    #[test]
+    fn test_client_argv() {
+        let zoxide = client_with(Vec::new());
+
+        zoxide.add("/src/api").unwrap();
+        zoxide.add_with_score("/src/web", 5.0).unwrap();
+        zoxide.query(&["work", "api"]).unwrap();
+        zoxide.list().unwrap();
+
+        assert_eq!(
+            zoxide.runner().argvs(),
+            vec![
+                vec!["zoxide", "add", "/src/api"],
+                vec!["zoxide", "add", "--score", "5", "/src/web"],
+                vec!["zoxide", "query", "--score", "work", "api"],
+                vec!["zoxide", "query", "--list", "--score"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mock_reports_access_times() {
+        let accessed = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let zoxide = MockZoxideClient::with_paths(HashMap::from([
+            (PathBuf::from("/a"), 1.0),
+            (PathBuf::from("/b"), 2.0),
+        ]))
+        .with_access_times(HashMap::from([(PathBuf::from("/a"), accessed)]));
+
+        let entries = zoxide.list().unwrap();
+        let a = entries.iter().find(|e| e.path == Path::new("/a")).unwrap();
+        let b = entries.iter().find(|e| e.path == Path::new("/b")).unwrap();
+        assert_eq!(a.last_access, Some(accessed));
+        assert_eq!(b.last_access, None);
+    }
+
+    #[test]
+    fn test_list_by_recency() {
+        let epoch = SystemTime::UNIX_EPOCH;
+        let zoxide = MockZoxideClient::with_paths(HashMap::from([
+            (PathBuf::from("/old"), 30.0),
+            (PathBuf::from("/new"), 10.0),
+            (PathBuf::from("/unknown-high"), 20.0),
+            (PathBuf::from("/unknown-low"), 5.0),
+        ]))
+        .with_access_times(HashMap::from([
+            (PathBuf::from("/old"), epoch + Duration::from_secs(10)),
+            (PathBuf::from("/new"), epoch + Duration::from_secs(20)),
+        ]));
+
+        let paths: Vec<PathBuf> = zoxide
+            .list_by_recency()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/new"),
+                PathBuf::from("/old"),
+                PathBuf::from("/unknown-high"),
+                PathBuf::from("/unknown-low"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_query_best_returns_top_match() {
+        let zoxide = MockZoxideClient::with_paths(HashMap::from([
+            (PathBuf::from("/src/project-a"), 5.0),
+            (PathBuf::from("/src/project-b"), 15.0),
+            (PathBuf::from("/other"), 50.0),
+        ]));
+
+        let best = zoxide.query_best(&["project"]).unwrap().unwrap();
+        assert_eq!(best.path, PathBuf::from("/src/project-b"));
+        assert_eq!(best.score, 15.0);
+    }
+
+    #[test]
+    fn test_query_best_no_match() {
+        let zoxide = MockZoxideClient::with_paths(HashMap::from([(PathBuf::from("/src/a"), 5.0)]));
+
+        assert_eq!(zoxide.query_best(&["missing"]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_score_for_exact_path() {
+        let zoxide = MockZoxideClient::with_paths(HashMap::from([
+            (PathBuf::from("/src/project"), 7.5),
+            (PathBuf::from("/src/project-b"), 3.0),
+        ]));
+
+        assert_eq!(zoxide.score_for("/src/project").unwrap(), Some(7.5));
+        assert_eq!(zoxide.score_for("/src/proj").unwrap(), None);
+        assert_eq!(zoxide.score_for("/missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_mock_query_requires_all_keywords() {
+        let zoxide = MockZoxideClient::new();
+        zoxide.add_with_score("/src/api/server", 2.0).unwrap();
+        zoxide.add_with_score("/src/web/server", 1.0).unwrap();
+
+        let matches = zoxide.query(&["server", "api"]).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].path, PathBuf::from("/src/api/server"));
+    }
+
+    #[test]
+    fn test_mock_add_records_access_time() {
+        let zoxide = MockZoxideClient::new();
+        zoxide.add("/fresh").unwrap();
+
+        let entries = zoxide.list_by_recency().unwrap();
+        assert_eq!(entries[0].path, PathBuf::from("/fresh"));
+        assert!(entries[0].last_access.is_some());
+    }
+
+    #[test]
+    fn test_mock_add_with_score() {
+        let zoxide = MockZoxideClient::with_paths(HashMap::from([
+            (PathBuf::from("/busy"), 5.0),
+            (PathBuf::from("/quiet"), 1.0),
+        ]));
+
+        zoxide.add_with_score("/quiet", 10.0).unwrap();
+        zoxide.add_with_score("/new", 2.5).unwrap();
+
+        let entries = zoxide.list().unwrap();
+        let scores: Vec<(PathBuf, f64)> = entries.into_iter().map(|e| (e.path, e.score)).collect();
+        assert_eq!(
+            scores,
+            vec![
+                (PathBuf::from("/quiet"), 11.0),
+                (PathBuf::from("/busy"), 5.0),
+                (PathBuf::from("/new"), 2.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mock_add_uses_default_increment() {
+        let zoxide = MockZoxideClient::new();
+        zoxide.add("/dir").unwrap();
+        zoxide.add("/dir").unwrap();
+
+        assert_eq!(zoxide.list().unwrap()[0].score, 2.0);
+    }
 }